@@ -0,0 +1,398 @@
+// Import of Anki `.apkg` packages.
+//
+// Reads the legacy collection schema (`collection.anki21` / `collection.anki2`),
+// which Anki writes when exporting with "Support older Anki versions". Bundled
+// media is copied into the collection's media directory under content-hash
+// names, so re-importing the same deck (or two decks sharing an image) stores
+// each file once.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::backup::read_entry;
+use crate::card::{self, Card};
+use crate::clock;
+use crate::error::{Error, Result};
+
+pub struct AnkiImport {
+    pub cards: Vec<Card>,
+    /// Media files newly written to the media directory.
+    pub media_copied: usize,
+    /// Media files whose content already existed in the media directory.
+    pub media_reused: usize,
+}
+
+pub fn import_apkg(path: &Path, media_dir: &Path) -> Result<AnkiImport> {
+    let mut archive = crate::backup::open(path, "an Anki package")?;
+
+    let collection_name = ["collection.anki21", "collection.anki2"]
+        .into_iter()
+        .find(|name| archive.index_for_name(name).is_some())
+        .ok_or_else(|| Error::NoAnkiCollection {
+            path: path.to_path_buf(),
+        })?;
+
+    let collection = read_entry(&mut archive, path, collection_name)?;
+    let (renames, media_copied, media_reused) = import_media(&mut archive, path, media_dir)?;
+    let notes = read_notes(&collection)?;
+
+    let cards = notes
+        .into_iter()
+        .map(|note| note_to_card(note, &renames))
+        .collect();
+
+    Ok(AnkiImport {
+        cards,
+        media_copied,
+        media_reused,
+    })
+}
+
+/// Copy bundled media into `media_dir`, returning a map from the original file
+/// name to the stored (content-addressed) name.
+fn import_media(
+    archive: &mut zip::ZipArchive<std::fs::File>,
+    path: &Path,
+    media_dir: &Path,
+) -> Result<(HashMap<String, String>, usize, usize)> {
+    let mut renames = HashMap::new();
+    if archive.index_for_name("media").is_none() {
+        return Ok((renames, 0, 0));
+    }
+
+    let manifest = read_entry(archive, path, "media")?;
+    let manifest: HashMap<String, String> =
+        serde_json::from_slice(&manifest).map_err(|source| Error::AnkiMedia { source })?;
+
+    let (mut copied, mut reused) = (0, 0);
+    for (entry_name, original) in manifest {
+        if archive.index_for_name(&entry_name).is_none() {
+            continue;
+        }
+        let bytes = read_entry(archive, path, &entry_name)?;
+        let stored = content_name(&original, &bytes);
+        let dest = media_dir.join(&stored);
+        if dest.exists() {
+            reused += 1;
+        } else {
+            std::fs::create_dir_all(media_dir).map_err(Error::io("create", media_dir))?;
+            std::fs::write(&dest, &bytes).map_err(Error::io("write", &dest))?;
+            copied += 1;
+        }
+        renames.insert(original, stored);
+    }
+    Ok((renames, copied, reused))
+}
+
+/// Name a media file by a prefix of its SHA-256, keeping the original extension.
+pub(crate) fn content_name(original: &str, bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let hash: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    match Path::new(original).extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{hash}.{}", ext.to_ascii_lowercase()),
+        None => hash,
+    }
+}
+
+struct Note {
+    deck: String,
+    fields: Vec<String>,
+    tags: String,
+    is_cloze: bool,
+    /// From the note id, which Anki takes from the time the note was added.
+    created: Option<chrono::NaiveDate>,
+}
+
+#[derive(serde::Deserialize)]
+struct Model {
+    #[serde(rename = "type", default)]
+    kind: i64,
+}
+
+#[derive(serde::Deserialize)]
+struct Deck {
+    name: String,
+}
+
+fn read_notes(collection: &[u8]) -> Result<Vec<Note>> {
+    let tmp = std::env::temp_dir().join(format!("rote-{}.anki2", uuid::Uuid::new_v4()));
+    std::fs::write(&tmp, collection).map_err(Error::io("write", &tmp))?;
+    let result = query_notes(&tmp);
+    let _ = std::fs::remove_file(&tmp);
+    result.map_err(|source| Error::AnkiCollection { source })
+}
+
+fn query_notes(db_path: &Path) -> rusqlite::Result<Vec<Note>> {
+    let conn = rusqlite::Connection::open(db_path)?;
+    let (models, decks): (String, String) =
+        conn.query_row("SELECT models, decks FROM col", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    let models: HashMap<String, Model> = serde_json::from_str(&models).unwrap_or_default();
+    let decks: HashMap<String, Deck> = serde_json::from_str(&decks).unwrap_or_default();
+
+    let mut stmt = conn.prepare(
+        "SELECT n.id, n.mid, n.flds, n.tags, (SELECT c.did FROM cards c WHERE c.nid = n.id ORDER BY c.ord LIMIT 1)
+         FROM notes n ORDER BY n.id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let nid: i64 = row.get(0)?;
+        let mid: i64 = row.get(1)?;
+        let flds: String = row.get(2)?;
+        let tags: String = row.get(3)?;
+        let did: Option<i64> = row.get(4)?;
+        Ok((nid, mid, flds, tags, did))
+    })?;
+
+    let mut notes = Vec::new();
+    for row in rows {
+        let (nid, mid, flds, tags, did) = row?;
+        let deck = did
+            .and_then(|d| decks.get(&d.to_string()))
+            .map(|d| d.name.clone())
+            .unwrap_or_else(|| "Default".to_string());
+        let is_cloze = models.get(&mid.to_string()).is_some_and(|m| m.kind == 1);
+        notes.push(Note {
+            deck,
+            fields: flds.split('\x1f').map(str::to_string).collect(),
+            tags,
+            is_cloze,
+            created: chrono::DateTime::from_timestamp_millis(nid).map(|at| clock::day_of(&at)),
+        });
+    }
+    Ok(notes)
+}
+
+fn note_to_card(note: Note, renames: &HashMap<String, String>) -> Card {
+    let mut media = Vec::new();
+    let mut field = |i: usize| {
+        let raw = note.fields.get(i).map(String::as_str).unwrap_or("");
+        let (text, refs) = extract_media(raw);
+        for r in refs {
+            let stored = renames.get(&r).cloned().unwrap_or(r);
+            if !media.contains(&stored) {
+                media.push(stored);
+            }
+        }
+        html_to_text(&text)
+    };
+
+    // Anki has no bracket syntax, so any brackets in the text are literal.
+    let mut front = card::escape_brackets(&field(0));
+    let back = field(1);
+    if note.is_cloze {
+        front = convert_cloze(&front);
+    }
+
+    Card {
+        deck: note.deck,
+        front,
+        back,
+        extra: String::new(),
+        origin: String::new(),
+        media: media.join(";"),
+        id: uuid::Uuid::new_v4().to_string(),
+        stability: None,
+        difficulty: None,
+        due: None,
+        last_review: None,
+        created: note.created,
+        tags: card::parse_tags(&note.tags),
+        suspended: false,
+        flagged: false,
+        ease: None,
+        interval: None,
+    }
+}
+
+/// Pull `<img src="...">` and `[sound:...]` references out of a field,
+/// returning the remaining text and the referenced file names.
+fn extract_media(field: &str) -> (String, Vec<String>) {
+    let mut refs = Vec::new();
+    let mut out = String::new();
+    let mut rest = field;
+
+    loop {
+        let img = rest.find("<img");
+        let sound = rest.find("[sound:");
+        let (start, is_img) = match (img, sound) {
+            (Some(i), Some(s)) if i < s => (i, true),
+            (Some(i), None) => (i, true),
+            (_, Some(s)) => (s, false),
+            (None, None) => break,
+        };
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let end = if is_img {
+            tail.find('>')
+        } else {
+            tail.find(']')
+        };
+        let Some(end) = end else {
+            out.push_str(tail);
+            rest = "";
+            break;
+        };
+        let tag = &tail[..=end];
+        if is_img {
+            if let Some(src) = attr_value(tag, "src") {
+                refs.push(decode_entities(&src));
+            }
+        } else {
+            refs.push(tag["[sound:".len()..tag.len() - 1].to_string());
+        }
+        rest = &tail[end + 1..];
+    }
+    out.push_str(rest);
+    (out, refs)
+}
+
+fn attr_value(tag: &str, name: &str) -> Option<String> {
+    let pos = tag.find(&format!("{name}="))?;
+    let value = &tag[pos + name.len() + 1..];
+    let quote = value.chars().next()?;
+    if quote == '"' || quote == '\'' {
+        let end = value[1..].find(quote)?;
+        Some(value[1..1 + end].to_string())
+    } else {
+        let end = value
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(value.len());
+        Some(value[..end].to_string())
+    }
+}
+
+/// Flatten Anki's field HTML into rote's plain-text form, where line breaks
+/// are stored as a literal `\n`.
+fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            out.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end]
+            .trim()
+            .trim_end_matches('/')
+            .to_ascii_lowercase();
+        let name = tag.split_whitespace().next().unwrap_or("");
+        if matches!(name, "br" | "/div" | "/p" | "/li") {
+            out.push_str("\\n");
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+
+    let text = decode_entities(&out);
+    let text = text.trim();
+    let mut text = text.trim_end_matches("\\n").to_string();
+    while text.contains("\\n\\n\\n") {
+        text = text.replace("\\n\\n\\n", "\\n\\n");
+    }
+    text
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Rewrite Anki cloze markup (`{{c1::answer}}`, `{{c2::answer::hint}}`) as
+/// rote's bracket syntax.
+fn convert_cloze(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{c") {
+        let after = &rest[start + 3..];
+        let digits = after.chars().take_while(|c| c.is_ascii_digit()).count();
+        let body_start = start + 3 + digits;
+        if digits == 0 || !rest[body_start..].starts_with("::") {
+            out.push_str(&rest[..start + 3]);
+            rest = &rest[start + 3..];
+            continue;
+        }
+        let Some(end) = rest[body_start..].find("}}") else {
+            break;
+        };
+        // The body is `answer` or `answer::hint`, as in rote.
+        let body = &rest[body_start + 2..body_start + end];
+        out.push_str(&rest[..start]);
+        out.push('[');
+        out.push_str(body);
+        out.push(']');
+        rest = &rest[body_start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cloze_conversion() {
+        assert_eq!(
+            convert_cloze("The {{c1::mitochondria}} is the {{c2::powerhouse::noun}}"),
+            "The [mitochondria] is the [powerhouse::noun]"
+        );
+        assert_eq!(convert_cloze("no cloze {{here}}"), "no cloze {{here}}");
+    }
+
+    #[test]
+    fn html_flattening() {
+        assert_eq!(
+            html_to_text("<div>line one</div><div>line&nbsp;two &amp; <b>three</b></div>"),
+            "line one\\nline two & three"
+        );
+        assert_eq!(html_to_text("a<br>b<br/>"), "a\\nb");
+    }
+
+    #[test]
+    fn media_references_extracted() {
+        let (text, refs) =
+            extract_media(r#"Heart <img src="heart diagram.png"> beats [sound:lub.mp3]"#);
+        assert_eq!(text, "Heart  beats ");
+        assert_eq!(refs, vec!["heart diagram.png", "lub.mp3"]);
+    }
+
+    #[test]
+    fn content_names_are_stable() {
+        let a = content_name("Photo.JPG", b"same bytes");
+        let b = content_name("other.jpg", b"same bytes");
+        assert_eq!(a, b);
+        assert!(a.ends_with(".jpg"));
+        assert_ne!(a, content_name("x.jpg", b"different"));
+    }
+
+    #[test]
+    fn note_media_rewritten_to_stored_names() {
+        let mut renames = HashMap::new();
+        renames.insert("heart.png".to_string(), "0a1b2c3d.png".to_string());
+        let note = Note {
+            deck: "Anatomy".into(),
+            fields: vec![
+                r#"<img src="heart.png">Name this organ"#.into(),
+                "Heart".into(),
+            ],
+            tags: " cardio anatomy ".into(),
+            is_cloze: false,
+            created: None,
+        };
+        let card = note_to_card(note, &renames);
+        assert_eq!(card.front, "Name this organ");
+        assert_eq!(card.back, "Heart");
+        assert_eq!(card.media, "0a1b2c3d.png");
+        assert_eq!(card.tags, vec!["cardio", "anatomy"]);
+        assert!(card.stability.is_none());
+    }
+}
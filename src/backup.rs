@@ -0,0 +1,250 @@
+// Snapshots: a collection's deck files and review log in one zstd-compressed
+// zip named by the time it was taken, for rolling a collection back whole:
+//
+//     rote backup ~/backups ~/cards --keep 30
+//     rote restore ~/backups/rote-20261016-081500.zip
+//
+// Commands that rewrite many cards at once (imports, `reschedule`,
+// `state import`, `restore` itself) first snapshot the files they touch into
+// `.rote/backups`, keeping the last `KEEP`.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::error::{Error, Result};
+
+const MANIFEST: &str = "manifest.json";
+const AUTO_DIR: &str = "backups";
+
+/// Snapshots kept in a directory unless told otherwise.
+pub const KEEP: usize = 10;
+
+/// What a snapshot holds and where each file goes back to.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub created: DateTime<Local>,
+    /// The collection's data directory, locked while restoring.
+    pub data_dir: PathBuf,
+    pub files: Vec<Entry>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    /// Name inside the zip.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// `files` plus the review log kept in `data_dir`.
+pub fn with_revlog(files: &[PathBuf], data_dir: &Path) -> Vec<PathBuf> {
+    let mut files = files.to_vec();
+    files.push(crate::revlog::revlog_path(data_dir));
+    files
+}
+
+/// Snapshot `files` of the collection whose data directory is `data_dir`
+/// into `out_dir`, then delete all but the newest `keep` snapshots there.
+/// `label` names what the snapshot was taken before. Files that do not exist
+/// are left out.
+pub fn create(
+    files: &[PathBuf],
+    data_dir: &Path,
+    out_dir: &Path,
+    label: Option<&str>,
+    keep: usize,
+) -> Result<PathBuf> {
+    let created = Local::now();
+    std::fs::create_dir_all(out_dir).map_err(Error::io("create", out_dir))?;
+    let stem = format!(
+        "rote-{}{}",
+        created.format("%Y%m%d-%H%M%S"),
+        label.map(|l| format!("-{l}")).unwrap_or_default()
+    );
+    let mut out = out_dir.join(format!("{stem}.zip"));
+    for n in 2.. {
+        if !out.exists() {
+            break;
+        }
+        out = out_dir.join(format!("{stem}-{n}.zip"));
+    }
+    // Written aside and renamed, so a half-written snapshot never counts.
+    let part = out.with_extension("zip.part");
+    let written = write(files, data_dir, created, &part);
+    if written.is_err() {
+        let _ = std::fs::remove_file(&part);
+    }
+    written?;
+    std::fs::rename(&part, &out).map_err(Error::io("write", &out))?;
+    rotate(out_dir, keep)?;
+    Ok(out)
+}
+
+/// Snapshot `files` into the collection's own `backups` directory before
+/// `action` changes them.
+pub fn before(action: &str, files: &[PathBuf], data_dir: &Path) -> Result<PathBuf> {
+    create(
+        files,
+        data_dir,
+        &data_dir.join(AUTO_DIR),
+        Some(action),
+        KEEP,
+    )
+}
+
+fn write(files: &[PathBuf], data_dir: &Path, created: DateTime<Local>, out: &Path) -> Result<()> {
+    let write_err = |source| Error::Zip {
+        path: out.to_path_buf(),
+        source,
+    };
+    let io_err = |e| Error::io("write", out)(e);
+    let file = std::fs::File::create(out).map_err(io_err)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Zstd);
+    let mut manifest = Manifest {
+        created,
+        data_dir: absolute(data_dir),
+        files: Vec::new(),
+    };
+    for (i, path) in files.iter().enumerate() {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::io("read", path)(e)),
+        };
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let name = format!("files/{i}/{file_name}");
+        zip.start_file(name.as_str(), options).map_err(write_err)?;
+        zip.write_all(&bytes).map_err(io_err)?;
+        manifest.files.push(Entry {
+            name,
+            path: absolute(path),
+        });
+    }
+    zip.start_file(MANIFEST, options).map_err(write_err)?;
+    let json = serde_json::to_vec_pretty(&manifest).unwrap();
+    zip.write_all(&json).map_err(io_err)?;
+    zip.finish().map_err(write_err)?;
+    Ok(())
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Delete all but the newest `keep` snapshots in `dir`.
+fn rotate(dir: &Path, keep: usize) -> Result<()> {
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(Error::io("read", dir))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("rote-") && n.ends_with(".zip"))
+        })
+        .collect();
+    // Oldest first; names alone misorder two taken within a second.
+    snapshots.sort_by_key(|path| {
+        let modified = path.metadata().and_then(|m| m.modified()).ok();
+        (modified, path.clone())
+    });
+    let excess = snapshots.len().saturating_sub(keep);
+    for path in &snapshots[..excess] {
+        std::fs::remove_file(path).map_err(Error::io("delete", path))?;
+    }
+    Ok(())
+}
+
+/// The manifest of the snapshot at `path`.
+pub fn manifest(path: &Path) -> Result<Manifest> {
+    let mut archive = open(path, "a rote snapshot")?;
+    let json = read_entry(&mut archive, path, MANIFEST)?;
+    serde_json::from_slice(&json).map_err(Error::json(path))
+}
+
+/// Write every file in the snapshot at `path` back where it was taken from.
+/// Each file is replaced whole, never left half-written.
+pub fn restore(path: &Path) -> Result<Vec<PathBuf>> {
+    let manifest = manifest(path)?;
+    let mut archive = open(path, "a rote snapshot")?;
+    let mut restored = Vec::new();
+    for entry in manifest.files {
+        let bytes = read_entry(&mut archive, path, &entry.name)?;
+        if let Some(parent) = entry.path.parent() {
+            std::fs::create_dir_all(parent).map_err(Error::io("create", parent))?;
+        }
+        let part = entry.path.with_extension("restore.part");
+        std::fs::write(&part, &bytes).map_err(Error::io("write", &entry.path))?;
+        std::fs::rename(&part, &entry.path).map_err(Error::io("write", &entry.path))?;
+        restored.push(entry.path);
+    }
+    Ok(restored)
+}
+
+/// Open the zip archive at `path`, which should be `kind` ("a rote
+/// snapshot"). Imports of other archives share this and `read_entry`.
+pub(crate) fn open(path: &Path, kind: &'static str) -> Result<zip::ZipArchive<std::fs::File>> {
+    let file = std::fs::File::open(path).map_err(Error::io("open", path))?;
+    zip::ZipArchive::new(file).map_err(|source| Error::NotArchive {
+        path: path.to_path_buf(),
+        kind,
+        source,
+    })
+}
+
+pub(crate) fn read_entry(
+    archive: &mut zip::ZipArchive<std::fs::File>,
+    path: &Path,
+    name: &str,
+) -> Result<Vec<u8>> {
+    let read_err = |source| Error::ArchiveEntry {
+        path: path.to_path_buf(),
+        name: name.to_string(),
+        source,
+    };
+    let mut entry = archive.by_name(name).map_err(read_err)?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| read_err(e.into()))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_restore_and_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let deck = dir.path().join("es.csv");
+        let data_dir = dir.path().join(".rote");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(&deck, "deck,front,back\nes,hola,hello\n").unwrap();
+        std::fs::write(data_dir.join("revlog.jsonl"), "{}\n").unwrap();
+        let files = with_revlog(std::slice::from_ref(&deck), &data_dir);
+        let out = dir.path().join("backups");
+
+        let snapshot = create(&files, &data_dir, &out, None, 2).unwrap();
+        std::fs::write(&deck, "").unwrap();
+        std::fs::remove_file(data_dir.join("revlog.jsonl")).unwrap();
+        assert_eq!(restore(&snapshot).unwrap().len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&deck).unwrap(),
+            "deck,front,back\nes,hola,hello\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(data_dir.join("revlog.jsonl")).unwrap(),
+            "{}\n"
+        );
+
+        let later = create(&files, &data_dir, &out, Some("import"), 2).unwrap();
+        let last = create(&files, &data_dir, &out, Some("import"), 2).unwrap();
+        assert_ne!(later, last);
+        assert!(!snapshot.exists());
+        assert_eq!(std::fs::read_dir(&out).unwrap().count(), 2);
+        assert_eq!(manifest(&last).unwrap().files[0].path, deck);
+    }
+}
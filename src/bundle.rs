@@ -0,0 +1,200 @@
+// Deck bundles (`.rote`): a zip of `cards.csv` plus the media files its cards
+// reference under `media/`, for sharing a deck as a single file. Scheduling
+// and flags are personal, so exports leave them out unless asked not to.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::backup::{self, read_entry};
+use crate::card::{self, Card};
+use crate::error::{Error, Result};
+use crate::export::Export;
+use crate::{anki, occlusion};
+
+const CARDS: &str = "cards.csv";
+const MEDIA: &str = "media/";
+
+pub struct BundleImport {
+    pub cards: Vec<Card>,
+    pub media_copied: usize,
+    pub media_reused: usize,
+}
+
+/// A temporary file path for the bundle's CSV, which `card` reads and writes
+/// by path.
+fn temp_csv() -> PathBuf {
+    std::env::temp_dir().join(format!("rote-{}.csv", uuid::Uuid::new_v4()))
+}
+
+pub fn export(cards: &[Card], sources: &[PathBuf], out: &Path, keep_state: bool) -> Result<Export> {
+    let write_err = |source| Error::Zip {
+        path: out.to_path_buf(),
+        source,
+    };
+    let shared: Vec<Card> = cards
+        .iter()
+        .map(|card| {
+            let mut card = card.clone();
+            if !keep_state {
+                card.stability = None;
+                card.difficulty = None;
+                card.due = None;
+                card.last_review = None;
+                card.ease = None;
+                card.interval = None;
+                card.suspended = false;
+                card.flagged = false;
+            }
+            card
+        })
+        .collect();
+    let tmp = temp_csv();
+    let csv = card::save_csv(&tmp, &shared, false)
+        .and_then(|()| std::fs::read(&tmp).map_err(Error::io("read", &tmp)));
+    let _ = std::fs::remove_file(&tmp);
+    let csv = csv?;
+
+    let file = std::fs::File::create(out).map_err(Error::io("create", out))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file(CARDS, options).map_err(write_err)?;
+    zip.write_all(&csv).map_err(Error::io("write", out))?;
+
+    let mut export = Export {
+        decks: cards.iter().map(|c| &c.deck).collect::<HashSet<_>>().len(),
+        cards: cards.len(),
+        media: 0,
+        missing: Vec::new(),
+    };
+    let mut added = HashSet::new();
+    for (card, source) in cards.iter().zip(sources) {
+        for name in card::media_files(card) {
+            if !added.insert(name) {
+                continue;
+            }
+            let path = card::media_dir(source).join(name);
+            let Ok(bytes) = std::fs::read(&path) else {
+                export.missing.push(name.to_string());
+                continue;
+            };
+            zip.start_file(format!("{MEDIA}{name}"), options)
+                .map_err(write_err)?;
+            zip.write_all(&bytes).map_err(Error::io("write", out))?;
+            export.media += 1;
+        }
+    }
+    zip.finish().map_err(write_err)?;
+    Ok(export)
+}
+
+/// Read a bundle, copying its media into `media_dir`. A file whose name is
+/// already taken by different content is stored under a content-hash name
+/// and the cards referring to it are updated.
+pub fn import(path: &Path, media_dir: &Path) -> Result<BundleImport> {
+    let mut archive = backup::open(path, "a rote bundle")?;
+
+    let csv = read_entry(&mut archive, path, CARDS).map_err(|_| Error::NotArchive {
+        path: path.to_path_buf(),
+        kind: "a rote bundle",
+        source: zip::result::ZipError::FileNotFound,
+    })?;
+    let tmp = temp_csv();
+    let cards = std::fs::write(&tmp, csv)
+        .map_err(Error::io("write", &tmp))
+        .and_then(|()| card::load_csv(&tmp));
+    let _ = std::fs::remove_file(&tmp);
+    let mut cards = cards?;
+
+    let mut renames = HashMap::new();
+    let (mut media_copied, mut media_reused) = (0, 0);
+    let names: Vec<String> = archive
+        .file_names()
+        .filter_map(|n| n.strip_prefix(MEDIA))
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .collect();
+    for name in names {
+        // Only plain file names; never write outside the media directory.
+        if Path::new(&name).file_name().and_then(|n| n.to_str()) != Some(name.as_str()) {
+            continue;
+        }
+        let bytes = read_entry(&mut archive, path, &format!("{MEDIA}{name}"))?;
+        let mut dest = media_dir.join(&name);
+        if let Ok(existing) = std::fs::read(&dest)
+            && existing != bytes
+        {
+            let stored = anki::content_name(&name, &bytes);
+            dest = media_dir.join(&stored);
+            renames.insert(name, stored);
+        }
+        if dest.exists() {
+            media_reused += 1;
+            continue;
+        }
+        std::fs::create_dir_all(media_dir).map_err(Error::io("create", media_dir))?;
+        std::fs::write(&dest, &bytes).map_err(Error::io("write", &dest))?;
+        media_copied += 1;
+    }
+    if !renames.is_empty() {
+        for card in &mut cards {
+            card.media = occlusion::rename_files(card, |m| renames.get(m).map(String::as_str));
+        }
+    }
+    Ok(BundleImport {
+        cards,
+        media_copied,
+        media_reused,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn bundles_carry_media_but_not_scheduling() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("mine/cards.csv");
+        std::fs::create_dir_all(dir.path().join("mine/media")).unwrap();
+        std::fs::write(dir.path().join("mine/media/a.png"), b"mine").unwrap();
+        let card = Card {
+            deck: "art".into(),
+            front: "Who painted this?".into(),
+            back: "Vermeer".into(),
+            media: "a.png;lost.png".into(),
+            id: "c1".into(),
+            stability: Some(4.0),
+            difficulty: Some(5.0),
+            due: NaiveDate::from_ymd_opt(2025, 6, 1),
+            last_review: NaiveDate::from_ymd_opt(2025, 5, 1),
+            tags: vec!["dutch".into()],
+            suspended: true,
+            ..Default::default()
+        };
+        let bundle = dir.path().join("art.rote");
+        let export = export(&[card], &[source], &bundle, false).unwrap();
+        assert_eq!((export.cards, export.media), (1, 1));
+        assert_eq!(export.missing, vec!["lost.png"]);
+
+        // The recipient already has a different a.png.
+        let theirs = dir.path().join("theirs/media");
+        std::fs::create_dir_all(&theirs).unwrap();
+        std::fs::write(theirs.join("a.png"), b"theirs").unwrap();
+        let imported = import(&bundle, &theirs).unwrap();
+        let card = &imported.cards[0];
+        assert_eq!((card.id.as_str(), card.back.as_str()), ("c1", "Vermeer"));
+        assert_eq!(card.tags, vec!["dutch"]);
+        assert!(card.due.is_none() && card.stability.is_none() && !card.suspended);
+        let stored = card::media_files(card)[0];
+        assert_ne!(stored, "a.png");
+        assert_eq!(std::fs::read(theirs.join(stored)).unwrap(), b"mine");
+        assert_eq!(std::fs::read(theirs.join("a.png")).unwrap(), b"theirs");
+
+        // Importing again stores nothing new.
+        let again = import(&bundle, &theirs).unwrap();
+        assert_eq!((again.media_copied, again.media_reused), (0, 1));
+    }
+}
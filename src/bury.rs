@@ -0,0 +1,72 @@
+// Buried cards: left out of today's sessions without touching their
+// scheduling, as in Anki, e.g. from the web UI's queue preview. The ids are
+// kept in `.rote/buried.json` with the day they were buried, so they come
+// back by themselves tomorrow:
+//
+//     {"day": "2026-03-01", "ids": ["c1", "c7"]}
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::error::{Error, Result};
+
+const FILE: &str = "buried.json";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Buried {
+    day: Option<NaiveDate>,
+    ids: BTreeSet<String>,
+}
+
+fn read(data_dir: &Path, today: NaiveDate) -> Buried {
+    std::fs::read_to_string(data_dir.join(FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str::<Buried>(&s).ok())
+        .filter(|b| b.day == Some(today))
+        .unwrap_or_default()
+}
+
+/// The ids buried on `today`; a missing or unreadable file buries nothing.
+pub fn load(data_dir: &Path, today: NaiveDate) -> BTreeSet<String> {
+    read(data_dir, today).ids
+}
+
+/// Bury `id` for the rest of `today`, or with `buried` false bring it back.
+pub fn set(data_dir: &Path, id: &str, buried: bool, today: NaiveDate) -> Result<()> {
+    let mut state = read(data_dir, today);
+    state.day = Some(today);
+    if buried {
+        state.ids.insert(id.to_string());
+    } else {
+        state.ids.remove(id);
+    }
+    let path = data_dir.join(FILE);
+    std::fs::create_dir_all(data_dir)
+        .and_then(|()| std::fs::write(&path, serde_json::to_string(&state).unwrap()))
+        .map_err(Error::io("write", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burials_last_until_the_next_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        set(dir.path(), "c1", true, today).unwrap();
+        set(dir.path(), "c2", true, today).unwrap();
+        set(dir.path(), "c1", false, today).unwrap();
+        assert_eq!(load(dir.path(), today), BTreeSet::from(["c2".to_string()]));
+
+        let tomorrow = today.succ_opt().unwrap();
+        assert!(load(dir.path(), tomorrow).is_empty());
+        set(dir.path(), "c3", true, tomorrow).unwrap();
+        assert_eq!(
+            load(dir.path(), tomorrow),
+            BTreeSet::from(["c3".to_string()])
+        );
+    }
+}
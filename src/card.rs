@@ -1,54 +1,295 @@
 use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, serde::Serialize)]
+use crate::error::{Error, Result};
+
+mod index;
+pub use index::{Index, index_csv, save_csv_indexed};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Card {
     pub deck: String,
     pub front: String,
     pub back: String,
+    /// Notes, a source or a mnemonic: never asked, shown under the answer.
+    #[serde(default)]
+    pub extra: String,
     pub media: String,
+    /// Where the card was imported from, as `file:line` or `file#anchor`
+    /// relative to its CSV, so the source of truth can be fixed.
+    #[serde(default)]
+    pub origin: String,
     pub id: String,
     pub stability: Option<f64>,
     pub difficulty: Option<f64>,
     pub due: Option<NaiveDate>,
     pub last_review: Option<NaiveDate>,
+    /// The day the card was added; `None` for cards older than the column.
+    #[serde(default)]
+    pub created: Option<NaiveDate>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Left out of every review session until unsuspended.
+    #[serde(default)]
+    pub suspended: bool,
+    /// Marked for later attention; has no effect on scheduling.
+    #[serde(default)]
+    pub flagged: bool,
+    /// SM-2's ease factor, kept apart from FSRS's difficulty so a deck can
+    /// switch schedulers and back (see `crate::scheduler`).
+    #[serde(default)]
+    pub ease: Option<f64>,
+    /// SM-2's last interval in days; cleared by FSRS reviews.
+    #[serde(default)]
+    pub interval: Option<u32>,
 }
 
-pub fn extract_cloze_deletions(text: &str) -> Vec<String> {
-    let mut results = Vec::new();
+/// `text`'s characters, each with whether it is a cloze bracket. `\[` and
+/// `\]` are literal brackets; they are passed through as typed for
+/// `expand_escapes` to resolve.
+pub fn cloze_chars(text: &str) -> impl Iterator<Item = (char, bool)> + '_ {
+    let mut escaped = false;
+    text.chars().map(move |ch| {
+        let bracket = matches!(ch, '[' | ']') && !escaped;
+        escaped = ch == '\\';
+        (ch, bracket)
+    })
+}
+
+/// A piece of card text: as typed, or an outermost `[answer]` /
+/// `[answer::hint]` cloze.
+#[derive(Debug, PartialEq)]
+pub enum ClozePart {
+    Text(String),
+    Cloze {
+        answer: String,
+        hint: Option<String>,
+    },
+}
+
+/// Split `text` into plain text and clozes. Brackets nested inside a cloze
+/// are kept in its answer; a `[` never closed and a `]` never opened are
+/// plain text.
+pub fn cloze_parts(text: &str) -> Vec<ClozePart> {
+    let mut parts = Vec::new();
+    let mut plain = String::new();
+    let mut inner = String::new();
     let mut depth = 0usize;
-    let mut current = String::new();
-    for ch in text.chars() {
+    for (ch, bracket) in cloze_chars(text) {
         match ch {
-            '[' => {
-                if depth == 0 {
-                    current.clear();
-                } else {
-                    current.push(ch);
+            '[' if bracket => {
+                if depth > 0 {
+                    inner.push(ch);
                 }
                 depth += 1;
             }
-            ']' => {
-                depth = depth.saturating_sub(1);
-                if depth == 0 && !current.is_empty() {
-                    results.push(current.clone());
-                    current.clear();
-                } else if depth > 0 {
-                    current.push(ch);
+            ']' if bracket && depth > 0 => {
+                depth -= 1;
+                if depth > 0 {
+                    inner.push(ch);
+                    continue;
                 }
+                if !plain.is_empty() {
+                    parts.push(ClozePart::Text(std::mem::take(&mut plain)));
+                }
+                let cloze = std::mem::take(&mut inner);
+                parts.push(match cloze.split_once("::") {
+                    Some((answer, hint)) => ClozePart::Cloze {
+                        answer: answer.to_string(),
+                        hint: Some(hint.to_string()),
+                    },
+                    None => ClozePart::Cloze {
+                        answer: cloze,
+                        hint: None,
+                    },
+                });
             }
-            _ => {
-                if depth > 0 {
-                    current.push(ch);
+            _ if depth > 0 => inner.push(ch),
+            _ => plain.push(ch),
+        }
+    }
+    if depth > 0 {
+        plain.push('[');
+        plain.push_str(&inner);
+    }
+    if !plain.is_empty() {
+        parts.push(ClozePart::Text(plain));
+    }
+    parts
+}
+
+/// The answers of `text`'s non-empty clozes, without their hints.
+pub fn extract_cloze_deletions(text: &str) -> Vec<String> {
+    cloze_parts(text)
+        .into_iter()
+        .filter_map(|part| match part {
+            ClozePart::Cloze { answer, .. } if !answer.is_empty() => Some(answer),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `text` with every cloze replaced by its answer.
+pub fn fill_clozes(text: &str) -> String {
+    cloze_parts(text)
+        .into_iter()
+        .map(|part| match part {
+            ClozePart::Text(text) => text,
+            ClozePart::Cloze { answer, .. } => fill_clozes(&answer),
+        })
+        .collect()
+}
+
+/// `fill_clozes`, with each outermost answer between `open` and `close`, to
+/// set it apart on reveal.
+pub fn fill_clozes_marked(text: &str, open: &str, close: &str) -> String {
+    cloze_parts(text)
+        .into_iter()
+        .map(|part| match part {
+            ClozePart::Text(text) => text,
+            ClozePart::Cloze { answer, .. } => format!("{open}{}{close}", fill_clozes(&answer)),
+        })
+        .collect()
+}
+
+/// Escape literal brackets so they are not read as clozes.
+pub fn escape_brackets(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+/// `front` reduced to what two cards asking the same thing share, to match
+/// duplicates: clozes filled in, HTML tags and entities dropped, punctuation
+/// and Markdown marks turned into spaces, lowercased, and whitespace
+/// collapsed. Letters of any script are kept, accents included.
+pub fn normalized_front(front: &str) -> String {
+    let text = fill_clozes(&front.replace("\\n", " "));
+    let mut plain = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(ch) = rest.chars().next() {
+        let markup = match ch {
+            '<' => rest.find('>').filter(|&end| {
+                let inner = &rest[1..end];
+                inner.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/')
+            }),
+            '&' => rest.find(';').filter(|&end| {
+                end > 1
+                    && end <= 10
+                    && rest[1..end]
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '#')
+            }),
+            _ => None,
+        };
+        match markup {
+            Some(end) => {
+                plain.push(' ');
+                rest = &rest[end + 1..];
+            }
+            None => {
+                if ch.is_alphanumeric() {
+                    plain.extend(ch.to_lowercase());
+                } else {
+                    plain.push(' ');
                 }
+                rest = &rest[ch.len_utf8()..];
             }
         }
     }
-    results
+    plain.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
-pub fn expand_newlines(s: &str) -> String {
+/// Drop the cards of `new` whose deck already has one with the same
+/// `normalized_front`, in `existing` or earlier in `new`, and return how
+/// many were dropped. Imports use it so pasting or importing the same list
+/// twice adds nothing.
+pub fn drop_known_fronts(existing: &[Card], new: &mut Vec<Card>) -> usize {
+    let mut known: HashSet<(String, String)> = existing
+        .iter()
+        .map(|c| (c.deck.clone(), normalized_front(&c.front)))
+        .collect();
+    let before = new.len();
+    new.retain(|c| known.insert((c.deck.clone(), normalized_front(&c.front))));
+    before - new.len()
+}
+
+/// Split a tag list on whitespace or commas, dropping duplicates.
+pub fn parse_tags(s: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in s.split(|c: char| c.is_whitespace() || c == ',') {
+        let tag = tag.trim().trim_start_matches('#');
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Parse the `flags` column: space-separated `suspended` / `flagged` words.
+fn parse_flags(s: &str) -> (bool, bool) {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    (words.contains(&"suspended"), words.contains(&"flagged"))
+}
+
+fn flags_field(card: &Card) -> String {
+    flags_text(card.suspended, card.flagged)
+}
+
+fn flags_text(suspended: bool, flagged: bool) -> String {
+    let mut words = Vec::new();
+    if suspended {
+        words.push("suspended");
+    }
+    if flagged {
+        words.push("flagged");
+    }
+    words.join(" ")
+}
+
+pub fn has_tag(card: &Card, tag: &str) -> bool {
+    card.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+}
+
+/// A token that changes whenever a field the card editor writes does, for
+/// refusing edits made to a copy that has since been saved over. Grading,
+/// suspending and masks leave it alone: an edit keeps those as saved.
+pub fn revision(card: &Card) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut h = std::hash::DefaultHasher::new();
+    (&card.deck, &card.front, &card.back, &card.extra, &card.tags).hash(&mut h);
+    format!("{:016x}", h.finish())
+}
+
+/// Media file names listed in a card's `media` column (`;`-separated),
+/// without the image masks some carry after a `#` (see `crate::occlusion`).
+pub fn media_files(card: &Card) -> Vec<&str> {
+    card.media
+        .split(';')
+        .map(|m| m.split_once('#').map_or(m, |(file, _)| file).trim())
+        .filter(|m| !m.is_empty())
+        .collect()
+}
+
+/// Directory holding media for cards stored in `csv_path`.
+pub fn media_dir(csv_path: &Path) -> PathBuf {
+    csv_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("media")
+}
+
+/// Resolve the escapes card text is stored with: `\n` for a line break and
+/// `\[` / `\]` for literal brackets.
+pub fn expand_escapes(s: &str) -> String {
     s.replace("\\n", "\n")
+        .replace("\\[", "[")
+        .replace("\\]", "]")
+}
+
+/// The old name of `expand_escapes`, from before `\[` and `\]` were escapes.
+#[deprecated(note = "renamed to `expand_escapes`")]
+pub fn expand_newlines(s: &str) -> String {
+    expand_escapes(s)
 }
 
 fn parse_optional_f64(s: &str) -> Option<f64> {
@@ -69,115 +310,659 @@ fn get_field(record: &csv::StringRecord, index: usize) -> String {
     record.get(index).unwrap_or("").to_string()
 }
 
-pub fn load_csv(path: &Path) -> Result<Vec<Card>, String> {
-    let default_deck = path
-        .file_stem()
+/// The deck for rows of `path` that leave theirs blank: the file's name.
+pub fn default_deck(path: &Path) -> String {
+    path.file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("default")
-        .to_string();
+        .to_string()
+}
 
-    let mut reader = csv::ReaderBuilder::new()
-        .flexible(true)
-        .from_path(path)
-        .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+/// A card's `origin`: the file it was imported from and where in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub path: PathBuf,
+    pub line: Option<u64>,
+    pub anchor: Option<String>,
+}
 
-    let mut cards = Vec::new();
-    for result in reader.records() {
-        let record = result.map_err(|e| format!("CSV parse error in {}: {}", path.display(), e))?;
+impl Origin {
+    /// Parse `file:line`, `file#anchor` or `file`, resolving a relative file
+    /// against the directory of `csv`, the card's own file.
+    pub fn parse(origin: &str, csv: &Path) -> Option<Origin> {
+        let origin = origin.trim();
+        if origin.is_empty() {
+            return None;
+        }
+        let (file, line, anchor) = match origin.rsplit_once(':') {
+            Some((file, line)) if !file.is_empty() && line.parse::<u64>().is_ok() => {
+                (file, line.parse().ok(), None)
+            }
+            _ => match origin.rsplit_once('#') {
+                Some((file, anchor)) if !file.is_empty() => (file, None, Some(anchor.to_string())),
+                _ => (origin, None, None),
+            },
+        };
+        let dir = csv.parent().unwrap_or(Path::new(""));
+        Some(Origin {
+            path: dir.join(file),
+            line,
+            anchor,
+        })
+    }
 
-        let deck_raw = get_field(&record, 0);
-        let deck = if deck_raw.trim().is_empty() {
-            default_deck.clone()
-        } else {
-            deck_raw
+    /// How `source`, a file cards saved to `csv` were imported from, is
+    /// written in their origin: relative to the CSV's directory when inside
+    /// it, otherwise absolute.
+    pub fn path_text(source: &Path, csv: &Path) -> String {
+        let Ok(source) = source.canonicalize() else {
+            return source.display().to_string();
+        };
+        let dir = match csv.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
         };
+        let relative = dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| source.strip_prefix(dir).ok().map(Path::to_path_buf));
+        relative.unwrap_or(source).display().to_string()
+    }
+}
 
-        let id_raw = get_field(&record, 4);
-        let id = if id_raw.trim().is_empty() {
-            uuid::Uuid::new_v4().to_string()
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        if let Some(anchor) = &self.anchor {
+            write!(f, "#{anchor}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Build a card from a CSV record. Without `content` the front, back and
+/// media are left empty (see `index_csv`).
+fn card_from_record(
+    record: &csv::StringRecord,
+    layout: &Layout,
+    default_deck: &str,
+    content: bool,
+) -> Card {
+    let deck_raw = layout.field(record, DECK);
+    let deck = if deck_raw.trim().is_empty() {
+        default_deck.to_string()
+    } else {
+        deck_raw
+    };
+
+    let id_raw = layout.field(record, ID);
+    let new = id_raw.trim().is_empty();
+    let id = if new {
+        uuid::Uuid::new_v4().to_string()
+    } else {
+        id_raw
+    };
+    let created = parse_optional_date(&layout.field(record, CREATED))
+        .or_else(|| new.then(crate::clock::today));
+
+    let field = |column| {
+        if content {
+            layout.field(record, column)
         } else {
-            id_raw
-        };
+            String::new()
+        }
+    };
+    let (suspended, flagged) = parse_flags(&layout.field(record, FLAGS));
+    Card {
+        deck,
+        front: field(FRONT),
+        back: field(BACK),
+        extra: field(EXTRA),
+        media: field(MEDIA),
+        origin: field(ORIGIN),
+        id,
+        stability: parse_optional_f64(&layout.field(record, STABILITY)),
+        difficulty: parse_optional_f64(&layout.field(record, DIFFICULTY)),
+        due: parse_optional_date(&layout.field(record, DUE)),
+        last_review: parse_optional_date(&layout.field(record, LAST_REVIEW)),
+        created,
+        tags: parse_tags(&layout.field(record, TAGS)),
+        suspended,
+        flagged,
+        ease: parse_optional_f64(&layout.field(record, EASE)),
+        interval: layout.field(record, INTERVAL).trim().parse().ok(),
+    }
+}
 
-        cards.push(Card {
-            deck,
-            front: get_field(&record, 1),
-            back: get_field(&record, 2),
-            media: get_field(&record, 3),
-            id,
-            stability: parse_optional_f64(&get_field(&record, 5)),
-            difficulty: parse_optional_f64(&get_field(&record, 6)),
-            due: parse_optional_date(&get_field(&record, 7)),
-            last_review: parse_optional_date(&get_field(&record, 8)),
-        });
+/// Where rote's columns sit in a file, found by header name so columns may be
+/// reordered and interleaved with the user's own. A header naming none of
+/// them is taken to be in the standard order.
+struct Layout {
+    /// Column names in file order, with any of rote's missing ones appended.
+    header: Vec<String>,
+    /// Index in `header` of each column of `HEADER`; `None` for an optional
+    /// column the file does not have.
+    columns: [Option<usize>; HEADER.len()],
+}
+
+impl Layout {
+    fn new(header: &csv::StringRecord) -> Layout {
+        let names: Vec<String> = header.iter().map(|h| h.trim().to_lowercase()).collect();
+        let mut header: Vec<String> = header.iter().map(str::to_string).collect();
+        if !HEADER.iter().any(|name| names.iter().any(|n| n == name)) {
+            for (i, name) in HEADER[..OPTIONAL].iter().enumerate() {
+                match header.get_mut(i) {
+                    Some(slot) => *slot = name.to_string(),
+                    None => header.push(name.to_string()),
+                }
+            }
+        }
+        let mut columns = [None; HEADER.len()];
+        for (column, name) in HEADER.iter().enumerate() {
+            let found = header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name));
+            columns[column] = found.or_else(|| {
+                (column < OPTIONAL).then(|| {
+                    header.push(name.to_string());
+                    header.len() - 1
+                })
+            });
+        }
+        Layout { header, columns }
+    }
+
+    fn standard() -> Layout {
+        Layout::new(&csv::StringRecord::from(HEADER[..OPTIONAL].to_vec()))
+    }
+
+    /// Add the optional columns some of `cards` have values for, so old
+    /// files only grow a column once it is used.
+    fn include<'a>(&mut self, cards: impl Iterator<Item = &'a Card> + Clone) {
+        for (column, name) in HEADER.iter().enumerate().skip(OPTIONAL) {
+            let used = |c: &Card| match column {
+                EXTRA => !c.extra.is_empty(),
+                ORIGIN => !c.origin.is_empty(),
+                CREATED => c.created.is_some(),
+                EASE => c.ease.is_some(),
+                _ => c.interval.is_some(),
+            };
+            if self.columns[column].is_none() && cards.clone().any(used) {
+                self.header.push(name.to_string());
+                self.columns[column] = Some(self.header.len() - 1);
+            }
+        }
+    }
+
+    /// Whether the file has columns rote does not know about.
+    fn has_extra(&self) -> bool {
+        self.header.len() > self.columns.iter().flatten().count()
+    }
+
+    fn field(&self, record: &csv::StringRecord, column: usize) -> String {
+        self.columns[column].map_or(String::new(), |i| get_field(record, i))
+    }
+
+    /// `card` in this layout, with other columns taken from `extra`.
+    fn record(&self, card: &Card, extra: Option<&csv::StringRecord>) -> Vec<String> {
+        let mut record: Vec<String> = (0..self.header.len())
+            .map(|i| extra.map(|r| get_field(r, i)).unwrap_or_default())
+            .collect();
+        for (column, value) in card_fields(card).into_iter().enumerate() {
+            if let Some(i) = self.columns[column] {
+                record[i] = value;
+            }
+        }
+        record
+    }
+}
+
+/// Reader positioned after the header row, so `position()` before each
+/// `read_record` is the start of that record.
+fn csv_reader(path: &Path) -> Result<(csv::Reader<std::fs::File>, Layout)> {
+    let file = std::fs::File::open(path).map_err(Error::io("open", path))?;
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(file);
+    let layout = Layout::new(reader.headers().map_err(Error::csv(path))?);
+    Ok((reader, layout))
+}
+
+pub fn load_csv(path: &Path) -> Result<Vec<Card>> {
+    let default_deck = default_deck(path);
+    let (mut reader, layout) = csv_reader(path)?;
+    let mut cards = Vec::new();
+    for result in reader.records() {
+        let record = result.map_err(Error::csv(path))?;
+        cards.push(card_from_record(&record, &layout, &default_deck, true));
     }
     Ok(cards)
 }
 
-pub fn save_csv(path: &Path, cards: &[Card]) -> Result<(), String> {
-    let mut writer = csv::Writer::from_path(path)
-        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+/// Like `load_csv`, with the line each card's row starts on.
+pub fn load_csv_lines(path: &Path) -> Result<Vec<(u64, Card)>> {
+    let default_deck = default_deck(path);
+    let (layout, records) = records_with_lines(path)?;
+    Ok(records
+        .iter()
+        .map(|(line, record)| {
+            let card = card_from_record(record, &layout, &default_deck, true);
+            (*line, card)
+        })
+        .collect())
+}
+
+/// The dates in `path` that do not parse, and so load as blank: the line,
+/// column and value of each.
+pub fn malformed_dates(path: &Path) -> Result<Vec<(u64, &'static str, String)>> {
+    let (layout, records) = records_with_lines(path)?;
+    let mut out = Vec::new();
+    for (line, record) in &records {
+        for column in [DUE, LAST_REVIEW, CREATED] {
+            let value = layout.field(record, column);
+            if !value.trim().is_empty() && parse_optional_date(&value).is_none() {
+                out.push((*line, HEADER[column], value));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Every record of `path` with the line it starts on.
+fn records_with_lines(path: &Path) -> Result<(Layout, Vec<(u64, csv::StringRecord)>)> {
+    let bytes = std::fs::read(path).map_err(Error::io("read", path))?;
+    let (mut reader, layout) = csv_reader(path)?;
+    let mut records = Vec::new();
+    loop {
+        let position = reader.position();
+        // A position between `\r` and `\n` has not counted that line yet.
+        let line = position.line() + u64::from(bytes.get(position.byte() as usize) == Some(&b'\n'));
+        let mut record = csv::StringRecord::new();
+        let more = reader.read_record(&mut record).map_err(Error::csv(path))?;
+        if !more {
+            break;
+        }
+        records.push((line, record));
+    }
+    Ok((layout, records))
+}
+
+/// Read the front, back, extra, media and origin of the record at `offset` into `card`.
+pub fn read_content(path: &Path, offset: u64, card: &mut Card) -> Result<()> {
+    use std::io::{Seek, SeekFrom};
+    let (_, layout) = csv_reader(path)?;
+    let mut file = std::fs::File::open(path).map_err(Error::io("open", path))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(Error::io("read", path))?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+    let mut record = csv::StringRecord::new();
+    if !reader.read_record(&mut record).map_err(Error::csv(path))? {
+        return Err(Error::NoRecord {
+            path: path.to_path_buf(),
+            offset,
+        });
+    }
+    card.front = layout.field(&record, FRONT);
+    card.back = layout.field(&record, BACK);
+    card.extra = layout.field(&record, EXTRA);
+    card.media = layout.field(&record, MEDIA);
+    card.origin = layout.field(&record, ORIGIN);
+    Ok(())
+}
+
+const HEADER: [&str; 16] = [
+    "deck",
+    "front",
+    "back",
+    "media",
+    "id",
+    "stability",
+    "difficulty",
+    "due",
+    "last_review",
+    "tags",
+    "flags",
+    "extra",
+    "origin",
+    "created",
+    "ease",
+    "interval",
+];
 
+const DECK: usize = 0;
+const FRONT: usize = 1;
+const BACK: usize = 2;
+const MEDIA: usize = 3;
+const ID: usize = 4;
+const STABILITY: usize = 5;
+const DIFFICULTY: usize = 6;
+const DUE: usize = 7;
+const LAST_REVIEW: usize = 8;
+const TAGS: usize = 9;
+const FLAGS: usize = 10;
+const EXTRA: usize = 11;
+const ORIGIN: usize = 12;
+const CREATED: usize = 13;
+const EASE: usize = 14;
+const INTERVAL: usize = 15;
+/// Columns from here on are only written to files that use them.
+const OPTIONAL: usize = EXTRA;
+
+/// `card`'s value for each column of `HEADER`.
+fn card_fields(card: &Card) -> [String; HEADER.len()] {
+    [
+        card.deck.clone(),
+        card.front.clone(),
+        card.back.clone(),
+        card.media.clone(),
+        card.id.clone(),
+        card.stability.map_or(String::new(), |v| format!("{v:.3}")),
+        card.difficulty.map_or(String::new(), |v| format!("{v:.3}")),
+        card.due
+            .map_or(String::new(), |d| d.format("%Y-%m-%d").to_string()),
+        card.last_review
+            .map_or(String::new(), |d| d.format("%Y-%m-%d").to_string()),
+        card.tags.join(" "),
+        flags_field(card),
+        card.extra.clone(),
+        card.origin.clone(),
+        card.created
+            .map_or(String::new(), |d| d.format("%Y-%m-%d").to_string()),
+        card.ease.map_or(String::new(), |v| format!("{v:.3}")),
+        card.interval.map_or(String::new(), |v| v.to_string()),
+    ]
+}
+
+/// The layout of the file at `path`, if it exists, and the records of any
+/// columns rote does not know about by card id, so they survive a rewrite.
+fn existing_layout(path: &Path) -> Result<(Layout, HashMap<String, csv::StringRecord>)> {
+    if !path.exists() {
+        return Ok((Layout::standard(), HashMap::new()));
+    }
+    let (mut reader, layout) = csv_reader(path)?;
+    let mut extra = HashMap::new();
+    if layout.has_extra() {
+        for result in reader.records() {
+            let record = result.map_err(Error::csv(path))?;
+            extra.insert(layout.field(&record, ID), record);
+        }
+    }
+    Ok((layout, extra))
+}
+
+fn csv_writer(path: &Path, layout: &Layout) -> Result<csv::Writer<Vec<u8>>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
     writer
-        .write_record([
-            "deck",
-            "front",
-            "back",
-            "media",
-            "id",
-            "stability",
-            "difficulty",
-            "due",
-            "last_review",
-        ])
-        .map_err(|e| format!("write error: {e}"))?;
+        .write_record(&layout.header)
+        .map_err(Error::csv_write(path))?;
+    Ok(writer)
+}
 
+/// Write `cards` to `path`. An existing file keeps its column order and any
+/// columns of its own; `git_friendly` keeps its unchanged rows and values
+/// byte for byte too, so files under version control get minimal diffs
+/// (`--git-friendly`).
+pub fn save_csv(path: &Path, cards: &[Card], git_friendly: bool) -> Result<()> {
+    if git_friendly && path.exists() {
+        return save_minimal(path, cards);
+    }
+    let (mut layout, extra) = existing_layout(path)?;
+    layout.include(cards.iter());
+    let mut writer = csv_writer(path, &layout)?;
     for card in cards {
         writer
-            .write_record([
-                &card.deck,
-                &card.front,
-                &card.back,
-                &card.media,
-                &card.id,
-                &card.stability.map_or(String::new(), |v| format!("{v:.3}")),
-                &card.difficulty.map_or(String::new(), |v| format!("{v:.3}")),
-                &card
-                    .due
-                    .map_or(String::new(), |d| d.format("%Y-%m-%d").to_string()),
-                &card
-                    .last_review
-                    .map_or(String::new(), |d| d.format("%Y-%m-%d").to_string()),
-            ])
-            .map_err(|e| format!("write error: {e}"))?;
-    }
-
-    writer.flush().map_err(|e| format!("flush error: {e}"))?;
-    Ok(())
+            .write_record(layout.record(card, extra.get(&card.id)))
+            .map_err(Error::csv_write(path))?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| Error::io("write", path)(e.into_error()))?;
+    replace(path, &bytes)
+}
+
+/// Replace `path` with `bytes` by way of a synced temporary file beside it,
+/// so a crash or a full disk partway through leaves the old deck whole.
+fn replace(path: &Path, bytes: &[u8]) -> Result<()> {
+    use std::io::Write;
+    let tmp = path.with_extension("csv.tmp");
+    let write = || {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(Error::io("write", &tmp)(e));
+    }
+    std::fs::rename(&tmp, path).map_err(Error::io("replace", path))
+}
+
+/// Rewrite `path` with `cards`, copying the old bytes of every row whose card
+/// is unchanged and, in changed rows, of every value that still means the
+/// same thing (`2.5` for a stability of 2.500, an empty deck for the file's
+/// default).
+fn save_minimal(path: &Path, cards: &[Card]) -> Result<()> {
+    let cards: Vec<&Card> = cards.iter().collect();
+    index::save(path, &cards, &mut Index::default(), true)
+}
+
+/// A value of column `kind` as rote would write it.
+fn normalize(kind: usize, value: &str, default_deck: &str) -> String {
+    let date = |v: &str| {
+        parse_optional_date(v).map_or(String::new(), |d| d.format("%Y-%m-%d").to_string())
+    };
+    match kind {
+        DECK if value.trim().is_empty() => default_deck.to_string(),
+        STABILITY | DIFFICULTY | EASE => {
+            parse_optional_f64(value).map_or(String::new(), |v| format!("{v:.3}"))
+        }
+        DUE | LAST_REVIEW | CREATED => date(value),
+        INTERVAL => value
+            .trim()
+            .parse::<u32>()
+            .map_or(String::new(), |v| v.to_string()),
+        TAGS => parse_tags(value).join(" "),
+        FLAGS => {
+            let (suspended, flagged) = parse_flags(value);
+            flags_text(suspended, flagged)
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Append a row copied from the old file, ending it like the others. The
+/// reader's positions can fall between `\r` and `\n`, so either end of
+/// `line` may carry part of a line break.
+fn push_line(out: &mut Vec<u8>, line: &[u8], crlf: bool) {
+    let is_break = |b: &u8| *b == b'\r' || *b == b'\n';
+    let start = line.iter().position(|b| !is_break(b)).unwrap_or(line.len());
+    let end = line
+        .iter()
+        .rposition(|b| !is_break(b))
+        .map_or(start, |i| i + 1);
+    out.extend_from_slice(&line[start..end]);
+    out.extend_from_slice(if crlf { b"\r\n" } else { b"\n" });
+}
+
+/// One CSV row, quoted only where needed.
+fn encode(fields: &[String], crlf: bool) -> csv::Result<Vec<u8>> {
+    let terminator = if crlf {
+        csv::Terminator::CRLF
+    } else {
+        csv::Terminator::Any(b'\n')
+    };
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(terminator)
+        .from_writer(Vec::new());
+    writer.write_record(fields)?;
+    Ok(writer.into_inner().map_err(|e| e.into_error())?)
+}
+
+/// Directory for rote's own bookkeeping files (journal, logs) for the
+/// collection rooted at the first path argument: `.rote` beside the decks,
+/// unless `--data-dir` chose another (see `OpenOptions::data_dir`).
+pub fn data_dir(paths: &[String]) -> PathBuf {
+    let first = paths.first().map_or(".", String::as_str);
+    if is_glob(first) {
+        return glob_root(first).join(".rote");
+    }
+    let first = PathBuf::from(first);
+    let root = if first.is_dir() {
+        first
+    } else {
+        first.parent().map(Path::to_path_buf).unwrap_or_default()
+    };
+    root.join(".rote")
+}
+
+/// Glob filters applied to discovered files. Patterns without a `/` match the
+/// file name; others match the path relative to the directory argument.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl PathFilter {
+    pub fn include(&mut self, pattern: &str) -> Result<()> {
+        self.include.push(parse_pattern(pattern)?);
+        Ok(())
+    }
+
+    pub fn exclude(&mut self, pattern: &str) -> Result<()> {
+        self.exclude.push(parse_pattern(pattern)?);
+        Ok(())
+    }
+
+    fn allows(&self, rel: &Path) -> bool {
+        let matches = |p: &glob::Pattern| {
+            if p.as_str().contains('/') {
+                p.matches_path_with(rel, GLOB_OPTIONS)
+            } else {
+                rel.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| p.matches_with(n, GLOB_OPTIONS))
+            }
+        };
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+const GLOB_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: true,
+};
+
+fn parse_pattern(pattern: &str) -> Result<glob::Pattern> {
+    glob::Pattern::new(pattern).map_err(|source| Error::Pattern {
+        pattern: pattern.to_string(),
+        source,
+    })
+}
+
+fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// The literal directory prefix of a glob, e.g. `decks` for `decks/**/*.csv`.
+fn glob_root(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| !is_glob(&c.as_os_str().to_string_lossy()))
+        .collect()
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+}
+
+/// Hidden files and directories (including `.rote` and LibreOffice's
+/// `.~lock` files) and editor backups are never decks.
+fn is_ignored(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    name.starts_with('.')
+        || name.starts_with('#')
+        || name.starts_with("~$")
+        || name.ends_with('~')
+        || name.ends_with('#')
+        || name.ends_with(".bak")
 }
 
 pub fn discover_files(paths: &[String]) -> Vec<PathBuf> {
+    discover_files_filtered(paths, &PathFilter::default())
+}
+
+/// CSV files named by `paths`: files, directories (searched recursively,
+/// following symlinks) and glob patterns such as `decks/**/*.csv`.
+pub fn discover_files_filtered(paths: &[String], filter: &PathFilter) -> Vec<PathBuf> {
     let mut files = Vec::new();
+    let mut visited = HashSet::new();
     for p in paths {
+        if is_glob(p) {
+            let Ok(matches) = glob::glob_with(p, GLOB_OPTIONS) else {
+                continue;
+            };
+            let root = glob_root(p);
+            for path in matches.flatten() {
+                if path.is_dir() {
+                    collect_csv_recursive(&path, &path, filter, &mut visited, &mut files);
+                } else if is_csv(&path) && !is_ignored(&path) {
+                    let rel = path.strip_prefix(&root).unwrap_or(&path).to_path_buf();
+                    if filter.allows(&rel) {
+                        files.push(path);
+                    }
+                }
+            }
+            continue;
+        }
         let path = PathBuf::from(p);
         if path.is_dir() {
-            collect_csv_recursive(&path, &mut files);
-        } else if path.extension().and_then(|e| e.to_str()) == Some("csv") {
+            collect_csv_recursive(&path, &path, filter, &mut visited, &mut files);
+        } else if is_csv(&path) && filter.allows(Path::new(path.file_name().unwrap_or_default())) {
             files.push(path);
         }
     }
+    let mut seen = HashSet::new();
+    files.retain(|f| seen.insert(std::fs::canonicalize(f).unwrap_or_else(|_| f.clone())));
     files
 }
 
-fn collect_csv_recursive(dir: &Path, files: &mut Vec<PathBuf>) {
+fn collect_csv_recursive(
+    root: &Path,
+    dir: &Path,
+    filter: &PathFilter,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) {
+    // Symlinked directories are followed, but each real directory only once.
+    let Ok(real) = std::fs::canonicalize(dir) else {
+        return;
+    };
+    if !visited.insert(real) {
+        return;
+    }
     let Ok(entries) = std::fs::read_dir(dir) else {
         return;
     };
-    for entry in entries.flatten() {
-        let path = entry.path();
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        if is_ignored(&path) {
+            continue;
+        }
         if path.is_dir() {
-            collect_csv_recursive(&path, files);
-        } else if path.extension().and_then(|e| e.to_str()) == Some("csv") {
+            collect_csv_recursive(root, &path, filter, visited, files);
+        } else if is_csv(&path) && filter.allows(path.strip_prefix(root).unwrap_or(&path)) {
             files.push(path);
         }
     }
@@ -210,9 +995,74 @@ mod tests {
     }
 
     #[test]
-    fn expand_newlines_works() {
-        assert_eq!(expand_newlines("line1\\nline2"), "line1\nline2");
-        assert_eq!(expand_newlines("no newlines"), "no newlines");
+    fn tags_parsing() {
+        assert_eq!(
+            parse_tags("anatomy, #heart  Anatomy cardio"),
+            vec!["anatomy", "heart", "cardio"]
+        );
+        assert!(parse_tags("  ").is_empty());
+    }
+
+    #[test]
+    fn normalized_fronts_match_across_formatting() {
+        let same = [
+            "What is the capital of France?",
+            "  what is the **capital** of\\nFrance ",
+            "What is the <b>capital</b>&nbsp;of [France::country]?",
+        ];
+        for front in same {
+            assert_eq!(normalized_front(front), "what is the capital of france");
+        }
+        assert_eq!(normalized_front("¿Qué ES el «Éxito»?"), "qué es el éxito");
+        assert_eq!(normalized_front("Привет, МИР!"), "привет мир");
+        assert_eq!(normalized_front("x < 2 > y & z;"), "x 2 y z");
+        assert_ne!(normalized_front("café"), normalized_front("cafe"));
+
+        let card = |deck: &str, front: &str| Card {
+            deck: deck.into(),
+            front: front.into(),
+            ..Default::default()
+        };
+        let existing = [card("geo", "Capital of France?")];
+        let mut new = vec![
+            card("geo", "capital of <i>France</i>"),
+            card("fr", "Capital of France?"),
+            card("geo", "Capital of Spain?"),
+            card("geo", "capital of spain"),
+        ];
+        assert_eq!(drop_known_fronts(&existing, &mut new), 2);
+        let kept: Vec<(&str, &str)> = new
+            .iter()
+            .map(|c| (c.deck.as_str(), c.front.as_str()))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![("fr", "Capital of France?"), ("geo", "Capital of Spain?")]
+        );
+    }
+
+    #[test]
+    fn media_files_split() {
+        let card = Card {
+            deck: "d".into(),
+            front: "q".into(),
+            back: "a".into(),
+            media: "a.png; b.mp3;;".into(),
+            id: "1".into(),
+            ..Default::default()
+        };
+        assert_eq!(media_files(&card), vec!["a.png", "b.mp3"]);
+        assert_eq!(
+            media_dir(Path::new("decks/bio.csv")),
+            PathBuf::from("decks/media")
+        );
+    }
+
+    #[test]
+    fn expand_escapes_works() {
+        assert_eq!(expand_escapes("line1\\nline2"), "line1\nline2");
+        assert_eq!(expand_escapes("no newlines"), "no newlines");
+        assert_eq!(expand_escapes("a\\[i\\]"), "a[i]");
     }
 
     #[test]
@@ -224,15 +1074,19 @@ mod tests {
             deck: "math".to_string(),
             front: "What is 2+2?".to_string(),
             back: "4".to_string(),
-            media: String::new(),
             id: "test-id-1".to_string(),
             stability: Some(3.173),
             difficulty: Some(5.5),
             due: NaiveDate::from_ymd_opt(2025, 6, 15),
             last_review: NaiveDate::from_ymd_opt(2025, 6, 1),
+            tags: vec!["arithmetic".to_string(), "easy".to_string()],
+            flagged: true,
+            ease: Some(2.36),
+            interval: Some(15),
+            ..Default::default()
         }];
 
-        save_csv(&path, &cards).unwrap();
+        save_csv(&path, &cards, false).unwrap();
         let loaded = load_csv(&path).unwrap();
         assert_eq!(loaded.len(), 1);
         assert_eq!(loaded[0].deck, "math");
@@ -243,6 +1097,11 @@ mod tests {
         assert!((loaded[0].difficulty.unwrap() - 5.5).abs() < 0.01);
         assert_eq!(loaded[0].due, NaiveDate::from_ymd_opt(2025, 6, 15));
         assert_eq!(loaded[0].last_review, NaiveDate::from_ymd_opt(2025, 6, 1));
+        assert_eq!(loaded[0].tags, vec!["arithmetic", "easy"]);
+        assert!(!loaded[0].suspended);
+        assert!(loaded[0].flagged);
+        assert_eq!(loaded[0].ease, Some(2.36));
+        assert_eq!(loaded[0].interval, Some(15));
     }
 
     #[test]
@@ -264,6 +1123,250 @@ mod tests {
         assert_eq!(cards[0].front, "What is Rust?");
         assert!(!cards[0].id.is_empty());
         assert!(cards[0].stability.is_none());
+        assert!(cards[0].tags.is_empty());
+    }
+
+    #[test]
+    fn extra_column_is_added_once_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bio.csv");
+        std::fs::write(&path, "deck,front,back\nbio,ATP,energy\n").unwrap();
+        let mut cards = load_csv(&path).unwrap();
+        save_csv(&path, &cards, false).unwrap();
+        let header = std::fs::read_to_string(&path).unwrap();
+        assert!(!header.lines().next().unwrap().contains("extra"));
+
+        cards[0].extra = "adenosine triphosphate".into();
+        save_csv(&path, &cards, false).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.lines().next().unwrap().ends_with(",extra"));
+        assert_eq!(load_csv(&path).unwrap()[0].extra, "adenosine triphosphate");
+    }
+
+    #[test]
+    fn created_is_set_for_new_rows_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bio.csv");
+        std::fs::write(
+            &path,
+            "deck,front,back,id\nbio,ATP,energy,c1\nbio,DNA,genes,\n",
+        )
+        .unwrap();
+        let cards = load_csv(&path).unwrap();
+        assert_eq!(cards[0].created, None);
+        assert_eq!(cards[1].created, Some(crate::clock::today()));
+
+        save_csv(&path, &cards, false).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.lines().next().unwrap().ends_with(",created"));
+        let reloaded = load_csv(&path).unwrap();
+        assert_eq!(reloaded[0].created, None);
+        assert_eq!(reloaded[1].created, cards[1].created);
+    }
+
+    #[test]
+    fn revision_follows_edits_not_reviews() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bio.csv");
+        std::fs::write(&path, "deck,front,back\nbio,ATP,energy\n").unwrap();
+        let mut card = load_csv(&path).unwrap().remove(0);
+        let opened = revision(&card);
+        card.due = NaiveDate::from_ymd_opt(2026, 10, 20);
+        card.suspended = true;
+        assert_eq!(revision(&card), opened);
+        card.back = "energy currency".into();
+        assert_ne!(revision(&card), opened);
+    }
+
+    #[test]
+    fn origin_points_back_to_the_imported_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bio.csv");
+        std::fs::create_dir(dir.path().join("notes")).unwrap();
+        let source = dir.path().join("notes").join("cells.tsv");
+        std::fs::write(&source, "ATP\tenergy\n").unwrap();
+        assert_eq!(Origin::path_text(&source, &path), "notes/cells.tsv");
+
+        std::fs::write(&path, "deck,front,back\nbio,ATP,energy\n").unwrap();
+        let mut cards = load_csv(&path).unwrap();
+        cards[0].origin = "notes/cells.tsv:1".into();
+        save_csv(&path, &cards, false).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.lines().next().unwrap().ends_with(",origin,created"));
+        let origin = load_csv(&path).unwrap()[0].origin.clone();
+        assert_eq!(
+            Origin::parse(&origin, &path),
+            Some(Origin {
+                path: dir.path().join("notes/cells.tsv"),
+                line: Some(1),
+                anchor: None,
+            })
+        );
+
+        let anchored = Origin::parse("Biology.md#Cell respiration", Path::new("bio.csv")).unwrap();
+        assert_eq!(anchored.to_string(), "Biology.md#Cell respiration");
+        assert_eq!(
+            Origin::parse("C:/notes.md:12", Path::new("x.csv"))
+                .unwrap()
+                .line,
+            Some(12)
+        );
+        assert_eq!(Origin::parse(" ", &path), None);
+    }
+
+    #[test]
+    fn csv_columns_by_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sheet.csv");
+        std::fs::write(
+            &path,
+            "Front,Notes,Back,id,Deck\nWhat is Rust?,ask Sam,A language,a,lang\nsecond,,two,b,\n",
+        )
+        .unwrap();
+        let mut cards = load_csv(&path).unwrap();
+        assert_eq!(cards[0].front, "What is Rust?");
+        assert_eq!(cards[0].back, "A language");
+        assert_eq!(cards[0].deck, "lang");
+        assert_eq!(cards[1].deck, "sheet");
+
+        cards[0].due = NaiveDate::from_ymd_opt(2025, 6, 15);
+        cards.swap(0, 1);
+        save_csv(&path, &cards, false).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let mut lines = saved.lines();
+        assert_eq!(
+            lines.next(),
+            Some("Front,Notes,Back,id,Deck,media,stability,difficulty,due,last_review,tags,flags")
+        );
+        assert_eq!(lines.next(), Some("second,,two,b,sheet,,,,,,,"));
+        assert_eq!(
+            lines.next(),
+            Some("What is Rust?,ask Sam,A language,a,lang,,,,2025-06-15,,,")
+        );
+
+        // Low-memory saves copy the other columns along with the content.
+        let mut index = Index::default();
+        let mut cards = index_csv(&path, &mut index, true).unwrap();
+        cards[1].tags = vec!["x".into()];
+        save_csv_indexed(&path, &cards.iter().collect::<Vec<_>>(), &mut index, false).unwrap();
+        let saved = load_csv(&path).unwrap();
+        assert_eq!(saved[1].front, "What is Rust?");
+        assert!(std::fs::read_to_string(&path).unwrap().contains("ask Sam"));
+    }
+
+    #[test]
+    fn minimal_saves_keep_unchanged_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("git.csv");
+        let original = "deck,front,back,media,id,stability,difficulty,due,last_review,tags,flags\r\n\
+                        ,\"quoted\",b,,a,2.5,5,2025-06-01,,x  y,\r\n\
+                        d,second,b,,b,1,5,2025-06-01,,,\r\n";
+        std::fs::write(&path, original).unwrap();
+
+        // Untouched cards are copied as they were.
+        let mut cards = load_csv(&path).unwrap();
+        save_minimal(&path, &cards).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+
+        cards[1].due = NaiveDate::from_ymd_opt(2025, 7, 1);
+        cards.push(Card {
+            id: "c".into(),
+            front: "new, card".into(),
+            ..cards[1].clone()
+        });
+        save_minimal(&path, &cards).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = saved.split("\r\n").collect();
+        assert_eq!(lines[1], ",\"quoted\",b,,a,2.5,5,2025-06-01,,x  y,");
+        assert_eq!(lines[2], "d,second,b,,b,1,5,2025-07-01,,,");
+        assert_eq!(lines[3], "d,\"new, card\",b,,c,1.000,5.000,2025-07-01,,,");
+
+        // Low-memory cards keep their content and get their new rows.
+        let mut index = Index::default();
+        let cards = index_csv(&path, &mut index, true).unwrap();
+        let rest: Vec<&Card> = cards[1..].iter().collect();
+        index::save(&path, &rest, &mut index, true).unwrap();
+        let mut card = cards[2].clone();
+        read_content(&path, index.lazy(&path, "c").unwrap(), &mut card).unwrap();
+        assert_eq!(card.front, "new, card");
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("quoted"));
+    }
+
+    #[test]
+    fn failed_saves_leave_the_deck_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("es.csv");
+        let original = "deck,front,back,media,id,stability,difficulty,due,last_review,tags,flags\n\
+                        es,uno,one,,a,1.000,5.000,2025-06-01,,,\n\
+                        es,dos,two,,b,1.000,5.000,2025-06-01,,,\n";
+        std::fs::write(&path, original).unwrap();
+        let mut index = Index::default();
+        let mut cards = index_csv(&path, &mut index, false).unwrap();
+        // The temporary file cannot be created, as on a full disk.
+        std::fs::create_dir_all(path.with_extension("csv.tmp").join("busy")).unwrap();
+
+        // A patched row, a rewrite with a new row, and a plain save.
+        cards[0].due = NaiveDate::from_ymd_opt(2025, 7, 1);
+        let refs: Vec<&Card> = cards.iter().collect();
+        assert!(save_csv_indexed(&path, &refs, &mut index, false).is_err());
+        cards[1].front = "dos, two".into();
+        let refs: Vec<&Card> = cards.iter().collect();
+        assert!(save_csv_indexed(&path, &refs, &mut index, true).is_err());
+        assert!(save_csv(&path, &cards, false).is_err());
+        assert!(save_csv(&path, &cards, true).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn index_reads_content_lazily() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.csv");
+        {
+            let mut f = std::fs::File::create(&path).unwrap();
+            writeln!(
+                f,
+                "deck,front,back,media,id,stability,difficulty,due,last_review"
+            )
+            .unwrap();
+            writeln!(f, "d,\"multi\nline, front\",back one,,a,,,,").unwrap();
+            writeln!(f, "d,second,back two,,b,,,,").unwrap();
+        }
+        let mut index = Index::default();
+        let mut cards = index_csv(&path, &mut index, true).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert!(cards[0].front.is_empty() && cards[1].back.is_empty());
+
+        read_content(&path, index.lazy(&path, "b").unwrap(), &mut cards[1]).unwrap();
+        assert_eq!(cards[1].back, "back two");
+
+        // Edit one card fully, reschedule the other without loading it.
+        index.set_loaded(&path, "b");
+        cards[1].front = "second, edited".into();
+        cards[0].due = NaiveDate::from_ymd_opt(2025, 1, 2);
+        save_csv_indexed(&path, &cards.iter().collect::<Vec<_>>(), &mut index, false).unwrap();
+
+        let saved = load_csv(&path).unwrap();
+        assert_eq!(saved[0].front, "multi\nline, front");
+        assert_eq!(saved[0].due, NaiveDate::from_ymd_opt(2025, 1, 2));
+        assert_eq!(saved[1].front, "second, edited");
+        read_content(&path, index.lazy(&path, "a").unwrap(), &mut cards[0]).unwrap();
+        assert_eq!(cards[0].back, "back one");
+    }
+
+    #[test]
+    fn load_csv_lines_counts_multiline_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("d.csv");
+        for eol in ["\n", "\r\n"] {
+            let csv = ["deck,front,back", "d,a,b", "d,\"x\ny\",c", "d,e,f", ""].join(eol);
+            std::fs::write(&path, csv).unwrap();
+            let lines: Vec<u64> = load_csv_lines(&path)
+                .unwrap()
+                .into_iter()
+                .map(|(line, _)| line)
+                .collect();
+            assert_eq!(lines, vec![2, 3, 5], "{eol:?}");
+        }
     }
 
     #[test]
@@ -279,4 +1382,68 @@ mod tests {
         assert_eq!(files.len(), 2);
         assert!(files.iter().all(|f| f.extension().unwrap() == "csv"));
     }
+
+    #[test]
+    fn discover_files_skips_hidden_and_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".rote")).unwrap();
+        std::fs::write(root.join(".rote/x.csv"), "").unwrap();
+        std::fs::write(root.join("A.CSV"), "").unwrap();
+        std::fs::write(root.join(".~lock.A.CSV#"), "").unwrap();
+        std::fs::write(root.join("#b.csv#"), "").unwrap();
+        std::fs::write(root.join("c.csv.bak"), "").unwrap();
+
+        let files = discover_files(&[root.to_str().unwrap().to_string()]);
+        assert_eq!(files, vec![root.join("A.CSV")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discover_files_follows_symlinks_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("real")).unwrap();
+        std::fs::write(root.join("real/a.csv"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("real"), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(root, root.join("real/loop")).unwrap();
+
+        let files = discover_files(&[root.to_str().unwrap().to_string()]);
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn discover_files_globs_and_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("lang/archive")).unwrap();
+        std::fs::write(root.join("lang/es.csv"), "").unwrap();
+        std::fs::write(root.join("lang/fr.csv"), "").unwrap();
+        std::fs::write(root.join("lang/archive/de.csv"), "").unwrap();
+        std::fs::write(root.join("top.csv"), "").unwrap();
+
+        let pattern = format!("{}/lang/**/*.csv", root.display());
+        assert_eq!(discover_files(std::slice::from_ref(&pattern)).len(), 3);
+
+        let mut filter = PathFilter::default();
+        filter.exclude("**/archive/**").unwrap();
+        filter.exclude("fr.csv").unwrap();
+        let files = discover_files_filtered(&[root.to_str().unwrap().to_string()], &filter);
+        assert_eq!(files, vec![root.join("lang/es.csv"), root.join("top.csv")]);
+
+        let mut filter = PathFilter::default();
+        filter.include("*.csv").unwrap();
+        filter.include("lang/*").unwrap();
+        filter.exclude("top*").unwrap();
+        let files = discover_files_filtered(&[root.to_str().unwrap().to_string()], &filter);
+        assert_eq!(files.len(), 3);
+    }
+
+    #[test]
+    fn data_dir_of_glob() {
+        assert_eq!(
+            data_dir(&["decks/**/*.csv".to_string()]),
+            PathBuf::from("decks/.rote")
+        );
+    }
 }
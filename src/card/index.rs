@@ -0,0 +1,507 @@
+// Row bookkeeping for processes that save the same files again and again
+// (`rote serve`, `--low-memory` drills): where each card's row sits and a
+// fingerprint of the card it held. Saves copy the rows of unchanged cards byte
+// for byte and encode only the rest, patching a copy of the old bytes when no
+// row changes length, so grading one card in a huge file encodes one row.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{
+    BACK, Card, EXTRA, FRONT, ID, Layout, MEDIA, ORIGIN, card_from_record, csv_reader,
+    default_deck, encode, get_field, normalize, push_line, replace,
+};
+use crate::error::{Error, Result};
+
+/// Where a card's row lies in its file and what it held.
+#[derive(Debug, Clone, Copy)]
+struct Row {
+    start: u64,
+    end: u64,
+    /// `fingerprint` of the card as last read or written, if known.
+    hash: Option<u64>,
+    /// Whether its front, back and media are still only on disk.
+    lazy: bool,
+}
+
+impl Row {
+    fn range(&self) -> Range<usize> {
+        self.start as usize..self.end as usize
+    }
+}
+
+#[derive(Default)]
+struct FileRows {
+    rows: HashMap<String, Row>,
+    /// An id more than one row has, which makes the file unsafe to save.
+    duplicate: Option<String>,
+    /// Size and modification time when last read or written, to notice
+    /// edits made behind our back.
+    stamp: Option<(u64, SystemTime)>,
+}
+
+/// The rows of every indexed file.
+#[derive(Default)]
+pub struct Index {
+    files: HashMap<PathBuf, FileRows>,
+}
+
+impl Index {
+    /// Where card `id`'s row starts in `path`, if its content has not been
+    /// read yet.
+    pub fn lazy(&self, path: &Path, id: &str) -> Option<u64> {
+        let row = self.files.get(path)?.rows.get(id)?;
+        row.lazy.then_some(row.start)
+    }
+
+    /// Card `id`'s content is in memory from now on and saved from there.
+    pub fn set_loaded(&mut self, path: &Path, id: &str) {
+        if let Some(row) = self.files.get_mut(path).and_then(|f| f.rows.get_mut(id)) {
+            row.lazy = false;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+fn stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+fn fingerprint(card: &Card) -> u64 {
+    let mut h = std::hash::DefaultHasher::new();
+    (
+        &card.deck,
+        &card.front,
+        &card.back,
+        &card.extra,
+        &card.media,
+        &card.origin,
+    )
+        .hash(&mut h);
+    card.id.hash(&mut h);
+    (
+        card.stability.map(f64::to_bits),
+        card.difficulty.map(f64::to_bits),
+        card.ease.map(f64::to_bits),
+        card.interval,
+    )
+        .hash(&mut h);
+    (card.due, card.last_review, &card.tags).hash(&mut h);
+    (card.suspended, card.flagged).hash(&mut h);
+    h.finish()
+}
+
+/// Like `load_csv`, but records every card's row in `index`. With `lazy` the
+/// front, back and media are left empty for `read_content` to fill in on
+/// demand, so huge collections fit in little memory.
+pub fn index_csv(path: &Path, index: &mut Index, lazy: bool) -> Result<Vec<Card>> {
+    let stamp = stamp(path);
+    let default_deck = default_deck(path);
+    let (mut reader, layout) = csv_reader(path)?;
+    let mut record = csv::StringRecord::new();
+    let mut cards = Vec::new();
+    let mut rows = HashMap::new();
+    let mut duplicate = None;
+    loop {
+        let start = reader.position().byte();
+        let more = reader.read_record(&mut record).map_err(Error::csv(path))?;
+        if !more {
+            break;
+        }
+        let card = card_from_record(&record, &layout, &default_deck, !lazy);
+        let row = Row {
+            start,
+            end: reader.position().byte(),
+            hash: Some(fingerprint(&card)),
+            lazy,
+        };
+        if rows.insert(card.id.clone(), row).is_some() && duplicate.is_none() {
+            duplicate = Some(card.id.clone());
+        }
+        cards.push(card);
+    }
+    index.files.insert(
+        path.to_path_buf(),
+        FileRows {
+            rows,
+            duplicate,
+            stamp,
+        },
+    );
+    Ok(cards)
+}
+
+/// Write `cards` to `path`, a file read with `index_csv`. Only cards changed
+/// since they were read or last saved are encoded; the other rows are copied.
+/// With `git_friendly`, changed rows keep the old text of values that still
+/// mean the same thing.
+pub fn save_csv_indexed(
+    path: &Path,
+    cards: &[&Card],
+    index: &mut Index,
+    git_friendly: bool,
+) -> Result<()> {
+    save(path, cards, index, git_friendly)
+}
+
+/// A row of the new file.
+enum Line {
+    /// Copied from this range of the old file.
+    Old(Range<usize>),
+    /// Encoded afresh, with its line break.
+    New(Vec<u8>),
+}
+
+pub(super) fn save(path: &Path, cards: &[&Card], index: &mut Index, minimal: bool) -> Result<()> {
+    let file = index.files.entry(path.to_path_buf()).or_default();
+    let parse_err = |e: csv::Error| Error::csv(path)(e);
+    let stamp = stamp(path);
+    let old = match std::fs::read(path) {
+        Ok(old) => old,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(Error::io("read", path)(e)),
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(old.as_slice());
+    let old_header = reader.headers().map_err(parse_err)?.clone();
+    let mut layout = if old.is_empty() {
+        Layout::standard()
+    } else {
+        Layout::new(&old_header)
+    };
+    layout.include(cards.iter().copied());
+    let header_end = reader.position().byte() as usize;
+    let crlf = old
+        .iter()
+        .position(|&b| b == b'\n')
+        .is_some_and(|i| i > 0 && old[i - 1] == b'\r');
+
+    if stamp.is_none() || stamp != file.stamp {
+        // Edited since we last saw it (or never seen): find the rows again.
+        let mut found = HashMap::new();
+        let mut record = csv::StringRecord::new();
+        file.duplicate = None;
+        loop {
+            let start = reader.position().byte();
+            if !reader.read_record(&mut record).map_err(parse_err)? {
+                break;
+            }
+            let id = layout.field(&record, ID);
+            if !id.is_empty()
+                && found
+                    .insert(id.clone(), start..reader.position().byte())
+                    .is_some()
+            {
+                file.duplicate.get_or_insert(id);
+            }
+        }
+        if let Some(id) = file
+            .rows
+            .iter()
+            .find(|(id, row)| row.lazy && !found.contains_key(*id))
+            .map(|(id, _)| id)
+        {
+            return Err(Error::ChangedOnDisk {
+                path: path.to_path_buf(),
+                id: id.clone(),
+            });
+        }
+        file.rows.retain(|id, _| found.contains_key(id));
+        for (id, range) in found {
+            let row = file.rows.entry(id).or_insert(Row {
+                start: 0,
+                end: 0,
+                hash: None,
+                lazy: false,
+            });
+            (row.start, row.end) = (range.start, range.end);
+        }
+    }
+
+    // Rows are found by id, so two with one id would overwrite each other.
+    if let Some(id) = &file.duplicate {
+        return Err(Error::DuplicateId {
+            path: path.to_path_buf(),
+            id: id.clone(),
+        });
+    }
+
+    // Rows can only be copied under the header they were written for.
+    let header_same = !old.is_empty() && layout.header.len() == old_header.len();
+    let default_deck = default_deck(path);
+    let mut lines = Vec::with_capacity(cards.len());
+    for card in cards {
+        let hash = fingerprint(card);
+        let line = match file.rows.get(&card.id) {
+            Some(row) if header_same && row.hash == Some(hash) => Line::Old(row.range()),
+            Some(row) => {
+                let new = changed_row(
+                    &old[row.range()],
+                    row.lazy,
+                    card,
+                    &layout,
+                    &default_deck,
+                    minimal,
+                    crlf,
+                )
+                .map_err(parse_err)?;
+                new.unwrap_or(Line::Old(row.range()))
+            }
+            None => {
+                Line::New(encode(&layout.record(card, None), crlf).map_err(Error::csv_write(path))?)
+            }
+        };
+        lines.push((line, hash));
+    }
+
+    if header_same && patch(path, &old, header_end, cards, &lines, file, crlf)? {
+        for (card, (line, hash)) in cards.iter().zip(&lines) {
+            if let (Line::New(_), Some(row)) = (line, file.rows.get_mut(&card.id)) {
+                row.hash = Some(*hash);
+            }
+        }
+        file.stamp = self::stamp(path);
+        return Ok(());
+    }
+
+    let mut out = Vec::with_capacity(old.len());
+    if header_same {
+        push_line(&mut out, &old[..header_end], crlf);
+    } else {
+        out.extend(encode(&layout.header, crlf).map_err(Error::csv_write(path))?);
+    }
+    let mut positions = Vec::with_capacity(cards.len());
+    for (line, _) in &lines {
+        let start = out.len() as u64;
+        match line {
+            Line::Old(range) => push_line(&mut out, &old[range.clone()], crlf),
+            Line::New(bytes) => out.extend(bytes),
+        }
+        positions.push((start, out.len() as u64));
+    }
+    replace(path, &out)?;
+
+    for ((card, (_, hash)), (start, end)) in cards.iter().zip(lines).zip(positions) {
+        let row = Row {
+            start,
+            end,
+            hash: Some(hash),
+            lazy: false,
+        };
+        match file.rows.get_mut(&card.id) {
+            Some(old) => {
+                *old = Row {
+                    lazy: old.lazy,
+                    ..row
+                }
+            }
+            None => {
+                file.rows.insert(card.id.clone(), row);
+            }
+        }
+    }
+    if file.rows.len() > cards.len() {
+        // Some cards were removed; forget their rows.
+        let ids: std::collections::HashSet<&str> = cards.iter().map(|c| c.id.as_str()).collect();
+        file.rows.retain(|id, _| ids.contains(id.as_str()));
+    }
+    file.stamp = self::stamp(path);
+    Ok(())
+}
+
+/// The new row for `card`, whose old row is `bytes`. A card whose content
+/// is still on disk takes it from there. `None` when a minimal save finds
+/// nothing to change.
+fn changed_row(
+    bytes: &[u8],
+    lazy: bool,
+    card: &Card,
+    layout: &Layout,
+    default_deck: &str,
+    minimal: bool,
+    crlf: bool,
+) -> csv::Result<Option<Line>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(bytes);
+    let mut record = csv::StringRecord::new();
+    reader.read_record(&mut record)?;
+    let mut card = card.clone();
+    if lazy {
+        card.front = layout.field(&record, FRONT);
+        card.back = layout.field(&record, BACK);
+        card.extra = layout.field(&record, EXTRA);
+        card.media = layout.field(&record, MEDIA);
+        card.origin = layout.field(&record, ORIGIN);
+    }
+    let mut fields = layout.record(&card, Some(&record));
+    if minimal {
+        // Which of rote's columns each column of the file is, if any.
+        let mut kinds = vec![None; layout.header.len()];
+        for (kind, column) in layout.columns.iter().enumerate() {
+            if let Some(i) = *column {
+                kinds[i] = Some(kind);
+            }
+        }
+        for (i, new) in fields.iter_mut().enumerate() {
+            let old = get_field(&record, i);
+            let same = match kinds[i] {
+                Some(kind) => normalize(kind, &old, default_deck) == *new,
+                None => true,
+            };
+            if same {
+                *new = old;
+            }
+        }
+        if fields.iter().map(String::as_str).eq(record.iter()) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(Line::New(encode(&fields, crlf)?)))
+}
+
+/// Rewrite `path` as `old` with just the changed rows overwritten, when every
+/// card is still where it was and each new row is as long as the old one.
+/// Returns whether it did.
+fn patch(
+    path: &Path,
+    old: &[u8],
+    header_end: usize,
+    cards: &[&Card],
+    lines: &[(Line, u64)],
+    file: &FileRows,
+    crlf: bool,
+) -> Result<bool> {
+    let terminator = if crlf { 2 } else { 1 };
+    let mut at = skip_breaks(old, header_end);
+    let mut patches = Vec::new();
+    for (card, (line, _)) in cards.iter().zip(lines) {
+        let Some(row) = file.rows.get(&card.id) else {
+            return Ok(false);
+        };
+        let content = trim_breaks(old, row.range());
+        if content.start != at {
+            return Ok(false);
+        }
+        at = skip_breaks(old, row.end as usize);
+        if let Line::New(bytes) = line {
+            let bytes = &bytes[..bytes.len() - terminator];
+            if bytes.len() != content.len() {
+                return Ok(false);
+            }
+            patches.push((content, bytes));
+        }
+    }
+    if at != old.len() {
+        return Ok(false);
+    }
+    if patches.is_empty() {
+        return Ok(true);
+    }
+    let mut out = old.to_vec();
+    for (range, bytes) in patches {
+        out[range].copy_from_slice(bytes);
+    }
+    replace(path, &out)?;
+    Ok(true)
+}
+
+fn is_break(b: &u8) -> bool {
+    *b == b'\r' || *b == b'\n'
+}
+
+/// The first position from `at` that is not a line break.
+fn skip_breaks(bytes: &[u8], at: usize) -> usize {
+    at + bytes[at..].iter().take_while(|b| is_break(b)).count()
+}
+
+/// `range` without the line breaks at either end. The reader's positions can
+/// fall between `\r` and `\n`, so both ends may carry part of one.
+fn trim_breaks(bytes: &[u8], range: Range<usize>) -> Range<usize> {
+    let start = skip_breaks(bytes, range.start).min(range.end);
+    let end = bytes[start..range.end]
+        .iter()
+        .rposition(|b| !is_break(b))
+        .map_or(start, |i| start + i + 1);
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn saves_touch_only_changed_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cards.csv");
+        let original = "deck,front,back,media,id,stability,difficulty,due,last_review,tags,flags\r\n\
+                        d,\"one, two\",b,,a,2.5,5,2025-06-01,,,\r\n\
+                        d,second,b,,b,1.000,5.000,2025-06-01,,,\r\n";
+        std::fs::write(&path, original).unwrap();
+        let mut index = Index::default();
+        let mut cards = index_csv(&path, &mut index, false).unwrap();
+        let save = |cards: &[Card], index: &mut Index| {
+            save(&path, &cards.iter().collect::<Vec<_>>(), index, false).unwrap();
+            std::fs::read_to_string(&path).unwrap()
+        };
+
+        // Same length: the row is patched and nothing else moves.
+        cards[1].due = NaiveDate::from_ymd_opt(2025, 7, 1);
+        assert_eq!(
+            save(&cards, &mut index),
+            original.replace("b,1.000,5.000,2025-06-01", "b,1.000,5.000,2025-07-01")
+        );
+
+        // Someone else edits the file; their change to an untouched card stays.
+        let edited = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"one, two\"", "\"one, two, three\"");
+        std::fs::write(&path, edited).unwrap();
+        cards[1].stability = Some(12.0);
+        cards.push(Card {
+            id: "c".into(),
+            ..cards[1].clone()
+        });
+        let saved = save(&cards, &mut index);
+        let lines: Vec<&str> = saved.split("\r\n").collect();
+        assert_eq!(lines[1], "d,\"one, two, three\",b,,a,2.5,5,2025-06-01,,,");
+        assert_eq!(lines[2], "d,second,b,,b,12.000,5.000,2025-07-01,,,");
+        assert_eq!(lines[3], "d,second,b,,c,12.000,5.000,2025-07-01,,,");
+
+        // Rows written by the last save are found again.
+        cards.remove(0);
+        cards[0].flagged = true;
+        let saved = save(&cards, &mut index);
+        assert_eq!(
+            saved.split("\r\n").nth(1),
+            Some("d,second,b,,b,12.000,5.000,2025-07-01,,,flagged")
+        );
+        assert!(!saved.contains("three"));
+    }
+
+    #[test]
+    fn files_with_a_duplicated_id_are_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cards.csv");
+        let original = "deck,front,back,media,id\n\
+                        d,first,b,,a\n\
+                        d,second,b,,a\n";
+        std::fs::write(&path, original).unwrap();
+        let mut index = Index::default();
+        let mut cards = index_csv(&path, &mut index, true).unwrap();
+        cards[1].flagged = true;
+        let err = save(&path, &cards.iter().collect::<Vec<_>>(), &mut index, false).unwrap_err();
+        assert!(matches!(&err, Error::DuplicateId { id, .. } if id == "a"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    }
+}
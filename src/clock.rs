@@ -0,0 +1,124 @@
+// What day it is, as far as scheduling goes. A review day starts at
+// `day_starts_at` (an hour, midnight by default), so reviews shortly after
+// midnight still count toward the evening's session, and `timezone` can pin
+// days to a fixed UTC offset instead of the system's, so travelling does not
+// shift due dates. Set once at startup from config.toml:
+//
+//     day_starts_at = 4
+//     timezone = "+01:00"
+//
+// Every command and the web server ask here rather than `Local::now()`.
+
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::error::{Error, Result};
+
+/// `"local"` (the system's zone, the default), `"UTC"`, or an offset such as
+/// `"+09:00"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum Timezone {
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    pub fn parse(s: &str) -> Result<Timezone> {
+        match s {
+            "local" => Ok(Timezone::Local),
+            "UTC" | "utc" | "Z" => Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap())),
+            _ => s.parse().map(Timezone::Fixed).map_err(|_| Error::Timezone {
+                text: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for Timezone {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Timezone> {
+        Timezone::parse(&s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Clock {
+    /// Hour (0-23) at which one review day ends and the next begins.
+    pub day_starts_at: u32,
+    pub timezone: Timezone,
+}
+
+static CLOCK: OnceLock<Clock> = OnceLock::new();
+
+/// Use `clock` from now on. Only the first call wins.
+pub fn configure(clock: Clock) {
+    let _ = CLOCK.set(clock);
+}
+
+fn current() -> Clock {
+    CLOCK.get().copied().unwrap_or_default()
+}
+
+impl Clock {
+    /// Wall-clock time at `at` in this clock's timezone.
+    pub fn local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self.timezone {
+            Timezone::Local => at.with_timezone(&chrono::Local).naive_local(),
+            Timezone::Fixed(offset) => at.with_timezone(&offset).naive_local(),
+        }
+    }
+
+    /// The review day `at` falls in.
+    pub fn day(&self, at: DateTime<Utc>) -> NaiveDate {
+        (self.local(at) - Duration::hours(self.day_starts_at.into())).date()
+    }
+}
+
+/// Wall-clock time now, e.g. for deck availability windows and reminders.
+pub fn now() -> NaiveDateTime {
+    current().local(Utc::now())
+}
+
+/// The current review day.
+pub fn today() -> NaiveDate {
+    current().day(Utc::now())
+}
+
+/// The review day a logged timestamp belongs to.
+pub fn day_of<Tz: TimeZone>(at: &DateTime<Tz>) -> NaiveDate {
+    current().day(at.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_rolls_over_at_cutover_in_timezone() {
+        let clock = Clock {
+            day_starts_at: 4,
+            timezone: Timezone::parse("+09:00").unwrap(),
+        };
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().to_utc();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+        // 02:30 and 04:30 on the 17th in Tokyo.
+        assert_eq!(clock.day(at("2026-10-16T17:30:00Z")), day(16));
+        assert_eq!(clock.day(at("2026-10-16T19:30:00Z")), day(17));
+        assert_eq!(
+            clock.local(at("2026-10-16T17:30:00Z")).to_string(),
+            "2026-10-17 02:30:00"
+        );
+
+        let utc = Clock {
+            day_starts_at: 0,
+            timezone: Timezone::parse("UTC").unwrap(),
+        };
+        assert_eq!(utc.day(at("2026-10-16T23:59:00-01:00")), day(17));
+        assert_eq!(Timezone::parse("local").unwrap(), Timezone::Local);
+        assert!(Timezone::parse("Europe/Paris").is_err());
+    }
+}
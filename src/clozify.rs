@@ -0,0 +1,243 @@
+// Cloze suggestions: find the parts of a sentence worth blanking out (numbers,
+// proper nouns, known terms such as deck tags) and wrap them in brackets.
+// Suggestions are heuristics for the author to confirm, not a parser.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Number,
+    ProperNoun,
+    Term,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Number => "number",
+            Kind::ProperNoun => "proper noun",
+            Kind::Term => "term",
+        }
+    }
+}
+
+/// A byte range of the input proposed as a cloze deletion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub start: usize,
+    pub end: usize,
+    pub kind: Kind,
+}
+
+struct Word {
+    start: usize,
+    end: usize,
+    sentence_start: bool,
+}
+
+/// Split into words, skipping anything already inside `[...]`. A word may
+/// contain `.`/`,` between digits, `-`/`'` between letters, and a trailing `%`.
+fn words(text: &str) -> Vec<Word> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut out = Vec::new();
+    let mut depth = 0usize;
+    let mut sentence_start = true;
+    let mut i = 0;
+    while i < chars.len() {
+        let (pos, ch) = chars[i];
+        let escaped = i > 0 && chars[i - 1].1 == '\\';
+        match ch {
+            '[' if !escaped => depth += 1,
+            ']' if !escaped => depth = depth.saturating_sub(1),
+            '.' | '!' | '?' | '\n' => sentence_start = true,
+            _ => {}
+        }
+        if !ch.is_alphanumeric() {
+            i += 1;
+            continue;
+        }
+        let mut j = i + 1;
+        while j < chars.len() {
+            let c = chars[j].1;
+            let joins = |pred: fn(char) -> bool| {
+                pred(chars[j - 1].1) && chars.get(j + 1).is_some_and(|&(_, n)| pred(n))
+            };
+            if c.is_alphanumeric()
+                || (matches!(c, '.' | ',') && joins(|c| c.is_ascii_digit()))
+                || (matches!(c, '-' | '\'' | '’') && joins(char::is_alphabetic))
+            {
+                j += 1;
+            } else {
+                break;
+            }
+        }
+        if chars.get(j).is_some_and(|&(_, c)| c == '%') && chars[j - 1].1.is_ascii_digit() {
+            j += 1;
+        }
+        let end = chars.get(j).map_or(text.len(), |&(p, _)| p);
+        if depth == 0 {
+            out.push(Word {
+                start: pos,
+                end,
+                sentence_start,
+            });
+        }
+        sentence_start = false;
+        i = j;
+    }
+    out
+}
+
+fn is_number(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '%'))
+}
+
+fn is_capitalized(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(char::is_uppercase) && chars.any(char::is_lowercase)
+}
+
+/// Suggest cloze deletions in `text`. `terms` (e.g. the deck's tags, with
+/// `-`/`_` read as spaces) are matched case-insensitively on word boundaries.
+pub fn suggest(text: &str, terms: &[String]) -> Vec<Suggestion> {
+    let words = words(text);
+    let mut found = Vec::new();
+
+    for w in &words {
+        if is_number(&text[w.start..w.end]) {
+            found.push(Suggestion {
+                start: w.start,
+                end: w.end,
+                kind: Kind::Number,
+            });
+        }
+    }
+
+    // Runs of capitalized words separated by single spaces. A run of one at
+    // the start of a sentence is probably just sentence case.
+    let mut i = 0;
+    while i < words.len() {
+        if !is_capitalized(&text[words[i].start..words[i].end]) {
+            i += 1;
+            continue;
+        }
+        let mut j = i + 1;
+        while j < words.len()
+            && &text[words[j - 1].end..words[j].start] == " "
+            && is_capitalized(&text[words[j].start..words[j].end])
+        {
+            j += 1;
+        }
+        if j - i > 1 || !words[i].sentence_start {
+            found.push(Suggestion {
+                start: words[i].start,
+                end: words[j - 1].end,
+                kind: Kind::ProperNoun,
+            });
+        }
+        i = j;
+    }
+
+    for term in terms {
+        let term: Vec<String> = term
+            .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+            .filter(|p| !p.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if term.is_empty() {
+            continue;
+        }
+        for start in 0..words.len().saturating_sub(term.len() - 1) {
+            let run = &words[start..start + term.len()];
+            let matches = run
+                .iter()
+                .zip(&term)
+                .all(|(w, t)| text[w.start..w.end].to_lowercase() == *t);
+            if matches {
+                found.push(Suggestion {
+                    start: run[0].start,
+                    end: run[run.len() - 1].end,
+                    kind: Kind::Term,
+                });
+            }
+        }
+    }
+
+    // Earliest first; on overlap keep the longer span.
+    found.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+    let mut out: Vec<Suggestion> = Vec::new();
+    for s in found {
+        if out.last().is_none_or(|last| s.start >= last.end) {
+            out.push(s);
+        }
+    }
+    out
+}
+
+/// Wrap each suggestion in brackets.
+pub fn apply(text: &str, suggestions: &[Suggestion]) -> String {
+    let mut out = String::with_capacity(text.len() + suggestions.len() * 2);
+    let mut pos = 0;
+    for s in suggestions {
+        out.push_str(&text[pos..s.start]);
+        out.push('[');
+        out.push_str(&text[s.start..s.end]);
+        out.push(']');
+        pos = s.end;
+    }
+    out.push_str(&text[pos..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clozify(text: &str, terms: &[&str]) -> String {
+        let terms: Vec<String> = terms.iter().map(|t| t.to_string()).collect();
+        apply(text, &suggest(text, &terms))
+    }
+
+    #[test]
+    fn numbers_and_proper_nouns() {
+        assert_eq!(
+            clozify("The storming of the Bastille happened in 1789.", &[]),
+            "The storming of the [Bastille] happened in [1789]."
+        );
+        assert_eq!(
+            clozify(
+                "Marie Curie won 2 Nobel Prizes, about 3.5% of laureates.",
+                &[]
+            ),
+            "[Marie Curie] won [2] [Nobel Prizes], about [3.5%] of laureates."
+        );
+    }
+
+    #[test]
+    fn sentence_case_is_not_a_proper_noun() {
+        assert_eq!(
+            clozify("Water boils at 100 degrees.", &[]),
+            "Water boils at [100] degrees."
+        );
+    }
+
+    #[test]
+    fn terms_from_tags() {
+        assert_eq!(
+            clozify(
+                "ATP is made by oxidative phosphorylation.",
+                &["oxidative-phosphorylation"]
+            ),
+            "ATP is made by [oxidative phosphorylation]."
+        );
+    }
+
+    #[test]
+    fn existing_clozes_are_left_alone() {
+        assert_eq!(
+            clozify("[Paris] is the capital of France", &[]),
+            "[Paris] is the capital of [France]"
+        );
+    }
+}
@@ -0,0 +1,401 @@
+// `Collection`: the cards under a set of paths and the files they came from.
+// This is the supported way to use rote as a library: open a collection, ask
+// what is due, grade, save.
+//
+//     let mut collection = Collection::open(&paths, &OpenOptions::default())?;
+//     let today = rote::clock::today();
+//     for i in collection.due(today) {
+//         collection.grade(i, Grade::Good, today, fsrs::DEFAULT_RETENTION)?;
+//     }
+//     collection.save()?;
+//
+// The web server keeps one open for as long as it runs; CLI commands open
+// their own. Opening one locks it against other writers (see `crate::lock`)
+// unless it is opened `read_only`; with a `user`, only that user's scheduling
+// is locked until a card is edited. The fields are public for callers that
+// need more than that, e.g. editing a card in place, after which `touch`
+// makes `save` write it.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::card::{self, Card};
+use crate::error::{Error, Result};
+use crate::fsrs::Grade;
+use crate::scheduler::{Fsrs, Scheduler};
+use crate::{lock, review, user};
+
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    pub files: card::PathFilter,
+    /// Index cards and leave their front, back and media on disk until
+    /// needed, so huge collections fit in little memory.
+    pub low_memory: bool,
+    /// Use this user's scheduling (see `crate::user`).
+    pub user: Option<String>,
+    /// Leave the lock to other processes; `save` then fails.
+    pub read_only: bool,
+    /// Keep rote's own files here rather than in `.rote` beside the decks
+    /// (`--data-dir`), e.g. a container volume apart from the deck files.
+    pub data_dir: Option<PathBuf>,
+    /// Save deck files with minimal diffs (see `card::save_csv`).
+    pub git_friendly: bool,
+}
+
+pub struct Collection {
+    pub cards: Vec<Card>,
+    /// The file each card was read from.
+    pub sources: Vec<PathBuf>,
+    /// Every deck file found under the paths.
+    pub files: Vec<PathBuf>,
+    /// Where each card's row is, so saves rewrite only changed ones.
+    pub index: card::Index,
+    /// Whose scheduling is shown and saved, when not the CSV's own.
+    pub profile: Option<user::Profile>,
+    /// Files that could not be read; their cards are left out.
+    pub skipped: Vec<Error>,
+    /// Files holding cards changed since they were last saved.
+    dirty: BTreeSet<PathBuf>,
+    /// Grades the profile has not saved yet.
+    profile_dirty: bool,
+    dir: PathBuf,
+    read_only: bool,
+    git_friendly: bool,
+    /// The deck files' lock; with a profile, taken on the first save that
+    /// writes one.
+    lock: Option<lock::Lock>,
+    /// The profile's own lock, so two users can open the collection at once.
+    profile_lock: Option<lock::Lock>,
+}
+
+impl Collection {
+    /// Load the deck files under `paths`.
+    pub fn open(paths: &[String], options: &OpenOptions) -> Result<Collection> {
+        let files = card::discover_files_filtered(paths, &options.files);
+        if files.is_empty() {
+            return Err(Error::NoFiles);
+        }
+        let dir = options
+            .data_dir
+            .clone()
+            .unwrap_or_else(|| card::data_dir(paths));
+        let lock = (!options.read_only && options.user.is_none())
+            .then(|| lock::acquire(&dir))
+            .transpose()?;
+        let mut collection = Collection {
+            cards: Vec::new(),
+            sources: Vec::new(),
+            files: Vec::new(),
+            index: card::Index::default(),
+            profile: None,
+            skipped: Vec::new(),
+            dirty: BTreeSet::new(),
+            profile_dirty: false,
+            dir,
+            read_only: options.read_only,
+            git_friendly: options.git_friendly,
+            lock,
+            profile_lock: None,
+        };
+        for file in files {
+            match card::index_csv(&file, &mut collection.index, options.low_memory) {
+                Ok(cards) => {
+                    let sources = std::iter::repeat_n(file.clone(), cards.len());
+                    collection.sources.extend(sources);
+                    collection.cards.extend(cards);
+                }
+                Err(e) => collection.skipped.push(e),
+            }
+            collection.files.push(file);
+        }
+        if let Some(name) = &options.user {
+            let mut profile = user::Profile::open(&collection.dir, name)?;
+            if !options.read_only {
+                collection.profile_lock = Some(lock::acquire_user(&collection.dir, name)?);
+            }
+            profile.apply(&mut collection.cards);
+            collection.profile = Some(profile);
+        }
+        Ok(collection)
+    }
+
+    /// The collection's own `.rote` directory, home of `decks.toml`.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the review log and journal go: the profile's directory, if any.
+    pub fn data_dir(&self) -> &Path {
+        self.profile.as_ref().map_or(&self.dir, |p| p.data_dir())
+    }
+
+    /// Card `i` with its content, read from disk if it was only indexed.
+    pub fn full_card(&self, i: usize) -> Result<Cow<'_, Card>> {
+        let card = &self.cards[i];
+        let Some(offset) = self.index.lazy(&self.sources[i], &card.id) else {
+            return Ok(Cow::Borrowed(card));
+        };
+        let mut full = card.clone();
+        card::read_content(&self.sources[i], offset, &mut full)?;
+        Ok(Cow::Owned(full))
+    }
+
+    /// Keep card `i`'s content in memory from now on, so it can be edited and
+    /// journaled like any other card.
+    pub fn materialize(&mut self, i: usize) -> Result<()> {
+        let Some(offset) = self.index.lazy(&self.sources[i], &self.cards[i].id) else {
+            return Ok(());
+        };
+        card::read_content(&self.sources[i], offset, &mut self.cards[i])?;
+        self.index.set_loaded(&self.sources[i], &self.cards[i].id);
+        Ok(())
+    }
+
+    /// Indices of the cards due on `today`, new ones included.
+    pub fn due(&self, today: NaiveDate) -> Vec<usize> {
+        review::filter_due(&self.cards, today)
+    }
+
+    /// Mark card `i` as changed, for `save` to write.
+    pub fn touch(&mut self, i: usize) {
+        self.dirty.insert(self.sources[i].clone());
+    }
+
+    /// Schedule card `i` with FSRS after a review graded `grade`, aiming for
+    /// `retention`. Returns the card as it was, e.g. for the journal. With a
+    /// profile only the profile changes; the card's file is left alone.
+    pub fn grade(
+        &mut self,
+        i: usize,
+        grade: Grade,
+        today: NaiveDate,
+        retention: f64,
+    ) -> Result<Card> {
+        self.grade_with(i, grade, today, retention, &Fsrs)
+    }
+
+    /// `grade` with another scheduler, e.g. the deck's (see `crate::scheduler`).
+    pub fn grade_with(
+        &mut self,
+        i: usize,
+        grade: Grade,
+        today: NaiveDate,
+        retention: f64,
+        scheduler: &dyn Scheduler,
+    ) -> Result<Card> {
+        self.materialize(i)?;
+        let before = self.cards[i].clone();
+        review::apply_grade(&mut self.cards[i], grade, today, retention, scheduler);
+        match self.profile {
+            Some(_) => self.profile_dirty = true,
+            None => self.touch(i),
+        }
+        Ok(before)
+    }
+
+    /// Swap the profile in use for `profile`, or with `None` for the deck
+    /// files' own scheduling, leaving the one that was in use in `profile`.
+    /// The cards' scheduling changes to match; card edits are kept. Grades
+    /// not yet saved to the outgoing profile are saved first.
+    pub fn swap_profile(&mut self, profile: &mut Option<user::Profile>) -> Result<()> {
+        if self.profile_dirty {
+            self.writable()?;
+            if let Some(current) = &mut self.profile {
+                current.save(&self.cards)?;
+            }
+            self.profile_dirty = false;
+        }
+        if let Some(current) = &self.profile {
+            current.unapply(&mut self.cards);
+        }
+        std::mem::swap(&mut self.profile, profile);
+        if let Some(next) = &mut self.profile {
+            next.apply(&mut self.cards);
+        }
+        Ok(())
+    }
+
+    /// Write every file holding a changed card, and the profile.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty.is_empty() || self.profile_dirty {
+            self.writable()?;
+        }
+        for path in std::mem::take(&mut self.dirty) {
+            self.save_file(&path)?;
+        }
+        if let Some(profile) = &mut self.profile
+            && std::mem::take(&mut self.profile_dirty)
+        {
+            profile.save(&self.cards)?;
+        }
+        Ok(())
+    }
+
+    fn writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(Error::ReadOnly {
+                dir: self.dir.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Lock the deck files for writing, if this collection does not hold
+    /// them already: opened with a profile, it only holds the profile's.
+    /// Fails with `Error::Locked` while another process writes them.
+    pub fn lock_files(&mut self) -> Result<()> {
+        self.writable()?;
+        if self.lock.is_none() {
+            self.lock = Some(lock::acquire(&self.dir)?);
+        }
+        Ok(())
+    }
+
+    /// Write the cards of one file. With a profile the file keeps its own
+    /// scheduling and the user's is saved apart.
+    pub fn save_file(&mut self, target: &Path) -> Result<()> {
+        self.lock_files()?;
+        self.dirty.remove(target);
+        let file_cards: Vec<&Card> = self
+            .cards
+            .iter()
+            .zip(&self.sources)
+            .filter(|(_, s)| *s == target)
+            .map(|(c, _)| c)
+            .collect();
+        match &mut self.profile {
+            Some(profile) => {
+                let owned: Vec<Card> = file_cards.into_iter().cloned().collect();
+                profile.save(&owned)?;
+                let shared = profile.shared(&owned);
+                card::save_csv_indexed(
+                    target,
+                    &shared.iter().collect::<Vec<_>>(),
+                    &mut self.index,
+                    self.git_friendly,
+                )
+            }
+            None => card::save_csv_indexed(target, &file_cards, &mut self.index, self.git_friendly),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_pick_the_data_dir_and_save_style() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.csv");
+        std::fs::write(&path, "deck,front,back,stability,id\nd,q1,a1,2.5,c1\n").unwrap();
+        let paths = vec![dir.path().to_string_lossy().into_owned()];
+        let elsewhere = dir.path().join("data");
+        let options = |git_friendly| OpenOptions {
+            data_dir: Some(elsewhere.clone()),
+            git_friendly,
+            ..OpenOptions::default()
+        };
+
+        let mut collection = Collection::open(&paths, &options(true)).unwrap();
+        assert_eq!(collection.dir(), elsewhere);
+        // The default data dir is not locked, so another collection can use it.
+        let beside = Collection::open(&paths, &OpenOptions::default()).unwrap();
+        assert_eq!(beside.dir(), dir.path().join(".rote"));
+        drop(beside);
+        let edit = |collection: &mut Collection, back: &str| {
+            collection.cards[0].back = back.into();
+            collection.touch(0);
+            collection.save().unwrap();
+            std::fs::read_to_string(&path).unwrap()
+        };
+        // Saved with minimal diffs, the stability keeps its old text.
+        assert!(edit(&mut collection, "b1").contains("d,q1,b1,2.5,c1"));
+        drop(collection);
+        let mut collection = Collection::open(&paths, &options(false)).unwrap();
+        assert!(edit(&mut collection, "c1").contains("d,q1,c1,2.500,c1"));
+    }
+
+    #[test]
+    fn open_grade_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.csv");
+        std::fs::write(
+            &path,
+            "deck,front,back,media,id\nd,q1,a1,,c1\nd,q2,a2,,c2\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.csv"), b"deck,front\n\xff,x\n").unwrap();
+        let paths = vec![dir.path().to_string_lossy().into_owned()];
+        let today = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+
+        let mut collection = Collection::open(&paths, &OpenOptions::default()).unwrap();
+        assert_eq!(collection.cards.len(), 2);
+        assert_eq!(collection.files.len(), 2);
+        assert_eq!(collection.skipped.len(), 1);
+        assert_eq!(collection.due(today), vec![0, 1]);
+
+        let before = collection
+            .grade(0, Grade::Good, today, crate::fsrs::DEFAULT_RETENTION)
+            .unwrap();
+        assert!(before.due.is_none());
+        collection.save().unwrap();
+        assert!(matches!(
+            Collection::open(&paths, &OpenOptions::default()),
+            Err(Error::Locked { .. })
+        ));
+        drop(collection);
+
+        let read_only = OpenOptions {
+            read_only: true,
+            ..OpenOptions::default()
+        };
+        let mut reopened = Collection::open(&paths, &read_only).unwrap();
+        assert_eq!(reopened.due(today), vec![1]);
+        assert!(reopened.cards[0].due.is_some_and(|d| d > today));
+        reopened.touch(1);
+        assert!(matches!(reopened.save(), Err(Error::ReadOnly { .. })));
+    }
+
+    #[test]
+    fn two_users_review_at_once() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.csv"),
+            "deck,front,back,media,id\nd,q1,a1,,c1\n",
+        )
+        .unwrap();
+        let paths = vec![dir.path().to_string_lossy().into_owned()];
+        let today = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let user = |name: &str| OpenOptions {
+            user: Some(name.to_string()),
+            ..OpenOptions::default()
+        };
+
+        let mut alex = Collection::open(&paths, &user("alex")).unwrap();
+        let mut sam = Collection::open(&paths, &user("sam")).unwrap();
+        for collection in [&mut alex, &mut sam] {
+            collection
+                .grade(0, Grade::Good, today, crate::fsrs::DEFAULT_RETENTION)
+                .unwrap();
+            collection.save().unwrap();
+        }
+        assert!(matches!(
+            Collection::open(&paths, &user("alex")),
+            Err(Error::UserLocked { .. })
+        ));
+
+        // Editing a card takes the deck files, which only one may hold.
+        alex.touch(0);
+        alex.save().unwrap();
+        sam.touch(0);
+        assert!(matches!(sam.save(), Err(Error::Locked { .. })));
+        assert!(matches!(
+            Collection::open(&paths, &OpenOptions::default()),
+            Err(Error::Locked { .. })
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+// User configuration, read from `~/.config/rote/config.toml` (or the file named
+// by `$ROTE_CONFIG`). Every key is optional; command-line flags override it.
+//
+//     paths = ["~/cards"]
+//     data_dir = "/data"
+//     port = 3000
+//     desired_retention = 0.9
+//     new_per_day = 20
+//     auto_reveal = 10
+//     theme = "dark"
+//     day_starts_at = 4
+//     order = "overdue"
+//     editor_url = "vscode://file{path}:{line}"
+//     log_level = "info"
+//
+//     [keys]
+//     suspend = "z"
+//
+//     [breaks]
+//     minutes = 25
+//     cards = 100
+//
+//     [[webhooks]]
+//     url = "https://ntfy.sh/my-rote"
+//     due_threshold = 50
+//
+//     [llm]
+//     url = "http://localhost:11434/v1/chat/completions"
+//     model = "llama3.1"
+
+use std::path::{Path, PathBuf};
+
+use crate::clock::Timezone;
+use crate::error::{Error, Result};
+use crate::fsrs;
+use crate::review::{Breaks, Order};
+use crate::web::{Keymap, Theme};
+use crate::webhook::Webhook;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Deck files or directories used when a command is given none.
+    pub paths: Vec<String>,
+    /// Where rote keeps its own files, instead of `.rote` beside the decks.
+    pub data_dir: Option<PathBuf>,
+    pub port: Option<u16>,
+    pub desired_retention: f64,
+    /// Maximum number of new cards introduced per day.
+    pub new_per_day: Option<usize>,
+    /// Session order for decks whose settings choose none.
+    pub order: Order,
+    /// Reveal the answer after this many seconds, for speed training.
+    pub auto_reveal: Option<u64>,
+    /// Suggest a break this often in long review sessions.
+    pub breaks: Breaks,
+    pub theme: Theme,
+    pub keys: Keymap,
+    /// Whose scheduling to use on shared decks (see `rote::user`).
+    pub user: Option<String>,
+    /// `user:password` the web server requires.
+    pub auth: Option<String>,
+    /// PEM files for serving HTTPS; both must be set.
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    /// Load Tailwind and KaTeX from CDNs instead of the bundled assets.
+    pub cdn: bool,
+    /// Link that opens a card's source in an editor, with `{path}` and
+    /// `{line}` filled in; `file://{path}` by default.
+    pub editor_url: Option<String>,
+    /// How much `rote serve` logs: "error" to "trace"; warnings by default.
+    pub log_level: Option<String>,
+    /// Log JSON lines instead of text.
+    pub log_json: bool,
+    /// Save deck files with minimal diffs (see `card::save_csv`).
+    pub git_friendly: bool,
+    /// Endpoints notified of finished sessions and piling-up reviews.
+    pub webhooks: Vec<Webhook>,
+    /// Hour at which a new review day begins (see `rote::clock`).
+    pub day_starts_at: u32,
+    pub timezone: Timezone,
+    /// Where the web UI asks for cards from study notes; used when built
+    /// with the `llm` feature.
+    pub llm: Option<Llm>,
+}
+
+/// An OpenAI-compatible chat completions endpoint.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Llm {
+    pub url: String,
+    pub model: String,
+    /// Environment variable holding the API key, sent as a bearer token.
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            paths: Vec::new(),
+            data_dir: None,
+            port: None,
+            desired_retention: fsrs::DEFAULT_RETENTION,
+            new_per_day: None,
+            order: Order::default(),
+            auto_reveal: None,
+            breaks: Breaks::default(),
+            theme: Theme::default(),
+            keys: Keymap::default(),
+            user: None,
+            auth: None,
+            tls_cert: None,
+            tls_key: None,
+            cdn: false,
+            editor_url: None,
+            log_level: None,
+            log_json: false,
+            git_friendly: false,
+            webhooks: Vec::new(),
+            day_starts_at: 0,
+            timezone: Timezone::default(),
+            llm: None,
+        }
+    }
+}
+
+/// Location of the config file, if a home directory can be found.
+pub fn path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os("ROTE_CONFIG") {
+        return Some(PathBuf::from(p));
+    }
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".config"),
+    };
+    Some(base.join("rote").join("config.toml"))
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Load the config file, falling back to defaults when it does not exist.
+pub fn load() -> Result<Config> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => parse(&path, &content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(Error::io("read", &path)(e)),
+    }
+}
+
+/// Parse `content`, read from `path`.
+pub fn parse(path: &Path, content: &str) -> Result<Config> {
+    let mut config: Config = toml::from_str(content).map_err(|source| Error::Toml {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let setting = |name, expected, got: &dyn std::fmt::Display| Error::Setting {
+        path: path.to_path_buf(),
+        name,
+        expected,
+        got: got.to_string(),
+    };
+    if !(config.desired_retention > 0.0 && config.desired_retention < 1.0) {
+        return Err(setting(
+            "desired_retention",
+            "between 0 and 1",
+            &config.desired_retention,
+        ));
+    }
+    if config.day_starts_at > 23 {
+        return Err(setting(
+            "day_starts_at",
+            "an hour from 0 to 23",
+            &config.day_starts_at,
+        ));
+    }
+    config.paths = config.paths.iter().map(|p| expand_home(p)).collect();
+    config.data_dir = config
+        .data_dir
+        .map(|d| PathBuf::from(expand_home(&d.to_string_lossy())));
+    Ok(config)
+}
+
+/// `path` with a leading `~/` replaced by the home directory.
+pub(crate) fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<Config> {
+        super::parse(Path::new("config.toml"), content)
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = parse("").unwrap();
+        assert!(config.paths.is_empty());
+        assert_eq!(config.desired_retention, fsrs::DEFAULT_RETENTION);
+        assert_eq!(config.keys.next, "j");
+    }
+
+    #[test]
+    fn parse_full_config() {
+        let config = parse(
+            r#"
+paths = ["/srv/cards", "extra.csv"]
+data_dir = "/data"
+port = 8080
+desired_retention = 0.85
+new_per_day = 15
+theme = "light"
+day_starts_at = 4
+timezone = "-05:00"
+editor_url = "vscode://file{path}:{line}"
+order = "overdue"
+log_level = "debug"
+
+[keys]
+suspend = "z"
+
+[breaks]
+minutes = 25
+
+[[webhooks]]
+url = "https://ntfy.sh/rote"
+due_threshold = 50
+
+[llm]
+url = "https://api.openai.com/v1/chat/completions"
+model = "gpt-4o-mini"
+api_key_env = "OPENAI_API_KEY"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.paths, vec!["/srv/cards", "extra.csv"]);
+        assert_eq!(config.data_dir, Some(PathBuf::from("/data")));
+        assert_eq!(config.port, Some(8080));
+        assert_eq!(config.desired_retention, 0.85);
+        assert_eq!(config.new_per_day, Some(15));
+        assert_eq!(config.order, crate::review::Order::Overdue);
+        assert_eq!(config.log_level.as_deref(), Some("debug"));
+        assert_eq!(config.theme, Theme::Light);
+        assert_eq!(config.keys.suspend, "z");
+        assert_eq!(config.keys.flag, "f");
+        assert_eq!(config.breaks.minutes, Some(25));
+        assert_eq!(config.breaks.cards, None);
+        assert_eq!(config.webhooks[0].due_threshold, Some(50));
+        assert_eq!(config.day_starts_at, 4);
+        assert_eq!(
+            config.editor_url.as_deref(),
+            Some("vscode://file{path}:{line}")
+        );
+        assert_eq!(config.timezone, Timezone::parse("-05:00").unwrap());
+        assert_eq!(
+            config.llm.unwrap().api_key_env.as_deref(),
+            Some("OPENAI_API_KEY")
+        );
+        assert!(parse("day_starts_at = 24").is_err());
+        assert!(parse("timezone = \"Mars/Olympus\"").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert_eq!(
+            parse("desired_retention = 1.5").unwrap_err().to_string(),
+            "config.toml: desired_retention must be between 0 and 1, got 1.5"
+        );
+        assert!(parse("unknown = 1").is_err());
+    }
+}
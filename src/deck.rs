@@ -0,0 +1,585 @@
+// Per-deck settings, read from `.rote/decks.toml` in the collection directory.
+// Tables are keyed by deck name; decks without one use the defaults.
+//
+//     [work]
+//     available = ["Mon-Fri 09:00-18:00"]
+//
+//     [spanish]
+//     description = "Verbs from *Gramática básica*. Answer in the infinitive."
+//     available = ["Sat,Sun", "19:00-23:00"]
+//     new_per_day = 10
+//     reviews_per_day = 100
+//     desired_retention = 0.85
+//     order = "risk"
+//     reversed = true
+//     scheduler = "sm2"
+//     pass_fail = true
+//     progressive_reveal = true
+//     fetch_media = true
+//
+//     [poems]
+//     overlap = 2
+//
+//     [spanish.tts]
+//     piper = "~/voices/es_ES-davefx-medium.onnx"
+//
+// Deck names nest with `::` (`Japanese::Vocab::N5`, quoted as a table name:
+// `["Japanese::Vocab"]`); a parent deck takes in every deck below it,
+// including its settings. The web UI's deck settings
+// page rewrites the file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::card::{self, Card};
+use crate::clock;
+use crate::error::{Error, Result};
+use crate::review::{DeckLimit, Order};
+use crate::revlog::Review;
+use crate::scheduler;
+use crate::tts::Tts;
+
+const FILE: &str = "decks.toml";
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeckConfig {
+    /// Markdown shown at the top of the deck's page, e.g. how to answer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// When the deck joins all-deck sessions; empty means always.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub available: Vec<Window>,
+    /// New cards introduced per day from this deck and those below it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_per_day: Option<usize>,
+    /// Already-seen cards reviewed per day from this deck and those below it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviews_per_day: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub desired_retention: Option<f64>,
+    /// Order of sessions started on this deck.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<Order>,
+    /// Give each card a back-to-front twin (see `reversed_card`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversed: Option<bool>,
+    /// FSRS unless set (see `crate::scheduler`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduler: Option<scheduler::Kind>,
+    /// Grade with just Forgot and Good, in the web UI and `rote drill`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pass_fail: Option<bool>,
+    /// Split each multi-line back into one card per line, asked after up to
+    /// this many lines before it; 0 shows every line before it (see
+    /// `line_cards`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlap: Option<usize>,
+    /// Reveal a card's cloze blanks one at a time in the web UI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progressive_reveal: Option<bool>,
+    /// Download the images a card links to when the web UI saves it (see
+    /// `crate::media::localize`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_media: Option<bool>,
+    /// Read cards aloud in the web UI (see `crate::tts`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts: Option<Tts>,
+}
+
+impl DeckConfig {
+    pub fn is_available(&self, now: NaiveDateTime) -> bool {
+        self.available.is_empty() || self.available.iter().any(|w| w.contains(now))
+    }
+}
+
+/// An availability window: optional days (`Mon-Fri`, `Sat,Sun`) and an optional
+/// time range (`09:00-17:00`, which may wrap past midnight), e.g.
+/// `"Mon-Fri 09:00-17:00"`.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Window {
+    days: Option<Vec<Weekday>>,
+    hours: Option<(NaiveTime, NaiveTime)>,
+    /// As written, so saving the file keeps it.
+    text: String,
+}
+
+impl TryFrom<String> for Window {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        Window::parse(&s)
+    }
+}
+
+impl From<Window> for String {
+    fn from(w: Window) -> String {
+        w.text
+    }
+}
+
+impl Window {
+    pub fn parse(s: &str) -> Result<Window> {
+        let invalid = |reason: String| Error::Window {
+            text: s.to_string(),
+            reason,
+        };
+        let mut window = Window {
+            days: None,
+            hours: None,
+            text: s.to_string(),
+        };
+        for part in s.split_whitespace() {
+            if part.contains(':') {
+                let (from, to) = part
+                    .split_once('-')
+                    .ok_or_else(|| invalid(format!("{part} is not a time range")))?;
+                let time = |t: &str| {
+                    NaiveTime::parse_from_str(t, "%H:%M")
+                        .map_err(|_| invalid(format!("{t} is not a time")))
+                };
+                window.hours = Some((time(from)?, time(to)?));
+            } else {
+                window.days =
+                    Some(parse_days(part).map_err(|d| invalid(format!("{d} is not a weekday")))?);
+            }
+        }
+        if window.days.is_none() && window.hours.is_none() {
+            return Err(invalid("it has no days or hours".to_string()));
+        }
+        Ok(window)
+    }
+
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let day_ok = self
+            .days
+            .as_ref()
+            .is_none_or(|days| days.contains(&now.weekday()));
+        let time_ok = self.hours.is_none_or(|(from, to)| {
+            let t = now.time();
+            if from <= to {
+                from <= t && t < to
+            } else {
+                t >= from || t < to
+            }
+        });
+        day_ok && time_ok
+    }
+}
+
+/// `Mon-Fri`, `Sat,Sun` or a mix such as `Mon,Wed-Fri`. Fails with the
+/// part that is not a weekday.
+fn parse_days<'a>(s: &'a str) -> Result<Vec<Weekday>, &'a str> {
+    let day = |d: &'a str| d.parse::<Weekday>().map_err(|_| d);
+    let mut days = Vec::new();
+    for part in s.split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (mut d, to) = (day(from)?, day(to)?);
+                days.push(d);
+                while d != to {
+                    d = d.succ();
+                    days.push(d);
+                }
+            }
+            None => days.push(day(part)?),
+        }
+    }
+    Ok(days)
+}
+
+/// Separates a deck from its parent in a nested name.
+pub const SEPARATOR: &str = "::";
+
+/// Whether `deck` is `parent` or nested anywhere below it.
+pub fn contains(parent: &str, deck: &str) -> bool {
+    deck.strip_prefix(parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+}
+
+/// The last part of a nested name: `N5` for `Japanese::Vocab::N5`.
+pub fn leaf(name: &str) -> &str {
+    name.rsplit(SEPARATOR).next().unwrap_or(name)
+}
+
+/// Deck settings by name. A missing file means every deck uses the defaults.
+pub fn load(data_dir: &Path) -> Result<HashMap<String, DeckConfig>> {
+    let path = data_dir.join(FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content).map_err(|source| Error::Toml { path, source }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(Error::io("read", path)(e)),
+    }
+}
+
+/// Write deck settings back to `decks.toml`, dropping decks left at the
+/// defaults.
+pub fn save(data_dir: &Path, configs: &HashMap<String, DeckConfig>) -> Result<()> {
+    let sorted: BTreeMap<&String, &DeckConfig> = configs
+        .iter()
+        .filter(|(_, c)| toml::to_string(c).is_ok_and(|t| !t.trim().is_empty()))
+        .collect();
+    let content = toml::to_string(&sorted).unwrap();
+    std::fs::create_dir_all(data_dir).map_err(Error::io("create", data_dir))?;
+    let path = data_dir.join(FILE);
+    std::fs::write(&path, content).map_err(Error::io("write", path))
+}
+
+/// A setting for `deck`, from its own table or else the nearest parent's.
+pub fn setting<T>(
+    configs: &HashMap<String, DeckConfig>,
+    deck: &str,
+    get: impl Fn(&DeckConfig) -> Option<T>,
+) -> Option<T> {
+    let mut name = deck;
+    loop {
+        if let Some(value) = configs.get(name).and_then(&get) {
+            return Some(value);
+        }
+        name = &name[..name.rfind(SEPARATOR)?];
+    }
+}
+
+/// What is left of each deck's daily new and review limits after `reviews`
+/// logged on `today`.
+pub fn limits(
+    configs: &HashMap<String, DeckConfig>,
+    reviews: &[Review],
+    today: NaiveDate,
+) -> Vec<DeckLimit> {
+    let mut limits: Vec<DeckLimit> = configs
+        .iter()
+        .filter(|(_, c)| c.new_per_day.is_some() || c.reviews_per_day.is_some())
+        .map(|(name, c)| {
+            let mut new = Vec::new();
+            let mut seen = Vec::new();
+            for r in reviews {
+                if r.practice || clock::day_of(&r.at) != today || !contains(name, &r.deck) {
+                    continue;
+                }
+                // A card first seen today counts as new, however often it comes back.
+                if new.contains(&&r.card_id) || seen.contains(&&r.card_id) {
+                    continue;
+                }
+                if r.elapsed_days.is_none() {
+                    new.push(&r.card_id);
+                } else {
+                    seen.push(&r.card_id);
+                }
+            }
+            DeckLimit {
+                deck: name.clone(),
+                new: c.new_per_day.map(|n| n.saturating_sub(new.len())),
+                reviews: c.reviews_per_day.map(|n| n.saturating_sub(seen.len())),
+            }
+        })
+        .collect();
+    limits.sort_by(|a, b| a.deck.cmp(&b.deck));
+    limits
+}
+
+/// Marks the id of a card's back-to-front twin.
+const REVERSED: &str = "-reversed";
+
+/// `card` asked back to front, as a new card with an id derived from the
+/// original's. Clozes, cards without a back and twins themselves have none.
+pub fn reversed_card(card: &Card) -> Option<Card> {
+    if reversed_of(card).is_some()
+        || card.back.trim().is_empty()
+        || !card::extract_cloze_deletions(&card.front).is_empty()
+    {
+        return None;
+    }
+    Some(Card {
+        deck: card.deck.clone(),
+        front: card.back.clone(),
+        back: card.front.clone(),
+        extra: card.extra.clone(),
+        media: card.media.clone(),
+        origin: card.origin.clone(),
+        id: format!("{}{REVERSED}", card.id),
+        stability: None,
+        difficulty: None,
+        due: None,
+        last_review: None,
+        created: Some(clock::today()),
+        tags: card.tags.clone(),
+        suspended: false,
+        flagged: false,
+        ease: None,
+        interval: None,
+    })
+}
+
+/// The id of the card `card` is the reversed twin of, if it is one.
+pub fn reversed_of(card: &Card) -> Option<&str> {
+    card.id.strip_suffix(REVERSED)
+}
+
+/// Indices of cards in decks set to `reversed` whose twin does not exist yet.
+pub fn needs_reversed(configs: &HashMap<String, DeckConfig>, cards: &[Card]) -> Vec<usize> {
+    let ids: std::collections::HashSet<&str> = cards.iter().map(|c| c.id.as_str()).collect();
+    (0..cards.len())
+        .filter(|&i| setting(configs, &cards[i].deck, |c| c.reversed) == Some(true))
+        .filter(|&i| reversed_card(&cards[i]).is_some_and(|twin| !ids.contains(twin.id.as_str())))
+        .collect()
+}
+
+/// Marks the id of a card asking for one line of another's back.
+const LINE: &str = "-line-";
+
+/// `card`'s back as one card per line, for learning poems and lists in order:
+/// each shows the front and up to `context` lines before its own (every line
+/// before it when 0) and asks for the next. Blank lines are skipped. Clozes,
+/// backs of one line and generated cards have none.
+pub fn line_cards(card: &Card, context: usize) -> Vec<Card> {
+    let back = card.back.replace("\\n", "\n");
+    let lines: Vec<&str> = back
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    if lines.len() < 2
+        || reversed_of(card).is_some()
+        || line_of(card).is_some()
+        || !card::extract_cloze_deletions(&card.front).is_empty()
+    {
+        return Vec::new();
+    }
+    let asked = |line: &str| card::escape_brackets(&card::expand_escapes(line));
+    (0..lines.len())
+        .map(|k| {
+            let start = if context == 0 {
+                0
+            } else {
+                k.saturating_sub(context)
+            };
+            let mut front = format!("{}\\n", card.front.trim_end());
+            for line in &lines[start..k] {
+                front.push_str(&format!("\\n{}", asked(line)));
+            }
+            front.push_str("\\n…");
+            Card {
+                deck: card.deck.clone(),
+                front,
+                back: lines[k].to_string(),
+                extra: card.extra.clone(),
+                media: String::new(),
+                origin: card.origin.clone(),
+                id: format!("{}{LINE}{}", card.id, k + 1),
+                stability: None,
+                difficulty: None,
+                due: None,
+                last_review: None,
+                created: Some(clock::today()),
+                tags: card.tags.clone(),
+                suspended: false,
+                flagged: false,
+                ease: None,
+                interval: None,
+            }
+        })
+        .collect()
+}
+
+/// The id of the card `card` asks a line of, if it is a line card.
+pub fn line_of(card: &Card) -> Option<&str> {
+    let (original, n) = card.id.rsplit_once(LINE)?;
+    (!n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())).then_some(original)
+}
+
+/// Line cards missing from decks set to `overlap`, each with the index of the
+/// card it was split from.
+pub fn missing_lines(configs: &HashMap<String, DeckConfig>, cards: &[Card]) -> Vec<(usize, Card)> {
+    let ids: std::collections::HashSet<&str> = cards.iter().map(|c| c.id.as_str()).collect();
+    (0..cards.len())
+        .filter_map(|i| Some((i, setting(configs, &cards[i].deck, |c| c.overlap)?)))
+        .flat_map(|(i, context)| {
+            line_cards(&cards[i], context)
+                .into_iter()
+                .filter(|line| !ids.contains(line.id.as_str()))
+                .map(move |line| (i, line))
+        })
+        .collect()
+}
+
+/// Decks outside their availability window at `now`, sorted by name.
+pub fn unavailable(configs: &HashMap<String, DeckConfig>, now: NaiveDateTime) -> Vec<String> {
+    let mut names: Vec<String> = configs
+        .iter()
+        .filter(|(_, c)| !c.is_available(now))
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, time: &str) -> NaiveDateTime {
+        // 2025-06-02 is a Monday.
+        NaiveDate::from_ymd_opt(2025, 6, day)
+            .unwrap()
+            .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    #[test]
+    fn nested_names() {
+        assert!(contains("Japanese", "Japanese"));
+        assert!(contains("Japanese", "Japanese::Vocab::N5"));
+        assert!(!contains("Japanese", "Japanese Extra"));
+        assert!(!contains("Japanese::Vocab", "Japanese"));
+        assert_eq!(leaf("Japanese::Vocab::N5"), "N5");
+        assert_eq!(leaf("spanish"), "spanish");
+    }
+
+    #[test]
+    fn settings_are_inherited_and_saved() {
+        let mut configs: HashMap<String, DeckConfig> = toml::from_str(
+            r#"
+[ja]
+available = ["Mon-Fri 09:00-18:00"]
+new_per_day = 2
+order = "risk"
+
+["ja::kanji"]
+new_per_day = 5
+reversed = true
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            setting(&configs, "ja::kanji::n5", |c| c.new_per_day),
+            Some(5)
+        );
+        assert_eq!(
+            setting(&configs, "ja::vocab", |c| c.order),
+            Some(Order::AtRisk)
+        );
+        assert_eq!(setting(&configs, "spanish", |c| c.order), None);
+
+        configs.insert("spanish".into(), DeckConfig::default());
+        let dir = tempfile::tempdir().unwrap();
+        save(dir.path(), &configs).unwrap();
+        let saved = std::fs::read_to_string(dir.path().join(FILE)).unwrap();
+        assert!(saved.contains(r#"available = ["Mon-Fri 09:00-18:00"]"#));
+        assert!(!saved.contains("spanish"));
+        let reloaded = load(dir.path()).unwrap();
+        assert_eq!(reloaded["ja::kanji"].reversed, Some(true));
+        assert_eq!(reloaded["ja"].available, configs["ja"].available);
+    }
+
+    #[test]
+    fn limits_count_todays_reviews() {
+        let configs: HashMap<String, DeckConfig> =
+            toml::from_str("[ja]\nnew_per_day = 2\nreviews_per_day = 10\n").unwrap();
+        let today = crate::clock::today();
+        let card = |id: &str, deck: &str, seen: bool| Card {
+            deck: deck.into(),
+            front: "q".into(),
+            back: "a".into(),
+            id: id.into(),
+            stability: seen.then_some(3.0),
+            difficulty: seen.then_some(5.0),
+            due: seen.then_some(today),
+            last_review: seen.then_some(today - chrono::Duration::days(3)),
+            ..Default::default()
+        };
+        let grade = crate::fsrs::Grade::Good;
+        let reviews = vec![
+            Review::new(&card("1", "ja::vocab", false), grade, "s", today),
+            Review::new(&card("2", "ja", true), grade, "s", today),
+            Review::new(&card("3", "spanish", false), grade, "s", today),
+        ];
+        assert_eq!(
+            limits(&configs, &reviews, today),
+            vec![DeckLimit {
+                deck: "ja".into(),
+                new: Some(1),
+                reviews: Some(9),
+            }]
+        );
+
+        let twin = reversed_card(&card("4", "ja", false)).unwrap();
+        assert_eq!((twin.front.as_str(), twin.back.as_str()), ("a", "q"));
+        assert_eq!(twin.id, "4-reversed");
+        assert!(reversed_card(&twin).is_none());
+    }
+
+    #[test]
+    fn backs_split_into_overlapping_lines() {
+        let poem = Card {
+            deck: "poems".into(),
+            front: "The Tyger".into(),
+            back: "Tyger Tyger, burning bright,\\nIn the forests of the night;\\n\\nWhat \\[immortal\\] hand or eye,".into(),
+            id: "t".into(),
+            ..Default::default()
+        };
+        let lines = line_cards(&poem, 1);
+        let ids: Vec<&str> = lines.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["t-line-1", "t-line-2", "t-line-3"]);
+        assert_eq!(lines[0].front, "The Tyger\\n\\n…");
+        assert_eq!(
+            lines[2].front,
+            "The Tyger\\n\\nIn the forests of the night;\\n…"
+        );
+        assert_eq!(lines[2].back, "What \\[immortal\\] hand or eye,");
+        assert!(card::extract_cloze_deletions(&lines[2].front).is_empty());
+        assert_eq!(line_of(&lines[1]), Some("t"));
+        assert!(line_cards(&lines[1], 1).is_empty());
+        assert_eq!(line_cards(&poem, 0)[2].front.matches("\\n").count(), 4);
+
+        let configs: HashMap<String, DeckConfig> =
+            toml::from_str("[poems]\noverlap = 1\n").unwrap();
+        let cards = vec![poem, lines[0].clone()];
+        let missing: Vec<String> = missing_lines(&configs, &cards)
+            .into_iter()
+            .map(|(i, c)| format!("{i}:{}", c.id))
+            .collect();
+        assert_eq!(missing, ["0:t-line-2", "0:t-line-3"]);
+    }
+
+    #[test]
+    fn weekday_and_hours() {
+        let w = Window::parse("Mon-Fri 09:00-18:00").unwrap();
+        assert!(w.contains(at(2, "09:00")));
+        assert!(!w.contains(at(2, "18:00")));
+        assert!(!w.contains(at(7, "12:00")));
+
+        let w = Window::parse("Sat,Sun").unwrap();
+        assert!(w.contains(at(8, "03:00")));
+        assert!(!w.contains(at(6, "03:00")));
+    }
+
+    #[test]
+    fn hours_wrap_past_midnight() {
+        let w = Window::parse("22:00-02:00").unwrap();
+        assert!(w.contains(at(3, "23:30")));
+        assert!(w.contains(at(3, "01:00")));
+        assert!(!w.contains(at(3, "12:00")));
+    }
+
+    #[test]
+    fn parse_deck_file() {
+        let configs: HashMap<String, DeckConfig> = toml::from_str(
+            r#"
+[work]
+available = ["Mon-Fri 09:00-18:00"]
+
+[spanish]
+"#,
+        )
+        .unwrap();
+        let saturday = at(7, "10:00");
+        assert_eq!(unavailable(&configs, saturday), vec!["work"]);
+        assert!(
+            toml::from_str::<HashMap<String, DeckConfig>>("[x]\navailable = [\"Funday\"]").is_err()
+        );
+    }
+}
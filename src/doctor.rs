@@ -0,0 +1,141 @@
+// Health checks across a collection for `rote doctor`: card ids used by more
+// than one row (copy-pasted rows, perhaps in another file), dates that do not
+// parse and media a card lists that its media folder lacks (URLs are left to
+// `rote media check --fetch`). Each is repaired in the cards as it is found:
+//
+//     chem.csv:3: id c1 is also used at bio.csv:14; given a new id
+//     bio.csv:20: due "2024-02-30" is not a date; cleared
+//     bio.csv:31: media heart.png is missing; reference removed
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::card::{self, Card};
+use crate::media;
+
+/// A deck file's cards, each with the line its row starts on.
+pub struct File {
+    pub path: PathBuf,
+    pub cards: Vec<(u64, Card)>,
+    /// From `card::malformed_dates`.
+    pub bad_dates: Vec<(u64, &'static str, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Finding {
+    pub file: PathBuf,
+    pub line: u64,
+    pub id: String,
+    pub problem: String,
+    pub repair: &'static str,
+}
+
+/// What is wrong with `files`, repaired in their cards as it is found. The
+/// first row to use an id keeps it, in the order of `files`.
+pub fn check(files: &mut [File]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut seen: HashMap<String, (PathBuf, u64)> = HashMap::new();
+    for file in files.iter_mut() {
+        let media_dir = card::media_dir(&file.path);
+        for (line, card) in &mut file.cards {
+            let mut found = |problem: String, repair, id: &str| {
+                findings.push(Finding {
+                    file: file.path.clone(),
+                    line: *line,
+                    id: id.to_string(),
+                    problem,
+                    repair,
+                });
+            };
+            if let Some((path, first)) = seen.get(&card.id) {
+                let problem = format!("id {} is also used at {}:{first}", card.id, path.display());
+                card.id = uuid::Uuid::new_v4().to_string();
+                found(problem, "given a new id", &card.id);
+            } else {
+                seen.insert(card.id.clone(), (file.path.clone(), *line));
+            }
+            for (_, column, value) in file.bad_dates.iter().filter(|d| d.0 == *line) {
+                found(
+                    format!("{column} {value:?} is not a date"),
+                    "cleared",
+                    &card.id,
+                );
+            }
+            let missing: Vec<String> = card::media_files(card)
+                .into_iter()
+                .filter(|m| !media::is_remote(m) && !media_dir.join(m).exists())
+                .map(String::from)
+                .collect();
+            for m in &missing {
+                found(
+                    format!("media {m} is missing"),
+                    "reference removed",
+                    &card.id,
+                );
+            }
+            if !missing.is_empty() {
+                card.media = card
+                    .media
+                    .split(';')
+                    .filter(|entry| {
+                        let name = entry.split_once('#').map_or(*entry, |(f, _)| f).trim();
+                        !name.is_empty() && !missing.iter().any(|m| m == name)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(";");
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_and_repairs_duplicates_dates_and_media() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("media")).unwrap();
+        std::fs::write(dir.path().join("media").join("cell.png"), b"png").unwrap();
+        let bio = dir.path().join("bio.csv");
+        let chem = dir.path().join("chem.csv");
+        std::fs::write(
+            &bio,
+            "deck,front,back,media,id,due\n\
+             bio,ATP,energy,cell.png;heart.png#0.1,c1,2024-02-30\n\
+             bio,DNA,genes,,c2,2026-01-01\n",
+        )
+        .unwrap();
+        std::fs::write(&chem, "deck,front,back,id\nchem,H2O,water,c1\n").unwrap();
+
+        let mut files: Vec<File> = [&bio, &chem]
+            .into_iter()
+            .map(|path| File {
+                path: path.clone(),
+                cards: card::load_csv_lines(path).unwrap(),
+                bad_dates: card::malformed_dates(path).unwrap(),
+            })
+            .collect();
+        let findings = check(&mut files);
+        let found: Vec<(u64, &str, &str)> = findings
+            .iter()
+            .map(|f| (f.line, f.problem.as_str(), f.repair))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, "due \"2024-02-30\" is not a date", "cleared"),
+                (2, "media heart.png is missing", "reference removed"),
+                (
+                    2,
+                    &*format!("id c1 is also used at {}:2", bio.display()),
+                    "given a new id"
+                ),
+            ]
+        );
+        assert_eq!(files[0].cards[0].1.media, "cell.png");
+        assert_ne!(files[1].cards[0].1.id, "c1");
+        assert_eq!(findings[2].id, files[1].cards[0].1.id);
+    }
+}
@@ -0,0 +1,114 @@
+// The unfinished part of a terminal drill, saved to `.rote/drill.json` as it
+// goes so `rote drill --resume` can carry on where an interrupted session
+// stopped. Like the web UI's sessions, cards are stored by id; cards that have
+// since disappeared are dropped.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::card::Card;
+use crate::error::{Error, Result};
+
+const FILE: &str = "drill.json";
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Progress {
+    pub session: String,
+    /// Ids of the cards still to grade, in order.
+    pub remaining: Vec<String>,
+    /// Cards in the session when it started.
+    pub total: usize,
+    pub counts: [u32; 4],
+    pub practice: bool,
+    pub started: NaiveDate,
+}
+
+fn path(data_dir: &Path) -> PathBuf {
+    data_dir.join(FILE)
+}
+
+pub fn save(data_dir: &Path, progress: &Progress) -> Result<()> {
+    std::fs::create_dir_all(data_dir).map_err(Error::io("create", data_dir))?;
+    let json = serde_json::to_string(progress).unwrap();
+    let path = path(data_dir);
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(Error::io("write", &tmp))?;
+    std::fs::rename(&tmp, &path).map_err(Error::io("write", &path))
+}
+
+/// The interrupted session, if there is one.
+pub fn load(data_dir: &Path) -> Result<Option<Progress>> {
+    let path = path(data_dir);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(Error::json(&path)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::io("read", &path)(e)),
+    }
+}
+
+/// Forget the saved session, once it has been finished.
+pub fn clear(data_dir: &Path) -> Result<()> {
+    match std::fs::remove_file(path(data_dir)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(Error::io("remove", path(data_dir))(e))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Progress {
+    /// Indices into `cards` of the cards still to grade.
+    pub fn remaining(&self, cards: &[Card]) -> Vec<usize> {
+        let index: HashMap<&str, usize> = cards
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.id.as_str(), i))
+            .collect();
+        self.remaining
+            .iter()
+            .filter_map(|id| index.get(id.as_str()).copied())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(id: &str) -> Card {
+        Card {
+            deck: "d".into(),
+            front: "f".into(),
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn progress_round_trips_and_drops_missing_cards() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load(dir.path()).unwrap(), None);
+
+        let progress = Progress {
+            session: "s1".into(),
+            remaining: vec!["c3".into(), "gone".into(), "c1".into()],
+            total: 5,
+            counts: [0, 1, 1, 0],
+            practice: false,
+            started: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+        };
+        save(dir.path(), &progress).unwrap();
+        let loaded = load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded, progress);
+
+        let cards = [card("c1"), card("c2"), card("c3")];
+        assert_eq!(loaded.remaining(&cards), vec![2, 0]);
+
+        clear(dir.path()).unwrap();
+        assert_eq!(load(dir.path()).unwrap(), None);
+    }
+}
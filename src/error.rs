@@ -0,0 +1,268 @@
+// The library's error type. Reading and writing deck files and running the
+// web server report failures as `Error` rather than printing them or exiting,
+// so rote can be embedded in other programs.
+
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A file could not be opened, read or written.
+    #[error("failed to {action} {}: {source}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// A deck file is not valid CSV.
+    #[error("CSV parse error in {}: {source}", path.display())]
+    Csv {
+        path: PathBuf,
+        #[source]
+        source: csv::Error,
+    },
+    /// Writing a deck file's rows failed.
+    #[error("failed to write {}: {source}", path.display())]
+    CsvWrite {
+        path: PathBuf,
+        #[source]
+        source: csv::Error,
+    },
+    #[error("{}: no record at byte {offset}", path.display())]
+    NoRecord { path: PathBuf, offset: u64 },
+    /// A file read without its content lost one of the cards still unread.
+    #[error("{} changed on disk and card {id} is gone from it; reload before saving", path.display())]
+    ChangedOnDisk { path: PathBuf, id: String },
+    /// Rows of one deck file share an id, so saving could mix them up.
+    #[error("{} has more than one row with id {id}; run `rote doctor` to give them their own", path.display())]
+    DuplicateId { path: PathBuf, id: String },
+    /// A JSON file rote keeps in its data directory does not parse.
+    #[error("{}: {source}", path.display())]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// `decks.toml` does not parse.
+    #[error("{}: {source}", path.display())]
+    Toml {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    /// A deck's `available` entry is not a window such as `Mon-Fri 18:00-22:00`.
+    #[error("invalid availability window {text:?}: {reason}")]
+    Window { text: String, reason: String },
+    #[error("invalid user name {name:?} (use letters, digits, '-' and '_')")]
+    InvalidUser { name: String },
+    /// Writing a snapshot archive failed.
+    #[error("failed to write {}: {source}", path.display())]
+    Zip {
+        path: PathBuf,
+        #[source]
+        source: zip::result::ZipError,
+    },
+    /// A file given to `rote restore` or `rote import` is not the archive it
+    /// should be, such as "a rote snapshot".
+    #[error("{} is not {kind}: {source}", path.display())]
+    NotArchive {
+        path: PathBuf,
+        kind: &'static str,
+        #[source]
+        source: zip::result::ZipError,
+    },
+    #[error("failed to read {name} from {}: {source}", path.display())]
+    ArchiveEntry {
+        path: PathBuf,
+        name: String,
+        #[source]
+        source: zip::result::ZipError,
+    },
+    /// A webhook's POST failed or was refused.
+    #[error("webhook {url} failed: {source}")]
+    Webhook {
+        url: String,
+        #[source]
+        source: ureq::Error,
+    },
+    #[error("invalid pattern {pattern}: {source}")]
+    Pattern {
+        pattern: String,
+        #[source]
+        source: glob::PatternError,
+    },
+    #[error("no CSV files found")]
+    NoFiles,
+    /// Another process is writing the collection (see `crate::lock`).
+    #[error("{holder} is already using the collection in {}; stop it first", dir.display())]
+    Locked { dir: PathBuf, holder: String },
+    /// Another process is writing this user's scheduling.
+    #[error("{holder} is already using {name}'s scheduling in {}; stop it first", dir.display())]
+    UserLocked {
+        dir: PathBuf,
+        name: String,
+        holder: String,
+    },
+    /// A collection opened read-only was asked to save.
+    #[error("the collection in {} was opened read-only", dir.display())]
+    ReadOnly { dir: PathBuf },
+    /// The web server could not listen on its address.
+    #[error("failed to listen on {addr}: {source}")]
+    Listen {
+        addr: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// An Anki package exported without "Support older Anki versions".
+    #[error("{} has no legacy collection; re-export it from Anki with \"Support older Anki versions\" checked", path.display())]
+    NoAnkiCollection { path: PathBuf },
+    #[error("unsupported media manifest in package: {source}")]
+    AnkiMedia {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("failed to read Anki collection: {source}")]
+    AnkiCollection {
+        #[source]
+        source: rusqlite::Error,
+    },
+    #[error("delimiters cannot be empty")]
+    EmptyDelimiter,
+    /// Quizlet text none of whose rows has the term delimiter.
+    #[error("no row contains the term delimiter {delimiter:?}; check the export settings")]
+    NoTermDelimiter { delimiter: String },
+    /// A `--map` entry that is not `field=column`.
+    #[error("invalid mapping {part:?} (expected field=column)")]
+    MappingSyntax { part: String },
+    #[error("unknown field {field:?} (expected front, back, deck, tags, media or extra)")]
+    UnknownField { field: String },
+    #[error("the mapping needs a front column (front=N)")]
+    NoFrontColumn,
+    #[error("columns are numbered from 1")]
+    ColumnZero,
+    #[error("no column named {name:?}")]
+    NoColumn { name: String },
+    /// A setting in the config file is out of range.
+    #[error("{}: {name} must be {expected}, got {got}", path.display())]
+    Setting {
+        path: PathBuf,
+        name: &'static str,
+        expected: &'static str,
+        got: String,
+    },
+    #[error("invalid timezone {text:?} (want local, UTC or +HH:MM)")]
+    Timezone { text: String },
+    #[error("invalid time {text:?} (want HH:MM)")]
+    Time { text: String },
+    #[error("invalid auth {spec:?} (expected user:password)")]
+    Auth { spec: String },
+    /// A TLS certificate or key file could not be read.
+    #[error("{}: {source}", path.display())]
+    Pem {
+        path: PathBuf,
+        #[source]
+        source: tokio_rustls::rustls::pki_types::pem::Error,
+    },
+    #[error("{}: no certificates found", path.display())]
+    NoCertificates { path: PathBuf },
+    #[error("invalid certificate or key: {source}")]
+    Tls {
+        #[source]
+        source: tokio_rustls::rustls::Error,
+    },
+    /// A program rote runs (the editor, piper, notify-send) could not start.
+    #[error("failed to run {program}: {source}")]
+    Spawn {
+        program: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{program} failed ({status}){}", if stderr.is_empty() { String::new() } else { format!(": {stderr}") })]
+    Exited {
+        program: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+    #[error("$EDITOR is empty")]
+    NoEditor,
+    /// A card edited in `$EDITOR` lost the line between front and back.
+    #[error("expected the front, a --- line, then the back")]
+    NoSeparator,
+    #[error("tts needs piper or url")]
+    NoTtsBackend,
+    /// Downloading media or speech failed.
+    #[error("failed to fetch {url}: {source}")]
+    Fetch {
+        url: String,
+        #[source]
+        source: ureq::Error,
+    },
+    #[error("sync with {url} failed: {source}")]
+    Sync {
+        url: String,
+        #[source]
+        source: ureq::Error,
+    },
+    #[error("unexpected reply from {url}: {source}")]
+    SyncReply {
+        url: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub(crate) fn io(
+        action: &'static str,
+        path: impl Into<PathBuf>,
+    ) -> impl FnOnce(std::io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Io {
+            action,
+            path,
+            source,
+        }
+    }
+
+    pub(crate) fn json(path: impl Into<PathBuf>) -> impl FnOnce(serde_json::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Json { path, source }
+    }
+
+    pub(crate) fn csv(path: impl Into<PathBuf>) -> impl FnOnce(csv::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Csv { path, source }
+    }
+
+    pub(crate) fn csv_write(path: impl Into<PathBuf>) -> impl FnOnce(csv::Error) -> Error {
+        let path = path.into();
+        move |source| Error::CsvWrite { path, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_name_what_failed() {
+        let missing = std::fs::read("/nonexistent/deck.csv")
+            .map_err(Error::io("read", "/nonexistent/deck.csv"))
+            .unwrap_err();
+        assert!(matches!(missing, Error::Io { action: "read", .. }));
+        assert!(
+            missing
+                .to_string()
+                .starts_with("failed to read /nonexistent/deck.csv: ")
+        );
+        assert_eq!(Error::NoFiles.to_string(), "no CSV files found");
+        let piper = Error::Exited {
+            program: "piper".into(),
+            status: std::process::ExitStatus::default(),
+            stderr: String::new(),
+        };
+        assert!(!piper.to_string().ends_with(": "));
+    }
+}
@@ -0,0 +1,264 @@
+// Export of decks for people who don't use rote: Mochi's `.mochi` archive
+// (a zip of `data.json` plus attachments) and plain Markdown, one readable
+// file per deck. Scheduling is left behind; only content, tags and media go.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::card::{self, Card};
+use crate::error::{Error, Result};
+
+pub struct Export {
+    pub decks: usize,
+    pub cards: usize,
+    /// Media files copied alongside the cards.
+    pub media: usize,
+    /// Media files referenced by a card but not found on disk.
+    pub missing: Vec<String>,
+}
+
+/// Card indices by deck name, in file order within each deck.
+fn by_deck(cards: &[Card]) -> BTreeMap<&str, Vec<usize>> {
+    let mut decks: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, card) in cards.iter().enumerate() {
+        decks.entry(&card.deck).or_default().push(i);
+    }
+    decks
+}
+
+/// Replace each outermost `[…]` cloze with `open…close`, dropping hints.
+fn replace_clozes(text: &str, open: &str, close: &str) -> String {
+    card::cloze_parts(text)
+        .into_iter()
+        .map(|part| match part {
+            card::ClozePart::Text(text) => text,
+            card::ClozePart::Cloze { answer, .. } => format!("{open}{answer}{close}"),
+        })
+        .collect()
+}
+
+fn is_image(name: &str) -> bool {
+    let ext = name.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    matches!(
+        ext.as_str(),
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp"
+    )
+}
+
+/// A deck name usable as a file name.
+fn file_name(deck: &str) -> String {
+    deck.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_matches(['.', ' '])
+        .to_string()
+}
+
+/// The media a card references, with where each file lives on disk.
+fn media_paths<'a>(card: &'a Card, source: &Path) -> Vec<(&'a str, PathBuf)> {
+    let dir = card::media_dir(source);
+    card::media_files(card)
+        .into_iter()
+        .map(|name| (name, dir.join(name)))
+        .collect()
+}
+
+/// Write one `<deck>.md` per deck into `dir`, with media copied to
+/// `dir/media`.
+pub fn markdown(cards: &[Card], sources: &[PathBuf], dir: &Path) -> Result<Export> {
+    let media_dir = dir.join("media");
+    std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+    let mut export = Export {
+        decks: 0,
+        cards: 0,
+        media: 0,
+        missing: Vec::new(),
+    };
+    let mut copied = HashSet::new();
+    for (deck, indices) in by_deck(cards) {
+        let mut md = format!("# {deck}\n");
+        for &i in &indices {
+            let card = &cards[i];
+            let front = card::expand_escapes(&replace_clozes(&card.front, "**", "**"));
+            md.push_str(&format!("\n---\n\n**Q:** {front}\n"));
+            if !card.back.trim().is_empty() {
+                md.push_str(&format!("\n**A:** {}\n", card::expand_escapes(&card.back)));
+            }
+            if !card.extra.trim().is_empty() {
+                md.push_str(&format!(
+                    "\n*{}*\n",
+                    card::expand_escapes(card.extra.trim())
+                ));
+            }
+            for (name, path) in media_paths(card, &sources[i]) {
+                if !path.exists() {
+                    export.missing.push(name.to_string());
+                    continue;
+                }
+                if copied.insert(name) {
+                    std::fs::create_dir_all(&media_dir).map_err(Error::io("create", &media_dir))?;
+                    std::fs::copy(&path, media_dir.join(name)).map_err(Error::io("copy", &path))?;
+                    export.media += 1;
+                }
+                let link = if is_image(name) { "!" } else { "" };
+                md.push_str(&format!("\n{link}[{name}](media/{name})\n"));
+            }
+            if !card.tags.is_empty() {
+                md.push_str(&format!("\n*Tags: {}*\n", card.tags.join(", ")));
+            }
+        }
+        let path = dir.join(format!("{}.md", file_name(deck)));
+        std::fs::write(&path, md).map_err(Error::io("write", &path))?;
+        export.decks += 1;
+        export.cards += indices.len();
+    }
+    Ok(export)
+}
+
+/// Write a Mochi import archive to `out`. Clozes become Mochi's `{{…}}` and
+/// media become attachments.
+pub fn mochi(cards: &[Card], sources: &[PathBuf], out: &Path) -> Result<Export> {
+    let write_err = |source| Error::Zip {
+        path: out.to_path_buf(),
+        source,
+    };
+    let file = std::fs::File::create(out).map_err(Error::io("create", out))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut export = Export {
+        decks: 0,
+        cards: 0,
+        media: 0,
+        missing: Vec::new(),
+    };
+    let mut attached = HashSet::new();
+    let mut decks = Vec::new();
+    for (deck, indices) in by_deck(cards) {
+        let deck_id = short_id(&uuid::Uuid::new_v4().to_string());
+        let mut deck_cards = Vec::new();
+        for &i in &indices {
+            let card = &cards[i];
+            let mut content = card::expand_escapes(&replace_clozes(&card.front, "{{", "}}"));
+            if !card.back.trim().is_empty() {
+                content.push_str("\n---\n");
+                content.push_str(&card::expand_escapes(&card.back));
+            }
+            if !card.extra.trim().is_empty() {
+                content.push_str("\n\n");
+                content.push_str(&card::expand_escapes(&card.extra));
+            }
+            for (name, path) in media_paths(card, &sources[i]) {
+                let Ok(bytes) = std::fs::read(&path) else {
+                    export.missing.push(name.to_string());
+                    continue;
+                };
+                if attached.insert(name) {
+                    zip.start_file(name, options).map_err(write_err)?;
+                    zip.write_all(&bytes).map_err(Error::io("write", out))?;
+                    export.media += 1;
+                }
+                let link = if is_image(name) { "!" } else { "" };
+                content.push_str(&format!("\n\n{link}[{name}](@media/{name})"));
+            }
+            deck_cards.push(serde_json::json!({
+                "id": short_id(&card.id),
+                "content": content,
+                "deck-id": deck_id,
+                "tags": card.tags,
+            }));
+        }
+        decks.push(serde_json::json!({
+            "id": deck_id,
+            "name": deck,
+            "cards": deck_cards,
+        }));
+        export.decks += 1;
+        export.cards += indices.len();
+    }
+    let data = serde_json::json!({ "version": 2, "decks": decks });
+    zip.start_file("data.json", options).map_err(write_err)?;
+    zip.write_all(data.to_string().as_bytes())
+        .map_err(Error::io("write", out))?;
+    zip.finish().map_err(write_err)?;
+    Ok(export)
+}
+
+/// Mochi uses short alphanumeric ids.
+fn short_id(id: &str) -> String {
+    id.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(8)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn card(deck: &str, front: &str, back: &str, media: &str) -> Card {
+        Card {
+            deck: deck.into(),
+            front: front.into(),
+            back: back.into(),
+            media: media.into(),
+            id: uuid::Uuid::new_v4().to_string(),
+            stability: Some(3.0),
+            difficulty: Some(5.0),
+            tags: vec!["bio".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exports_markdown_and_mochi() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("cards.csv");
+        std::fs::create_dir(dir.path().join("media")).unwrap();
+        std::fs::write(dir.path().join("media/cell.png"), b"png").unwrap();
+        let cards = vec![
+            card("bio/cells", "The [mitochondria] makes ATP", "", "cell.png"),
+            card("bio/cells", "Line one\\nline two", "Answer", "gone.mp3"),
+            card("chem", "H2O?", "Water", ""),
+        ];
+        let sources = vec![source; 3];
+
+        let out = dir.path().join("md");
+        let export = markdown(&cards, &sources, &out).unwrap();
+        assert_eq!((export.decks, export.cards, export.media), (2, 3, 1));
+        assert_eq!(export.missing, vec!["gone.mp3"]);
+        let md = std::fs::read_to_string(out.join("bio-cells.md")).unwrap();
+        assert!(md.starts_with("# bio/cells\n"));
+        assert!(md.contains("**Q:** The **mitochondria** makes ATP\n"));
+        assert!(md.contains("![cell.png](media/cell.png)"));
+        assert!(md.contains("**Q:** Line one\nline two\n\n**A:** Answer\n"));
+        assert!(out.join("media/cell.png").exists());
+
+        let archive = dir.path().join("deck.mochi");
+        mochi(&cards, &sources, &archive).unwrap();
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&archive).unwrap()).unwrap();
+        assert!(zip.by_name("cell.png").is_ok());
+        let mut data = String::new();
+        zip.by_name("data.json")
+            .unwrap()
+            .read_to_string(&mut data)
+            .unwrap();
+        let data: serde_json::Value = serde_json::from_str(&data).unwrap();
+        let first = &data["decks"][0]["cards"][0];
+        assert_eq!(data["decks"][0]["name"], "bio/cells");
+        assert_eq!(
+            first["content"],
+            "The {{mitochondria}} makes ATP\n\n![cell.png](@media/cell.png)"
+        );
+        assert_eq!(first["deck-id"], data["decks"][0]["id"]);
+        assert_eq!(data["decks"][1]["cards"][0]["content"], "H2O?\n---\nWater");
+    }
+}
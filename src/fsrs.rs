@@ -1,5 +1,6 @@
 // FSRS (Free Spaced Repetition Scheduler) implementation.
-// Transcribed from Borretti's 100-line implementation.
+// Transcribed from Borretti's 100-line implementation, with FSRS-5's
+// short-term stability (w17, w18) for cards reviewed again the same day.
 
 use chrono::NaiveDate;
 
@@ -10,7 +11,8 @@ type T = f64;
 
 const F: f64 = 19.0 / 81.0;
 const C: f64 = -0.5;
-const DESIRED_RETENTION: f64 = 0.9;
+/// Target probability of recall when a card comes due.
+pub const DEFAULT_RETENTION: f64 = 0.9;
 
 const W: [f64; 19] = [
     0.40255, 1.18385, 3.173, 15.69105, 7.1949, 0.5345, 1.4604, 0.0046, 1.54575, 0.1192, 1.01925,
@@ -35,6 +37,23 @@ impl Grade {
             _ => None,
         }
     }
+
+    /// The grade a pass/fail review records: Forgot, or Good for any pass.
+    pub fn pass_fail(self) -> Grade {
+        match self {
+            Grade::Forgot => Grade::Forgot,
+            _ => Grade::Good,
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Grade::Forgot => 1,
+            Grade::Hard => 2,
+            Grade::Good => 3,
+            Grade::Easy => 4,
+        }
+    }
 }
 
 impl From<Grade> for f64 {
@@ -52,14 +71,24 @@ pub struct ReviewOutcome {
     pub stability: f64,
     pub difficulty: f64,
     pub due: NaiveDate,
+    /// SM-2's ease factor and interval, when SM-2 scheduled the review (see
+    /// `crate::scheduler`); `None` from FSRS.
+    pub ease: Option<f64>,
+    pub interval: Option<u32>,
 }
 
-fn retrievability(t: T, s: S) -> R {
+/// Probability of recall `t` days after a review that left stability `s`.
+pub fn retrievability(t: T, s: S) -> R {
     (1.0 + F * (t / s)).powf(C)
 }
 
-fn interval(s: S) -> T {
-    (s / F) * (DESIRED_RETENTION.powf(1.0 / C) - 1.0)
+fn interval(s: S, retention: R) -> T {
+    (s / F) * (retention.powf(1.0 / C) - 1.0)
+}
+
+/// Whole days until a card with stability `s` should be seen again.
+pub fn interval_days(s: S, retention: R) -> u64 {
+    f64::max(interval(s, retention).round(), 1.0) as u64
 }
 
 fn s_0(g: Grade) -> S {
@@ -108,6 +137,13 @@ fn stability(d: D, s: S, r: R, g: Grade) -> S {
     }
 }
 
+/// Stability after a review on the same day as the last one, when the
+/// forgetting curve has had no time to act.
+fn s_short_term(s: S, g: Grade) -> S {
+    let g: f64 = g.into();
+    s * f64::exp(W[17] * (g - 3.0 + W[18]))
+}
+
 fn delta_d(g: Grade) -> f64 {
     let g: f64 = g.into();
     -W[6] * (g - 3.0)
@@ -121,34 +157,45 @@ fn difficulty(d: D, g: Grade) -> D {
     clamp_d(W[7] * d_0(Grade::Easy) + (1.0 - W[7]) * dp(d, g))
 }
 
-pub fn review_new(grade: Grade, today: NaiveDate) -> ReviewOutcome {
+pub fn review_new(grade: Grade, today: NaiveDate, retention: f64) -> ReviewOutcome {
     let s = s_0(grade);
     let d = d_0(grade);
-    let i = f64::max(interval(s).round(), 1.0);
-    let due = today + chrono::Days::new(i as u64);
+    let due = today + chrono::Days::new(interval_days(s, retention));
     ReviewOutcome {
         stability: s,
         difficulty: d,
         due,
+        ease: None,
+        interval: None,
     }
 }
 
+/// Review a card last seen `days_elapsed` days ago. Reviewing ahead of the
+/// due date is fine: recall was likelier then, so stability grows less. A
+/// last review dated after `today` (clock skew, another time zone) counts as
+/// a review today, which scales stability by FSRS-5's short-term factor
+/// instead.
 pub fn review_existing(
     d: f64,
     s: f64,
     days_elapsed: f64,
     grade: Grade,
     today: NaiveDate,
+    retention: f64,
 ) -> ReviewOutcome {
-    let r = retrievability(days_elapsed, s);
-    let new_s = stability(d, s, r, grade);
+    let new_s = if days_elapsed < 1.0 {
+        s_short_term(s, grade)
+    } else {
+        stability(d, s, retrievability(days_elapsed, s), grade)
+    };
     let new_d = difficulty(d, grade);
-    let i = f64::max(interval(new_s).round(), 1.0);
-    let due = today + chrono::Days::new(i as u64);
+    let due = today + chrono::Days::new(interval_days(new_s, retention));
     ReviewOutcome {
         stability: new_s,
         difficulty: new_d,
         due,
+        ease: None,
+        interval: None,
     }
 }
 
@@ -166,7 +213,7 @@ mod tests {
     fn interval_roundtrip() {
         // For desired retention 0.9, interval(s) should equal s
         let s = 5.0;
-        let i = interval(s);
+        let i = interval(s, DEFAULT_RETENTION);
         assert!((i - s).abs() < 1e-10);
     }
 
@@ -210,7 +257,7 @@ mod tests {
     #[test]
     fn review_new_produces_future_due() {
         let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
-        let outcome = review_new(Grade::Good, today);
+        let outcome = review_new(Grade::Good, today, DEFAULT_RETENTION);
         assert!(outcome.due > today);
         assert!(outcome.stability > 0.0);
         assert!(outcome.difficulty >= 1.0);
@@ -220,7 +267,7 @@ mod tests {
     #[test]
     fn review_existing_good_extends_interval() {
         let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
-        let first = review_new(Grade::Good, today);
+        let first = review_new(Grade::Good, today, DEFAULT_RETENTION);
         let days = (first.due - today).num_days() as f64;
         let second = review_existing(
             first.difficulty,
@@ -228,8 +275,177 @@ mod tests {
             days,
             Grade::Good,
             first.due,
+            DEFAULT_RETENTION,
         );
         assert!(second.due > first.due);
         assert!(second.stability > first.stability);
     }
+
+    #[test]
+    fn reviewing_ahead_grows_stability_less() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let review = |elapsed| review_existing(5.0, 10.0, elapsed, Grade::Good, today, 0.9);
+        let early = review(3.0);
+        let on_time = review(10.0);
+        assert!(early.stability > 10.0);
+        assert!(early.stability < on_time.stability);
+        assert!(early.due < on_time.due);
+        // Negative elapsed time is a same-day review, not NaN.
+        let skewed = review(-40.0);
+        assert_eq!(skewed.stability, review(0.0).stability);
+        assert_eq!(skewed.due, review(0.0).due);
+    }
+
+    #[test]
+    fn higher_retention_shortens_interval() {
+        assert!(interval(10.0, 0.95) < interval(10.0, DEFAULT_RETENTION));
+        assert!(interval(10.0, 0.8) > interval(10.0, DEFAULT_RETENTION));
+    }
+
+    const GRADES: [Grade; 4] = [Grade::Forgot, Grade::Hard, Grade::Good, Grade::Easy];
+
+    /// Stabilities, difficulties and elapsed days to sweep properties over.
+    fn grid() -> impl Iterator<Item = (S, D, T)> {
+        let stabilities = [0.1, 0.4, 1.0, 3.2, 10.0, 45.0, 365.0, 3650.0];
+        let difficulties = [1.0, 2.5, 5.0, 7.5, 10.0];
+        let days = [0.0, 1.0, 3.0, 10.0, 100.0, 1000.0];
+        stabilities.into_iter().flat_map(move |s| {
+            difficulties
+                .into_iter()
+                .flat_map(move |d| days.into_iter().map(move |t| (s, d, t)))
+        })
+    }
+
+    // From fsrs-rs 1.x (FSRS-5, the default weights above), `test_next_interval`
+    // in src/inference.rs: a stability of 1 scheduled for desired retentions
+    // 0.1, 0.2, ... 1.0.
+    #[test]
+    fn matches_fsrs_rs_intervals() {
+        let intervals: Vec<u64> = (1..=10)
+            .map(|i| interval_days(1.0, f64::from(i) / 10.0))
+            .collect();
+        assert_eq!(intervals, [422, 102, 43, 22, 13, 8, 4, 2, 1, 1]);
+    }
+
+    // Memory states after each review of a card, worked out independently
+    // from the published FSRS-5 formulas and the default weights above (the
+    // ones fsrs-rs's `next_states` and `memory_state` use): the first review
+    // goes through `review_new`, the rest through `review_existing` with the
+    // given days elapsed, 0 taking the short-term path.
+    #[test]
+    fn matches_fsrs5_memory_states() {
+        use Grade::*;
+        let cases: [&[(Grade, f64, S, D)]; 4] = [
+            &[
+                (Good, 0.0, 3.1730, 5.2824),
+                (Good, 3.0, 10.7389, 5.2730),
+                (Good, 8.0, 28.5786, 5.2635),
+                (Good, 21.0, 70.3635, 5.2542),
+            ],
+            &[
+                (Forgot, 0.0, 0.4026, 7.1949),
+                (Good, 0.0, 0.5667, 7.1766),
+                (Good, 1.0, 2.4805, 7.1585),
+                (Forgot, 5.0, 1.0401, 8.0583),
+                (Good, 1.0, 2.5198, 8.0361),
+            ],
+            &[
+                (Easy, 0.0, 15.6911, 3.2245),
+                (Hard, 16.0, 26.1125, 4.3189),
+                (Easy, 30.0, 227.5729, 3.3962),
+            ],
+            &[
+                (Hard, 0.0, 1.1839, 6.4883),
+                (Hard, 0.0, 0.9942, 7.0405),
+                (Good, 2.0, 4.6196, 7.0229),
+            ],
+        ];
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        for reviews in cases {
+            let mut outcome = review_new(reviews[0].0, today, 0.9);
+            for (i, &(grade, elapsed, s, d)) in reviews.iter().enumerate() {
+                if i > 0 {
+                    outcome = review_existing(
+                        outcome.difficulty,
+                        outcome.stability,
+                        elapsed,
+                        grade,
+                        today,
+                        0.9,
+                    );
+                }
+                let state = (outcome.stability, outcome.difficulty);
+                assert!(
+                    (state.0 - s).abs() < 1e-3 && (state.1 - d).abs() < 1e-3,
+                    "review {i} of {reviews:?}: got {state:?}, want ({s}, {d})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn initial_state_matches_weights() {
+        for (grade, s) in GRADES.into_iter().zip(W) {
+            assert_eq!(s_0(grade), s);
+        }
+        assert!((d_0(Grade::Good) - 5.2824).abs() < 1e-4);
+        assert_eq!(d_0(Grade::Forgot), W[4]);
+    }
+
+    #[test]
+    fn same_day_reviews_use_short_term_stability() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let factors = GRADES.map(|g| review_existing(5.0, 2.0, 0.0, g, today, 0.9).stability / 2.0);
+        let expected = [0.5010, 0.8398, 1.4078, 2.3598];
+        for (got, want) in factors.into_iter().zip(expected) {
+            assert!((got - want).abs() < 1e-3, "{factors:?}");
+        }
+        // Difficulty moves as it does for any other review.
+        let same_day = review_existing(5.0, 2.0, 0.0, Grade::Good, today, 0.9);
+        let next_day = review_existing(5.0, 2.0, 1.0, Grade::Good, today, 0.9);
+        assert_eq!(same_day.difficulty, next_day.difficulty);
+    }
+
+    #[test]
+    fn retrievability_decreases_over_time() {
+        for (s, _, t) in grid() {
+            let r = retrievability(t, s);
+            assert!(r > 0.0 && r <= 1.0);
+            assert!(retrievability(t + 1.0, s) < r, "s={s} t={t}");
+            // Higher stability forgets more slowly.
+            assert!(retrievability(t + 1.0, s * 2.0) > retrievability(t + 1.0, s));
+        }
+        for s in [0.5, 3.0, 80.0] {
+            assert!((retrievability(interval(s, 0.9), s) - 0.9).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn difficulty_stays_in_bounds_and_orders_by_grade() {
+        for (_, d, _) in grid() {
+            let next: Vec<D> = GRADES.iter().map(|&g| difficulty(d, g)).collect();
+            assert!(next.iter().all(|d| (1.0..=10.0).contains(d)), "d={d}");
+            // A worse grade never leaves the card easier.
+            assert!(next.windows(2).all(|w| w[0] >= w[1]), "d={d}: {next:?}");
+        }
+    }
+
+    #[test]
+    fn stability_moves_the_right_way() {
+        for (s, d, t) in grid() {
+            let r = retrievability(t, s);
+            let next: Vec<S> = GRADES.iter().map(|&g| stability(d, s, r, g)).collect();
+            assert!(next.iter().all(|n| n.is_finite() && *n > 0.0));
+            assert!(next[0] <= s, "forgetting grew stability: s={s} d={d} t={t}");
+            assert!(next[1..].iter().all(|&n| n >= s), "s={s} d={d} t={t}");
+            assert!(next[1] <= next[2] && next[2] <= next[3], "{next:?}");
+            // Recalling after longer gaps earns more stability.
+            let later = retrievability(t + 1.0, s);
+            assert!(stability(d, s, later, Grade::Good) >= next[2]);
+            // Harder cards gain less.
+            if d < 10.0 {
+                assert!(stability(d + 0.5, s, r, Grade::Good) <= next[2]);
+            }
+        }
+    }
 }
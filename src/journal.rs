@@ -0,0 +1,254 @@
+// Undo journal: an append-only log of recent mutations to the collection.
+//
+// Each entry records the before/after state of every card it touched, so undo
+// is a matter of writing the "before" side back. Entries are stored one JSON
+// object per line in `journal.jsonl` inside the collection's data directory.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::card::Card;
+use crate::error::{Error, Result};
+
+const JOURNAL_FILE: &str = "journal.jsonl";
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Change {
+    pub file: PathBuf,
+    /// Position of the card within its file, used to reinsert deleted cards.
+    pub index: usize,
+    pub before: Option<Card>,
+    pub after: Option<Card>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    pub at: DateTime<Local>,
+    pub action: String,
+    pub changes: Vec<Change>,
+}
+
+impl Entry {
+    pub fn new(action: &str) -> Entry {
+        Entry {
+            at: Local::now(),
+            action: action.to_string(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Record a change to the card at `global_index` of the parallel
+    /// `cards`/`sources` vectors, as they were *before* the change.
+    pub fn push(
+        &mut self,
+        sources: &[PathBuf],
+        global_index: usize,
+        file: &Path,
+        before: Option<Card>,
+        after: Option<Card>,
+    ) {
+        let index = sources[..global_index.min(sources.len())]
+            .iter()
+            .filter(|s| s.as_path() == file)
+            .count();
+        self.changes.push(Change {
+            file: canonical(file),
+            index,
+            before,
+            after,
+        });
+    }
+
+    pub fn describe(&self) -> String {
+        let n = self.changes.len();
+        let noun = if n == 1 { "card" } else { "cards" };
+        format!("{} ({n} {noun})", self.action)
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn journal_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(JOURNAL_FILE)
+}
+
+fn read_entries(data_dir: &Path) -> Vec<Entry> {
+    let Ok(content) = std::fs::read_to_string(journal_path(data_dir)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn write_entries(data_dir: &Path, entries: &[Entry]) -> Result<()> {
+    let path = journal_path(data_dir);
+    let mut out = String::new();
+    for entry in entries {
+        let line = serde_json::to_string(entry).unwrap();
+        out.push_str(&line);
+        out.push('\n');
+    }
+    std::fs::write(&path, out).map_err(Error::io("write", path))
+}
+
+/// Append an entry, keeping only the most recent `MAX_ENTRIES`.
+pub fn record(data_dir: &Path, entry: &Entry) -> Result<()> {
+    if entry.changes.is_empty() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(data_dir).map_err(Error::io("create", data_dir))?;
+    let path = journal_path(data_dir);
+    let line = serde_json::to_string(entry).unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(Error::io("open", &path))?;
+    writeln!(file, "{line}").map_err(Error::io("write", &path))?;
+
+    let entries = read_entries(data_dir);
+    if entries.len() > MAX_ENTRIES {
+        write_entries(data_dir, &entries[entries.len() - MAX_ENTRIES..])?;
+    }
+    Ok(())
+}
+
+/// The most recent entry, if any.
+pub fn last(data_dir: &Path) -> Option<Entry> {
+    read_entries(data_dir).pop()
+}
+
+/// Remove and return the most recent entry.
+pub fn pop(data_dir: &Path) -> Result<Option<Entry>> {
+    let mut entries = read_entries(data_dir);
+    let entry = entries.pop();
+    if entry.is_some() {
+        write_entries(data_dir, &entries)?;
+    }
+    Ok(entry)
+}
+
+/// Apply the inverse of `entry` to the parallel `cards`/`sources` vectors,
+/// returning the files that need saving.
+pub fn revert(entry: &Entry, cards: &mut Vec<Card>, sources: &mut Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut touched: Vec<PathBuf> = Vec::new();
+    let mut resolved: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+    // Undo in reverse so later changes are unwound before earlier ones.
+    for change in entry.changes.iter().rev() {
+        let mut in_file = |sources: &[PathBuf], i: usize| {
+            let path = resolved
+                .entry(sources[i].clone())
+                .or_insert_with(|| canonical(&sources[i]));
+            *path == change.file
+        };
+        let id = change
+            .after
+            .as_ref()
+            .or(change.before.as_ref())
+            .map(|c| c.id.clone())
+            .unwrap_or_default();
+        let existing = (0..cards.len()).find(|&i| cards[i].id == id && in_file(sources, i));
+
+        let source = match (existing, &change.before) {
+            (Some(i), Some(before)) => {
+                cards[i] = before.clone();
+                sources[i].clone()
+            }
+            (Some(i), None) => {
+                cards.remove(i);
+                sources.remove(i)
+            }
+            (None, Some(before)) => {
+                let file_positions: Vec<usize> =
+                    (0..cards.len()).filter(|&i| in_file(sources, i)).collect();
+                let source = file_positions
+                    .first()
+                    .map(|&i| sources[i].clone())
+                    .unwrap_or_else(|| change.file.clone());
+                let at = match file_positions.get(change.index) {
+                    Some(&i) => i,
+                    None => file_positions.last().map_or(cards.len(), |&i| i + 1),
+                };
+                cards.insert(at, before.clone());
+                sources.insert(at, source.clone());
+                source
+            }
+            (None, None) => continue,
+        };
+        if !touched.contains(&source) {
+            touched.push(source);
+        }
+    }
+    touched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(id: &str, front: &str) -> Card {
+        Card {
+            deck: "d".into(),
+            front: front.into(),
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn record_and_pop() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.csv");
+        let mut entry = Entry::new("edit");
+        entry.push(
+            std::slice::from_ref(&file),
+            0,
+            &file,
+            Some(card("1", "old")),
+            Some(card("1", "new")),
+        );
+        record(dir.path(), &entry).unwrap();
+        assert_eq!(last(dir.path()).unwrap().action, "edit");
+        assert_eq!(pop(dir.path()).unwrap().unwrap().changes.len(), 1);
+        assert!(pop(dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn revert_edit_add_and_delete() {
+        let file = PathBuf::from("deck.csv");
+        let mut sources = vec![file.clone(), file.clone()];
+        let mut cards = vec![card("1", "one"), card("2", "two")];
+
+        // Edit card 1, delete card 2, add card 3.
+        let mut entry = Entry::new("mixed");
+        entry.push(
+            &sources,
+            0,
+            &file,
+            Some(card("1", "one")),
+            Some(card("1", "uno")),
+        );
+        entry.push(&sources, 1, &file, Some(card("2", "two")), None);
+        cards[0].front = "uno".into();
+        cards.remove(1);
+        sources.remove(1);
+        entry.push(&sources, 1, &file, None, Some(card("3", "three")));
+        cards.push(card("3", "three"));
+        sources.push(file.clone());
+
+        let touched = revert(&entry, &mut cards, &mut sources);
+        assert_eq!(touched, vec![file]);
+        let fronts: Vec<&str> = cards.iter().map(|c| c.front.as_str()).collect();
+        assert_eq!(fronts, vec!["one", "two"]);
+        assert_eq!(sources.len(), 2);
+    }
+}
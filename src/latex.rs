@@ -0,0 +1,410 @@
+// Best-effort LaTeX to Unicode for the terminal: `$x^2 + \alpha$` becomes
+// `x² + α`. Covers superscripts, subscripts, Greek letters, common symbols,
+// fractions and roots; anything else is left as written, minus the braces.
+
+/// Replace every `$…$` and `$$…$$` span in `text` with its Unicode rendering.
+/// Unterminated delimiters and `\$` are left alone.
+pub fn to_unicode(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = find_dollar(rest) {
+        let display = rest[start..].starts_with("$$");
+        let delim = if display { "$$" } else { "$" };
+        let body = &rest[start + delim.len()..];
+        let Some(end) = (if display {
+            body.find("$$")
+        } else {
+            find_dollar(body)
+        }) else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(&convert(&body[..end]));
+        rest = &body[end + delim.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Byte offset of the first `$` not escaped with a backslash.
+fn find_dollar(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '$' if !escaped => return Some(i),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Render the inside of a math span.
+fn convert(math: &str) -> String {
+    let mut parser = Parser {
+        chars: math.chars().collect(),
+        pos: 0,
+    };
+    parser.expr()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    /// Everything up to the end or an unmatched `}`.
+    fn expr(&mut self) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '}' => break,
+                '^' | '_' => {
+                    self.pos += 1;
+                    let arg = self.argument();
+                    out.push_str(&script(&arg, c == '^'));
+                }
+                _ => out.push_str(&self.atom()),
+            }
+        }
+        out
+    }
+
+    /// A braced group, a command or a single character.
+    fn atom(&mut self) -> String {
+        match self.next() {
+            Some('{') => {
+                let inner = self.expr();
+                self.next();
+                inner
+            }
+            Some('\\') => self.command(),
+            Some(c) => c.to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// A command's argument, skipping the spaces TeX ignores before it.
+    fn argument(&mut self) -> String {
+        while self.peek() == Some(' ') {
+            self.pos += 1;
+        }
+        self.atom()
+    }
+
+    fn command(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            // A control symbol such as `\{` or `\,`.
+            return match self.next() {
+                Some(',' | ';' | ':' | ' ') => " ".to_string(),
+                Some('!') => String::new(),
+                Some(c) => c.to_string(),
+                None => "\\".to_string(),
+            };
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.argument();
+                let den = self.argument();
+                format!("{}/{}", group(&num), group(&den))
+            }
+            "sqrt" => {
+                let mut index = String::new();
+                if self.peek() == Some('[') {
+                    self.pos += 1;
+                    while let Some(c) = self.next() {
+                        if c == ']' {
+                            break;
+                        }
+                        index.push(c);
+                    }
+                }
+                let radicand = group(&self.argument());
+                match index.as_str() {
+                    "" | "2" => format!("√{radicand}"),
+                    "3" => format!("∛{radicand}"),
+                    "4" => format!("∜{radicand}"),
+                    _ => format!("{}√{radicand}", script(&index, true)),
+                }
+            }
+            "text" | "textrm" | "textbf" | "textit" | "mathrm" | "mathbf" | "mathit" | "mathsf"
+            | "mathtt" | "operatorname" | "boldsymbol" => self.argument(),
+            "mathbb" => self.argument().chars().map(double_struck).collect(),
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" => {
+                if self.peek() == Some('.') {
+                    self.pos += 1;
+                }
+                String::new()
+            }
+            "sin" | "cos" | "tan" | "log" | "ln" | "exp" | "lim" | "max" | "min" | "det" => name,
+            "quad" => "  ".to_string(),
+            "qquad" => "    ".to_string(),
+            _ => match symbol(&name) {
+                Some(s) => s.to_string(),
+                None => format!("\\{name}"),
+            },
+        }
+    }
+}
+
+/// Parenthesise a fraction part or radicand that is more than one term.
+fn group(s: &str) -> String {
+    if s.chars().count() <= 1 || s.chars().all(|c| c.is_alphanumeric() || c == '.') {
+        s.to_string()
+    } else {
+        format!("({s})")
+    }
+}
+
+/// `s` in superscript or subscript characters, falling back to `^(…)` or
+/// `_(…)` when one of them has no Unicode form.
+fn script(s: &str, superscript: bool) -> String {
+    let map = if superscript {
+        superscript_char
+    } else {
+        subscript_char
+    };
+    match s.chars().map(map).collect::<Option<String>>() {
+        Some(converted) if !converted.is_empty() => converted,
+        _ => {
+            let mark = if superscript { '^' } else { '_' };
+            if s.chars().count() == 1 {
+                format!("{mark}{s}")
+            } else {
+                format!("{mark}({s})")
+            }
+        }
+    }
+}
+
+fn superscript_char(c: char) -> Option<char> {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    Some(match c {
+        '0'..='9' => DIGITS[c as usize - '0' as usize],
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'a' => 'ᵃ',
+        'b' => 'ᵇ',
+        'c' => 'ᶜ',
+        'd' => 'ᵈ',
+        'e' => 'ᵉ',
+        'f' => 'ᶠ',
+        'g' => 'ᵍ',
+        'h' => 'ʰ',
+        'i' => 'ⁱ',
+        'j' => 'ʲ',
+        'k' => 'ᵏ',
+        'l' => 'ˡ',
+        'm' => 'ᵐ',
+        'n' => 'ⁿ',
+        'o' => 'ᵒ',
+        'p' => 'ᵖ',
+        'r' => 'ʳ',
+        's' => 'ˢ',
+        't' => 'ᵗ',
+        'u' => 'ᵘ',
+        'v' => 'ᵛ',
+        'w' => 'ʷ',
+        'x' => 'ˣ',
+        'y' => 'ʸ',
+        'z' => 'ᶻ',
+        'T' => 'ᵀ',
+        '\'' | '′' => '′',
+        '∗' | '*' => '*',
+        _ => return None,
+    })
+}
+
+fn subscript_char(c: char) -> Option<char> {
+    const DIGITS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+    Some(match c {
+        '0'..='9' => DIGITS[c as usize - '0' as usize],
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'h' => 'ₕ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'l' => 'ₗ',
+        'm' => 'ₘ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'p' => 'ₚ',
+        'r' => 'ᵣ',
+        's' => 'ₛ',
+        't' => 'ₜ',
+        'u' => 'ᵤ',
+        'v' => 'ᵥ',
+        'x' => 'ₓ',
+        _ => return None,
+    })
+}
+
+fn double_struck(c: char) -> char {
+    match c {
+        'C' => 'ℂ',
+        'H' => 'ℍ',
+        'N' => 'ℕ',
+        'P' => 'ℙ',
+        'Q' => 'ℚ',
+        'R' => 'ℝ',
+        'Z' => 'ℤ',
+        _ => c,
+    }
+}
+
+fn symbol(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "rho" => "ρ",
+        "sigma" => "σ",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "cdot" => "·",
+        "times" => "×",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "le" | "leq" => "≤",
+        "ge" | "geq" => "≥",
+        "ne" | "neq" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "ll" => "≪",
+        "gg" => "≫",
+        "infty" => "∞",
+        "sum" => "∑",
+        "prod" => "∏",
+        "int" => "∫",
+        "oint" => "∮",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "setminus" => "∖",
+        "emptyset" | "varnothing" => "∅",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "oplus" => "⊕",
+        "otimes" => "⊗",
+        "circ" => "∘",
+        "degree" => "°",
+        "angle" => "∠",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "prime" => "′",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "mid" => "∣",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latex_to_unicode() {
+        assert_eq!(to_unicode("$E = mc^2$"), "E = mc²");
+        assert_eq!(to_unicode(r"$\alpha + \beta_{12}$"), "α + β₁₂");
+        assert_eq!(
+            to_unicode(r"$$\frac{1}{2}$$ and $\frac{a+b}{c}$"),
+            "1/2 and (a+b)/c"
+        );
+        assert_eq!(to_unicode(r"$\sqrt{x+1} \leq \sqrt[3]{y}$"), "√(x+1) ≤ ∛y");
+        assert_eq!(to_unicode(r"$x \in \mathbb{R}$"), "x ∈ ℝ");
+        // No Unicode form, so spelled out.
+        assert_eq!(
+            to_unicode(r"$x_{bc}$, $2^{q}$, $\log_2 n$"),
+            "x_(bc), 2^q, log₂ n"
+        );
+        assert_eq!(to_unicode(r"$\foo$"), r"\foo");
+        assert_eq!(to_unicode(r"costs \$5, or $5"), r"costs \$5, or $5");
+    }
+}
@@ -1,4 +1,43 @@
+pub mod anki;
+pub mod backup;
+pub mod bundle;
+pub mod bury;
 pub mod card;
+pub mod clock;
+pub mod clozify;
+pub mod collection;
+pub mod config;
+pub mod deck;
+pub mod doctor;
+pub mod drill;
+pub mod error;
+pub mod export;
 pub mod fsrs;
+pub mod journal;
+pub mod latex;
+pub mod lint;
+#[cfg(feature = "llm")]
+pub mod llm;
+pub mod lock;
+pub mod media;
+pub mod occlusion;
+pub mod quiz;
+pub mod quizlet;
+pub mod remind;
+pub mod reschedule;
 pub mod review;
+pub mod revlog;
+pub mod scheduler;
+pub mod sheet;
+pub mod simulate;
+pub mod state;
+pub mod stats;
+pub mod sync;
+pub mod trash;
+pub mod tts;
+pub mod user;
 pub mod web;
+pub mod webhook;
+
+pub use collection::{Collection, OpenOptions};
+pub use error::{Error, Result};
@@ -0,0 +1,93 @@
+// Checks for card text that would not review as intended: unbalanced or empty
+// cloze brackets, brackets in the back (shown there as typed), leftovers of
+// the `\n` line-break escape and empty fronts. Shared by `rote lint` and the
+// web editor's preview.
+
+/// What is wrong with a card's front and back, one message per problem.
+pub fn check(front: &str, back: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    if front.replace("\\n", "").trim().is_empty() {
+        problems.push("empty front".to_string());
+    }
+    brackets(front, &mut problems);
+    if !crate::card::extract_cloze_deletions(back).is_empty() {
+        problems.push("cloze brackets in the back are shown as typed".to_string());
+    }
+    for (field, text) in [("front", front), ("back", back)] {
+        line_breaks(field, text, &mut problems);
+    }
+    problems
+}
+
+fn brackets(text: &str, problems: &mut Vec<String>) {
+    let chars: Vec<(char, bool)> = crate::card::cloze_chars(text).collect();
+    let mut open = Vec::new();
+    for (i, &(ch, bracket)) in chars.iter().enumerate() {
+        match ch {
+            _ if !bracket => {}
+            '[' => open.push(i),
+            ']' => match open.pop() {
+                None => problems.push(format!("unmatched ] at character {}", i + 1)),
+                Some(start) => {
+                    let inner: String = chars[start + 1..i].iter().map(|&(c, _)| c).collect();
+                    let answer = inner.split("::").next().unwrap_or("");
+                    if answer.trim().is_empty() {
+                        problems.push(format!("empty cloze [] at character {}", start + 1));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    for start in open {
+        problems.push(format!("unclosed [ at character {}", start + 1));
+    }
+}
+
+fn line_breaks(field: &str, text: &str, problems: &mut Vec<String>) {
+    let trimmed = text.trim();
+    if trimmed.starts_with("\\n") || trimmed.ends_with("\\n") {
+        problems.push(format!("{field} starts or ends with a stray \\n"));
+    }
+    if text.contains("\\\\n") {
+        problems.push(format!(
+            "{field} has \\\\n, shown as a backslash and a line break"
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_cloze_and_line_break_mistakes() {
+        assert!(check("The [mitochondria] makes [ATP]", "Line one\\nline two").is_empty());
+        assert!(check("[x] in a\\[i\\]\\]", "a\\[0\\]").is_empty());
+        assert_eq!(
+            check("The [mitochondria makes ATP] ]", ""),
+            vec!["unmatched ] at character 30"]
+        );
+        assert_eq!(
+            check("[] and [open [::hint]", ""),
+            vec![
+                "empty cloze [] at character 1",
+                "empty cloze [] at character 14",
+                "unclosed [ at character 8",
+            ]
+        );
+        assert_eq!(
+            check(" \\n", "[answer]\\n"),
+            vec![
+                "empty front",
+                "cloze brackets in the back are shown as typed",
+                "front starts or ends with a stray \\n",
+                "back starts or ends with a stray \\n",
+            ]
+        );
+        assert_eq!(
+            check("a\\\\nb", "b"),
+            vec!["front has \\\\n, shown as a backslash and a line break"]
+        );
+    }
+}
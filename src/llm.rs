@@ -0,0 +1,109 @@
+// Card suggestions from study notes, asked of an OpenAI-compatible chat
+// completions endpoint (OpenAI itself, or a local Ollama or llama.cpp server)
+// for the web UI's Generate page. Built with `--features llm` and set up in
+// config.toml:
+//
+//     [llm]
+//     url = "http://localhost:11434/v1/chat/completions"
+//     model = "llama3.1"
+//     api_key_env = "OPENAI_API_KEY"   # only for hosted endpoints
+
+use std::time::Duration;
+
+use crate::config::Llm;
+
+const PROMPT: &str = "You write flashcards for spaced repetition from the user's study notes. \
+Reply with only a JSON array of cards, each {\"front\": \"...\", \"back\": \"...\"}. \
+Write short question and answer cards, one fact each, and cloze cards: a sentence from the \
+notes in \"front\" with each term to recall in square brackets, like \
+\"The [mitochondria] makes [ATP]\", and an empty \"back\". Do not use square brackets \
+anywhere else.";
+
+/// A proposed card; a cloze card's back is empty.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Suggestion {
+    pub front: String,
+    #[serde(default)]
+    pub back: String,
+}
+
+/// Cards for `notes`, as proposed by the endpoint in `config`.
+pub fn suggest(config: &Llm, notes: &str) -> Result<Vec<Suggestion>, String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(120)))
+        .build()
+        .into();
+    let body = serde_json::json!({
+        "model": config.model,
+        "temperature": 0.2,
+        "messages": [
+            { "role": "system", "content": PROMPT },
+            { "role": "user", "content": notes },
+        ],
+    });
+    let mut request = agent
+        .post(&config.url)
+        .header("Content-Type", "application/json");
+    if let Some(key) = config
+        .api_key_env
+        .as_deref()
+        .and_then(|v| std::env::var(v).ok())
+    {
+        request = request.header("Authorization", &format!("Bearer {key}"));
+    }
+    let text = request
+        .send(body.to_string())
+        .map_err(|e| format!("card suggestions failed: {e}"))?
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("card suggestions failed: {e}"))?;
+    let reply: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| format!("card suggestions failed: {e}"))?;
+    let content = reply["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or("card suggestions failed: the reply had no message")?;
+    parse(content)
+}
+
+/// The cards in a model's reply: a JSON array, perhaps in a code fence or
+/// after some words of its own.
+fn parse(reply: &str) -> Result<Vec<Suggestion>, String> {
+    let not_cards = || "the model did not reply with a list of cards".to_string();
+    let start = reply.find('[').ok_or_else(not_cards)?;
+    let end = reply.rfind(']').ok_or_else(not_cards)?;
+    let cards: Vec<Suggestion> =
+        serde_json::from_str(reply.get(start..=end).ok_or_else(not_cards)?)
+            .map_err(|_| not_cards())?;
+    Ok(cards
+        .into_iter()
+        .map(|c| Suggestion {
+            front: c.front.trim().to_string(),
+            back: c.back.trim().to_string(),
+        })
+        .filter(|c| !c.front.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cards_out_of_a_chatty_reply() {
+        let reply = "Here are your cards:\n```json\n[\n  {\"front\": \"What makes ATP?\", \"back\": \"The mitochondria\"},\n  {\"front\": \"The [nucleus] holds DNA \"},\n  {\"front\": \" \", \"back\": \"x\"}\n]\n```";
+        assert_eq!(
+            parse(reply).unwrap(),
+            vec![
+                Suggestion {
+                    front: "What makes ATP?".into(),
+                    back: "The mitochondria".into(),
+                },
+                Suggestion {
+                    front: "The [nucleus] holds DNA".into(),
+                    back: String::new(),
+                },
+            ]
+        );
+        assert!(parse("Sorry, I can't help with that.").is_err());
+    }
+}
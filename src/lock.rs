@@ -0,0 +1,133 @@
+// One writer per collection. Commands that change deck files hold an advisory
+// lock on `.rote/lock` while they run, so a second `rote serve`, or a
+// `rote drill` next to one, refuses to start instead of silently overwriting
+// the other's saves. The OS releases the lock when the process exits, crash or
+// not; the file only says who holds it:
+//
+//     4242 rote serve ~/cards
+//
+// With `--user` only that user's scheduling is written, so the lock is on
+// `.rote/users/<name>/lock` instead and two users can review side by side.
+// Their deck files are locked the first time one of them edits a card.
+// Commands that only read (`list`, `due`, `export`, ...) open collections
+// read-only, without either.
+
+use std::fs::{File, TryLockError};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+const FILE: &str = "lock";
+
+/// Held for as long as the process may write the collection.
+#[derive(Debug)]
+pub struct Lock {
+    _file: File,
+}
+
+/// Lock the collection whose data directory is `dir`, or fail with
+/// `Error::Locked` naming the process that has it.
+pub fn acquire(dir: &Path) -> Result<Lock> {
+    take(dir)?.map_err(|holder| Error::Locked {
+        dir: collection_root(dir),
+        holder,
+    })
+}
+
+/// Lock `name`'s scheduling in the collection whose data directory is `dir`,
+/// leaving the deck files to `acquire`. Fails with `Error::UserLocked`.
+pub fn acquire_user(dir: &Path, name: &str) -> Result<Lock> {
+    take(&dir.join("users").join(name))?.map_err(|holder| Error::UserLocked {
+        dir: collection_root(dir),
+        name: name.to_string(),
+        holder,
+    })
+}
+
+/// Lock `dir/lock`, or describe who holds it.
+fn take(dir: &Path) -> Result<std::result::Result<Lock, String>> {
+    std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+    let path = dir.join(FILE);
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(Error::io("open", &path))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let mut holder = String::new();
+            let _ = file.read_to_string(&mut holder);
+            return Ok(Err(describe(&holder)));
+        }
+        Err(TryLockError::Error(e)) => return Err(Error::io("lock", &path)(e)),
+    }
+    file.set_len(0)
+        .and_then(|()| writeln!(file, "{} {}", std::process::id(), command_line()))
+        .map_err(Error::io("write", &path))?;
+    Ok(Ok(Lock { _file: file }))
+}
+
+/// The directory holding `dir`, absolute, to name the collection by.
+fn collection_root(dir: &Path) -> PathBuf {
+    let root = match dir.parent() {
+        Some(root) if !root.as_os_str().is_empty() => root,
+        _ => Path::new("."),
+    };
+    std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf())
+}
+
+/// `rote serve ~/cards (pid 4242)` from the lock file's `4242 rote serve ~/cards`.
+fn describe(holder: &str) -> String {
+    match holder.trim().split_once(' ') {
+        Some((pid, command)) => format!("{command} (pid {pid})"),
+        None => "another rote".to_string(),
+    }
+}
+
+fn command_line() -> String {
+    let mut args = std::env::args();
+    let program = args
+        .next()
+        .and_then(|p| Some(Path::new(&p).file_name()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "rote".to_string());
+    std::iter::once(program)
+        .chain(args)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_writer_is_refused_until_the_first_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join(".rote");
+        let lock = acquire(&data).unwrap();
+        let Err(Error::Locked { holder, .. }) = acquire(&data) else {
+            panic!("the collection was locked twice");
+        };
+        assert!(holder.ends_with(&format!("(pid {})", std::process::id())));
+        drop(lock);
+        assert!(acquire(&data).is_ok());
+        assert_eq!(describe(""), "another rote");
+    }
+
+    #[test]
+    fn users_lock_their_own_scheduling() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join(".rote");
+        let _alex = acquire_user(&data, "alex").unwrap();
+        let _sam = acquire_user(&data, "sam").unwrap();
+        let _files = acquire(&data).unwrap();
+        assert!(matches!(
+            acquire_user(&data, "alex"),
+            Err(Error::UserLocked { name, .. }) if name == "alex"
+        ));
+    }
+}
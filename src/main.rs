@@ -1,165 +1,881 @@
-use std::collections::HashMap;
-use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-use rote::{card, fsrs, review};
+use rote::config::Config;
+use rote::web::ServeOptions;
+use rote::{Collection, OpenOptions};
+use rote::{
+    anki, backup, bundle, bury, card, clock, config, deck, drill, export, fsrs, journal, quizlet,
+    remind, review, revlog, sheet, user, webhook,
+};
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    // Global: machine-readable output wherever a command prints results.
+    let json = args.iter().any(|a| a == "--json");
+    args.retain(|a| a != "--json");
+    // Global: rewrite only what changed when saving deck files.
+    let git_friendly = args.iter().any(|a| a == "--git-friendly");
+    args.retain(|a| a != "--git-friendly");
+    // Global: keep rote's own files here rather than in `.rote` by the decks.
+    let data_dir = args.iter().position(|a| a == "--data-dir").map(|i| {
+        if i + 1 >= args.len() {
+            eprintln!("--data-dir needs a directory");
+            std::process::exit(1);
+        }
+        args.remove(i);
+        PathBuf::from(args.remove(i))
+    });
+    // Global: keep scheduling in this user's state file instead of the CSVs.
+    let user = args.iter().position(|a| a == "--user").map(|i| {
+        if i + 1 >= args.len() {
+            eprintln!("--user needs a name");
+            std::process::exit(1);
+        }
+        args.remove(i);
+        args.remove(i)
+    });
 
     if args.len() < 2 {
         eprintln!("Usage: rote <command> [args...]");
         eprintln!("Commands:");
-        eprintln!("  drill <paths...>            Review cards in the terminal");
-        eprintln!("  serve <paths...> [-p PORT]   Start web UI (default port 3000)");
+        eprintln!("  drill <paths...> [options]  Review cards in the terminal");
+        eprintln!("        --deck NAME  --all  --limit N");
+        eprintln!("        --tag T  --due-within N  --ahead N  --forgotten  --hardest N");
+        eprintln!("        --sample N  --no-schedule  --order ORDER  --cram");
+        eprintln!("        --include GLOB  --exclude GLOB  --low-memory  --unicode-math");
+        eprintln!("        --auto-reveal SECS  --resume");
+        eprintln!("  import apkg <file> <csv>    Import an Anki package into a deck file");
+        eprintln!("  import csv <file> <csv>     Import a CSV/TSV export into a deck file");
+        eprintln!("        --map front=N,back=N,deck=N,tags=N,media=N,extra=N  --deck NAME");
+        eprintln!("        --delimiter C  --no-header");
+        eprintln!("  import quizlet <file> <csv> Import a Quizlet export into a deck file");
+        eprintln!("        --term-sep SEP  --row-sep SEP  --deck NAME");
+        eprintln!("  export markdown <dir> <paths...>");
+        eprintln!("  export mochi <out.mochi> <paths...>");
+        eprintln!("                              Export decks for other apps (--deck NAME)");
+        eprintln!("  export bundle <deck.rote> <paths...> [--keep-state]");
+        eprintln!("  import bundle <deck.rote> <csv>");
+        eprintln!("                              Share decks with their media");
+        eprintln!("  undo <paths...> [-n N]      Revert the most recent change(s)");
+        eprintln!("  state export|import <state.json> <paths...>");
+        eprintln!("                              Save or restore scheduling state by card id");
+        eprintln!("  reschedule spread|reset|retention <paths...> [--deck NAME]... [--tag TAG]...");
+        eprintln!("        --days N  --retention R");
+        eprintln!(
+            "                              Spread a backlog, reset cards, or refit due dates"
+        );
+        eprintln!("  sync <url> <paths...> [--auth USER:PASS]");
+        eprintln!("                              Exchange scheduling with a rote server");
+        eprintln!("  backup <dir> <paths...> [--keep N]");
+        eprintln!("                              Snapshot deck files and review log");
+        eprintln!("  restore <snapshot.zip>      Put a snapshot's files back");
+        eprintln!("  merge <a.csv> <b.csv> -o <merged.csv>");
+        eprintln!("                              Combine two copies of a deck file by card id");
+        eprintln!(
+            "  list <paths...> [--due] [--deck NAME]... [--tag TAG]... [--format text|tsv|json]"
+        );
+        eprintln!("                              Print cards");
+        eprintln!("  due <paths...> [--deck NAME]... [--format text|tsv|json] [--notify]");
+        eprintln!("                              Print the number of cards due");
+        eprintln!("  remind <paths...> [--at HH:MM]... [--url URL]");
+        eprintln!("                              Desktop notification of the cards due");
+        eprintln!("  lint <paths...> [--deck NAME]... [--format text|tsv|json]");
+        eprintln!("                              Report cloze and line-break mistakes");
+        eprintln!("  doctor <paths...> [--dry-run] [--format text|tsv|json]");
+        eprintln!("                              Repair duplicate ids, bad dates, missing media");
+        eprintln!("  media check <paths...> [--prune] [--fetch] [--format text|tsv|json]");
+        eprintln!("                              Missing, unused and remote media files");
+        eprintln!("  stats <paths...> [--all] [--hardest N]");
+        eprintln!("                              Review sessions and retention, or hardest cards");
+        eprintln!("  forecast <paths...> [--days N] [--deck NAME]...");
+        eprintln!("                              Cards falling due per day");
+        eprintln!("  simulate [paths...] [--days N] [--new-per-day N] [--retention R]");
+        eprintln!("        [--runs N] [--seed N] [--fresh]");
+        eprintln!("                              Simulate workload and retention ahead");
+        eprintln!("  clozify [text] [--deck PATH]... [--term T]... [-i]");
+        eprintln!(
+            "                              Suggest cloze deletions (reads stdin without text)"
+        );
+        eprintln!("  serve <paths...> [-p PORT] [--base-path PREFIX]");
+        eprintln!("        [--include GLOB] [--exclude GLOB] [--low-memory] [--auth USER:PASS]");
+        eprintln!("        [--tls-cert PEM --tls-key PEM] [--cdn] [--auto-reveal SECS]");
+        eprintln!("        [--log-level LEVEL] [--log-json] [--read-only]");
+        eprintln!("                              Start web UI (default port 3000)");
+        eprintln!();
+        eprintln!("--json makes drill, list, due, lint, stats, forecast and simulate print JSON.");
+        eprintln!("--user NAME keeps that user's scheduling apart from the shared CSVs.");
+        eprintln!("--git-friendly saves deck files with the smallest possible diff.");
+        eprintln!("--data-dir DIR (or $ROTE_DATA_DIR) keeps rote's own files there, not in .rote.");
+        eprintln!(
+            "Defaults (paths, port, retention, ...) are read from ~/.config/rote/config.toml."
+        );
         std::process::exit(1);
     }
 
+    let mut config = config::load().unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    if user.is_some() {
+        config.user = user;
+    }
+    config.git_friendly |= git_friendly;
+    config.data_dir = data_dir
+        .or_else(|| std::env::var_os("ROTE_DATA_DIR").map(PathBuf::from))
+        .or(config.data_dir.take());
+    clock::configure(clock::Clock {
+        day_starts_at: config.day_starts_at,
+        timezone: config.timezone,
+    });
+
     match args[1].as_str() {
         "drill" => {
-            if args.len() < 3 {
-                eprintln!("Usage: rote drill <paths...>");
+            let drill_args = parse_drill_args(&args[2..], &config);
+            if drill_args.paths.is_empty() {
+                eprintln!(
+                    "Usage: rote drill <paths...> [--deck NAME]... [--all] [--limit N] [--tag TAG]... [--due-within N] [--ahead N] [--forgotten] [--hardest N] [--sample N] [--no-schedule] [--order random|risk|overdue|difficulty|interleaved|created] [--cram] [--auto-reveal SECS] [--resume]"
+                );
                 std::process::exit(1);
             }
-            drill(&args[2..]);
+            drill(&drill_args, &config, json);
         }
         "serve" => {
-            if args.len() < 3 {
-                eprintln!("Usage: rote serve <paths...> [-p PORT]");
+            let (paths, options) = parse_serve_args(&args[2..], &config);
+            if paths.is_empty() {
+                eprintln!("Usage: rote serve <paths...> [-p PORT] [--base-path PREFIX]");
                 std::process::exit(1);
             }
-            let (paths, port) = parse_serve_args(&args[2..]);
-            tokio::runtime::Runtime::new()
+            let served = tokio::runtime::Runtime::new()
                 .unwrap()
-                .block_on(rote::web::serve(paths, port));
+                .block_on(rote::web::serve(paths, options));
+            if let Err(e) = served {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
         }
+        "import" => import(&args[2..], &config),
+        "export" => export(&args[2..], &config),
+        "undo" => undo(&args[2..], &config),
+        "clozify" => clozify(&args[2..]),
+        "list" => list(&args[2..], &config, json),
+        "due" => due(&args[2..], &config, json),
+        "remind" => remind(&args[2..], &config),
+        "lint" => lint(&args[2..], &config, json),
+        "doctor" => doctor(&args[2..], &config, json),
+        "media" => media(&args[2..], &config, json),
+        "stats" => stats(&args[2..], &config, json),
+        "forecast" => forecast(&args[2..], &config, json),
+        "simulate" => simulate(&args[2..], &config, json),
+        "state" => state(&args[2..], &config),
+        "reschedule" => reschedule(&args[2..], &config),
+        "sync" => sync(&args[2..], &config),
+        "merge" => merge(&args[2..], &config),
+        "backup" => backup(&args[2..], &config),
+        "restore" => restore(&args[2..]),
         _ => {
             eprintln!("Unknown command: {}", args[1]);
-            eprintln!("Commands: drill, serve");
+            eprintln!(
+                "Commands: drill, serve, import, export, undo, clozify, list, due, lint, doctor, media, forecast, simulate, stats, state, reschedule, sync, merge, backup, restore, remind"
+            );
             std::process::exit(1);
         }
     }
 }
 
-fn parse_serve_args(args: &[String]) -> (Vec<String>, u16) {
+fn parse_serve_args(args: &[String], config: &Config) -> (Vec<String>, ServeOptions) {
     let mut paths = Vec::new();
-    let mut port = 3000u16;
+    let mut tls_cert = config.tls_cert.clone();
+    let mut tls_key = config.tls_key.clone();
+    let mut options = ServeOptions {
+        port: config.port.unwrap_or(3000),
+        retention: config.desired_retention,
+        new_per_day: config.new_per_day,
+        order: config.order,
+        auto_reveal: config.auto_reveal,
+        breaks: config.breaks,
+        theme: config.theme,
+        keymap: config.keys.clone(),
+        user: config.user.clone(),
+        auth: config.auth.clone(),
+        cdn: config.cdn,
+        editor_url: config.editor_url.clone(),
+        webhooks: config.webhooks.clone(),
+        llm: config.llm.clone(),
+        log_json: config.log_json,
+        data_dir: config.data_dir.clone(),
+        git_friendly: config.git_friendly,
+        ..ServeOptions::default()
+    };
+    let mut log_level = config.log_level.clone();
     let mut i = 0;
     while i < args.len() {
         if args[i] == "-p" && i + 1 < args.len() {
-            port = args[i + 1].parse().unwrap_or_else(|_| {
+            options.port = args[i + 1].parse().unwrap_or_else(|_| {
                 eprintln!("Invalid port: {}", args[i + 1]);
                 std::process::exit(1);
             });
             i += 2;
+        } else if args[i] == "--auto-reveal" && i + 1 < args.len() {
+            options.auto_reveal = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                eprintln!("Invalid value for --auto-reveal: {}", args[i + 1]);
+                std::process::exit(1);
+            }));
+            i += 2;
+        } else if args[i] == "--low-memory" {
+            options.low_memory = true;
+            i += 1;
+        } else if args[i] == "--cdn" {
+            options.cdn = true;
+            i += 1;
+        } else if args[i] == "--tls-cert" && i + 1 < args.len() {
+            tls_cert = Some(PathBuf::from(&args[i + 1]));
+            i += 2;
+        } else if args[i] == "--tls-key" && i + 1 < args.len() {
+            tls_key = Some(PathBuf::from(&args[i + 1]));
+            i += 2;
+        } else if args[i] == "--auth" && i + 1 < args.len() {
+            options.auth = Some(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--base-path" && i + 1 < args.len() {
+            options.base_path = args[i + 1].clone();
+            i += 2;
+        } else if args[i] == "--log-level" && i + 1 < args.len() {
+            log_level = Some(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--log-json" {
+            options.log_json = true;
+            i += 1;
+        } else if args[i] == "--read-only" {
+            options.read_only = true;
+            i += 1;
+        } else if parse_path_filter(&args[i..], &mut options.files) {
+            i += 2;
         } else {
             paths.push(args[i].clone());
             i += 1;
         }
     }
-    (paths, port)
+    options.tls = match (tls_cert, tls_key) {
+        (Some(cert), Some(key)) => Some((cert, key)),
+        (None, None) => None,
+        _ => {
+            eprintln!("--tls-cert and --tls-key must be given together");
+            std::process::exit(1);
+        }
+    };
+    if let Some(level) = log_level {
+        options.log_level = level.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid log level: {level} (expected error, warn, info, debug or trace)");
+            std::process::exit(1);
+        });
+    }
+    if paths.is_empty() {
+        paths = config.paths.clone();
+    }
+    (paths, options)
 }
 
-fn drill(args: &[String]) {
-    let files = card::discover_files(args);
-    if files.is_empty() {
-        eprintln!("No CSV files found.");
+/// Consume `--include PATTERN` / `--exclude PATTERN` at the start of `args`.
+fn parse_path_filter(args: &[String], filter: &mut card::PathFilter) -> bool {
+    let [flag, pattern, ..] = args else {
+        return false;
+    };
+    let result = match flag.as_str() {
+        "--include" => filter.include(pattern),
+        "--exclude" => filter.exclude(pattern),
+        _ => return false,
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
         std::process::exit(1);
     }
+    true
+}
 
-    // Load all cards, tracking source file per card
-    let mut all_cards: Vec<card::Card> = Vec::new();
-    let mut card_source: Vec<PathBuf> = Vec::new();
+struct DrillArgs {
+    paths: Vec<String>,
+    files: card::PathFilter,
+    filter: review::SessionFilter,
+    /// Decks chosen on the command line (empty means all); `None` prompts.
+    decks: Option<Vec<String>>,
+    retention: f64,
+    new_per_day: Option<usize>,
+    /// Seconds before the answer is revealed without pressing Enter.
+    auto_reveal: Option<u64>,
+    breaks: review::Breaks,
+    /// `--order`; without it a single deck's settings choose, then this.
+    order: Option<review::Order>,
+    default_order: review::Order,
+    low_memory: bool,
+    unicode_math: bool,
+    user: Option<String>,
+    webhooks: Vec<webhook::Webhook>,
+    /// Continue the interrupted session instead of starting one.
+    resume: bool,
+}
 
-    for file in &files {
-        match card::load_csv(file) {
-            Ok(cards) => {
-                for c in cards {
-                    card_source.push(file.clone());
-                    all_cards.push(c);
-                }
+fn parse_drill_args(args: &[String], config: &Config) -> DrillArgs {
+    let mut parsed = DrillArgs {
+        paths: Vec::new(),
+        files: card::PathFilter::default(),
+        filter: review::SessionFilter::default(),
+        decks: None,
+        retention: config.desired_retention,
+        new_per_day: config.new_per_day,
+        auto_reveal: config.auto_reveal,
+        breaks: config.breaks,
+        order: None,
+        default_order: config.order,
+        low_memory: false,
+        unicode_math: false,
+        user: config.user.clone(),
+        webhooks: config.webhooks.clone(),
+        resume: false,
+    };
+    let number = |flag: &str, value: &str| -> usize {
+        value.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid value for {flag}: {value}");
+            std::process::exit(1);
+        })
+    };
+    let mut ahead = false;
+    let mut i = 0;
+    while i < args.len() {
+        let has_value = i + 1 < args.len();
+        match args[i].as_str() {
+            "--tag" if has_value => {
+                parsed.filter.tags.push(args[i + 1].clone());
+                i += 2;
             }
-            Err(e) => {
-                eprintln!("Warning: {e}");
+            "--deck" if has_value => {
+                parsed
+                    .decks
+                    .get_or_insert_with(Vec::new)
+                    .push(args[i + 1].clone());
+                i += 2;
+            }
+            "--all" => {
+                parsed.decks = Some(Vec::new());
+                i += 1;
+            }
+            "--limit" if has_value => {
+                parsed.filter.limit = Some(number(&args[i], &args[i + 1]));
+                i += 2;
+            }
+            "--auto-reveal" if has_value => {
+                parsed.auto_reveal = Some(number(&args[i], &args[i + 1]) as u64);
+                i += 2;
+            }
+            "--due-within" if has_value => {
+                parsed.filter.due_within = Some(number(&args[i], &args[i + 1]) as i64);
+                i += 2;
+            }
+            "--ahead" if has_value => {
+                parsed.filter.due_within = Some(number(&args[i], &args[i + 1]) as i64);
+                ahead = true;
+                i += 2;
+            }
+            "--hardest" if has_value => {
+                parsed.filter.hardest = Some(number(&args[i], &args[i + 1]));
+                i += 2;
+            }
+            "--sample" if has_value => {
+                parsed.filter.sample = Some(number(&args[i], &args[i + 1]));
+                i += 2;
+            }
+            "--order" if has_value => {
+                parsed.order = Some(review::Order::parse(&args[i + 1]).unwrap_or_else(|| {
+                    let names: Vec<&str> = review::Order::ALL.iter().map(|o| o.as_str()).collect();
+                    eprintln!(
+                        "Invalid order: {} (expected one of {})",
+                        args[i + 1],
+                        names.join(", ")
+                    );
+                    std::process::exit(1);
+                }));
+                i += 2;
+            }
+            "--forgotten" => {
+                parsed.filter.forgotten = true;
+                i += 1;
+            }
+            "--cram" => {
+                parsed.filter.all = true;
+                parsed.filter.practice = true;
+                i += 1;
+            }
+            "--no-schedule" => {
+                parsed.filter.practice = true;
+                i += 1;
+            }
+            "--resume" => {
+                parsed.resume = true;
+                i += 1;
+            }
+            "--low-memory" => {
+                parsed.low_memory = true;
+                i += 1;
+            }
+            "--unicode-math" => {
+                parsed.unicode_math = true;
+                i += 1;
+            }
+            _ if parse_path_filter(&args[i..], &mut parsed.files) => i += 2,
+            _ => {
+                parsed.paths.push(args[i].clone());
+                i += 1;
             }
         }
     }
+    // Reviewing ahead, the cards likeliest forgotten by the due date come
+    // first, so a session cut short still covers them.
+    if ahead && parsed.order.is_none() {
+        parsed.order = Some(review::Order::AtRisk);
+    }
+    if parsed.paths.is_empty() {
+        parsed.paths = config.paths.clone();
+    }
+    parsed
+}
 
-    if all_cards.is_empty() {
-        eprintln!("No cards found.");
+/// Open the collection under `paths`, warning about files that could not be
+/// read. Exits when there are none or the profile is invalid. Where rote's
+/// own files go and how deck files are saved come from `config`.
+fn open_collection(paths: &[String], options: OpenOptions, config: &Config) -> Collection {
+    let options = OpenOptions {
+        data_dir: config.data_dir.clone(),
+        git_friendly: config.git_friendly,
+        ..options
+    };
+    let collection = Collection::open(paths, &options).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
         std::process::exit(1);
+    });
+    for e in &collection.skipped {
+        eprintln!("Warning: {e}");
     }
+    collection
+}
 
-    let today = chrono::Local::now().date_naive();
+/// The data directory of the collection under `paths`: `--data-dir`, or
+/// `.rote` beside the decks.
+fn data_dir(config: &Config, paths: &[String]) -> PathBuf {
+    config
+        .data_dir
+        .clone()
+        .unwrap_or_else(|| card::data_dir(paths))
+}
 
-    // Show deck summaries
-    let summaries = review::deck_summaries(&all_cards, today);
-    println!("Decks:");
-    for (i, s) in summaries.iter().enumerate() {
-        println!(
-            "  {}: {} ({} due / {} total)",
-            i + 1,
-            s.name,
-            s.due,
-            s.total
-        );
+/// Lock the collection under `paths` for a command that writes its files
+/// without opening it. Exits when another instance holds the lock.
+fn lock_collection(config: &Config, paths: &[String]) -> rote::lock::Lock {
+    rote::lock::acquire(&data_dir(config, paths)).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Open the `--user` profile for the collection under `paths` and swap their
+/// scheduling into `cards`. Exits on an invalid profile.
+fn open_profile(
+    config: &Config,
+    paths: &[String],
+    cards: &mut [card::Card],
+) -> Option<user::Profile> {
+    let user = config.user.as_deref()?;
+    let mut profile = user::Profile::open(&data_dir(config, paths), user).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    profile.apply(cards);
+    Some(profile)
+}
+
+/// Where revlog and journal entries go: the user's directory, if any.
+fn user_data_dir(profile: Option<&user::Profile>, config: &Config, paths: &[String]) -> PathBuf {
+    profile.map_or_else(|| data_dir(config, paths), |p| p.data_dir().to_path_buf())
+}
+
+/// Write one file's cards. With a profile the CSV keeps its own scheduling;
+/// the user's is saved separately by `Profile::save`.
+fn save_file(
+    path: &std::path::Path,
+    file_cards: Vec<card::Card>,
+    index: &mut card::Index,
+    profile: Option<&user::Profile>,
+    git_friendly: bool,
+) -> rote::Result<()> {
+    let file_cards = match profile {
+        Some(p) => p.shared(&file_cards),
+        None => file_cards,
+    };
+    if index.is_empty() {
+        card::save_csv(path, &file_cards, git_friendly)
+    } else {
+        card::save_csv_indexed(
+            path,
+            &file_cards.iter().collect::<Vec<_>>(),
+            index,
+            git_friendly,
+        )
     }
-    println!("  0: All decks");
-    println!();
+}
 
-    // Prompt for selection
-    let selected_decks = prompt_deck_selection(&summaries);
+fn save_profile(profile: Option<&mut user::Profile>, cards: &[card::Card]) {
+    if let Some(profile) = profile
+        && let Err(e) = profile.save(cards)
+    {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
 
-    // Filter to due cards in selected decks
-    let due_indices = review::filter_due(&all_cards, today);
-    let due_in_selected: Vec<usize> = due_indices
-        .into_iter()
-        .filter(|&i| selected_decks.is_empty() || selected_decks.contains(&all_cards[i].deck))
+/// A drill's changes not yet written out.
+#[derive(Default)]
+struct Unsaved {
+    journal: Vec<journal::Entry>,
+    reviews: Vec<revlog::Review>,
+}
+
+impl Unsaved {
+    /// Save the files holding graded or edited cards, leaving the others (and
+    /// their mtimes) alone, then the journal and review log.
+    fn save(&mut self, collection: &mut Collection) {
+        if let Err(e) = collection.save() {
+            eprintln!("Error: {e}");
+        }
+        for entry in &self.journal {
+            if let Err(e) = journal::record(collection.data_dir(), entry) {
+                eprintln!("Warning: {e}");
+            }
+        }
+        if let Err(e) = revlog::append(collection.data_dir(), &self.reviews) {
+            eprintln!("Warning: {e}");
+        }
+        *self = Unsaved::default();
+    }
+}
+
+/// Record the cards still in `queue` for `rote drill --resume`.
+fn save_progress(
+    data_dir: &std::path::Path,
+    progress: &mut drill::Progress,
+    counts: [u32; 4],
+    queue: &VecDeque<&review::ReviewItem>,
+    cards: &[card::Card],
+) -> rote::Result<()> {
+    progress.counts = counts;
+    progress.remaining = queue
+        .iter()
+        .map(|item| cards[item.card_index].id.clone())
         .collect();
+    drill::save(data_dir, progress)
+}
 
-    if due_in_selected.is_empty() {
-        println!("No cards due for review.");
-        return;
+/// A drill saves its progress after this many grades, besides at the end.
+const SAVE_EVERY: u32 = 10;
+
+/// Lines from stdin, read on a background thread so a read can time out.
+/// Stdin is only read when a line is asked for, so an editor launched in
+/// between gets the terminal to itself.
+///
+/// The first Ctrl-C ends the wait for a line and sets `interrupted`, so the
+/// drill can save before it stops; a second one quits at once.
+struct Input {
+    requests: std::sync::mpsc::Sender<()>,
+    lines: std::sync::mpsc::Receiver<String>,
+    /// A line was asked for and has not arrived yet.
+    pending: bool,
+    interrupted: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl Input {
+    fn spawn() -> Self {
+        let (requests, asked) = std::sync::mpsc::channel::<()>();
+        let (sent, lines) = std::sync::mpsc::channel();
+        let interrupted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (flag, wake) = (interrupted.clone(), sent.clone());
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    flag.store(true, std::sync::atomic::Ordering::SeqCst);
+                    let _ = wake.send(String::new());
+                }
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
+                }
+            });
+        });
+        std::thread::spawn(move || {
+            while asked.recv().is_ok() {
+                let mut buf = String::new();
+                if io::stdin().read_line(&mut buf).is_err() || sent.send(buf).is_err() {
+                    break;
+                }
+            }
+        });
+        Input {
+            requests,
+            lines,
+            pending: false,
+            interrupted,
+        }
+    }
+
+    fn interrupted(&self) -> bool {
+        self.interrupted.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// The next line, or `None` if `timeout` passes first. A line typed after
+    /// the timeout is returned by the next call.
+    fn line(&mut self, timeout: Option<std::time::Duration>) -> Option<String> {
+        if !self.pending {
+            self.requests.send(()).ok()?;
+            self.pending = true;
+        }
+        let line = match timeout {
+            Some(t) => self.lines.recv_timeout(t).ok()?,
+            None => self.lines.recv().ok()?,
+        };
+        self.pending = false;
+        Some(line)
+    }
+}
+
+fn drill(args: &DrillArgs, config: &Config, json: bool) {
+    let mut collection = open_collection(
+        &args.paths,
+        OpenOptions {
+            files: args.files.clone(),
+            low_memory: args.low_memory,
+            user: args.user.clone(),
+            ..OpenOptions::default()
+        },
+        config,
+    );
+    if collection.cards.is_empty() {
+        eprintln!("No cards found.");
+        std::process::exit(1);
     }
 
-    println!("{} cards due for review.\n", due_in_selected.len());
+    let today = clock::today();
+
+    let data_dir = collection.data_dir().to_path_buf();
+    let decks = deck::load(collection.dir()).unwrap_or_else(|e| {
+        eprintln!("Warning: {e}");
+        HashMap::new()
+    });
+    let retention_for =
+        |deck: &str| deck::setting(&decks, deck, |c| c.desired_retention).unwrap_or(args.retention);
+    let scheduler_for = |deck: &str| {
+        deck::setting(&decks, deck, |c| c.scheduler)
+            .unwrap_or_default()
+            .scheduler()
+    };
+    let pass_fail_for = |deck: &str| deck::setting(&decks, deck, |c| c.pass_fail).unwrap_or(false);
+    let saved = drill::load(&data_dir).unwrap_or_else(|e| {
+        eprintln!("Warning: {e}");
+        None
+    });
+    let mut filter = args.filter.clone();
+    let (selected, mut progress) = if args.resume {
+        let Some(progress) = saved else {
+            println!("No interrupted session to resume.");
+            return;
+        };
+        filter.practice = progress.practice;
+        (progress.remaining(&collection.cards), progress)
+    } else {
+        if let Some(saved) = &saved {
+            println!(
+                "An interrupted session has {} cards left; `rote drill --resume` continues it.",
+                saved.remaining.len()
+            );
+        }
+        let selected = choose_cards(
+            args,
+            &collection.cards,
+            &mut filter,
+            &decks,
+            &data_dir,
+            today,
+        );
+        let progress = drill::Progress {
+            session: uuid::Uuid::new_v4().to_string(),
+            remaining: Vec::new(),
+            total: selected.len(),
+            counts: [0; 4],
+            practice: filter.practice,
+            started: today,
+        };
+        (selected, progress)
+    };
+    if selected.is_empty() {
+        if args.resume {
+            println!("None of the session's cards are left.");
+            if let Err(e) = drill::clear(&data_dir) {
+                eprintln!("Warning: {e}");
+            }
+        }
+        return;
+    }
+    // Only the cards in this session need their content.
+    for &i in &selected {
+        if let Err(e) = collection.materialize(i) {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+    // Highlight cloze answers on reveal, unless piped or NO_COLOR is set.
+    let marks = if io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        review::ANSI_HIGHLIGHT
+    } else {
+        ("", "")
+    };
+    let items = review::build_review_items(&collection.cards, &selected, args.unicode_math, marks);
 
-    // Build review items and shuffle
-    let mut items = review::build_review_items(&all_cards, &due_in_selected);
-    shuffle(&mut items);
+    if args.resume {
+        println!(
+            "Resuming: {} of {} cards left.",
+            items.len(),
+            progress.total
+        );
+    } else {
+        println!("{} cards to review.", items.len());
+    }
+    if filter.practice {
+        println!("Practice session: scheduling will not be changed.");
+    }
+    println!();
 
     // Drill loop
-    let mut counts = [0u32; 4]; // forgot, hard, good, easy
-    let stdin = io::stdin();
-    let mut stdin = stdin.lock();
+    let mut counts = progress.counts; // forgot, hard, good, easy
+    let mut graded_decks = Vec::new();
+    let mut unsaved = Unsaved::default();
+    let session_id = progress.session.clone();
+    let mut input = Input::spawn();
+    let auto_reveal = args.auto_reveal.map(std::time::Duration::from_secs);
 
-    for (i, item) in items.iter().enumerate() {
-        println!("[{}/{}] {}", i + 1, items.len(), item.deck);
+    // Cards still to grade; a skipped one goes to the back.
+    let mut queue: VecDeque<&review::ReviewItem> = items.iter().collect();
+    let mut done = progress.total.saturating_sub(items.len());
+    let started = std::time::Instant::now();
+    // Since the session started or the last break was suggested.
+    let mut since_break = (started, 0);
+    while let Some(item) = queue.pop_front() {
+        println!(
+            "[{}/{} · {}] {}",
+            done + 1,
+            progress.total,
+            review::format_elapsed(started.elapsed().as_secs()),
+            item.deck
+        );
         println!();
         println!("{}", item.front_display);
         println!();
+        let shown = std::time::Instant::now();
 
-        // Wait for Enter to reveal
-        print!("Press Enter to reveal...");
+        // Wait for Enter (or the auto-reveal timeout) to reveal
+        print!("Press Enter to reveal (s to skip)...");
         io::stdout().flush().unwrap();
-        let mut buf = String::new();
-        stdin.read_line(&mut buf).unwrap();
+        let buf = input.line(auto_reveal).unwrap_or_else(|| {
+            println!();
+            String::new()
+        });
+        if input.interrupted() {
+            queue.push_front(item);
+            break;
+        }
+        if buf.trim() == "s" {
+            queue.push_back(item);
+            println!();
+            continue;
+        }
+        done += 1;
 
         println!("{}", item.reveal_display);
         println!();
 
-        // Get rating
+        // Get rating, showing where each grade would schedule the card. Pass/fail
+        // decks offer just Forgot and Good, as in the web UI.
+        let card = &collection.cards[item.card_index];
+        let pass_fail = pass_fail_for(&card.deck);
+        let prompt = match (filter.practice, pass_fail) {
+            (true, true) => "Rate (1=forgot, 3=good, e=edit): ".to_string(),
+            (true, false) => "Rate (1=forgot, 2=hard, 3=good, 4=easy, e=edit): ".to_string(),
+            (false, _) => {
+                let [forgot, hard, good, easy] = review::next_intervals(
+                    card,
+                    today,
+                    retention_for(&card.deck),
+                    scheduler_for(&card.deck),
+                )
+                .map(review::format_interval);
+                if pass_fail {
+                    format!("Rate (1=forgot · {forgot}, 3=good · {good}, e=edit): ")
+                } else {
+                    format!(
+                        "Rate (1=forgot · {forgot}, 2=hard · {hard}, 3=good · {good}, 4=easy · {easy}, e=edit): "
+                    )
+                }
+            }
+        };
         let grade = loop {
-            print!("Rate (1=forgot, 2=hard, 3=good, 4=easy): ");
+            print!("{prompt}");
             io::stdout().flush().unwrap();
-            buf.clear();
-            stdin.read_line(&mut buf).unwrap();
+            let buf = input.line(None).unwrap_or_default();
+            if input.interrupted() {
+                break None;
+            }
+            if buf.trim() == "e" {
+                let before = collection.cards[item.card_index].clone();
+                match edit_card(&mut collection.cards[item.card_index]) {
+                    Ok(true) => {
+                        let mut entry = journal::Entry::new("edit");
+                        entry.push(
+                            &collection.sources,
+                            item.card_index,
+                            &collection.sources[item.card_index],
+                            Some(before),
+                            Some(collection.cards[item.card_index].clone()),
+                        );
+                        unsaved.journal.push(entry);
+                        collection.touch(item.card_index);
+                        let revised = review::build_review_items(
+                            &collection.cards,
+                            &[item.card_index],
+                            args.unicode_math,
+                            marks,
+                        );
+                        println!();
+                        println!("{}", revised[0].reveal_display);
+                        println!();
+                    }
+                    Ok(false) => println!("No changes."),
+                    Err(e) => eprintln!("Error: {e}"),
+                }
+                continue;
+            }
             if let Ok(n) = buf.trim().parse::<u8>()
                 && let Some(g) = fsrs::Grade::from_u8(n)
             {
-                break g;
+                break Some(if pass_fail { g.pass_fail() } else { g });
             }
-            println!("Please enter 1, 2, 3, or 4.");
+            if pass_fail {
+                println!("Please enter 1 or 3 (or e to edit the card).");
+            } else {
+                println!("Please enter 1, 2, 3, or 4 (or e to edit the card).");
+            }
+        };
+        let Some(grade) = grade else {
+            queue.push_front(item);
+            break;
         };
 
         let grade_idx = match grade {
@@ -169,84 +885,2175 @@ fn drill(args: &[String]) {
             fsrs::Grade::Easy => 3,
         };
         counts[grade_idx] += 1;
+        graded_decks.push(collection.cards[item.card_index].deck.clone());
 
-        review::apply_grade(&mut all_cards[item.card_index], grade, today);
+        let mut logged = revlog::Review::new(
+            &collection.cards[item.card_index],
+            grade,
+            &session_id,
+            today,
+        );
+        logged.practice = filter.practice;
+        logged.answer_ms = Some(shown.elapsed().as_millis() as u64);
+        unsaved.reviews.push(logged);
+        if !filter.practice {
+            let i = item.card_index;
+            let deck = &collection.cards[i].deck;
+            let (retention, scheduler) = (retention_for(deck), scheduler_for(deck));
+            // The card's content is in memory, so grading cannot fail.
+            let before = collection
+                .grade_with(i, grade, today, retention, scheduler)
+                .unwrap();
+            let mut entry = journal::Entry::new("grade");
+            entry.push(
+                &collection.sources,
+                i,
+                &collection.sources[i],
+                Some(before),
+                Some(collection.cards[i].clone()),
+            );
+            unsaved.journal.push(entry);
+        }
         println!();
+        if counts.iter().sum::<u32>() % SAVE_EVERY == 0 {
+            unsaved.save(&mut collection);
+            let saved = save_progress(&data_dir, &mut progress, counts, &queue, &collection.cards);
+            if let Err(e) = saved {
+                eprintln!("Warning: {e}");
+            }
+        }
+        since_break.1 += 1;
+        let resting = since_break.0.elapsed().as_secs();
+        if !queue.is_empty() && args.breaks.due(resting, since_break.1) {
+            print!(
+                "{} cards in {}. Time for a break? Press Enter to go on, or Ctrl-C to stop here.",
+                since_break.1,
+                review::format_elapsed(resting)
+            );
+            io::stdout().flush().unwrap();
+            input.line(None);
+            if input.interrupted() {
+                break;
+            }
+            println!();
+            since_break = (std::time::Instant::now(), 0);
+        }
     }
 
-    // Save all cards back to their source files
-    let mut files_to_save: HashMap<PathBuf, Vec<usize>> = HashMap::new();
-    for (i, source) in card_source.iter().enumerate() {
-        files_to_save.entry(source.clone()).or_default().push(i);
+    unsaved.save(&mut collection);
+    let interrupted = input.interrupted();
+    let saved = if interrupted {
+        save_progress(&data_dir, &mut progress, counts, &queue, &collection.cards)
+    } else {
+        drill::clear(&data_dir)
+    };
+    if let Err(e) = saved {
+        eprintln!("Warning: {e}");
     }
 
-    for (path, indices) in &files_to_save {
-        let file_cards: Vec<card::Card> = indices.iter().map(|&i| all_cards[i].clone()).collect();
-        if let Err(e) = card::save_csv(path, &file_cards) {
-            eprintln!("Error saving {}: {e}", path.display());
+    if !interrupted && counts.iter().sum::<u32>() > 0 {
+        graded_decks.sort();
+        graded_decks.dedup();
+        let event = webhook::Event::SessionComplete {
+            counts,
+            decks: graded_decks,
+            practice: filter.practice,
+        };
+        for e in webhook::send(&args.webhooks, &event) {
+            eprintln!("Warning: {e}");
         }
     }
 
     // Session summary
-    println!("Session complete!");
-    println!(
-        "  Forgot: {}, Hard: {}, Good: {}, Easy: {}",
-        counts[0], counts[1], counts[2], counts[3]
-    );
-}
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "session": session_id,
+                "reviews": counts.iter().sum::<u32>(),
+                "counts": session_counts(counts),
+                "practice": filter.practice,
+                "interrupted": interrupted,
+            })
+        );
+    } else if interrupted {
+        println!();
+        println!(
+            "Session interrupted; saved {} of {} cards. `rote drill --resume` continues it.",
+            counts.iter().sum::<u32>(),
+            progress.total
+        );
+        println!(
+            "  Forgot: {}, Hard: {}, Good: {}, Easy: {}",
+            counts[0], counts[1], counts[2], counts[3]
+        );
+    } else {
+        println!("Session complete!");
+        println!(
+            "  Forgot: {}, Hard: {}, Good: {}, Easy: {}",
+            counts[0], counts[1], counts[2], counts[3]
+        );
+    }
 
-fn prompt_deck_selection(summaries: &[review::DeckSummary]) -> Vec<String> {
-    let stdin = io::stdin();
-    let mut stdin = stdin.lock();
-    loop {
-        print!("Select deck(s) (comma-separated numbers, or 0 for all): ");
+    if interrupted {
+        return;
+    }
+
+    // Optional note, e.g. "tired, grades unreliable"
+    print!("Session note (Enter to skip): ");
+    io::stdout().flush().unwrap();
+    let text = input.line(None).unwrap_or_default();
+    let text = text.trim();
+    if !text.is_empty() {
+        print!("Exclude this session from retention stats? [y/N] ");
         io::stdout().flush().unwrap();
-        let mut buf = String::new();
-        stdin.read_line(&mut buf).unwrap();
+        let answer = input.line(None).unwrap_or_default();
+        let note = revlog::SessionNote {
+            at: chrono::Local::now(),
+            session: session_id,
+            note: text.to_string(),
+            exclude: answer.trim().eq_ignore_ascii_case("y"),
+        };
+        if let Err(e) = revlog::add_note(&data_dir, &note) {
+            eprintln!("Warning: {e}");
+        }
+    }
+}
 
-        let mut selected = Vec::new();
-        let mut valid = true;
+/// Pick a new session's cards: the decks from `--deck` or a prompt, then the
+/// filter, limits and order. Prints why when there are none.
+fn choose_cards(
+    args: &DrillArgs,
+    cards: &[card::Card],
+    filter: &mut review::SessionFilter,
+    decks: &HashMap<String, deck::DeckConfig>,
+    data_dir: &std::path::Path,
+    today: chrono::NaiveDate,
+) -> Vec<usize> {
+    let summaries = review::deck_summaries(cards, today);
+    if let Some(decks) = &args.decks {
+        if let Some(unknown) = decks
+            .iter()
+            .find(|d| !summaries.iter().any(|s| &s.name == *d))
+        {
+            eprintln!("Unknown deck: {unknown}");
+            std::process::exit(1);
+        }
+        filter.decks = decks.clone();
+    } else {
+        // Show deck summaries
+        println!("Decks:");
+        for (i, s) in summaries.iter().enumerate() {
+            println!(
+                "  {}: {}{} ({} due / {} total)",
+                i + 1,
+                "  ".repeat(s.depth),
+                deck::leaf(&s.name),
+                s.due,
+                s.total
+            );
+        }
+        println!("  0: All decks");
+        println!();
 
-        for part in buf.trim().split(',') {
-            let part = part.trim();
-            if part.is_empty() {
-                continue;
-            }
-            match part.parse::<usize>() {
-                Ok(0) => return Vec::new(), // all decks
-                Ok(n) if n >= 1 && n <= summaries.len() => {
-                    selected.push(summaries[n - 1].name.clone());
-                }
-                _ => {
-                    valid = false;
-                    break;
-                }
-            }
+        // Prompt for selection
+        filter.decks = prompt_deck_selection(&summaries);
+    }
+
+    filter.order = match (args.order, filter.decks.as_slice()) {
+        (Some(order), _) => order,
+        (None, [name]) => deck::setting(decks, name, |c| c.order).unwrap_or(args.default_order),
+        (None, _) => args.default_order,
+    };
+    if filter.decks.is_empty() {
+        filter.skip_decks = deck::unavailable(decks, clock::now());
+        if !filter.skip_decks.is_empty() {
+            println!(
+                "Skipping decks outside their availability window: {}",
+                filter.skip_decks.join(", ")
+            );
         }
+    }
+    filter.skip_cards = bury::load(data_dir, clock::today());
+    let reviews = revlog::load(data_dir);
+    let forgotten = revlog::forgotten_in_last_session(&reviews);
+    if !filter.practice {
+        filter.new_limit = args
+            .new_per_day
+            .map(|n| n.saturating_sub(revlog::new_cards_on(&reviews, today)));
+        filter.deck_limits = deck::limits(decks, &reviews, today);
+    }
+    let mut selected = filter.select(cards, today, &forgotten);
 
-        if valid && !selected.is_empty() {
-            return selected;
+    if selected.is_empty() {
+        println!("No cards match.");
+        return selected;
+    }
+
+    // Shuffle, then sample and order
+    review::shuffle(cards, &mut selected, review::time_seed());
+    let selected = filter.arrange(cards, selected, today);
+    if selected.is_empty() {
+        println!("No cards left under today's limits.");
+    }
+    selected
+}
+
+/// Open `card`'s front and back in `$VISUAL` or `$EDITOR`, as the front, a
+/// `---` line, then the back. Returns whether either changed.
+fn edit_card(card: &mut card::Card) -> rote::Result<bool> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut command = editor.split_whitespace();
+    let program = command.next().ok_or(rote::Error::NoEditor)?;
+    let path = std::env::temp_dir().join(format!("rote-{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, format!("{}\n---\n{}\n", card.front, card.back)).map_err(|source| {
+        rote::Error::Io {
+            action: "write",
+            path: path.clone(),
+            source,
         }
-        println!("Invalid selection. Try again.");
+    })?;
+    let status = std::process::Command::new(program)
+        .args(command)
+        .arg(&path)
+        .status();
+    let text = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            return Err(rote::Error::Exited {
+                program: editor,
+                status,
+                stderr: String::new(),
+            });
+        }
+        Err(source) => {
+            return Err(rote::Error::Spawn {
+                program: editor,
+                source,
+            });
+        }
+    }
+    let text = text.map_err(|source| rote::Error::Io {
+        action: "read",
+        path: path.clone(),
+        source,
+    })?;
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    let Some((front, back)) = text
+        .split_once("\n---\n")
+        .or_else(|| text.strip_suffix("\n---").map(|front| (front, "")))
+    else {
+        return Err(rote::Error::NoSeparator);
+    };
+    if front == card.front && back == card.back {
+        return Ok(false);
+    }
+    card.front = front.to_string();
+    card.back = back.to_string();
+    Ok(true)
+}
+
+fn import(args: &[String], config: &Config) {
+    match args.first().map(String::as_str) {
+        Some("apkg") => import_apkg(&args[1..], config),
+        Some("csv") => import_csv(&args[1..], config),
+        Some("quizlet") => import_quizlet(&args[1..], config),
+        Some("bundle") => import_bundle(&args[1..], config),
+        Some(kind) => {
+            eprintln!("Unknown import format: {kind} (expected apkg, csv, quizlet or bundle)");
+            std::process::exit(1);
+        }
+        None => {
+            eprintln!("Usage: rote import apkg|csv|quizlet|bundle <source> <deck.csv> [options]");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn import_apkg(args: &[String], config: &Config) {
+    let [source, target] = args else {
+        eprintln!("Usage: rote import apkg <package.apkg> <deck.csv>");
+        std::process::exit(1);
+    };
+    let imported = anki::import_apkg(
+        &PathBuf::from(source),
+        &card::media_dir(&PathBuf::from(target)),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    add_imported(target, imported.cards, false, config);
+    println!(
+        "Media: {} copied, {} already present.",
+        imported.media_copied, imported.media_reused
+    );
+}
+
+fn import_bundle(args: &[String], config: &Config) {
+    let [source, target] = args else {
+        eprintln!("Usage: rote import bundle <deck.rote> <deck.csv>");
+        std::process::exit(1);
+    };
+    let target_path = PathBuf::from(target);
+    let imported = bundle::import(&PathBuf::from(source), &card::media_dir(&target_path))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        });
+    // Re-importing an updated bundle must not duplicate cards.
+    let existing: HashSet<String> = card::load_csv(&target_path)
+        .map(|cards| cards.into_iter().map(|c| c.id).collect())
+        .unwrap_or_default();
+    let (present, new): (Vec<_>, Vec<_>) = imported
+        .cards
+        .into_iter()
+        .partition(|c| existing.contains(&c.id));
+    add_imported(target, new, false, config);
+    if !present.is_empty() {
+        println!("Skipped {} cards already in {target}.", present.len());
     }
+    println!(
+        "Media: {} copied, {} already present.",
+        imported.media_copied, imported.media_reused
+    );
 }
 
-fn shuffle<T>(items: &mut [T]) {
-    // Simple Fisher-Yates using a basic seeded RNG (xorshift64)
-    let mut state: u64 = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64;
-    if state == 0 {
-        state = 1;
+fn import_csv(args: &[String], config: &Config) {
+    let mut positional = Vec::new();
+    let mut map = None;
+    let mut deck = None;
+    let mut delimiter = None;
+    let mut has_header = true;
+    let mut i = 0;
+    while i < args.len() {
+        let has_value = i + 1 < args.len();
+        match args[i].as_str() {
+            "--map" if has_value => {
+                map = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--deck" if has_value => {
+                deck = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--delimiter" if has_value => {
+                let value = args[i + 1].replace("\\t", "\t");
+                let [byte] = value.as_bytes() else {
+                    eprintln!(
+                        "Invalid delimiter: {} (expected one character)",
+                        args[i + 1]
+                    );
+                    std::process::exit(1);
+                };
+                delimiter = Some(*byte);
+                i += 2;
+            }
+            "--no-header" => {
+                has_header = false;
+                i += 1;
+            }
+            _ => {
+                positional.push(args[i].clone());
+                i += 1;
+            }
+        }
     }
+    let [source, target] = positional.as_slice() else {
+        eprintln!(
+            "Usage: rote import csv <file> <deck.csv> [--map front=N,back=N,deck=N,tags=N,media=N,extra=N]"
+        );
+        eprintln!("       [--deck NAME] [--delimiter C] [--no-header]");
+        std::process::exit(1);
+    };
 
-    for i in (1..items.len()).rev() {
-        // xorshift64
-        state ^= state << 13;
-        state ^= state >> 7;
-        state ^= state << 17;
-        let j = (state as usize) % (i + 1);
-        items.swap(i, j);
+    let table = sheet::read(&PathBuf::from(source), delimiter, has_header).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    let map = map.unwrap_or_else(|| prompt_mapping(&table));
+    let mapping = sheet::Mapping::parse(&map, table.header.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    let deck = deck.unwrap_or_else(|| card::default_deck(&PathBuf::from(target)));
+    let origin = card::Origin::path_text(Path::new(source), Path::new(target));
+    let cards = sheet::to_cards(&table, &mapping, &deck, Some(&origin));
+    let skipped = table.rows.len() - cards.len();
+    add_imported(target, cards, true, config);
+    if skipped > 0 {
+        println!("Skipped {skipped} rows with an empty front.");
+    }
+}
+
+fn import_quizlet(args: &[String], config: &Config) {
+    let mut positional = Vec::new();
+    let mut term_sep = "\t".to_string();
+    let mut row_sep = "\n".to_string();
+    let mut deck = None;
+    let mut i = 0;
+    while i < args.len() {
+        let has_value = i + 1 < args.len();
+        match args[i].as_str() {
+            "--term-sep" if has_value => {
+                term_sep = quizlet::delimiter(&args[i + 1]);
+                i += 2;
+            }
+            "--row-sep" if has_value => {
+                row_sep = quizlet::delimiter(&args[i + 1]);
+                i += 2;
+            }
+            "--deck" if has_value => {
+                deck = Some(args[i + 1].clone());
+                i += 2;
+            }
+            _ => {
+                positional.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+    let [source, target] = positional.as_slice() else {
+        eprintln!(
+            "Usage: rote import quizlet <export.txt> <deck.csv> [--term-sep SEP] [--row-sep SEP]"
+        );
+        eprintln!("       [--deck NAME]   (SEP: tab, comma, semicolon, newline or text)");
+        std::process::exit(1);
+    };
+    let text = std::fs::read_to_string(source).unwrap_or_else(|e| {
+        eprintln!("Error: failed to read {source}: {e}");
+        std::process::exit(1);
+    });
+    let deck = deck.unwrap_or_else(|| card::default_deck(&PathBuf::from(target)));
+    let imported = quizlet::parse(&text, &term_sep, &row_sep, &deck).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    add_imported(target, imported.cards, true, config);
+    if imported.skipped > 0 {
+        println!(
+            "Skipped {} rows without a term delimiter.",
+            imported.skipped
+        );
+    }
+}
+
+fn export(args: &[String], config: &Config) {
+    let usage = || -> ! {
+        eprintln!("Usage: rote export markdown <dir> <paths...> [--deck NAME]...");
+        eprintln!("       rote export mochi <deck.mochi> <paths...> [--deck NAME]...");
+        eprintln!(
+            "       rote export bundle <deck.rote> <paths...> [--deck NAME]... [--keep-state]"
+        );
+        std::process::exit(1);
+    };
+    let [format, out, rest @ ..] = args else {
+        usage();
+    };
+    let mut paths = Vec::new();
+    let mut decks = Vec::new();
+    let mut files = card::PathFilter::default();
+    let mut keep_state = false;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i] == "--deck" && i + 1 < rest.len() {
+            decks.push(rest[i + 1].clone());
+            i += 2;
+        } else if rest[i] == "--keep-state" {
+            keep_state = true;
+            i += 1;
+        } else if parse_path_filter(&rest[i..], &mut files) {
+            i += 2;
+        } else {
+            paths.push(rest[i].clone());
+            i += 1;
+        }
+    }
+    if paths.is_empty() {
+        paths = config.paths.clone();
+    }
+    if paths.is_empty() {
+        usage();
+    }
+    let Collection { cards, sources, .. } = open_collection(
+        &paths,
+        OpenOptions {
+            files,
+            read_only: true,
+            ..OpenOptions::default()
+        },
+        config,
+    );
+    let (cards, sources): (Vec<_>, Vec<_>) = cards
+        .into_iter()
+        .zip(sources)
+        .filter(|(c, _)| decks.is_empty() || decks.iter().any(|d| deck::contains(d, &c.deck)))
+        .unzip();
+    if cards.is_empty() {
+        eprintln!("No cards to export.");
+        std::process::exit(1);
+    }
+    let out = PathBuf::from(out);
+    let result = match format.as_str() {
+        "markdown" => export::markdown(&cards, &sources, &out),
+        "mochi" => export::mochi(&cards, &sources, &out),
+        "bundle" => bundle::export(&cards, &sources, &out, keep_state),
+        _ => usage(),
+    };
+    let exported = result.unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    println!(
+        "Exported {} cards in {} decks to {} ({} media files).",
+        exported.cards,
+        exported.decks,
+        out.display(),
+        exported.media
+    );
+    if !exported.missing.is_empty() {
+        eprintln!("Warning: media not found: {}", exported.missing.join(", "));
+    }
+}
+
+/// Ask which column holds each field, showing the header and first row.
+fn prompt_mapping(table: &sheet::Table) -> String {
+    if !io::stdin().is_terminal() {
+        eprintln!("Pass --map front=N,back=N,... to choose columns.");
+        std::process::exit(1);
+    }
+    let sample = table.rows.first();
+    println!("Columns:");
+    for c in 0..table.width() {
+        let name = table.header.as_ref().and_then(|h| h.get(c));
+        let example = sample.and_then(|r| r.get(c)).map_or("", String::as_str);
+        let example: String = example.chars().take(40).collect();
+        match name {
+            Some(name) => println!("  {}. {name}  (e.g. {example:?})", c + 1),
+            None => println!("  {}. (e.g. {example:?})", c + 1),
+        }
+    }
+    let mut spec = Vec::new();
+    for (field, required) in [
+        ("front", true),
+        ("back", false),
+        ("deck", false),
+        ("tags", false),
+        ("media", false),
+        ("extra", false),
+    ] {
+        loop {
+            if required {
+                print!("{field} column: ");
+            } else {
+                print!("{field} column (Enter for none): ");
+            }
+            io::stdout().flush().unwrap();
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+                std::process::exit(1);
+            }
+            let answer = answer.trim();
+            if answer.is_empty() && !required {
+                break;
+            }
+            // Check the answer on its own before moving on.
+            let check = format!("front={answer}");
+            match sheet::Mapping::parse(&check, table.header.as_deref()) {
+                Ok(_) => {
+                    spec.push(format!("{field}={answer}"));
+                    break;
+                }
+                Err(e) => println!("{e}"),
+            }
+        }
+    }
+    spec.join(",")
+}
+
+/// Append imported cards to `target` (created if missing), as one undoable
+/// journal entry. With `match_fronts`, cards whose front the file already has
+/// are left out (see `card::drop_known_fronts`), for imports whose cards get
+/// new ids each time.
+fn add_imported(
+    target_arg: &str,
+    mut imported: Vec<card::Card>,
+    match_fronts: bool,
+    config: &Config,
+) {
+    let target = PathBuf::from(target_arg);
+    if imported.is_empty() {
+        println!("No new cards for {}.", target.display());
+        return;
+    }
+    let _lock = lock_collection(config, &[target_arg.to_string()]);
+    let mut cards = if target.exists() {
+        card::load_csv(&target).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        })
+    } else {
+        Vec::new()
+    };
+    if match_fronts {
+        let known = card::drop_known_fronts(&cards, &mut imported);
+        if known > 0 {
+            println!("Skipped {known} cards whose front is already in {target_arg}.");
+        }
+        if imported.is_empty() {
+            println!("No new cards for {}.", target.display());
+            return;
+        }
+    }
+    if target.exists() {
+        let data_dir = data_dir(config, &[target_arg.to_string()]);
+        let files = backup::with_revlog(std::slice::from_ref(&target), &data_dir);
+        if let Err(e) = backup::before("import", &files, &data_dir) {
+            eprintln!("Warning: no snapshot before import: {e}");
+        }
+    }
+    let count = imported.len();
+    let mut entry = journal::Entry::new("import");
+    let sources = vec![target.clone(); cards.len() + count];
+    for (i, c) in imported.iter().enumerate() {
+        entry.push(&sources, cards.len() + i, &target, None, Some(c.clone()));
+    }
+    cards.extend(imported);
+    if let Err(e) = card::save_csv(&target, &cards, config.git_friendly) {
+        eprintln!("Error saving {}: {e}", target.display());
+        std::process::exit(1);
+    }
+    if let Err(e) = journal::record(&data_dir(config, &[target_arg.to_string()]), &entry) {
+        eprintln!("Warning: {e}");
+    }
+
+    println!("Imported {count} cards into {}.", target.display());
+}
+
+fn undo(args: &[String], config: &Config) {
+    let mut paths = Vec::new();
+    let mut steps = 1usize;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-n" && i + 1 < args.len() {
+            steps = args[i + 1].parse().unwrap_or_else(|_| {
+                eprintln!("Invalid count: {}", args[i + 1]);
+                std::process::exit(1);
+            });
+            i += 2;
+        } else {
+            paths.push(args[i].clone());
+            i += 1;
+        }
+    }
+
+    if paths.is_empty() {
+        paths = config.paths.clone();
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: rote undo <paths...> [-n N]");
+        std::process::exit(1);
+    }
+    let _lock = lock_collection(config, &paths);
+
+    let mut profile = open_profile(config, &paths, &mut []);
+    let data_dir = user_data_dir(profile.as_ref(), config, &paths);
+    for _ in 0..steps {
+        let entry = match journal::pop(&data_dir) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => {
+                println!("Nothing to undo.");
+                return;
+            }
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        };
+
+        // Load every file the entry touched, revert, and write them back.
+        let mut cards = Vec::new();
+        let mut sources = Vec::new();
+        let mut files: Vec<PathBuf> = entry.changes.iter().map(|c| c.file.clone()).collect();
+        files.sort();
+        files.dedup();
+        for file in &files {
+            if !file.exists() {
+                continue;
+            }
+            match card::load_csv(file) {
+                Ok(loaded) => {
+                    sources.extend(std::iter::repeat_n(file.clone(), loaded.len()));
+                    cards.extend(loaded);
+                }
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        if let Some(profile) = &mut profile {
+            profile.apply(&mut cards);
+        }
+        for path in journal::revert(&entry, &mut cards, &mut sources) {
+            let file_cards: Vec<card::Card> = cards
+                .iter()
+                .zip(&sources)
+                .filter(|(_, s)| **s == path)
+                .map(|(c, _)| c.clone())
+                .collect();
+            let saved = save_file(
+                &path,
+                file_cards,
+                &mut card::Index::default(),
+                profile.as_ref(),
+                config.git_friendly,
+            );
+            if let Err(e) = saved {
+                eprintln!("Error saving {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+        save_profile(profile.as_mut(), &cards);
+        println!(
+            "Undid {} from {}.",
+            entry.describe(),
+            entry.at.format("%Y-%m-%d %H:%M")
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Tsv,
+    Json,
+}
+
+struct ListArgs {
+    paths: Vec<String>,
+    files: card::PathFilter,
+    decks: Vec<String>,
+    tags: Vec<String>,
+    due_only: bool,
+    format: Format,
+    user: Option<String>,
+}
+
+fn parse_list_args(args: &[String], config: &Config, json: bool) -> ListArgs {
+    let mut parsed = ListArgs {
+        paths: Vec::new(),
+        files: card::PathFilter::default(),
+        decks: Vec::new(),
+        tags: Vec::new(),
+        due_only: false,
+        format: if json { Format::Json } else { Format::Text },
+        user: config.user.clone(),
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let has_value = i + 1 < args.len();
+        match args[i].as_str() {
+            "--deck" if has_value => {
+                parsed.decks.push(args[i + 1].clone());
+                i += 2;
+            }
+            "--tag" if has_value => {
+                parsed.tags.push(args[i + 1].clone());
+                i += 2;
+            }
+            "--format" if has_value => {
+                parsed.format = match args[i + 1].as_str() {
+                    "text" => Format::Text,
+                    "tsv" => Format::Tsv,
+                    "json" => Format::Json,
+                    other => {
+                        eprintln!("Invalid format: {other} (expected text, tsv or json)");
+                        std::process::exit(1);
+                    }
+                };
+                i += 2;
+            }
+            "--due" => {
+                parsed.due_only = true;
+                i += 1;
+            }
+            _ if parse_path_filter(&args[i..], &mut parsed.files) => i += 2,
+            _ => {
+                parsed.paths.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+    if parsed.paths.is_empty() {
+        parsed.paths = config.paths.clone();
+    }
+    if parsed.paths.is_empty() {
+        eprintln!(
+            "Usage: rote list|due|lint <paths...> [--deck NAME]... [--tag TAG]... [--format text|tsv|json]"
+        );
+        std::process::exit(1);
+    }
+    parsed
+}
+
+/// Cards matching the deck and tag filters.
+fn matching_cards(args: &ListArgs, config: &Config) -> Vec<card::Card> {
+    matching_cards_with_sources(args, config)
+        .into_iter()
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Like `matching_cards`, with the file each card was read from.
+fn matching_cards_with_sources(args: &ListArgs, config: &Config) -> Vec<(PathBuf, card::Card)> {
+    let collection = open_collection(
+        &args.paths,
+        OpenOptions {
+            files: args.files.clone(),
+            user: args.user.clone(),
+            read_only: true,
+            ..OpenOptions::default()
+        },
+        config,
+    );
+    collection
+        .sources
+        .into_iter()
+        .zip(collection.cards)
+        .filter(|(_, c)| {
+            args.decks.is_empty() || args.decks.iter().any(|d| deck::contains(d, &c.deck))
+        })
+        .filter(|(_, c)| args.tags.is_empty() || args.tags.iter().any(|t| card::has_tag(c, t)))
+        .collect()
+}
+
+/// The line each card's row starts on in `files`, by id.
+fn row_lines<'a>(files: impl IntoIterator<Item = &'a PathBuf>) -> HashMap<String, u64> {
+    let mut lines = HashMap::new();
+    for file in files.into_iter().collect::<HashSet<_>>() {
+        for (line, c) in card::load_csv_lines(file).unwrap_or_default() {
+            lines.insert(c.id, line);
+        }
+    }
+    lines
+}
+
+/// Collapse a field onto one line for tabular output.
+fn one_line(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn list(args: &[String], config: &Config, json: bool) {
+    let args = parse_list_args(args, config, json);
+    let today = clock::today();
+    let cards: Vec<(PathBuf, card::Card)> = matching_cards_with_sources(&args, config)
+        .into_iter()
+        .filter(|(_, c)| !args.due_only || review::is_due(c, today))
+        .collect();
+    let lines = row_lines(cards.iter().map(|(file, _)| file));
+    let line = |c: &card::Card| lines.get(&c.id).copied().unwrap_or(0);
+
+    let due = |c: &card::Card| c.due.map_or("new".to_string(), |d| d.to_string());
+    match args.format {
+        Format::Json => {
+            let cards: Vec<_> = cards
+                .iter()
+                .map(|(file, c)| {
+                    let mut value = serde_json::to_value(c).unwrap();
+                    value["file"] = file.display().to_string().into();
+                    value["line"] = line(c).into();
+                    value
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&cards).unwrap());
+        }
+        Format::Tsv => {
+            println!("id\tdeck\tdue\tstability\tdifficulty\tfront\tback\tfile\tline\torigin");
+            for (file, c) in &cards {
+                let number = |v: Option<f64>| v.map_or(String::new(), |v| format!("{v:.2}"));
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    c.id,
+                    c.deck,
+                    due(c),
+                    number(c.stability),
+                    number(c.difficulty),
+                    one_line(&c.front),
+                    one_line(&c.back),
+                    file.display(),
+                    line(c),
+                    c.origin
+                );
+            }
+        }
+        Format::Text => {
+            // Imported cards point at the file they came from, the one to fix.
+            for (file, c) in &cards {
+                let at = card::Origin::parse(&c.origin, file).map_or_else(
+                    || format!("{}:{}", file.display(), line(c)),
+                    |o| o.to_string(),
+                );
+                println!("{at}: {:<10}  {}  {}", due(c), c.deck, one_line(&c.front));
+            }
+        }
+    }
+}
+
+/// Report cards whose text has cloze or line-break mistakes, by file and
+/// line. Exits with 1 when any are found.
+fn lint(args: &[String], config: &Config, json: bool) {
+    let args = parse_list_args(args, config, json);
+    let files = card::discover_files_filtered(&args.paths, &args.files);
+    if files.is_empty() {
+        eprintln!("No CSV files found.");
+        std::process::exit(1);
+    }
+    let mut problems = Vec::new();
+    for file in &files {
+        let cards = card::load_csv_lines(file).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        });
+        for (line, c) in cards {
+            if !args.decks.is_empty() && !args.decks.iter().any(|d| deck::contains(d, &c.deck))
+                || !args.tags.is_empty() && !args.tags.iter().any(|t| card::has_tag(&c, t))
+            {
+                continue;
+            }
+            let origin = card::Origin::parse(&c.origin, file).map(|o| o.to_string());
+            for problem in rote::lint::check(&c.front, &c.back) {
+                problems.push((file, line, c.id.clone(), problem, origin.clone()));
+            }
+        }
+    }
+
+    match args.format {
+        Format::Json => {
+            let problems: Vec<_> = problems
+                .iter()
+                .map(|(file, line, id, problem, origin)| {
+                    serde_json::json!({
+                        "file": file, "line": line, "id": id, "problem": problem,
+                        "origin": origin,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&problems).unwrap());
+        }
+        Format::Tsv => {
+            println!("file\tline\tid\tproblem\torigin");
+            for (file, line, id, problem, origin) in &problems {
+                let origin = origin.as_deref().unwrap_or("");
+                println!("{}\t{line}\t{id}\t{problem}\t{origin}", file.display());
+            }
+        }
+        Format::Text => {
+            for (file, line, _, problem, origin) in &problems {
+                match origin {
+                    Some(origin) => {
+                        println!("{}:{line}: {problem} (from {origin})", file.display())
+                    }
+                    None => println!("{}:{line}: {problem}", file.display()),
+                }
+            }
+        }
+    }
+    if !problems.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Check every deck file for duplicate ids, unparseable dates and missing
+/// media, and repair them unless `--dry-run`.
+fn doctor(args: &[String], config: &Config, json: bool) {
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    let args: Vec<String> = args.iter().filter(|a| *a != "--dry-run").cloned().collect();
+    let args = parse_list_args(&args, config, json);
+    let paths = card::discover_files_filtered(&args.paths, &args.files);
+    if paths.is_empty() {
+        eprintln!("No CSV files found.");
+        std::process::exit(1);
+    }
+    let _lock = (!dry_run).then(|| lock_collection(config, &args.paths));
+    let mut files: Vec<rote::doctor::File> = paths
+        .iter()
+        .map(|path| {
+            let read = || -> rote::Result<_> {
+                Ok(rote::doctor::File {
+                    path: path.clone(),
+                    cards: card::load_csv_lines(path)?,
+                    bad_dates: card::malformed_dates(path)?,
+                })
+            };
+            read().unwrap_or_else(|e| {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            })
+        })
+        .collect();
+    let before: Vec<Vec<card::Card>> = files
+        .iter()
+        .map(|f| f.cards.iter().map(|(_, c)| c.clone()).collect())
+        .collect();
+    let findings = rote::doctor::check(&mut files);
+
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&findings).unwrap()),
+        Format::Tsv => {
+            println!("file\tline\tid\tproblem\trepair");
+            for f in &findings {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    f.file.display(),
+                    f.line,
+                    f.id,
+                    f.problem,
+                    f.repair
+                );
+            }
+        }
+        Format::Text => {
+            for f in &findings {
+                println!(
+                    "{}:{}: {}; {}",
+                    f.file.display(),
+                    f.line,
+                    f.problem,
+                    f.repair
+                );
+            }
+            let n = findings.len();
+            let plural = if n == 1 { "" } else { "s" };
+            match n {
+                0 => println!("No problems found."),
+                _ if dry_run => {
+                    println!("{n} problem{plural} found; run without --dry-run to repair.")
+                }
+                _ => println!("Repaired {n} problem{plural}."),
+            }
+        }
+    }
+    if dry_run || findings.is_empty() {
+        return;
+    }
+
+    let data_dir = data_dir(config, &args.paths);
+    let changed: Vec<&rote::doctor::File> = files
+        .iter()
+        .filter(|f| findings.iter().any(|p| p.file == f.path))
+        .collect();
+    let changed_paths: Vec<PathBuf> = changed.iter().map(|f| f.path.clone()).collect();
+    let snapshot = backup::with_revlog(&changed_paths, &data_dir);
+    if let Err(e) = backup::before("doctor", &snapshot, &data_dir) {
+        eprintln!("Warning: no snapshot before doctor: {e}");
+    }
+    let mut entry = journal::Entry::new("doctor");
+    for (file, before) in files.iter().zip(&before) {
+        if !changed_paths.contains(&file.path) {
+            continue;
+        }
+        let cards: Vec<card::Card> = file.cards.iter().map(|(_, c)| c.clone()).collect();
+        let sources = vec![file.path.clone(); cards.len()];
+        for (i, (old, new)) in before.iter().zip(&cards).enumerate() {
+            if findings
+                .iter()
+                .any(|f| f.file == file.path && f.line == file.cards[i].0)
+            {
+                entry.push(
+                    &sources,
+                    i,
+                    &file.path,
+                    Some(old.clone()),
+                    Some(new.clone()),
+                );
+            }
+        }
+        if let Err(e) = card::save_csv(&file.path, &cards, config.git_friendly) {
+            eprintln!("Error saving {}: {e}", file.path.display());
+            std::process::exit(1);
+        }
+    }
+    if let Err(e) = journal::record(&data_dir, &entry) {
+        eprintln!("Warning: {e}");
+    }
+}
+
+/// `rote media check`: report missing, unreferenced and remote media, and
+/// delete the unreferenced with `--prune` or download the remote with
+/// `--fetch`.
+fn media(args: &[String], config: &Config, json: bool) {
+    let usage = || -> ! {
+        eprintln!(
+            "Usage: rote media check <paths...> [--prune] [--fetch] [--format text|tsv|json]"
+        );
+        std::process::exit(1);
+    };
+    let [action, rest @ ..] = args else {
+        usage();
+    };
+    if action != "check" {
+        usage();
+    }
+    let prune = rest.iter().any(|a| a == "--prune");
+    let fetch = rest.iter().any(|a| a == "--fetch");
+    let rest: Vec<String> = rest
+        .iter()
+        .filter(|a| *a != "--prune" && *a != "--fetch")
+        .cloned()
+        .collect();
+    let args = parse_list_args(&rest, config, json);
+    let mut collection = open_collection(
+        &args.paths,
+        OpenOptions {
+            files: args.files.clone(),
+            ..OpenOptions::default()
+        },
+        config,
+    );
+    let report = rote::media::check(&collection.cards, &collection.sources);
+    let at = |i: usize| (collection.sources[i].display(), &collection.cards[i].id);
+
+    match args.format {
+        Format::Json => {
+            let refs = |list: &[(usize, String)]| -> Vec<serde_json::Value> {
+                list.iter()
+                    .map(|(i, media)| {
+                        let (file, id) = at(*i);
+                        serde_json::json!({ "file": file.to_string(), "id": id, "media": media })
+                    })
+                    .collect()
+            };
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "missing": refs(&report.missing),
+                    "orphans": report.orphans,
+                    "remote": refs(&report.remote),
+                }))
+                .unwrap()
+            );
+        }
+        Format::Tsv => {
+            println!("kind\tfile\tid\tmedia");
+            for (kind, list) in [("missing", &report.missing), ("remote", &report.remote)] {
+                for (i, media) in list {
+                    let (file, id) = at(*i);
+                    println!("{kind}\t{file}\t{id}\t{media}");
+                }
+            }
+            for path in &report.orphans {
+                println!("orphan\t{}\t\t", path.display());
+            }
+        }
+        Format::Text => {
+            for (i, media) in &report.missing {
+                let (file, id) = at(*i);
+                println!("{file}: card {id} lists {media}, which is missing");
+            }
+            for path in &report.orphans {
+                println!("{}: no card lists it", path.display());
+            }
+            for (i, url) in &report.remote {
+                let (file, id) = at(*i);
+                println!("{file}: card {id} lists {url}");
+            }
+            if report == rote::media::Report::default() {
+                println!("All media accounted for.");
+            }
+        }
+    }
+
+    if prune && !report.orphans.is_empty() {
+        if !collection.skipped.is_empty() {
+            eprintln!(
+                "Not pruning: some deck files could not be read, so their media may look unused."
+            );
+            std::process::exit(1);
+        }
+        if let Err(e) = backup::before("media-prune", &report.orphans, collection.dir()) {
+            eprintln!("Not pruning: no snapshot of the files: {e}");
+            std::process::exit(1);
+        }
+        let mut deleted = 0;
+        for path in &report.orphans {
+            match std::fs::remove_file(path) {
+                Ok(()) => deleted += 1,
+                Err(e) => eprintln!("Warning: failed to delete {}: {e}", path.display()),
+            }
+        }
+        let plural = if deleted == 1 { "" } else { "s" };
+        eprintln!("Deleted {deleted} unused file{plural}.");
+    }
+
+    if fetch && !report.remote.is_empty() {
+        let before = collection.cards.clone();
+        let mut changed = Vec::new();
+        let mut fetched = 0;
+        for (i, url) in &report.remote {
+            let dir = card::media_dir(&collection.sources[*i]);
+            match rote::media::fetch(url, &dir) {
+                Ok(name) => {
+                    fetched += 1;
+                    let card = &mut collection.cards[*i];
+                    card.media = rote::occlusion::rename_files(card, |m| {
+                        (m == url).then_some(name.as_str())
+                    });
+                    if !changed.contains(i) {
+                        changed.push(*i);
+                    }
+                }
+                Err(e) => eprintln!("Warning: {e}"),
+            }
+        }
+        if !changed.is_empty() {
+            snapshot_before("media-fetch", &collection);
+            save_changed("media fetch", &mut collection, &before, &changed);
+            let n = report.remote.len();
+            eprintln!(
+                "Fetched {fetched} of {n} remote file{}.",
+                if n == 1 { "" } else { "s" }
+            );
+        }
+    }
+
+    if !report.missing.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn due(args: &[String], config: &Config, json: bool) {
+    let notify = args.iter().any(|a| a == "--notify");
+    let args: Vec<String> = args.iter().filter(|a| *a != "--notify").cloned().collect();
+    let args = parse_list_args(&args, config, json);
+    let today = clock::today();
+    let summaries = review::deck_summaries(&matching_cards(&args, config), today);
+    let total: usize = summaries
+        .iter()
+        .filter(|s| s.depth == 0)
+        .map(|s| s.due)
+        .sum();
+
+    match args.format {
+        Format::Json => {
+            let decks: serde_json::Map<String, serde_json::Value> = summaries
+                .iter()
+                .map(|s| (s.name.clone(), s.due.into()))
+                .collect();
+            println!("{}", serde_json::json!({ "due": total, "decks": decks }));
+        }
+        Format::Tsv => {
+            for s in &summaries {
+                println!("{}\t{}", s.name, s.due);
+            }
+        }
+        Format::Text => println!("{total}"),
+    }
+    if notify {
+        let data_dir = data_dir(config, &args.paths);
+        for e in webhook::notify_due(&config.webhooks, total, today, &data_dir) {
+            eprintln!("Warning: {e}");
+        }
+    }
+}
+
+fn remind(args: &[String], config: &Config) {
+    let mut times = Vec::new();
+    let mut url = format!("http://localhost:{}", config.port.unwrap_or(3000));
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let has_value = i + 1 < args.len();
+        match args[i].as_str() {
+            "--at" if has_value => {
+                times.push(remind::parse_time(&args[i + 1]).unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }));
+                i += 2;
+            }
+            "--url" if has_value => {
+                url = args[i + 1].clone();
+                i += 2;
+            }
+            _ => {
+                rest.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+    let args = parse_list_args(&rest, config, false);
+    let check = || {
+        let today = clock::today();
+        let due = matching_cards(&args, config)
+            .iter()
+            .filter(|c| review::is_due(c, today))
+            .count();
+        if due > 0 {
+            let (title, body) = remind::message(due, &url);
+            if let Err(e) = remind::notify(&title, &body) {
+                eprintln!("Warning: {e}");
+            }
+        }
+    };
+
+    // Without --at, remind once (e.g. from cron).
+    if times.is_empty() {
+        check();
+        return;
+    }
+    loop {
+        let next = remind::next_run(&times, clock::now()).unwrap();
+        println!("Next reminder at {}.", next.format("%Y-%m-%d %H:%M"));
+        // Sleep in short steps: a suspended laptop's monotonic clock stops,
+        // and a single long sleep would wake up late.
+        while clock::now() < next {
+            std::thread::sleep(std::time::Duration::from_secs(30));
+        }
+        check();
+    }
+}
+
+fn state(args: &[String], config: &Config) {
+    let usage = || -> ! {
+        eprintln!("Usage: rote state export|import <state.json> <paths...>");
+        std::process::exit(1);
+    };
+    let [action, file, rest @ ..] = args else {
+        usage();
+    };
+    let mut paths = rest.to_vec();
+    if paths.is_empty() {
+        paths = config.paths.clone();
+    }
+    if paths.is_empty() {
+        usage();
+    }
+    let mut collection = open_collection(
+        &paths,
+        OpenOptions {
+            user: config.user.clone(),
+            read_only: action == "export",
+            ..OpenOptions::default()
+        },
+        config,
+    );
+
+    match action.as_str() {
+        "export" => {
+            let states = rote::state::export(&collection.cards);
+            let json = serde_json::to_string_pretty(&states).unwrap();
+            if let Err(e) = std::fs::write(file, json + "\n") {
+                eprintln!("Error writing {file}: {e}");
+                std::process::exit(1);
+            }
+            println!("Exported state for {} cards to {file}.", states.len());
+        }
+        "import" => {
+            let states = std::fs::read_to_string(file)
+                .map_err(|e| e.to_string())
+                .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error reading {file}: {e}");
+                    std::process::exit(1);
+                });
+            let before = collection.cards.clone();
+            snapshot_before("state-import", &collection);
+            let changed = rote::state::import(&mut collection.cards, &states);
+            save_changed("state import", &mut collection, &before, &changed);
+            let unmatched = states.len().saturating_sub(changed.len());
+            println!(
+                "Restored state for {} cards ({} entries unchanged or unmatched).",
+                changed.len(),
+                unmatched
+            );
+        }
+        _ => usage(),
+    }
+}
+
+fn reschedule(args: &[String], config: &Config) {
+    let usage = || -> ! {
+        eprintln!(
+            "Usage: rote reschedule spread|reset|retention <paths...> [--deck NAME]... [--tag TAG]... [--days N] [--retention R]"
+        );
+        std::process::exit(1);
+    };
+    let [action, args @ ..] = args else {
+        usage();
+    };
+    let mut days = 7u32;
+    let mut retention = None;
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--days" if i + 1 < args.len() => {
+                days = args[i + 1]
+                    .parse()
+                    .ok()
+                    .filter(|&d| d > 0)
+                    .unwrap_or_else(|| {
+                        eprintln!("Invalid value for --days: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                i += 2;
+            }
+            "--retention" if i + 1 < args.len() => {
+                retention = args[i + 1]
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|r| *r > 0.0 && *r < 1.0)
+                    .map(Some)
+                    .unwrap_or_else(|| {
+                        eprintln!("Invalid value for --retention: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                i += 2;
+            }
+            _ => {
+                rest.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+    let args = parse_list_args(&rest, config, false);
+    let mut collection = open_collection(
+        &args.paths,
+        OpenOptions {
+            files: args.files.clone(),
+            user: args.user.clone(),
+            ..OpenOptions::default()
+        },
+        config,
+    );
+    let selected: Vec<usize> = (0..collection.cards.len())
+        .filter(|&i| {
+            let c = &collection.cards[i];
+            (args.decks.is_empty() || args.decks.iter().any(|d| deck::contains(d, &c.deck)))
+                && (args.tags.is_empty() || args.tags.iter().any(|t| card::has_tag(c, t)))
+        })
+        .collect();
+    let today = clock::today();
+    let before = collection.cards.clone();
+    let changed = match action.as_str() {
+        "spread" => rote::reschedule::spread(&mut collection.cards, &selected, today, days),
+        "reset" => rote::reschedule::reset(&mut collection.cards, &selected),
+        "retention" => {
+            let decks = deck::load(collection.dir()).unwrap_or_else(|e| {
+                eprintln!("Warning: {e}");
+                HashMap::new()
+            });
+            // --retention overrides every deck; otherwise each deck's own.
+            let retention_for = |name: &str| {
+                retention.unwrap_or_else(|| {
+                    deck::setting(&decks, name, |c| c.desired_retention)
+                        .unwrap_or(config.desired_retention)
+                })
+            };
+            rote::reschedule::retention(&mut collection.cards, &selected, retention_for)
+        }
+        _ => usage(),
+    };
+    if changed.is_empty() {
+        println!("No cards to reschedule.");
+        return;
+    }
+    snapshot_before("reschedule", &collection);
+    save_changed(
+        &format!("reschedule {action}"),
+        &mut collection,
+        &before,
+        &changed,
+    );
+    let plural = if changed.len() == 1 { "" } else { "s" };
+    match action.as_str() {
+        "spread" => println!(
+            "Spread {} overdue card{plural} over {days} day{}.",
+            changed.len(),
+            if days == 1 { "" } else { "s" }
+        ),
+        "reset" => println!("Reset {} card{plural} to new.", changed.len()),
+        _ => println!("Moved the due dates of {} card{plural}.", changed.len()),
+    }
+}
+
+/// Save the files holding the `changed` cards and journal the change as one
+/// undoable `action`. `before` is the cards as loaded.
+/// Snapshot the collection's files before `action` rewrites them (see
+/// `rote::backup`). A failed snapshot only warns.
+fn snapshot_before(action: &str, collection: &Collection) {
+    let files = backup::with_revlog(&collection.files, collection.data_dir());
+    if let Err(e) = backup::before(action, &files, collection.dir()) {
+        eprintln!("Warning: no snapshot before {action}: {e}");
+    }
+}
+
+fn save_changed(
+    action: &str,
+    collection: &mut Collection,
+    before: &[card::Card],
+    changed: &[usize],
+) {
+    let mut entry = journal::Entry::new(action);
+    for &i in changed {
+        entry.push(
+            &collection.sources,
+            i,
+            &collection.sources[i],
+            Some(before[i].clone()),
+            Some(collection.cards[i].clone()),
+        );
+        collection.touch(i);
+    }
+    if let Err(e) = collection.save() {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = journal::record(collection.data_dir(), &entry) {
+        eprintln!("Warning: {e}");
+    }
+}
+
+fn sync(args: &[String], config: &Config) {
+    let usage = || -> ! {
+        eprintln!("Usage: rote sync <url> <paths...> [--auth USER:PASS]");
+        std::process::exit(1);
+    };
+    let [url, rest @ ..] = args else {
+        usage();
+    };
+    let mut paths = Vec::new();
+    let mut auth = config.auth.clone();
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i] == "--auth" && i + 1 < rest.len() {
+            auth = Some(rest[i + 1].clone());
+            i += 2;
+        } else {
+            paths.push(rest[i].clone());
+            i += 1;
+        }
+    }
+    if paths.is_empty() {
+        paths = config.paths.clone();
+    }
+    if paths.is_empty() {
+        usage();
+    }
+    let mut collection = open_collection(
+        &paths,
+        OpenOptions {
+            user: config.user.clone(),
+            ..OpenOptions::default()
+        },
+        config,
+    );
+
+    let local = rote::state::export(&collection.cards);
+    let remote = rote::sync::exchange(url, auth.as_deref(), &local).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    let before = collection.cards.clone();
+    let changed = rote::state::merge(&mut collection.cards, &remote, true);
+    save_changed("sync", &mut collection, &before, &changed);
+    println!(
+        "Sent {} reviewed cards; took {} newer ones from {url}.",
+        local.len(),
+        changed.len()
+    );
+}
+
+fn backup(args: &[String], config: &Config) {
+    let usage = || -> ! {
+        eprintln!("Usage: rote backup <dir> <paths...> [--keep N]");
+        std::process::exit(1);
+    };
+    let mut positional = Vec::new();
+    let mut keep = backup::KEEP;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--keep" && i + 1 < args.len() {
+            keep = args[i + 1]
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .unwrap_or_else(|| {
+                    eprintln!("Invalid value for --keep: {}", args[i + 1]);
+                    std::process::exit(1);
+                });
+            i += 2;
+        } else {
+            positional.push(args[i].clone());
+            i += 1;
+        }
+    }
+    let [dir, rest @ ..] = positional.as_slice() else {
+        usage();
+    };
+    let mut paths = rest.to_vec();
+    if paths.is_empty() {
+        paths = config.paths.clone();
+    }
+    if paths.is_empty() {
+        usage();
+    }
+    let files = card::discover_files(&paths);
+    if files.is_empty() {
+        eprintln!("Error: {}", rote::Error::NoFiles);
+        std::process::exit(1);
+    }
+    let profile = open_profile(config, &paths, &mut []);
+    let files = backup::with_revlog(&files, &user_data_dir(profile.as_ref(), config, &paths));
+    match backup::create(
+        &files,
+        &data_dir(config, &paths),
+        Path::new(dir),
+        None,
+        keep,
+    ) {
+        Ok(path) => println!("Saved snapshot {}.", path.display()),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn restore(args: &[String]) {
+    let [snapshot] = args else {
+        eprintln!("Usage: rote restore <snapshot.zip>");
+        std::process::exit(1);
+    };
+    let snapshot = Path::new(snapshot);
+    let exit = |e: rote::Error| -> ! {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    };
+    let manifest = backup::manifest(snapshot).unwrap_or_else(|e| exit(e));
+    let _lock = rote::lock::acquire(&manifest.data_dir).unwrap_or_else(|e| exit(e));
+    // Restoring is itself undone by restoring what it replaced.
+    let current: Vec<PathBuf> = manifest.files.iter().map(|f| f.path.clone()).collect();
+    let saved = backup::before("restore", &current, &manifest.data_dir).unwrap_or_else(|e| exit(e));
+    let restored = backup::restore(snapshot).unwrap_or_else(|e| exit(e));
+    println!(
+        "Restored {} files from {}.",
+        restored.len(),
+        manifest.created.format("%Y-%m-%d %H:%M")
+    );
+    println!("The files they replaced are in {}.", saved.display());
+}
+
+fn merge(args: &[String], config: &Config) {
+    let usage = || -> ! {
+        eprintln!("Usage: rote merge <a.csv> <b.csv> -o <merged.csv>");
+        std::process::exit(1);
+    };
+    let mut files = Vec::new();
+    let mut out = None;
+    let mut i = 0;
+    while i < args.len() {
+        if (args[i] == "-o" || args[i] == "--output") && i + 1 < args.len() {
+            out = Some(PathBuf::from(&args[i + 1]));
+            i += 2;
+        } else {
+            files.push(PathBuf::from(&args[i]));
+            i += 1;
+        }
+    }
+    let ([a, b], Some(out)) = (files.as_slice(), out) else {
+        usage();
+    };
+    let load = |path: &PathBuf| {
+        card::load_csv(path).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        })
+    };
+    let merged = rote::state::merge_copies(load(a), load(b));
+    if let Err(e) = card::save_csv(&out, &merged.cards, config.git_friendly) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    println!(
+        "Merged {} cards into {}: {} with newer reviews from {}, {} only in {}.",
+        merged.cards.len(),
+        out.display(),
+        merged.updated,
+        b.display(),
+        merged.added,
+        b.display()
+    );
+    if !merged.conflicts.is_empty() {
+        eprintln!(
+            "Warning: {} cards differ in text, tags or flags; kept the version in {}:",
+            merged.conflicts.len(),
+            a.display()
+        );
+        for id in &merged.conflicts {
+            eprintln!("  {id}");
+        }
+    }
+}
+
+fn forecast(args: &[String], config: &Config, json: bool) {
+    let mut days = 14usize;
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--days" && i + 1 < args.len() {
+            days = args[i + 1].parse().unwrap_or_else(|_| {
+                eprintln!("Invalid value for --days: {}", args[i + 1]);
+                std::process::exit(1);
+            });
+            i += 2;
+        } else {
+            rest.push(args[i].clone());
+            i += 1;
+        }
+    }
+    let args = parse_list_args(&rest, config, json);
+    let today = clock::today();
+    let counts = review::forecast(&matching_cards(&args, config), today, days);
+
+    if args.format == Format::Json {
+        let days: Vec<serde_json::Value> = counts
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                let date = today + chrono::Duration::days(i as i64);
+                serde_json::json!({ "date": date.to_string(), "due": n })
+            })
+            .collect();
+        println!("{}", serde_json::Value::from(days));
+        return;
+    }
+    let widest = counts.iter().copied().max().unwrap_or(0).max(1);
+    for (i, n) in counts.iter().enumerate() {
+        let date = today + chrono::Duration::days(i as i64);
+        let bar = "#".repeat((n * 40).div_ceil(widest));
+        println!("{}  {n:>5}  {bar}", date.format("%a %m-%d"));
+    }
+}
+
+fn simulate(args: &[String], config: &Config, json: bool) {
+    let mut options = rote::simulate::Options {
+        days: 365,
+        new_per_day: config.new_per_day.unwrap_or(20),
+        retention: config.desired_retention,
+        new_cards: None,
+        runs: 20,
+        seed: review::time_seed(),
+    };
+    let mut fresh = false;
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        match flag {
+            "--days" | "--new-per-day" | "--runs" | "--seed" | "--retention"
+                if i + 1 < args.len() =>
+            {
+                let value = &args[i + 1];
+                let invalid = || -> ! {
+                    eprintln!("Invalid value for {flag}: {value}");
+                    std::process::exit(1);
+                };
+                match flag {
+                    "--days" => options.days = value.parse().unwrap_or_else(|_| invalid()),
+                    "--new-per-day" => {
+                        options.new_per_day = value.parse().unwrap_or_else(|_| invalid())
+                    }
+                    "--runs" => options.runs = value.parse().unwrap_or_else(|_| invalid()),
+                    "--seed" => options.seed = value.parse().unwrap_or_else(|_| invalid()),
+                    _ => {
+                        options.retention = value
+                            .parse()
+                            .ok()
+                            .filter(|r| *r > 0.0 && *r < 1.0)
+                            .unwrap_or_else(|| invalid())
+                    }
+                }
+                i += 2;
+            }
+            "--fresh" => {
+                fresh = true;
+                i += 1;
+            }
+            _ => {
+                rest.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+    // Start from the collection unless there is none or --fresh asks for an
+    // empty one with an endless supply of new cards.
+    let cards = if fresh || (rest.is_empty() && config.paths.is_empty()) {
+        Vec::new()
+    } else {
+        let cards = matching_cards(&parse_list_args(&rest, config, json), config);
+        let new = cards
+            .iter()
+            .filter(|c| !c.suspended && c.stability.is_none())
+            .count();
+        options.new_cards = Some(new);
+        cards
+    };
+    let today = clock::today();
+    let sim = rote::simulate::run(&cards, today, &options);
+    let reviews: f64 = sim.days.iter().map(|d| d.reviews).sum();
+    let lapses: f64 = sim.days.iter().map(|d| d.lapses).sum();
+    let learned: f64 = sim.days.iter().map(|d| d.new).sum();
+    let retention = (reviews > 0.0).then(|| 1.0 - lapses / reviews);
+
+    if json {
+        let days: Vec<serde_json::Value> = sim
+            .days
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let date = today + chrono::Duration::days(i as i64);
+                serde_json::json!({
+                    "date": date.to_string(),
+                    "reviews": d.reviews,
+                    "new": d.new,
+                    "lapses": d.lapses,
+                    "retention": d.retention(),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({
+                "days": days,
+                "reviews_per_day": reviews / options.days.max(1) as f64,
+                "retention": retention,
+                "new": learned,
+                "memorized": sim.memorized,
+            })
+        );
+        return;
+    }
+    // A row per week, or per day for a month or less.
+    let span = if options.days > 31 { 7 } else { 1 };
+    let rows: Vec<(usize, f64)> = sim
+        .days
+        .chunks(span)
+        .enumerate()
+        .map(|(i, days)| {
+            let load: f64 = days.iter().map(|d| d.reviews + d.new).sum();
+            (i * span, load / days.len() as f64)
+        })
+        .collect();
+    let widest = rows.iter().map(|r| r.1).fold(1.0, f64::max);
+    println!("Reviews and new cards per day:");
+    for (offset, load) in &rows {
+        let date = today + chrono::Duration::days(*offset as i64);
+        let bar = "#".repeat((load * 40.0 / widest).ceil() as usize);
+        println!("{}  {load:>7.1}  {bar}", date.format("%Y-%m-%d"));
+    }
+    println!();
+    println!(
+        "Average {:.1} reviews and {:.1} new cards a day, peak {:.1}.",
+        reviews / options.days.max(1) as f64,
+        learned / options.days.max(1) as f64,
+        sim.days
+            .iter()
+            .map(|d| d.reviews + d.new)
+            .fold(0.0, f64::max)
+    );
+    if let Some(r) = retention {
+        println!("Recalled {:.1}% of reviews.", r * 100.0);
+    }
+    println!(
+        "After {} days about {:.0} cards would be remembered.",
+        options.days, sim.memorized
+    );
+}
+
+fn stats(args: &[String], config: &Config, json: bool) {
+    let mut include_excluded = false;
+    let mut hardest = None;
+    let mut paths = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--all" => include_excluded = true,
+            "--hardest" if i + 1 < args.len() => {
+                hardest = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                    eprintln!("Invalid value for --hardest: {}", args[i + 1]);
+                    std::process::exit(1);
+                }));
+                i += 1;
+            }
+            _ => paths.push(args[i].clone()),
+        }
+        i += 1;
+    }
+    if paths.is_empty() {
+        paths = config.paths.clone();
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: rote stats <paths...> [--all] [--hardest N]");
+        std::process::exit(1);
+    }
+    if let Some(n) = hardest {
+        return print_hardest(&paths, config, n, json);
+    }
+    let profile = open_profile(config, &paths, &mut []);
+    let data_dir = user_data_dir(profile.as_ref(), config, &paths);
+    let reviews = revlog::load(&data_dir);
+    let sessions = rote::stats::sessions(&reviews, &revlog::load_notes(&data_dir));
+    let retention = rote::stats::retention(&sessions, include_excluded);
+    let answer_secs = rote::stats::answer_secs(&sessions);
+    let today = clock::today();
+    let activity = rote::stats::daily_reviews(&reviews, today, 365);
+
+    if json {
+        let sessions: Vec<serde_json::Value> = sessions
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "session": s.session,
+                    "started": s.started.to_rfc3339(),
+                    "decks": s.decks,
+                    "counts": session_counts(s.counts.map(|n| n as u32)),
+                    "reviews": s.reviews(),
+                    "retention": s.retention(),
+                    "answer_secs": s.answer_secs(),
+                    "practice": s.practice,
+                    "note": s.note.as_ref().map(|n| &n.note),
+                    "excluded": s.note.as_ref().is_some_and(|n| n.exclude),
+                })
+            })
+            .collect();
+        let activity: serde_json::Map<String, serde_json::Value> = activity
+            .iter()
+            .filter(|&&(_, n)| n > 0)
+            .map(|(date, n)| (date.to_string(), (*n).into()))
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({
+                "retention": retention,
+                "answer_secs": answer_secs,
+                "sessions": sessions,
+                "activity": activity,
+            })
+        );
+        return;
+    }
+
+    println!("Reviews per day, past year:");
+    print!("{}", rote::stats::heatmap_ascii(&activity));
+    println!();
+
+    let reviews: usize = sessions.iter().map(|s| s.reviews()).sum();
+    println!(
+        "{} sessions, {reviews} reviews, retention {}, {} per card",
+        sessions.len(),
+        percent(retention),
+        seconds(answer_secs)
+    );
+    for s in &sessions {
+        let mut line = format!(
+            "{}  {:>4} reviews  {:>4}  {:>6}  {}",
+            s.started.format("%Y-%m-%d %H:%M"),
+            s.reviews(),
+            percent(s.retention()),
+            seconds(s.answer_secs()),
+            s.decks.join(", ")
+        );
+        if s.practice {
+            line.push_str("  (practice)");
+        }
+        if let Some(note) = &s.note {
+            line.push_str(&format!("  — {}", note.note));
+            if note.exclude {
+                line.push_str(" (excluded)");
+            }
+        }
+        println!("{line}");
+    }
+}
+
+/// The `n` cards with the highest difficulty, to rewrite or delete.
+fn print_hardest(paths: &[String], config: &Config, n: usize, json: bool) {
+    let collection = open_collection(
+        paths,
+        OpenOptions {
+            user: config.user.clone(),
+            read_only: true,
+            ..OpenOptions::default()
+        },
+        config,
+    );
+    let reviews = revlog::load(collection.data_dir());
+    let hardest = rote::stats::hardest(&collection.cards, &reviews, n);
+    let front = |i: usize| {
+        collection
+            .full_card(i)
+            .map(|c| c.front.clone())
+            .unwrap_or_default()
+    };
+    if json {
+        let cards: Vec<serde_json::Value> = hardest
+            .iter()
+            .map(|h| {
+                let card = &collection.cards[h.index];
+                serde_json::json!({
+                    "id": card.id,
+                    "deck": card.deck,
+                    "front": front(h.index),
+                    "difficulty": h.difficulty,
+                    "lapses": h.lapses,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(cards));
+        return;
+    }
+    if hardest.is_empty() {
+        println!("No reviewed cards.");
+        return;
+    }
+    for h in &hardest {
+        let card = &collection.cards[h.index];
+        println!(
+            "{:>4.1}  {:>3} lapses  {}  {}",
+            h.difficulty,
+            h.lapses,
+            card.deck,
+            front(h.index).replace('\n', " ")
+        );
+    }
+}
+
+fn percent(ratio: Option<f64>) -> String {
+    ratio.map_or("—".to_string(), |r| format!("{:.0}%", r * 100.0))
+}
+
+fn seconds(secs: Option<f64>) -> String {
+    secs.map_or("—".to_string(), |s| format!("{s:.1}s"))
+}
+
+/// Grade counts keyed by name, for JSON output.
+fn session_counts(counts: [u32; 4]) -> serde_json::Value {
+    serde_json::json!({
+        "forgot": counts[0],
+        "hard": counts[1],
+        "good": counts[2],
+        "easy": counts[3],
+    })
+}
+
+fn clozify(args: &[String]) {
+    let mut words = Vec::new();
+    let mut decks = Vec::new();
+    let mut terms = Vec::new();
+    let mut interactive = false;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--deck" if i + 1 < args.len() => {
+                decks.push(args[i + 1].clone());
+                i += 2;
+            }
+            "--term" if i + 1 < args.len() => {
+                terms.push(args[i + 1].clone());
+                i += 2;
+            }
+            "-i" => {
+                interactive = true;
+                i += 1;
+            }
+            _ => {
+                words.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+
+    // Tags used anywhere in the given decks count as terms.
+    for path in card::discover_files(&decks) {
+        let cards = card::load_csv(&path).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        });
+        for tag in cards.into_iter().flat_map(|c| c.tags) {
+            if !terms.contains(&tag) {
+                terms.push(tag);
+            }
+        }
+    }
+
+    if words.is_empty() {
+        if interactive {
+            eprintln!("Usage: rote clozify -i <text> [--deck PATH]... [--term T]...");
+            std::process::exit(1);
+        }
+        for line in io::stdin().lock().lines() {
+            let line = line.unwrap();
+            let suggestions = rote::clozify::suggest(&line, &terms);
+            println!("{}", rote::clozify::apply(&line, &suggestions));
+        }
+        return;
+    }
+
+    let text = words.join(" ");
+    let mut suggestions = rote::clozify::suggest(&text, &terms);
+    if interactive {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        suggestions.retain(|s| {
+            print!("[{}] ({})? [Y/n] ", &text[s.start..s.end], s.kind.label());
+            io::stdout().flush().unwrap();
+            let mut answer = String::new();
+            stdin.read_line(&mut answer).unwrap();
+            !answer.trim().eq_ignore_ascii_case("n")
+        });
+    }
+    println!("{}", rote::clozify::apply(&text, &suggestions));
+}
+
+fn prompt_deck_selection(summaries: &[review::DeckSummary]) -> Vec<String> {
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    loop {
+        print!("Select deck(s) (comma-separated numbers, or 0 for all): ");
+        io::stdout().flush().unwrap();
+        let mut buf = String::new();
+        stdin.read_line(&mut buf).unwrap();
+
+        let mut selected = Vec::new();
+        let mut valid = true;
+
+        for part in buf.trim().split(',') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            match part.parse::<usize>() {
+                Ok(0) => return Vec::new(), // all decks
+                Ok(n) if n >= 1 && n <= summaries.len() => {
+                    selected.push(summaries[n - 1].name.clone());
+                }
+                _ => {
+                    valid = false;
+                    break;
+                }
+            }
+        }
+
+        if valid && !selected.is_empty() {
+            return selected;
+        }
+        println!("Invalid selection. Try again.");
     }
 }
@@ -0,0 +1,252 @@
+// Media bookkeeping for `rote media check`: files cards list that their deck
+// file's `media` folder lacks, files there that no card lists, and `http(s)`
+// references, which review cannot show until downloaded:
+//
+//     rote media check cards/            # report
+//     rote media check cards/ --prune    # delete files no card lists
+//     rote media check cards/ --fetch    # download URLs and use the copies
+//
+// Decks with `fetch_media = true` in decks.toml have the same done by the web
+// UI whenever a card is saved, for `<img src="https://...">` in the front too.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::card::{self, Card};
+use crate::error::{Error, Result};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    /// Files that do not exist: the index of the card listing it, and its name.
+    pub missing: Vec<(usize, String)>,
+    /// Files in a media folder that no card lists.
+    pub orphans: Vec<PathBuf>,
+    /// `http(s)` references: the index of the card listing it, and the URL.
+    pub remote: Vec<(usize, String)>,
+}
+
+/// Whether a media entry is a URL rather than a file in the media folder.
+pub fn is_remote(name: &str) -> bool {
+    name.starts_with("http://") || name.starts_with("https://")
+}
+
+/// Check the media of `cards`, read from the parallel `sources`. Only the
+/// media folders of `sources` are searched for orphans, and hidden files
+/// there are left alone.
+pub fn check(cards: &[Card], sources: &[PathBuf]) -> Report {
+    let mut report = Report::default();
+    let mut listed: BTreeMap<PathBuf, HashSet<&str>> = BTreeMap::new();
+    for (i, (card, source)) in cards.iter().zip(sources).enumerate() {
+        let dir = card::media_dir(source);
+        for name in card::media_files(card) {
+            if is_remote(name) {
+                report.remote.push((i, name.to_string()));
+            } else if !dir.join(name).exists() {
+                report.missing.push((i, name.to_string()));
+            }
+        }
+        listed
+            .entry(dir)
+            .or_default()
+            .extend(card::media_files(card));
+    }
+    for (dir, names) in &listed {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut orphans: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+            .filter(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                !name.starts_with('.') && !names.contains(name.as_ref())
+            })
+            .map(|e| e.path())
+            .collect();
+        orphans.sort();
+        report.orphans.extend(orphans);
+    }
+    report
+}
+
+/// Whether `card` lists a URL as media or has an `<img>` of one in its front.
+pub fn has_remote(card: &Card) -> bool {
+    card::media_files(card).into_iter().any(is_remote) || !remote_images(&card.front).is_empty()
+}
+
+/// The `<img>` tags in `text` whose `src` is a URL: each tag's byte range
+/// and the URL.
+fn remote_images(text: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let lower = text.to_ascii_lowercase();
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(start) = lower[from..].find("<img").map(|i| from + i) {
+        let Some(end) = lower[start..].find('>').map(|i| start + i + 1) else {
+            break;
+        };
+        let tag = &text[start..end];
+        let src = ["src=\"", "src='"].iter().find_map(|attr| {
+            let at = tag.to_ascii_lowercase().find(attr)? + attr.len();
+            let quote = attr.chars().last()?;
+            tag[at..].find(quote).map(|len| &tag[at..at + len])
+        });
+        if let Some(url) = src.filter(|u| is_remote(u)) {
+            found.push((start..end, url.to_string()));
+        }
+        from = end;
+    }
+    found
+}
+
+/// Replace `card`'s remote media by local copies: each URL in its media
+/// column by the name `fetch` gives it, and each `<img>` of a URL in its
+/// front by that name appended to the media column. What fails to download
+/// stays as it was; the errors are returned.
+pub fn localize(card: &mut Card, mut fetch: impl FnMut(&str) -> Result<String>) -> Vec<Error> {
+    let mut errors = Vec::new();
+    let mut names = HashMap::new();
+    for url in card::media_files(card).into_iter().filter(|m| is_remote(m)) {
+        match fetch(url) {
+            Ok(name) => {
+                names.insert(url.to_string(), name);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    card.media = crate::occlusion::rename_files(card, |m| names.get(m).map(String::as_str));
+    let mut added = Vec::new();
+    for (range, url) in remote_images(&card.front).into_iter().rev() {
+        match fetch(&url) {
+            Ok(name) => {
+                card.front.replace_range(range, "");
+                added.push(name);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    for name in added.into_iter().rev() {
+        if !card::media_files(card).contains(&name.as_str()) {
+            card.media = if card.media.is_empty() {
+                name
+            } else {
+                format!("{};{name}", card.media)
+            };
+        }
+    }
+    card.front = card.front.trim().to_string();
+    errors
+}
+
+/// Download `url` into `media_dir` under a name taken from its content, as
+/// for imported Anki media, and return that name.
+pub fn fetch(url: &str, media_dir: &Path) -> Result<String> {
+    let fetch_err = |source| Error::Fetch {
+        url: url.to_string(),
+        source,
+    };
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(30)))
+        .build()
+        .into();
+    let mut response = agent.get(url).call().map_err(fetch_err)?;
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let bytes = response.body_mut().read_to_vec().map_err(fetch_err)?;
+    let name = crate::anki::content_name(&remote_name(url, &content_type), &bytes);
+    let dest = media_dir.join(&name);
+    if !dest.exists() {
+        std::fs::create_dir_all(media_dir).map_err(Error::io("create", media_dir))?;
+        std::fs::write(&dest, &bytes).map_err(Error::io("write", &dest))?;
+    }
+    Ok(name)
+}
+
+/// The file name at the end of `url`'s path, given an extension from
+/// `content_type` when it has none.
+fn remote_name(url: &str, content_type: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let last = path.rsplit('/').next().unwrap_or("");
+    if Path::new(last).extension().is_some() {
+        return last.to_string();
+    }
+    let subtype = content_type
+        .split(';')
+        .next()
+        .and_then(|t| t.trim().split_once('/'))
+        .map_or("", |(_, sub)| sub);
+    let ext = match subtype {
+        "mpeg" => "mp3",
+        "svg+xml" => "svg",
+        "" => return last.to_string(),
+        other => other,
+    };
+    format!("{last}.{ext}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_missing_orphaned_and_remote_media() {
+        let dir = tempfile::tempdir().unwrap();
+        let media = dir.path().join("media");
+        std::fs::create_dir(&media).unwrap();
+        for name in ["used.png", "old.png", ".DS_Store"] {
+            std::fs::write(media.join(name), b"x").unwrap();
+        }
+        let path = dir.path().join("bio.csv");
+        std::fs::write(
+            &path,
+            "deck,front,back,media\n\
+             bio,Cell,cell,used.png;lost.png#0.1\n\
+             bio,Heart,heart,https://example.com/img/heart\n",
+        )
+        .unwrap();
+        let cards = card::load_csv(&path).unwrap();
+        let report = check(&cards, &vec![path; cards.len()]);
+        assert_eq!(
+            report,
+            Report {
+                missing: vec![(0, "lost.png".into())],
+                orphans: vec![media.join("old.png")],
+                remote: vec![(1, "https://example.com/img/heart".into())],
+            }
+        );
+        assert_eq!(
+            remote_name("https://example.com/img/heart?s=2", "image/png"),
+            "heart.png"
+        );
+        assert_eq!(remote_name("https://example.com/a.ogg", ""), "a.ogg");
+    }
+
+    #[test]
+    fn localize_moves_remote_images_into_the_media_column() {
+        let mut card = Card {
+            deck: "bio".into(),
+            front: "Name it <IMG alt='' src='https://x.org/heart.png'> <img src=\"a.png\">".into(),
+            back: "heart".into(),
+            media: "https://x.org/cell.jpg#0.1".into(),
+            id: "c1".into(),
+            ..Default::default()
+        };
+        assert!(has_remote(&card));
+        let errors = localize(&mut card, |url| match url {
+            "https://x.org/heart.png" => Ok("h1.png".into()),
+            _ => Err(Error::Fetch {
+                url: url.into(),
+                source: ureq::Error::StatusCode(404),
+            }),
+        });
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], Error::Fetch { url, .. } if url == "https://x.org/cell.jpg"));
+        assert_eq!(card.front, "Name it  <img src=\"a.png\">");
+        assert_eq!(card.media, "https://x.org/cell.jpg#0.1;h1.png");
+    }
+}
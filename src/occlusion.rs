@@ -0,0 +1,171 @@
+// Image occlusion: an image with rectangles hidden until the answer is shown,
+// for diagrams, maps and anatomy. The rectangles are kept with the image in
+// the card's media column, after a `#`, each `x,y,w,h` in percent of the
+// image and separated by spaces:
+//
+//     deck,front,back,media
+//     anatomy,Name the hidden bone,Femur,skeleton.png#40,52,8,20
+//
+// The web review covers them and lifts them on reveal; the mask editor
+// (`/card/{id}/masks`) draws them by dragging over the image.
+
+use crate::card::Card;
+
+/// One hidden rectangle, in percent of the image's width and height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mask {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+/// Parse space-separated `x,y,w,h` rectangles.
+pub fn parse(s: &str) -> Result<Vec<Mask>, String> {
+    s.split_whitespace()
+        .map(|rect| {
+            let n: Vec<f64> = rect
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("invalid mask {rect:?} (want x,y,w,h)"))?;
+            let [x, y, w, h] = n[..] else {
+                return Err(format!("invalid mask {rect:?} (want x,y,w,h)"));
+            };
+            if !n.iter().all(|v| (0.0..=100.0).contains(v)) || w == 0.0 || h == 0.0 {
+                return Err(format!("mask {rect:?} is outside the image"));
+            }
+            Ok(Mask { x, y, w, h })
+        })
+        .collect()
+}
+
+/// The rectangles as stored after the `#`.
+pub fn format(masks: &[Mask]) -> String {
+    masks
+        .iter()
+        .map(|m| {
+            format!(
+                "{},{},{},{}",
+                round(m.x),
+                round(m.y),
+                round(m.w),
+                round(m.h)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn round(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+/// Each media file of `card` with its masks. Masks that do not parse are
+/// dropped rather than failing the review.
+pub fn media(card: &Card) -> Vec<(&str, Vec<Mask>)> {
+    entries(&card.media)
+        .map(|(file, masks)| (file, masks.and_then(|m| parse(m).ok()).unwrap_or_default()))
+        .collect()
+}
+
+fn entries(media: &str) -> impl Iterator<Item = (&str, Option<&str>)> {
+    media
+        .split(';')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| match m.split_once('#') {
+            Some((file, masks)) => (file.trim(), Some(masks)),
+            None => (m, None),
+        })
+}
+
+/// Store `masks` for `file` in `card`'s media column, keeping its other
+/// entries. Returns false when the card has no such file.
+pub fn set(card: &mut Card, file: &str, masks: &[Mask]) -> bool {
+    let mut found = false;
+    let media: Vec<String> = entries(&card.media)
+        .map(|(name, old)| {
+            if name != file {
+                return old.map_or(name.to_string(), |m| format!("{name}#{m}"));
+            }
+            found = true;
+            match masks {
+                [] => name.to_string(),
+                _ => format!("{name}#{}", format(masks)),
+            }
+        })
+        .collect();
+    if found {
+        card.media = media.join(";");
+    }
+    found
+}
+
+/// `card`'s media column with each file renamed by `rename`, masks kept.
+pub fn rename_files<'a>(card: &Card, rename: impl Fn(&str) -> Option<&'a str>) -> String {
+    entries(&card.media)
+        .map(|(file, masks)| {
+            let file = rename(file).unwrap_or(file);
+            masks.map_or(file.to_string(), |m| format!("{file}#{m}"))
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_round_trip_through_the_media_column() {
+        let mut card = Card {
+            deck: "anatomy".into(),
+            front: "Name the hidden bone".into(),
+            back: "Femur".into(),
+            media: "skeleton.png#40,52,8,20 10,10,5.5,5;note.mp3".into(),
+            id: "c1".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            crate::card::media_files(&card),
+            vec!["skeleton.png", "note.mp3"]
+        );
+        let media = media(&card);
+        assert_eq!(media[0].0, "skeleton.png");
+        assert_eq!(
+            media[0].1,
+            vec![
+                Mask {
+                    x: 40.0,
+                    y: 52.0,
+                    w: 8.0,
+                    h: 20.0
+                },
+                Mask {
+                    x: 10.0,
+                    y: 10.0,
+                    w: 5.5,
+                    h: 5.0
+                },
+            ]
+        );
+        assert!(media[1].1.is_empty());
+
+        let masks = parse("1.234,2,3,4").unwrap();
+        assert!(set(&mut card, "skeleton.png", &masks));
+        assert_eq!(card.media, "skeleton.png#1.2,2,3,4;note.mp3");
+        assert!(!set(&mut card, "missing.png", &masks));
+        assert_eq!(
+            rename_files(&card, |f| (f == "skeleton.png").then_some("skel-2.png")),
+            "skel-2.png#1.2,2,3,4;note.mp3"
+        );
+        assert!(set(&mut card, "skeleton.png", &[]));
+        assert_eq!(card.media, "skeleton.png;note.mp3");
+
+        assert!(parse("1,2,3").is_err());
+        assert!(parse("1,2,x,4").is_err());
+        assert!(parse("50,50,0,10").is_err());
+        assert!(parse("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+// Multiple choice, for recognition practice where typing or self-grading is
+// a chore (on a phone, say): the front is shown with its answer among up to
+// three others drawn from the same deck, and picking one grades the card,
+// Good when right and Forgot when wrong. Started from the web UI with
+// `?quiz=1` on a review URL.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::card::{self, Card};
+
+/// Wrong answers offered next to the right one.
+pub const DISTRACTORS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Choice {
+    pub text: String,
+    pub correct: bool,
+}
+
+/// What a card asks for: its clozes, or else its back.
+pub fn answer(card: &Card) -> String {
+    let clozes = card::extract_cloze_deletions(&card.front);
+    if clozes.is_empty() {
+        card::expand_escapes(card.back.trim())
+    } else {
+        card::fill_clozes(&clozes.join(", "))
+    }
+}
+
+/// The other cards of `cards[i]`'s deck, in an order fixed by `seed` so a
+/// card keeps its choices when the page is reloaded.
+pub fn candidates(cards: &[Card], i: usize, seed: &str) -> Vec<usize> {
+    let mut others: Vec<usize> = (0..cards.len())
+        .filter(|&j| j != i && cards[j].deck == cards[i].deck)
+        .collect();
+    others.sort_by_key(|&j| rank(seed, &cards[j].id));
+    others
+}
+
+/// `card`'s answer and the first `DISTRACTORS` different answers among
+/// `others`, shuffled by `seed`. Empty when no other answer was found, since
+/// a single choice is no question.
+pub fn choices(card: &Card, others: impl IntoIterator<Item = Card>, seed: &str) -> Vec<Choice> {
+    let right = answer(card);
+    if right.is_empty() {
+        return Vec::new();
+    }
+    let mut texts = vec![right.clone()];
+    for other in others {
+        if texts.len() > DISTRACTORS {
+            break;
+        }
+        let text = answer(&other);
+        if !text.is_empty() && !texts.iter().any(|t| t.eq_ignore_ascii_case(&text)) {
+            texts.push(text);
+        }
+    }
+    if texts.len() < 2 {
+        return Vec::new();
+    }
+    texts.sort_by_key(|t| rank(seed, t));
+    texts
+        .into_iter()
+        .map(|text| Choice {
+            correct: text == right,
+            text,
+        })
+        .collect()
+}
+
+fn rank(seed: &str, key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (seed, key).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(deck: &str, front: &str, back: &str, id: &str) -> Card {
+        Card {
+            deck: deck.into(),
+            front: front.into(),
+            back: back.into(),
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn offers_answers_from_the_same_deck() {
+        let cards = vec![
+            card("es", "gato", "cat", "1"),
+            card("es", "perro", "dog", "2"),
+            card("es", "can", "Dog", "3"),
+            card("es", "El [pájaro] canta", "", "4"),
+            card("es", "vaca", "cow", "5"),
+            card("es", "oveja", "sheep", "6"),
+            card("de", "Katze", "cat (de)", "7"),
+        ];
+        assert_eq!(answer(&cards[3]), "pájaro");
+
+        let others = candidates(&cards, 0, "s1");
+        assert_eq!(others.len(), 5);
+        assert!(!others.contains(&0) && !others.contains(&6));
+        assert_eq!(others, candidates(&cards, 0, "s1"));
+
+        let picked = choices(&cards[0], others.iter().map(|&j| cards[j].clone()), "s1");
+        assert_eq!(picked.len(), DISTRACTORS + 1);
+        assert_eq!(picked.iter().filter(|c| c.correct).count(), 1);
+        assert!(picked.iter().any(|c| c.correct && c.text == "cat"));
+        // "dog" and "Dog" are one answer.
+        assert!(
+            picked
+                .iter()
+                .filter(|c| c.text.eq_ignore_ascii_case("dog"))
+                .count()
+                <= 1
+        );
+        assert!(picked.iter().all(|c| c.text != "cat (de)"));
+
+        assert!(choices(&cards[6], Vec::new(), "s1").is_empty());
+    }
+}
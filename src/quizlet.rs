@@ -0,0 +1,106 @@
+// Import of Quizlet's "Export" text: one term and definition per row, with no
+// quoting. Quizlet lets the user pick both delimiters (tab or comma between
+// term and definition, newline or semicolon between rows, or any custom
+// string), so both are parameters here.
+
+use crate::card::Card;
+use crate::error::{Error, Result};
+
+pub struct QuizletImport {
+    pub cards: Vec<Card>,
+    /// Non-empty rows with no term delimiter in them.
+    pub skipped: usize,
+}
+
+/// Parse exported text into cards in `deck` with fresh ids and no scheduling.
+pub fn parse(text: &str, term_sep: &str, row_sep: &str, deck: &str) -> Result<QuizletImport> {
+    if term_sep.is_empty() || row_sep.is_empty() {
+        return Err(Error::EmptyDelimiter);
+    }
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut cards = Vec::new();
+    let mut skipped = 0;
+    for row in text.split(row_sep) {
+        // Exports saved on Windows end lines with "\r\n".
+        let row = row.strip_suffix('\r').unwrap_or(row);
+        if row.trim().is_empty() {
+            continue;
+        }
+        let Some((term, definition)) = row.split_once(term_sep) else {
+            skipped += 1;
+            continue;
+        };
+        cards.push(Card {
+            deck: deck.to_string(),
+            front: term.trim().to_string(),
+            back: definition.trim().to_string(),
+            extra: String::new(),
+            origin: String::new(),
+            media: String::new(),
+            id: uuid::Uuid::new_v4().to_string(),
+            stability: None,
+            difficulty: None,
+            due: None,
+            last_review: None,
+            created: Some(crate::clock::today()),
+            tags: Vec::new(),
+            suspended: false,
+            flagged: false,
+            ease: None,
+            interval: None,
+        });
+    }
+    if cards.is_empty() && skipped > 0 {
+        return Err(Error::NoTermDelimiter {
+            delimiter: term_sep.to_string(),
+        });
+    }
+    Ok(QuizletImport { cards, skipped })
+}
+
+/// The term delimiter of pasted `front<TAB>back` or `front;back` lines: a tab
+/// if any line has one, otherwise a semicolon.
+pub fn guess_term_sep(text: &str) -> &'static str {
+    if text.contains('\t') { "\t" } else { ";" }
+}
+
+/// A delimiter from the command line: `tab`, `comma`, `semicolon`, `newline`,
+/// or a literal string in which `\t` and `\n` stand for tab and newline.
+pub fn delimiter(arg: &str) -> String {
+    match arg {
+        "tab" => "\t".to_string(),
+        "comma" => ",".to_string(),
+        "semicolon" => ";".to_string(),
+        "newline" => "\n".to_string(),
+        _ => arg.replace("\\t", "\t").replace("\\n", "\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quizlet_export() {
+        let text = "hola\thello\r\nperro\tdog, hound\r\n\r\nno delimiter\r\n";
+        let import = parse(text, "\t", "\n", "es").unwrap();
+        assert_eq!(import.cards.len(), 2);
+        assert_eq!(import.skipped, 1);
+        assert_eq!(import.cards[1].front, "perro");
+        assert_eq!(import.cards[1].back, "dog, hound");
+        assert_eq!(import.cards[0].deck, "es");
+        assert!(import.cards[0].due.is_none());
+
+        // Custom delimiters let definitions span lines.
+        let import = parse("a - one\ntwo;;b - three;;", " - ", ";;", "d").unwrap();
+        assert_eq!(import.cards[0].back, "one\ntwo");
+        assert_eq!(import.cards[1].front, "b");
+        assert_eq!(delimiter("semicolon"), ";");
+        assert_eq!(delimiter("\\n\\n"), "\n\n");
+
+        assert!(parse("a,b\nc,d", "\t", "\n", "d").is_err());
+
+        assert_eq!(guess_term_sep("a;b\nc\td"), "\t");
+        assert_eq!(guess_term_sep("a;b\nc;d"), ";");
+    }
+}
@@ -0,0 +1,89 @@
+// `rote remind`: desktop notifications of the due count, sent at fixed times
+// of day. Notifications go through `notify-send` on Linux and the BSDs and
+// `osascript` on macOS, so nothing beyond what the desktop already has is
+// needed.
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+
+use crate::error::{Error, Result};
+
+/// Parse `HH:MM`.
+pub fn parse_time(s: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| Error::Time {
+        text: s.to_string(),
+    })
+}
+
+/// The first of `times` strictly after `now`, today or tomorrow.
+pub fn next_run(times: &[NaiveTime], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let today = now.date();
+    let tomorrow = today + Duration::days(1);
+    [today, tomorrow]
+        .into_iter()
+        .flat_map(|day| times.iter().map(move |&t| day.and_time(t)))
+        .filter(|&at| at > now)
+        .min()
+}
+
+/// Title and body of the reminder for `due` cards, pointing at `url`.
+pub fn message(due: usize, url: &str) -> (String, String) {
+    let plural = if due == 1 { "" } else { "s" };
+    (
+        format!("{due} card{plural} due"),
+        format!("Review at {url}"),
+    )
+}
+
+/// Show a desktop notification.
+pub fn notify(title: &str, body: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let script = format!(
+            "display notification {} with title \"rote\" subtitle {}",
+            quote(body),
+            quote(title)
+        );
+        let mut c = std::process::Command::new("osascript");
+        c.arg("-e").arg(script);
+        c
+    } else {
+        let mut c = std::process::Command::new("notify-send");
+        c.args(["--app-name", "rote", title, body]);
+        c
+    };
+    let program = command.get_program().to_string_lossy().into_owned();
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(Error::Exited {
+            program,
+            status,
+            stderr: String::new(),
+        }),
+        Err(source) => Err(Error::Spawn { program, source }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_run_wraps_to_tomorrow() {
+        let times = [parse_time("08:00").unwrap(), parse_time("18:30").unwrap()];
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(
+            next_run(&times, at("2026-10-16 07:59")),
+            Some(at("2026-10-16 08:00"))
+        );
+        assert_eq!(
+            next_run(&times, at("2026-10-16 08:00")),
+            Some(at("2026-10-16 18:30"))
+        );
+        assert_eq!(
+            next_run(&times, at("2026-10-16 20:00")),
+            Some(at("2026-10-17 08:00"))
+        );
+        assert_eq!(next_run(&[], at("2026-10-16 20:00")), None);
+        assert!(parse_time("8am").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Bulk changes to due dates, for `rote reschedule` and the deck settings page:
+// spread an overdue backlog over the coming days, reset cards to new, or move
+// due dates to what the desired retention now asks for. Each works from the
+// cards' stability rather than picking dates; each returns the indices of the
+// cards it changed, so callers save and journal only those.
+
+use chrono::NaiveDate;
+
+use crate::card::Card;
+use crate::{fsrs, review};
+
+/// Give the reviewed cards among `selected` that are due by `today` new due
+/// dates over the `days` days from `today`, the cards most likely forgotten
+/// first, so a backlog becomes an even daily load.
+pub fn spread(cards: &mut [Card], selected: &[usize], today: NaiveDate, days: u32) -> Vec<usize> {
+    let mut backlog: Vec<(usize, f64)> = selected
+        .iter()
+        .filter(|&&i| cards[i].due.is_some_and(|due| due <= today))
+        .filter_map(|&i| Some((i, review::predicted_retrievability(&cards[i], today)?)))
+        .collect();
+    backlog.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let days = days.max(1) as usize;
+    let n = backlog.len();
+    let mut changed = Vec::new();
+    for (k, (i, _)) in backlog.into_iter().enumerate() {
+        let due = today + chrono::Days::new((k * days / n) as u64);
+        if cards[i].due != Some(due) {
+            cards[i].due = Some(due);
+            changed.push(i);
+        }
+    }
+    changed
+}
+
+/// Forget the scheduling of `selected`, making them new cards again.
+pub fn reset(cards: &mut [Card], selected: &[usize]) -> Vec<usize> {
+    let mut changed = Vec::new();
+    for &i in selected {
+        let card = &mut cards[i];
+        if card.stability.is_some() || card.due.is_some() || card.last_review.is_some() {
+            card.stability = None;
+            card.difficulty = None;
+            card.due = None;
+            card.last_review = None;
+            card.ease = None;
+            card.interval = None;
+            changed.push(i);
+        }
+    }
+    changed
+}
+
+/// Due dates of `selected` recomputed from their last review and stability
+/// for the retention `retention_for` gives each card's deck.
+pub fn retention(
+    cards: &mut [Card],
+    selected: &[usize],
+    retention_for: impl Fn(&str) -> f64,
+) -> Vec<usize> {
+    let mut changed = Vec::new();
+    for &i in selected {
+        let card = &mut cards[i];
+        let (Some(s), Some(last)) = (card.stability, card.last_review) else {
+            continue;
+        };
+        let due = last + chrono::Days::new(fsrs::interval_days(s, retention_for(&card.deck)));
+        if card.due != Some(due) {
+            card.due = Some(due);
+            changed.push(i);
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(stability: Option<f64>, last: Option<NaiveDate>, due: Option<NaiveDate>) -> Card {
+        Card {
+            deck: "d".into(),
+            front: "f".into(),
+            id: "c".into(),
+            stability,
+            difficulty: stability.map(|_| 5.0),
+            due,
+            last_review: last,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn spreads_backlog_riskiest_first_and_recomputes_for_retention() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+        let today = day(16);
+        let mut cards = vec![
+            card(Some(2.0), Some(day(1)), Some(day(3))),
+            card(Some(30.0), Some(day(1)), Some(day(10))),
+            card(Some(5.0), Some(day(2)), Some(day(7))),
+            card(Some(20.0), Some(day(15)), Some(day(30))),
+            card(None, None, None),
+        ];
+        let all: Vec<usize> = (0..cards.len()).collect();
+
+        let changed = spread(&mut cards, &all, today, 3);
+        assert_eq!(changed, vec![0, 2, 1]);
+        let dues: Vec<_> = cards.iter().map(|c| c.due).collect();
+        assert_eq!(
+            dues,
+            vec![
+                Some(day(16)),
+                Some(day(18)),
+                Some(day(17)),
+                Some(day(30)),
+                None
+            ]
+        );
+
+        let changed = retention(&mut cards, &all, |_| 0.9);
+        assert_eq!(changed, vec![0, 1, 2, 3]);
+        assert_eq!(cards[1].due, Some(day(31)));
+        let stricter = retention(&mut cards, &[1], |_| 0.95);
+        assert_eq!(stricter, vec![1]);
+        assert!(cards[1].due < Some(day(31)));
+
+        assert_eq!(reset(&mut cards, &[0, 4]), vec![0]);
+        assert_eq!(cards[0].stability, None);
+        assert_eq!(cards[0].due, None);
+    }
+}
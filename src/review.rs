@@ -1,7 +1,11 @@
 use chrono::NaiveDate;
+use std::collections::BTreeSet;
 
 use crate::card::{self, Card};
+use crate::deck;
 use crate::fsrs::{self, Grade};
+use crate::latex;
+use crate::scheduler::Scheduler;
 
 #[derive(serde::Serialize)]
 pub struct ReviewItem {
@@ -14,52 +18,98 @@ pub struct ReviewItem {
 #[derive(serde::Serialize)]
 pub struct DeckSummary {
     pub name: String,
+    /// How far the deck is nested: 0 for `Japanese`, 1 for `Japanese::Vocab`.
+    pub depth: usize,
     pub total: usize,
     pub due: usize,
+    /// Cards at each `Maturity`, in order.
+    pub maturity: [usize; 4],
 }
 
-pub fn render_front(text: &str) -> String {
-    let clozes = card::extract_cloze_deletions(text);
-    if clozes.is_empty() {
-        card::expand_newlines(text)
-    } else {
-        let mut result = text.to_string();
-        // Replace each [...] with _____
-        let mut out = String::new();
-        let mut in_bracket = 0usize;
-        for ch in result.chars() {
-            match ch {
-                '[' => {
-                    if in_bracket == 0 {
-                        out.push_str("_____");
-                    }
-                    in_bracket += 1;
-                }
-                ']' => {
-                    in_bracket = in_bracket.saturating_sub(1);
-                }
-                _ => {
-                    if in_bracket == 0 {
-                        out.push(ch);
-                    }
-                }
-            }
+/// How well learned a card is, by its FSRS stability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Maturity {
+    /// Never reviewed.
+    New,
+    /// Stability under a day: just forgotten, or only just introduced.
+    Learning,
+    /// Stability under `MATURE_DAYS`.
+    Young,
+    Mature,
+}
+
+/// Stability, in days, from which a card counts as mature.
+pub const MATURE_DAYS: f64 = 21.0;
+
+impl Maturity {
+    pub const ALL: [Maturity; 4] = [
+        Maturity::New,
+        Maturity::Learning,
+        Maturity::Young,
+        Maturity::Mature,
+    ];
+
+    pub fn of(card: &Card) -> Maturity {
+        match card.stability {
+            None => Maturity::New,
+            Some(s) if s < 1.0 => Maturity::Learning,
+            Some(s) if s < MATURE_DAYS => Maturity::Young,
+            Some(_) => Maturity::Mature,
         }
-        result = out;
-        card::expand_newlines(&result)
     }
-}
 
-pub fn render_reveal(front: &str, back: &str) -> String {
-    // Remove brackets but keep content
-    let mut full_front = String::new();
-    for ch in front.chars() {
-        if ch != '[' && ch != ']' {
-            full_front.push(ch);
+    pub fn label(self) -> &'static str {
+        match self {
+            Maturity::New => "New",
+            Maturity::Learning => "Learning",
+            Maturity::Young => "Young",
+            Maturity::Mature => "Mature",
         }
     }
-    let full_front = card::expand_newlines(&full_front);
-    let back = card::expand_newlines(back);
+}
+
+/// The front as asked: each cloze blanked, or shown as `[hint...]` when it
+/// has one.
+pub fn render_front(text: &str) -> String {
+    if card::extract_cloze_deletions(text).is_empty() {
+        return card::expand_escapes(text);
+    }
+    let asked: String = card::cloze_parts(text)
+        .into_iter()
+        .map(|part| match part {
+            card::ClozePart::Text(text) => text,
+            card::ClozePart::Cloze { hint: None, .. } => "_____".to_string(),
+            card::ClozePart::Cloze {
+                hint: Some(hint), ..
+            } => format!("[{hint}...]"),
+        })
+        .collect();
+    card::expand_escapes(&asked)
+}
+
+/// `reveal` followed by a card's extra notes, quoted, if it has any.
+pub fn with_extra(reveal: String, extra: &str) -> String {
+    let extra = card::expand_escapes(extra.trim());
+    if extra.is_empty() {
+        return reveal;
+    }
+    let quoted: Vec<String> = extra.lines().map(|l| format!("> {l}")).collect();
+    format!("{reveal}\n\n{}", quoted.join("\n"))
+}
+
+pub fn render_reveal(front: &str, back: &str) -> String {
+    render_reveal_marked(front, back, ("", ""))
+}
+
+/// Bold cyan, for cloze answers revealed in a terminal.
+pub const ANSI_HIGHLIGHT: (&str, &str) = ("\x1b[1;36m", "\x1b[0m");
+
+/// `render_reveal` with each cloze answer between `marks`, e.g.
+/// `ANSI_HIGHLIGHT`.
+pub fn render_reveal_marked(front: &str, back: &str, marks: (&str, &str)) -> String {
+    let (open, close) = marks;
+    let full_front = card::expand_escapes(&card::fill_clozes_marked(front, open, close));
+    let back = card::expand_escapes(back);
 
     if back.trim().is_empty() {
         full_front
@@ -68,82 +118,491 @@ pub fn render_reveal(front: &str, back: &str) -> String {
     }
 }
 
-pub fn build_review_items(cards: &[Card], indices: &[usize]) -> Vec<ReviewItem> {
+/// With `unicode_math`, `$…$` spans are shown as Unicode rather than LaTeX.
+/// Revealed cloze answers are put between `marks`.
+pub fn build_review_items(
+    cards: &[Card],
+    indices: &[usize],
+    unicode_math: bool,
+    marks: (&str, &str),
+) -> Vec<ReviewItem> {
+    let math = |text: String| {
+        if unicode_math {
+            latex::to_unicode(&text)
+        } else {
+            text
+        }
+    };
     indices
         .iter()
         .map(|&i| {
             let card = &cards[i];
             ReviewItem {
                 card_index: i,
-                front_display: render_front(&card.front),
-                reveal_display: render_reveal(&card.front, &card.back),
+                front_display: math(render_front(&card.front)),
+                reveal_display: math(with_extra(
+                    render_reveal_marked(&card.front, &card.back, marks),
+                    &card.extra,
+                )),
                 deck: card.deck.clone(),
             }
         })
         .collect()
 }
 
+/// New and overdue cards are due; suspended cards never are.
+pub fn is_due(card: &Card, today: NaiveDate) -> bool {
+    !card.suspended && card.due.is_none_or(|due| due <= today)
+}
+
 pub fn filter_due(cards: &[Card], today: NaiveDate) -> Vec<usize> {
     cards
         .iter()
         .enumerate()
-        .filter(|(_, card)| match card.due {
-            None => true, // new card
-            Some(due) => due <= today,
-        })
+        .filter(|(_, card)| is_due(card, today))
         .map(|(i, _)| i)
         .collect()
 }
 
+/// How cards are ordered within a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    #[default]
+    Random,
+    /// Lowest predicted retrievability first, so quitting early still covers
+    /// the cards most at risk of being forgotten.
+    #[serde(rename = "risk")]
+    AtRisk,
+    /// Earliest due date first.
+    Overdue,
+    /// Highest difficulty first.
+    Difficulty,
+    /// One card from each deck in turn, so no deck comes in a long run.
+    Interleaved,
+    /// The order cards were added in, which is their order in the files.
+    Created,
+}
+
+impl Order {
+    pub const ALL: [Order; 6] = [
+        Order::Random,
+        Order::AtRisk,
+        Order::Overdue,
+        Order::Difficulty,
+        Order::Interleaved,
+        Order::Created,
+    ];
+
+    pub fn parse(s: &str) -> Option<Order> {
+        Order::ALL.into_iter().find(|o| o.as_str() == s)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Order::Random => "random",
+            Order::AtRisk => "risk",
+            Order::Overdue => "overdue",
+            Order::Difficulty => "difficulty",
+            Order::Interleaved => "interleaved",
+            Order::Created => "created",
+        }
+    }
+
+    /// How the web UI names it.
+    pub fn label(self) -> &'static str {
+        match self {
+            Order::Random => "Random",
+            Order::AtRisk => "Most at risk first",
+            Order::Overdue => "Most overdue first",
+            Order::Difficulty => "Hardest first",
+            Order::Interleaved => "Decks in turn",
+            Order::Created => "Order added",
+        }
+    }
+}
+
+/// `queue` rearranged to take one card from each deck in turn, decks in the
+/// order they first appear and each deck's cards in their order in `queue`.
+fn interleave(cards: &[Card], queue: Vec<usize>) -> Vec<usize> {
+    let mut decks: Vec<(&str, std::collections::VecDeque<usize>)> = Vec::new();
+    for &i in &queue {
+        match decks.iter_mut().find(|(d, _)| *d == cards[i].deck) {
+            Some((_, q)) => q.push_back(i),
+            None => decks.push((&cards[i].deck, [i].into())),
+        }
+    }
+    let mut out = Vec::with_capacity(queue.len());
+    while out.len() < queue.len() {
+        out.extend(decks.iter_mut().filter_map(|(_, q)| q.pop_front()));
+    }
+    out
+}
+
+/// xorshift64*: plenty for shuffling and sampling grades, and reproducible
+/// from a seed.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    /// Uniform in [0, 1).
+    pub(crate) fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in [0, n).
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next() * n as f64) as usize).min(n - 1)
+    }
+
+    /// Index drawn with the given weights, which sum to 1.
+    pub(crate) fn pick(&mut self, weights: &[f64]) -> usize {
+        let mut x = self.next();
+        for (i, w) in weights.iter().enumerate() {
+            if x < *w {
+                return i;
+            }
+            x -= w;
+        }
+        weights.len() - 1
+    }
+}
+
+/// A seed that differs from run to run.
+pub fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(1, |d| d.as_nanos() as u64)
+}
+
+/// Shuffle `selection` (indices into `cards`), then move cards apart from
+/// their siblings where it can, so a card's reversed twin or another cloze
+/// of the same sentence does not come straight after it.
+pub fn shuffle(cards: &[Card], selection: &mut [usize], seed: u64) {
+    let mut rng = Rng::new(seed);
+    for i in (1..selection.len()).rev() {
+        selection.swap(i, rng.below(i + 1));
+    }
+    let mut keys: Vec<String> = selection.iter().map(|&i| sibling_key(&cards[i])).collect();
+    for i in 1..selection.len() {
+        if keys[i] != keys[i - 1] {
+            continue;
+        }
+        // Swap in a later card, or else move this one back to a gap between
+        // two cards that are not its siblings.
+        if let Some(j) = (i + 1..selection.len()).find(|&j| keys[j] != keys[i - 1]) {
+            selection.swap(i, j);
+            keys.swap(i, j);
+        } else if let Some(p) =
+            (0..i).find(|&p| keys[p] != keys[i] && (p == 0 || keys[p - 1] != keys[i]))
+        {
+            selection[p..=i].rotate_right(1);
+            keys[p..=i].rotate_right(1);
+        }
+    }
+}
+
+/// What siblings have in common: the original's id for a reversed twin or a
+/// line card, the filled-in sentence for a cloze.
+fn sibling_key(card: &Card) -> String {
+    if let Some(original) = deck::reversed_of(card).or_else(|| deck::line_of(card)) {
+        return original.to_string();
+    }
+    if card::extract_cloze_deletions(&card.front).is_empty() {
+        card.id.clone()
+    } else {
+        format!("{}\n{}", card.deck, card::fill_clozes(&card.front))
+    }
+}
+
+/// Predicted probability of recalling `card` today, or `None` for new cards.
+pub fn predicted_retrievability(card: &Card, today: NaiveDate) -> Option<f64> {
+    let (s, last) = (card.stability?, card.last_review?);
+    let elapsed = (today - last).num_days().max(0) as f64;
+    Some(fsrs::retrievability(elapsed, s))
+}
+
+/// How many more new and already-seen cards a session may take from a deck
+/// and the decks below it; `None` means no cap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeckLimit {
+    pub deck: String,
+    pub new: Option<usize>,
+    pub reviews: Option<usize>,
+}
+
+/// Criteria for building a review session. The default selects every due card.
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    /// Restrict to these decks; empty means all.
+    pub decks: Vec<String>,
+    /// Leave out these decks, e.g. ones outside their availability window.
+    pub skip_decks: Vec<String>,
+    /// Leave out these card ids, e.g. buried ones (see `crate::bury`).
+    pub skip_cards: BTreeSet<String>,
+    /// Cards carrying at least one of these tags; empty means any.
+    pub tags: Vec<String>,
+    /// Include cards falling due within this many days.
+    pub due_within: Option<i64>,
+    /// Only cards graded Forgot in the previous session.
+    pub forgotten: bool,
+    /// Keep only the N cards with the highest difficulty.
+    pub hardest: Option<usize>,
+    /// Review a random sample of N cards (see `arrange`).
+    pub sample: Option<usize>,
+    /// Include every card regardless of due date.
+    pub all: bool,
+    /// Log grades without updating scheduling.
+    pub practice: bool,
+    pub order: Order,
+    /// At most this many new cards (see `arrange`).
+    pub new_limit: Option<usize>,
+    /// Per-deck caps from deck settings (see `deck::limits`).
+    pub deck_limits: Vec<DeckLimit>,
+    /// Stop after this many cards, taken after ordering.
+    pub limit: Option<usize>,
+}
+
+impl SessionFilter {
+    /// Forgotten, hardest and sample sessions draw from all cards, not just
+    /// due ones, unless `due_within` is also given.
+    fn ignores_due(&self) -> bool {
+        self.all
+            || self.due_within.is_none()
+                && (self.forgotten || self.hardest.is_some() || self.sample.is_some())
+    }
+
+    /// Indices of matching cards. `forgotten` holds the ids graded Forgot in
+    /// the previous session (see `revlog::forgotten_in_last_session`).
+    pub fn select(&self, cards: &[Card], today: NaiveDate, forgotten: &[String]) -> Vec<usize> {
+        let horizon = today + chrono::Duration::days(self.due_within.unwrap_or(0));
+        let mut selected: Vec<usize> = cards
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.suspended)
+            .filter(|(_, c)| {
+                self.decks.is_empty() || self.decks.iter().any(|d| deck::contains(d, &c.deck))
+            })
+            .filter(|(_, c)| !self.skip_decks.iter().any(|d| deck::contains(d, &c.deck)))
+            .filter(|(_, c)| !self.skip_cards.contains(&c.id))
+            .filter(|(_, c)| self.tags.is_empty() || self.tags.iter().any(|t| card::has_tag(c, t)))
+            .filter(|(_, c)| self.ignores_due() || c.due.is_none_or(|d| d <= horizon))
+            .filter(|(_, c)| !self.forgotten || forgotten.contains(&c.id))
+            .map(|(i, _)| i)
+            .collect();
+
+        if let Some(n) = self.hardest {
+            selected.retain(|&i| cards[i].difficulty.is_some());
+            selected.sort_by(|&a, &b| {
+                let (da, db) = (cards[a].difficulty, cards[b].difficulty);
+                db.partial_cmp(&da).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            selected.truncate(n);
+        }
+        selected
+    }
+
+    /// Apply the new-card limit, sampling and ordering to a shuffled selection. The sort is
+    /// stable, so ties (and new cards, which go last) keep their random order.
+    pub fn arrange(
+        &self,
+        cards: &[Card],
+        mut shuffled: Vec<usize>,
+        today: NaiveDate,
+    ) -> Vec<usize> {
+        // In the order added, the limits take the oldest new cards first; a
+        // sample stays random and is put in order afterwards.
+        if self.order == Order::Created && self.sample.is_none() {
+            shuffled.sort();
+        }
+        if let Some(limit) = self.new_limit {
+            let mut new_seen = 0;
+            shuffled.retain(|&i| {
+                if cards[i].due.is_some() {
+                    return true;
+                }
+                new_seen += 1;
+                new_seen <= limit
+            });
+        }
+        if !self.deck_limits.is_empty() {
+            // New and seen cards taken so far under each limit.
+            let mut taken = vec![[0, 0]; self.deck_limits.len()];
+            shuffled.retain(|&i| {
+                let kind = usize::from(cards[i].due.is_some());
+                let applies: Vec<usize> = (0..self.deck_limits.len())
+                    .filter(|&l| deck::contains(&self.deck_limits[l].deck, &cards[i].deck))
+                    .collect();
+                let fits = applies.iter().all(|&l| {
+                    let limit = &self.deck_limits[l];
+                    let cap = [limit.new, limit.reviews][kind];
+                    cap.is_none_or(|cap| taken[l][kind] < cap)
+                });
+                if fits {
+                    for &l in &applies {
+                        taken[l][kind] += 1;
+                    }
+                }
+                fits
+            });
+        }
+        if let Some(n) = self.sample {
+            shuffled.truncate(n);
+        }
+        match self.order {
+            Order::Random => {}
+            Order::Created => shuffled.sort(),
+            Order::AtRisk => shuffled.sort_by(|&a, &b| {
+                let ra = predicted_retrievability(&cards[a], today).unwrap_or(f64::INFINITY);
+                let rb = predicted_retrievability(&cards[b], today).unwrap_or(f64::INFINITY);
+                ra.partial_cmp(&rb).unwrap_or(std::cmp::Ordering::Equal)
+            }),
+            Order::Overdue => shuffled.sort_by_key(|&i| cards[i].due.unwrap_or(NaiveDate::MAX)),
+            Order::Difficulty => shuffled.sort_by(|&a, &b| {
+                let (da, db) = (cards[a].difficulty, cards[b].difficulty);
+                let (da, db) = (
+                    da.unwrap_or(f64::NEG_INFINITY),
+                    db.unwrap_or(f64::NEG_INFINITY),
+                );
+                db.partial_cmp(&da).unwrap_or(std::cmp::Ordering::Equal)
+            }),
+            Order::Interleaved => shuffled = interleave(cards, shuffled),
+        }
+        if let Some(n) = self.limit {
+            shuffled.truncate(n);
+        }
+        shuffled
+    }
+}
+
+/// Counts for every deck and each of its parents, in tree order. A parent's
+/// counts include the decks below it.
 pub fn deck_summaries(cards: &[Card], today: NaiveDate) -> Vec<DeckSummary> {
-    let mut decks: std::collections::BTreeMap<String, (usize, usize)> =
+    // Keyed by name parts, so each parent sorts right before its children.
+    let mut decks: std::collections::BTreeMap<Vec<&str>, (usize, usize, [usize; 4])> =
         std::collections::BTreeMap::new();
     for card in cards {
-        let entry = decks.entry(card.deck.clone()).or_insert((0, 0));
-        entry.0 += 1;
-        let is_due = match card.due {
-            None => true,
-            Some(due) => due <= today,
-        };
-        if is_due {
-            entry.1 += 1;
+        let parts: Vec<&str> = card.deck.split(deck::SEPARATOR).collect();
+        let maturity = Maturity::of(card) as usize;
+        for n in 1..=parts.len() {
+            let entry = decks.entry(parts[..n].to_vec()).or_insert((0, 0, [0; 4]));
+            entry.0 += 1;
+            if is_due(card, today) {
+                entry.1 += 1;
+            }
+            entry.2[maturity] += 1;
         }
     }
     decks
         .into_iter()
-        .map(|(name, (total, due))| DeckSummary { name, total, due })
+        .map(|(parts, (total, due, maturity))| DeckSummary {
+            name: parts.join(deck::SEPARATOR),
+            depth: parts.len() - 1,
+            total,
+            due,
+            maturity,
+        })
         .collect()
 }
 
-pub fn apply_grade(card: &mut Card, grade: Grade, today: NaiveDate) {
-    let outcome =
-        if card.stability.is_some() && card.difficulty.is_some() && card.last_review.is_some() {
-            let days_elapsed = (today - card.last_review.unwrap()).num_days() as f64;
-            let days_elapsed = if days_elapsed < 0.0 {
-                0.0
-            } else {
-                days_elapsed
-            };
-            fsrs::review_existing(
-                card.difficulty.unwrap(),
-                card.stability.unwrap(),
-                days_elapsed,
-                grade,
-                today,
-            )
-        } else {
-            fsrs::review_new(grade, today)
-        };
+/// Scheduled cards falling due on each of the next `days` days, starting
+/// today; overdue cards count towards today. New and suspended cards are left
+/// out.
+pub fn forecast(cards: &[Card], today: NaiveDate, days: usize) -> Vec<usize> {
+    let mut counts = vec![0; days];
+    for card in cards.iter().filter(|c| !c.suspended) {
+        let Some(due) = card.due else { continue };
+        let offset = (due - today).num_days().max(0) as usize;
+        if let Some(count) = counts.get_mut(offset) {
+            *count += 1;
+        }
+    }
+    counts
+}
 
+pub fn apply_grade(
+    card: &mut Card,
+    grade: Grade,
+    today: NaiveDate,
+    retention: f64,
+    scheduler: &dyn Scheduler,
+) {
+    let outcome = scheduler.review(card, grade, today, retention);
     card.stability = Some(outcome.stability);
     card.difficulty = Some(outcome.difficulty);
     card.due = Some(outcome.due);
     card.last_review = Some(today);
+    // FSRS keeps SM-2's ease for when the deck goes back to SM-2; the
+    // interval it leaves would be stale by then.
+    card.ease = outcome.ease.or(card.ease);
+    card.interval = outcome.interval;
+}
+
+/// Days until `card` would be due again after each grade, Forgot to Easy.
+pub fn next_intervals(
+    card: &Card,
+    today: NaiveDate,
+    retention: f64,
+    scheduler: &dyn Scheduler,
+) -> [i64; 4] {
+    [Grade::Forgot, Grade::Hard, Grade::Good, Grade::Easy].map(|grade| {
+        let mut card = card.clone();
+        apply_grade(&mut card, grade, today, retention, scheduler);
+        card.due.map_or(0, |due| (due - today).num_days())
+    })
+}
+
+/// An interval as shown on grade buttons: `12d`, `1.1mo`, `2y`.
+pub fn format_interval(days: i64) -> String {
+    let short = |n: f64, unit: &str| {
+        let n = format!("{n:.1}");
+        format!("{}{unit}", n.strip_suffix(".0").unwrap_or(&n))
+    };
+    match days {
+        ..30 => format!("{days}d"),
+        30..365 => short(days as f64 / 30.4, "mo"),
+        _ => short(days as f64 / 365.25, "y"),
+    }
+}
+
+/// Time spent in a session as shown while reviewing: `4:07`, `1:02:30`.
+pub fn format_elapsed(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// When a long session suggests a break, from `[breaks]` in config.toml:
+/// after `minutes` of reviewing or `cards` graded since the last one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Breaks {
+    pub minutes: Option<u64>,
+    pub cards: Option<usize>,
+}
+
+impl Breaks {
+    /// Whether a break is due `secs` seconds and `cards` grades after the
+    /// last one (or the start).
+    pub fn due(&self, secs: u64, cards: usize) -> bool {
+        self.minutes.is_some_and(|m| m > 0 && secs >= m * 60)
+            || self.cards.is_some_and(|n| n > 0 && cards >= n)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scheduler::Fsrs;
 
     #[test]
     fn render_front_no_cloze() {
@@ -175,6 +634,77 @@ mod tests {
         assert_eq!(result, "mitochondria");
     }
 
+    #[test]
+    fn extra_is_quoted_under_the_answer() {
+        let reveal = render_reveal("[mitochondria]", "organelle");
+        assert_eq!(
+            with_extra(reveal.clone(), "Greek *mitos*, thread\\nSee ch. 4"),
+            "mitochondria\n---\norganelle\n\n> Greek *mitos*, thread\n> See ch. 4"
+        );
+        assert_eq!(with_extra(reveal.clone(), " "), reveal);
+    }
+
+    #[test]
+    fn escaped_brackets_are_literal() {
+        let front = "In `a\\[i\\]`, [i] is the \\[index\\]";
+        assert_eq!(card::extract_cloze_deletions(front), vec!["i"]);
+        assert_eq!(render_front(front), "In `a[i]`, _____ is the [index]");
+        assert_eq!(render_reveal(front, ""), "In `a[i]`, i is the [index]");
+        assert_eq!(render_front("a\\[i\\] = 0"), "a[i] = 0");
+    }
+
+    #[test]
+    fn hints_replace_the_blank() {
+        let front = "[perro::p] is a [dog] and [a [b]]";
+        assert_eq!(
+            card::extract_cloze_deletions(front),
+            vec!["perro", "dog", "a [b]"]
+        );
+        assert_eq!(render_front(front), "[p...] is a _____ and _____");
+        assert_eq!(render_reveal(front, ""), "perro is a dog and a b");
+        assert_eq!(
+            render_reveal_marked(front, "", ("<", ">")),
+            "<perro> is a <dog> and <a b>"
+        );
+    }
+
+    #[test]
+    fn breaks_after_minutes_or_cards() {
+        let breaks = Breaks {
+            minutes: Some(25),
+            cards: Some(100),
+        };
+        assert!(!breaks.due(24 * 60, 99));
+        assert!(breaks.due(25 * 60, 10));
+        assert!(breaks.due(60, 100));
+        assert!(!Breaks::default().due(10_000, 10_000));
+        assert_eq!(format_elapsed(247), "4:07");
+        assert_eq!(format_elapsed(3750), "1:02:30");
+    }
+
+    #[test]
+    fn intervals_grow_with_the_grade() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let card = Card {
+            deck: "d".into(),
+            front: "q".into(),
+            back: "a".into(),
+            id: "1".into(),
+            stability: Some(10.0),
+            difficulty: Some(5.0),
+            due: Some(today),
+            last_review: NaiveDate::from_ymd_opt(2025, 5, 22),
+            ..Default::default()
+        };
+        let days = next_intervals(&card, today, fsrs::DEFAULT_RETENTION, &Fsrs);
+        assert!(days.windows(2).all(|w| w[0] < w[1]), "{days:?}");
+        assert_eq!(card.due, Some(today));
+
+        assert_eq!(format_interval(12), "12d");
+        assert_eq!(format_interval(33), "1.1mo");
+        assert_eq!(format_interval(365), "1y");
+    }
+
     #[test]
     fn filter_due_new_cards() {
         let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
@@ -182,12 +712,8 @@ mod tests {
             deck: "test".into(),
             front: "q".into(),
             back: "a".into(),
-            media: String::new(),
             id: "1".into(),
-            stability: None,
-            difficulty: None,
-            due: None,
-            last_review: None,
+            ..Default::default()
         }];
         let due = filter_due(&cards, today);
         assert_eq!(due, vec![0]);
@@ -200,12 +726,12 @@ mod tests {
             deck: "test".into(),
             front: "q".into(),
             back: "a".into(),
-            media: String::new(),
             id: "1".into(),
             stability: Some(3.0),
             difficulty: Some(5.0),
             due: NaiveDate::from_ymd_opt(2025, 6, 5),
             last_review: NaiveDate::from_ymd_opt(2025, 6, 1),
+            ..Default::default()
         }];
         let due = filter_due(&cards, today);
         assert_eq!(due, vec![0]);
@@ -218,12 +744,12 @@ mod tests {
             deck: "test".into(),
             front: "q".into(),
             back: "a".into(),
-            media: String::new(),
             id: "1".into(),
             stability: Some(3.0),
             difficulty: Some(5.0),
             due: NaiveDate::from_ymd_opt(2025, 6, 10),
             last_review: NaiveDate::from_ymd_opt(2025, 6, 1),
+            ..Default::default()
         }];
         let due = filter_due(&cards, today);
         assert!(due.is_empty());
@@ -236,14 +762,16 @@ mod tests {
             deck: "test".into(),
             front: "q".into(),
             back: "a".into(),
-            media: String::new(),
             id: "1".into(),
-            stability: None,
-            difficulty: None,
-            due: None,
-            last_review: None,
+            ..Default::default()
         };
-        apply_grade(&mut card, Grade::Good, today);
+        apply_grade(
+            &mut card,
+            Grade::Good,
+            today,
+            fsrs::DEFAULT_RETENTION,
+            &Fsrs,
+        );
         assert!(card.stability.is_some());
         assert!(card.difficulty.is_some());
         assert!(card.due.is_some());
@@ -258,15 +786,21 @@ mod tests {
             deck: "test".into(),
             front: "q".into(),
             back: "a".into(),
-            media: String::new(),
             id: "1".into(),
             stability: Some(3.173),
             difficulty: Some(5.5),
             due: Some(today),
             last_review: NaiveDate::from_ymd_opt(2025, 5, 28),
+            ..Default::default()
         };
         let old_stability = card.stability.unwrap();
-        apply_grade(&mut card, Grade::Good, today);
+        apply_grade(
+            &mut card,
+            Grade::Good,
+            today,
+            fsrs::DEFAULT_RETENTION,
+            &Fsrs,
+        );
         assert!(card.stability.unwrap() > old_stability);
         assert!(card.due.unwrap() > today);
     }
@@ -279,34 +813,26 @@ mod tests {
                 deck: "math".into(),
                 front: "q1".into(),
                 back: "a1".into(),
-                media: String::new(),
                 id: "1".into(),
-                stability: None,
-                difficulty: None,
-                due: None,
-                last_review: None,
+                ..Default::default()
             },
             Card {
                 deck: "math".into(),
                 front: "q2".into(),
                 back: "a2".into(),
-                media: String::new(),
                 id: "2".into(),
                 stability: Some(3.0),
                 difficulty: Some(5.0),
                 due: NaiveDate::from_ymd_opt(2025, 7, 1),
                 last_review: Some(today),
+                ..Default::default()
             },
             Card {
                 deck: "science".into(),
                 front: "q3".into(),
                 back: "a3".into(),
-                media: String::new(),
                 id: "3".into(),
-                stability: None,
-                difficulty: None,
-                due: None,
-                last_review: None,
+                ..Default::default()
             },
         ];
         let summaries = deck_summaries(&cards, today);
@@ -318,4 +844,283 @@ mod tests {
         assert_eq!(science.total, 1);
         assert_eq!(science.due, 1);
     }
+
+    #[test]
+    fn nested_decks_roll_up() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let cards = vec![
+            card_with("1", "ja::vocab::n5", None, None),
+            card_with(
+                "2",
+                "ja::vocab::n4",
+                Some(5.0),
+                NaiveDate::from_ymd_opt(2025, 7, 1),
+            ),
+            card_with("3", "ja", None, None),
+            card_with("4", "ja extra", None, None),
+        ];
+        let summaries: Vec<(String, usize, usize, usize)> = deck_summaries(&cards, today)
+            .into_iter()
+            .map(|s| (s.name, s.depth, s.total, s.due))
+            .collect();
+        assert_eq!(
+            summaries,
+            vec![
+                ("ja".to_string(), 0, 3, 2),
+                ("ja::vocab".to_string(), 1, 2, 1),
+                ("ja::vocab::n4".to_string(), 2, 1, 0),
+                ("ja::vocab::n5".to_string(), 2, 1, 1),
+                ("ja extra".to_string(), 0, 1, 1),
+            ]
+        );
+        assert_eq!(deck_summaries(&cards, today)[0].maturity, [2, 0, 1, 0]);
+        let stabilities = [0.4, 20.9, 21.0].map(|s| {
+            let mut card = card_with("5", "ja", Some(5.0), None);
+            card.stability = Some(s);
+            Maturity::of(&card)
+        });
+        assert_eq!(
+            stabilities,
+            [Maturity::Learning, Maturity::Young, Maturity::Mature]
+        );
+
+        let filter = SessionFilter {
+            decks: vec!["ja::vocab".into()],
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.select(&cards, today, &[]), vec![0]);
+    }
+
+    #[test]
+    fn deck_limits_cap_new_and_seen_cards() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let cards = vec![
+            card_with("1", "ja::vocab", None, None),
+            card_with("2", "ja::kanji", None, None),
+            card_with("3", "ja", Some(5.0), Some(today)),
+            card_with("4", "ja", Some(5.0), Some(today)),
+            card_with("5", "spanish", None, None),
+        ];
+        let filter = SessionFilter {
+            deck_limits: vec![DeckLimit {
+                deck: "ja".into(),
+                new: Some(1),
+                reviews: Some(1),
+            }],
+            ..SessionFilter::default()
+        };
+        assert_eq!(
+            filter.arrange(&cards, vec![0, 1, 2, 3, 4], today),
+            vec![0, 2, 4]
+        );
+    }
+
+    fn card_with(id: &str, deck: &str, difficulty: Option<f64>, due: Option<NaiveDate>) -> Card {
+        Card {
+            deck: deck.into(),
+            front: "q".into(),
+            back: "a".into(),
+            id: id.into(),
+            stability: difficulty.map(|_| 3.0),
+            difficulty,
+            due,
+            last_review: due.map(|d| d - chrono::Duration::days(3)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn session_filter_due_within() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let cards = vec![
+            card_with("1", "a", Some(5.0), NaiveDate::from_ymd_opt(2025, 6, 1)),
+            card_with("2", "a", Some(5.0), NaiveDate::from_ymd_opt(2025, 6, 4)),
+            card_with("3", "b", Some(5.0), NaiveDate::from_ymd_opt(2025, 6, 4)),
+            card_with("4", "a", Some(5.0), NaiveDate::from_ymd_opt(2025, 6, 20)),
+        ];
+        let mut filter = SessionFilter::default();
+        assert_eq!(filter.select(&cards, today, &[]), vec![0]);
+        filter.due_within = Some(3);
+        assert_eq!(filter.select(&cards, today, &[]), vec![0, 1, 2]);
+        filter.decks = vec!["a".into()];
+        assert_eq!(filter.select(&cards, today, &[]), vec![0, 1]);
+    }
+
+    #[test]
+    fn session_filter_hardest_and_forgotten() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let later = NaiveDate::from_ymd_opt(2025, 7, 1);
+        let cards = vec![
+            card_with("1", "a", Some(2.0), later),
+            card_with("2", "a", Some(9.0), later),
+            card_with("3", "a", None, None),
+            card_with("4", "a", Some(6.0), later),
+        ];
+        let filter = SessionFilter {
+            hardest: Some(2),
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.select(&cards, today, &[]), vec![1, 3]);
+
+        let filter = SessionFilter {
+            forgotten: true,
+            ..SessionFilter::default()
+        };
+        assert_eq!(
+            filter.select(&cards, today, &["1".into(), "3".into()]),
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn arrange_at_risk_first() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let mut cards = vec![
+            card_with("new", "a", None, None),
+            card_with("fresh", "a", Some(5.0), Some(today)),
+            card_with("stale", "a", Some(5.0), Some(today)),
+        ];
+        cards[1].last_review = Some(today - chrono::Duration::days(1));
+        cards[2].last_review = Some(today - chrono::Duration::days(30));
+        let filter = SessionFilter {
+            order: Order::AtRisk,
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.arrange(&cards, vec![0, 1, 2], today), vec![2, 1, 0]);
+
+        let filter = SessionFilter {
+            sample: Some(2),
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.arrange(&cards, vec![2, 0, 1], today), vec![2, 0]);
+
+        let filter = SessionFilter {
+            order: Order::AtRisk,
+            limit: Some(1),
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.arrange(&cards, vec![0, 1, 2], today), vec![2]);
+    }
+
+    #[test]
+    fn arrange_by_due_difficulty_deck_and_creation() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let mut cards = vec![
+            card_with("new", "a", None, None),
+            card_with("late", "a", Some(5.0), NaiveDate::from_ymd_opt(2025, 5, 1)),
+            card_with("today", "b", Some(5.0), Some(today)),
+            card_with("new b", "b", None, None),
+        ];
+        cards[1].difficulty = Some(3.0);
+        cards[2].difficulty = Some(8.0);
+        let arranged = |order, new_limit| {
+            let filter = SessionFilter {
+                order,
+                new_limit,
+                ..SessionFilter::default()
+            };
+            filter.arrange(&cards, vec![3, 2, 0, 1], today)
+        };
+        assert_eq!(arranged(Order::Overdue, None), vec![1, 2, 3, 0]);
+        assert_eq!(arranged(Order::Difficulty, None), vec![2, 1, 3, 0]);
+        assert_eq!(arranged(Order::Interleaved, None), vec![3, 0, 2, 1]);
+        assert_eq!(arranged(Order::Created, Some(1)), vec![0, 1, 2]);
+        assert_eq!(Order::parse("interleaved"), Some(Order::Interleaved));
+        assert_eq!(Order::parse("due"), None);
+    }
+
+    #[test]
+    fn shuffle_is_seeded_and_keeps_siblings_apart() {
+        let mut cards = vec![
+            card_with("1", "a", None, None),
+            card_with("1-reversed", "a", None, None),
+            card_with("2", "a", None, None),
+            card_with("3", "a", None, None),
+            card_with("4", "a", None, None),
+        ];
+        cards[3].front = "[Paris] is in France".into();
+        cards[4].front = "Paris is in [France]".into();
+        for seed in 0..50 {
+            let mut first: Vec<usize> = (0..cards.len()).collect();
+            let mut second = first.clone();
+            shuffle(&cards, &mut first, seed);
+            shuffle(&cards, &mut second, seed);
+            assert_eq!(first, second);
+            let mut sorted = first.clone();
+            sorted.sort();
+            assert_eq!(sorted, vec![0, 1, 2, 3, 4]);
+            for pair in first.windows(2) {
+                let pair = [pair[0].min(pair[1]), pair[0].max(pair[1])];
+                assert!(pair != [0, 1] && pair != [3, 4], "{first:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn cram_includes_cards_not_due() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let cards = vec![
+            card_with("1", "a", Some(5.0), NaiveDate::from_ymd_opt(2026, 1, 1)),
+            card_with("2", "b", Some(5.0), NaiveDate::from_ymd_opt(2026, 1, 1)),
+        ];
+        let filter = SessionFilter {
+            decks: vec!["a".into()],
+            all: true,
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.select(&cards, today, &[]), vec![0]);
+
+        let filter = SessionFilter {
+            skip_decks: vec!["a".into()],
+            all: true,
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.select(&cards, today, &[]), vec![1]);
+    }
+
+    #[test]
+    fn forecast_counts_overdue_today() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 10).unwrap();
+        let cards = vec![
+            card_with("late", "a", Some(1.0), NaiveDate::from_ymd_opt(2025, 6, 1)),
+            card_with("today", "a", Some(1.0), Some(today)),
+            card_with("soon", "a", Some(1.0), NaiveDate::from_ymd_opt(2025, 6, 12)),
+            card_with("later", "a", Some(1.0), NaiveDate::from_ymd_opt(2025, 7, 1)),
+            card_with("new", "a", None, None),
+        ];
+        assert_eq!(forecast(&cards, today, 3), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn suspended_cards_are_never_due() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let mut cards = vec![
+            card_with("1", "a", None, None),
+            card_with("2", "a", None, None),
+        ];
+        cards[1].suspended = true;
+        assert_eq!(filter_due(&cards, today), vec![0]);
+        assert_eq!(deck_summaries(&cards, today)[0].due, 1);
+        let cram = SessionFilter {
+            all: true,
+            ..SessionFilter::default()
+        };
+        assert_eq!(cram.select(&cards, today, &[]), vec![0]);
+    }
+
+    #[test]
+    fn arrange_limits_new_cards() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let cards = vec![
+            card_with("1", "a", None, None),
+            card_with("2", "a", Some(5.0), Some(today)),
+            card_with("3", "a", None, None),
+            card_with("4", "a", None, None),
+        ];
+        let filter = SessionFilter {
+            new_limit: Some(1),
+            ..SessionFilter::default()
+        };
+        assert_eq!(filter.arrange(&cards, vec![0, 1, 2, 3], today), vec![0, 1]);
+    }
 }
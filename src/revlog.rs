@@ -0,0 +1,275 @@
+// Review log: one line of JSON per graded card, appended to `revlog.jsonl` in
+// the collection's data directory. Unlike the undo journal this is never
+// trimmed; it is the history that statistics and session filters read from.
+//
+// Session notes share the file. They have no `card_id` and reviews have no
+// `note`, so each loader simply skips the other kind of line.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::card::Card;
+use crate::clock;
+use crate::error::{Error, Result};
+use crate::fsrs::Grade;
+
+const REVLOG_FILE: &str = "revlog.jsonl";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Review {
+    pub at: DateTime<Local>,
+    pub card_id: String,
+    pub deck: String,
+    /// 1 = forgot, 2 = hard, 3 = good, 4 = easy.
+    pub grade: u8,
+    pub session: String,
+    /// Days between the previous review and the due date it set.
+    #[serde(default)]
+    pub scheduled_days: Option<i64>,
+    /// Days actually elapsed since the previous review.
+    #[serde(default)]
+    pub elapsed_days: Option<i64>,
+    /// Graded in a session that left scheduling untouched.
+    #[serde(default)]
+    pub practice: bool,
+    /// Milliseconds from the card being shown to its grade.
+    #[serde(default)]
+    pub answer_ms: Option<u64>,
+}
+
+impl Review {
+    /// Build a log entry from the card's state *before* the grade was applied.
+    pub fn new(card: &Card, grade: Grade, session: &str, today: NaiveDate) -> Review {
+        let scheduled_days = match (card.last_review, card.due) {
+            (Some(last), Some(due)) => Some((due - last).num_days()),
+            _ => None,
+        };
+        Review {
+            at: Local::now(),
+            card_id: card.id.clone(),
+            deck: card.deck.clone(),
+            grade: grade.as_u8(),
+            session: session.to_string(),
+            scheduled_days,
+            elapsed_days: card.last_review.map(|last| (today - last).num_days()),
+            practice: false,
+            answer_ms: None,
+        }
+    }
+}
+
+/// A reviewer's remark about a whole session, e.g. "tired, grades unreliable".
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SessionNote {
+    pub at: DateTime<Local>,
+    pub session: String,
+    pub note: String,
+    /// Leave this session's grades out of retention statistics.
+    #[serde(default)]
+    pub exclude: bool,
+}
+
+pub fn revlog_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(REVLOG_FILE)
+}
+
+pub fn append(data_dir: &Path, reviews: &[Review]) -> Result<()> {
+    append_lines(data_dir, reviews)
+}
+
+/// Attach a note to `session`. A later note for the same session replaces it.
+pub fn add_note(data_dir: &Path, note: &SessionNote) -> Result<()> {
+    append_lines(data_dir, std::slice::from_ref(note))
+}
+
+fn append_lines<T: serde::Serialize>(data_dir: &Path, items: &[T]) -> Result<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(data_dir).map_err(Error::io("create", data_dir))?;
+    let path = revlog_path(data_dir);
+    let mut out = String::new();
+    for item in items {
+        let line = serde_json::to_string(item).unwrap();
+        out.push_str(&line);
+        out.push('\n');
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(Error::io("open", &path))?;
+    file.write_all(out.as_bytes())
+        .map_err(Error::io("write", path))
+}
+
+fn read_lines<T: serde::de::DeserializeOwned>(data_dir: &Path) -> Vec<T> {
+    let Ok(content) = std::fs::read_to_string(revlog_path(data_dir)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Every logged review, oldest first. Unparseable lines are skipped.
+pub fn load(data_dir: &Path) -> Vec<Review> {
+    read_lines(data_dir)
+}
+
+/// The latest note for each session.
+pub fn load_notes(data_dir: &Path) -> HashMap<String, SessionNote> {
+    read_lines::<SessionNote>(data_dir)
+        .into_iter()
+        .map(|n| (n.session.clone(), n))
+        .collect()
+}
+
+/// Ids of cards graded Forgot in the most recent session.
+pub fn forgotten_in_last_session(reviews: &[Review]) -> Vec<String> {
+    let Some(last) = reviews.iter().max_by_key(|r| r.at) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = reviews
+        .iter()
+        .filter(|r| r.session == last.session && r.grade == 1)
+        .map(|r| r.card_id.clone())
+        .collect();
+    ids.dedup();
+    ids
+}
+
+/// Number of distinct new cards first reviewed (outside practice) on `day`.
+pub fn new_cards_on(reviews: &[Review], day: NaiveDate) -> usize {
+    let mut ids: Vec<&str> = reviews
+        .iter()
+        .filter(|r| r.elapsed_days.is_none() && !r.practice && clock::day_of(&r.at) == day)
+        .map(|r| r.card_id.as_str())
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.len()
+}
+
+/// `card_id`'s reviews, oldest first.
+pub fn history<'a>(reviews: &'a [Review], card_id: &str) -> Vec<&'a Review> {
+    reviews.iter().filter(|r| r.card_id == card_id).collect()
+}
+
+/// Times a card was forgotten after having been learned, outside practice.
+pub fn lapses(history: &[&Review]) -> usize {
+    history
+        .iter()
+        .filter(|r| r.grade == 1 && r.elapsed_days.is_some() && !r.practice)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(card_id: &str, grade: u8, session: &str, minutes: i64) -> Review {
+        Review {
+            at: Local::now() + chrono::Duration::minutes(minutes),
+            card_id: card_id.into(),
+            deck: "d".into(),
+            grade,
+            session: session.into(),
+            scheduled_days: None,
+            elapsed_days: None,
+            practice: false,
+            answer_ms: None,
+        }
+    }
+
+    #[test]
+    fn append_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        append(dir.path(), &[review("a", 3, "s1", 0)]).unwrap();
+        append(dir.path(), &[review("b", 1, "s1", 1)]).unwrap();
+        let loaded = load(dir.path());
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].card_id, "b");
+        assert_eq!(loaded[1].grade, 1);
+    }
+
+    #[test]
+    fn notes_and_reviews_share_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        append(dir.path(), &[review("a", 3, "s1", 0)]).unwrap();
+        for text in ["first", "tired"] {
+            let note = SessionNote {
+                at: Local::now(),
+                session: "s1".into(),
+                note: text.into(),
+                exclude: true,
+            };
+            add_note(dir.path(), &note).unwrap();
+        }
+        assert_eq!(load(dir.path()).len(), 1);
+        let notes = load_notes(dir.path());
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes["s1"].note, "tired");
+    }
+
+    #[test]
+    fn forgotten_only_from_latest_session() {
+        let reviews = vec![
+            review("old", 1, "s1", 0),
+            review("a", 1, "s2", 10),
+            review("b", 3, "s2", 11),
+            review("c", 1, "s2", 12),
+        ];
+        assert_eq!(forgotten_in_last_session(&reviews), vec!["a", "c"]);
+        assert!(forgotten_in_last_session(&[]).is_empty());
+    }
+
+    #[test]
+    fn card_history_and_lapses() {
+        let mut reviews = vec![
+            review("a", 1, "s1", 0),
+            review("b", 3, "s1", 1),
+            review("a", 1, "s2", 10),
+            review("a", 1, "s3", 20),
+        ];
+        reviews[2].elapsed_days = Some(3);
+        reviews[3].elapsed_days = Some(1);
+        reviews[3].practice = true;
+        let history = history(&reviews, "a");
+        assert_eq!(history.len(), 3);
+        assert_eq!(lapses(&history), 1);
+    }
+
+    #[test]
+    fn counts_new_cards_per_day() {
+        let mut reviews = vec![review("a", 3, "s", 0), review("b", 3, "s", 0)];
+        reviews[1].practice = true;
+        reviews.push(review("a", 3, "s2", 0));
+        let today = clock::day_of(&reviews[0].at);
+        assert_eq!(new_cards_on(&reviews, today), 1);
+    }
+
+    #[test]
+    fn new_records_intervals() {
+        let card = Card {
+            deck: "d".into(),
+            front: "q".into(),
+            back: "a".into(),
+            id: "1".into(),
+            stability: Some(3.0),
+            difficulty: Some(5.0),
+            due: NaiveDate::from_ymd_opt(2025, 6, 4),
+            last_review: NaiveDate::from_ymd_opt(2025, 6, 1),
+            ..Default::default()
+        };
+        let today = NaiveDate::from_ymd_opt(2025, 6, 6).unwrap();
+        let r = Review::new(&card, Grade::Good, "s", today);
+        assert_eq!(r.grade, 3);
+        assert_eq!(r.scheduled_days, Some(3));
+        assert_eq!(r.elapsed_days, Some(5));
+    }
+}
@@ -0,0 +1,181 @@
+// Schedulers: what a grade does to a card's stability, difficulty and due
+// date. FSRS is the default; SM-2 (SuperMemo 2, as in older Anki) can be
+// chosen per deck in `.rote/decks.toml`:
+//
+//     [spanish]
+//     scheduler = "sm2"
+//
+// SM-2 keeps its ease factor and interval in columns of their own (`ease` and
+// `interval`) and ignores desired retention. It still updates FSRS's
+// stability and difficulty as FSRS would, so stats, risk ordering and a later
+// switch back to FSRS see a real memory state. A card FSRS scheduled starts
+// SM-2 from the interval it was given, at the starting ease.
+
+use chrono::NaiveDate;
+
+use crate::card::Card;
+use crate::fsrs::{self, Grade, ReviewOutcome};
+
+pub trait Scheduler {
+    /// The result of grading `card` `grade` on `today`.
+    fn review(&self, card: &Card, grade: Grade, today: NaiveDate, retention: f64) -> ReviewOutcome;
+}
+
+pub struct Fsrs;
+
+impl Scheduler for Fsrs {
+    fn review(&self, card: &Card, grade: Grade, today: NaiveDate, retention: f64) -> ReviewOutcome {
+        if let (Some(s), Some(d), Some(last)) = (card.stability, card.difficulty, card.last_review)
+        {
+            let days_elapsed = (today - last).num_days().max(0) as f64;
+            fsrs::review_existing(d, s, days_elapsed, grade, today, retention)
+        } else {
+            fsrs::review_new(grade, today, retention)
+        }
+    }
+}
+
+pub struct Sm2;
+
+const START_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+impl Scheduler for Sm2 {
+    fn review(&self, card: &Card, grade: Grade, today: NaiveDate, retention: f64) -> ReviewOutcome {
+        let ease = card.ease.map_or(START_EASE, |e| e.max(MIN_EASE));
+        let last = card.interval.or_else(|| {
+            let days = (card.due? - card.last_review?).num_days();
+            Some(days.max(1) as u32)
+        });
+        let (interval, ease) = match (grade, last) {
+            (Grade::Forgot, _) => (1, ease),
+            (_, None) => (1, next_ease(ease, grade)),
+            (_, Some(last)) if last < 6 => (6, next_ease(ease, grade)),
+            (_, Some(last)) => (
+                (f64::from(last) * ease).round() as u32,
+                next_ease(ease, grade),
+            ),
+        };
+        let memory = Fsrs.review(card, grade, today, retention);
+        ReviewOutcome {
+            due: today + chrono::Days::new(u64::from(interval)),
+            ease: Some(ease),
+            interval: Some(interval),
+            ..memory
+        }
+    }
+}
+
+/// SM-2's ease update, with Hard, Good and Easy as qualities 3, 4 and 5.
+fn next_ease(ease: f64, grade: Grade) -> f64 {
+    let q = match grade {
+        Grade::Forgot | Grade::Hard => 3.0,
+        Grade::Good => 4.0,
+        Grade::Easy => 5.0,
+    };
+    (ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(MIN_EASE)
+}
+
+/// Which scheduler a deck uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    #[default]
+    Fsrs,
+    Sm2,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 2] = [Kind::Fsrs, Kind::Sm2];
+
+    pub fn parse(s: &str) -> Option<Kind> {
+        Kind::ALL.into_iter().find(|k| k.as_str() == s)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Fsrs => "fsrs",
+            Kind::Sm2 => "sm2",
+        }
+    }
+
+    /// How the web UI names it.
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Fsrs => "FSRS",
+            Kind::Sm2 => "SM-2",
+        }
+    }
+
+    pub fn scheduler(self) -> &'static dyn Scheduler {
+        match self {
+            Kind::Fsrs => &Fsrs,
+            Kind::Sm2 => &Sm2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card() -> Card {
+        Card {
+            deck: "es".into(),
+            front: "hola".into(),
+            back: "hello".into(),
+            id: "c1".into(),
+            ..Default::default()
+        }
+    }
+
+    /// Grade `card` Good on its due date, returning the days to the next one.
+    fn good(card: &mut Card, scheduler: &dyn Scheduler) -> i64 {
+        let today = card
+            .due
+            .unwrap_or(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+        crate::review::apply_grade(card, Grade::Good, today, 0.9, scheduler);
+        (card.due.unwrap() - today).num_days()
+    }
+
+    #[test]
+    fn sm2_intervals_and_ease() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let mut card = card();
+        let mut days = Vec::new();
+        for grade in [Grade::Good, Grade::Good, Grade::Good, Grade::Forgot] {
+            crate::review::apply_grade(&mut card, grade, today, 0.9, &Sm2);
+            days.push((card.due.unwrap() - today).num_days());
+        }
+        assert_eq!(days, vec![1, 6, 15, 1]);
+        assert_eq!(card.ease, Some(2.5));
+
+        let hard = Sm2.review(&card, Grade::Hard, today, 0.9);
+        assert!((hard.ease.unwrap() - 2.36).abs() < 1e-9);
+        assert_eq!(Kind::parse("sm2"), Some(Kind::Sm2));
+    }
+
+    #[test]
+    fn switching_schedulers_keeps_intervals_sane() {
+        let mut card = card();
+        let fsrs: Vec<i64> = (0..3).map(|_| good(&mut card, &Fsrs)).collect();
+        assert!(fsrs.is_sorted());
+
+        // SM-2 carries on from FSRS's last interval at the starting ease.
+        let sm2 = [good(&mut card, &Sm2), good(&mut card, &Sm2)];
+        assert_eq!(sm2[0], (fsrs[2] as f64 * START_EASE).round() as i64);
+        assert_eq!(sm2[1], (sm2[0] as f64 * START_EASE).round() as i64);
+        assert_eq!(card.ease, Some(START_EASE));
+        assert!((1.0..=10.0).contains(&card.difficulty.unwrap()));
+
+        // Back on FSRS, the memory state SM-2 kept up gives a longer interval
+        // than the last, not one read from the ease factor.
+        let after = good(&mut card, &Fsrs);
+        assert!(
+            after > sm2[1] && after < sm2[1] * 4,
+            "{fsrs:?} {sm2:?} {after}"
+        );
+        assert_eq!(card.interval, None);
+        assert!((1.0..=10.0).contains(&card.difficulty.unwrap()));
+    }
+}
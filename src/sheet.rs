@@ -0,0 +1,222 @@
+// Import of arbitrary CSV/TSV exports (Google Sheets, Excel, Quizlet's CSV
+// download). The caller says which columns hold the front, back and so on;
+// every imported card gets a fresh id and no scheduling, and notes the file
+// and line it came from as its origin.
+
+use std::path::Path;
+
+use crate::card::{self, Card};
+use crate::error::{Error, Result};
+
+pub struct Table {
+    /// The first row, unless the file was read without a header.
+    pub header: Option<Vec<String>>,
+    pub rows: Vec<Vec<String>>,
+    /// The line of the file each row starts on.
+    pub lines: Vec<u64>,
+}
+
+impl Table {
+    pub fn width(&self) -> usize {
+        self.header
+            .iter()
+            .chain(&self.rows)
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Read `path`, guessing the delimiter from the extension or first line when
+/// `delimiter` is `None`.
+pub fn read(path: &Path, delimiter: Option<u8>, has_header: bool) -> Result<Table> {
+    let content = std::fs::read_to_string(path).map_err(Error::io("read", path))?;
+    let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+    let delimiter = delimiter.unwrap_or_else(|| sniff(path, content));
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut rows = Vec::new();
+    let mut lines = Vec::new();
+    for result in reader.records() {
+        let record = result.map_err(Error::csv(path))?;
+        lines.push(record.position().map_or(0, |p| p.line()));
+        rows.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    let header = (has_header && !rows.is_empty()).then(|| {
+        lines.remove(0);
+        rows.remove(0)
+    });
+    Ok(Table {
+        header,
+        rows,
+        lines,
+    })
+}
+
+fn sniff(path: &Path, content: &str) -> u8 {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if ext.eq_ignore_ascii_case("tsv") || ext.eq_ignore_ascii_case("tab") {
+        return b'\t';
+    }
+    let first = content.lines().next().unwrap_or("");
+    [b',', b'\t', b';']
+        .into_iter()
+        .max_by_key(|&d| (first.matches(d as char).count(), d == b','))
+        .unwrap()
+}
+
+/// Which columns (0-based) of a table become which card fields.
+#[derive(Debug, PartialEq)]
+pub struct Mapping {
+    pub front: usize,
+    pub back: Option<usize>,
+    pub deck: Option<usize>,
+    pub tags: Option<usize>,
+    pub media: Option<usize>,
+    pub extra: Option<usize>,
+}
+
+impl Mapping {
+    /// Parse `front=2,back=3,deck=1`. Columns are numbered from 1 or named by
+    /// their header; `front` is required.
+    pub fn parse(spec: &str, header: Option<&[String]>) -> Result<Mapping> {
+        let mut front = None;
+        let mut mapping = Mapping {
+            front: 0,
+            back: None,
+            deck: None,
+            tags: None,
+            media: None,
+            extra: None,
+        };
+        for part in spec.split(',').filter(|p| !p.trim().is_empty()) {
+            let Some((field, value)) = part.split_once('=') else {
+                return Err(Error::MappingSyntax {
+                    part: part.to_string(),
+                });
+            };
+            let column = column(value.trim(), header)?;
+            let slot = match field.trim() {
+                "front" => &mut front,
+                "back" => &mut mapping.back,
+                "deck" => &mut mapping.deck,
+                "tags" => &mut mapping.tags,
+                "media" => &mut mapping.media,
+                "extra" => &mut mapping.extra,
+                other => {
+                    return Err(Error::UnknownField {
+                        field: other.to_string(),
+                    });
+                }
+            };
+            *slot = Some(column);
+        }
+        mapping.front = front.ok_or(Error::NoFrontColumn)?;
+        Ok(mapping)
+    }
+}
+
+fn column(value: &str, header: Option<&[String]>) -> Result<usize> {
+    if let Ok(n) = value.parse::<usize>() {
+        return n.checked_sub(1).ok_or(Error::ColumnZero);
+    }
+    header
+        .and_then(|h| {
+            h.iter()
+                .position(|name| name.trim().eq_ignore_ascii_case(value))
+        })
+        .ok_or_else(|| Error::NoColumn {
+            name: value.to_string(),
+        })
+}
+
+/// One card per row with a non-empty front. Rows without a deck column (or
+/// with it blank) go to `deck`. With `source`, each card's origin is
+/// `source:line`.
+pub fn to_cards(table: &Table, mapping: &Mapping, deck: &str, source: Option<&str>) -> Vec<Card> {
+    let cell = |row: &[String], column: Option<usize>| {
+        column
+            .and_then(|c| row.get(c))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+    table
+        .rows
+        .iter()
+        .zip(&table.lines)
+        .filter(|(row, _)| !cell(row, Some(mapping.front)).is_empty())
+        .map(|(row, line)| {
+            let row_deck = cell(row, mapping.deck);
+            Card {
+                deck: if row_deck.is_empty() {
+                    deck.to_string()
+                } else {
+                    row_deck
+                },
+                front: cell(row, Some(mapping.front)),
+                back: cell(row, mapping.back),
+                extra: cell(row, mapping.extra),
+                media: cell(row, mapping.media),
+                origin: source.map_or(String::new(), |s| format!("{s}:{line}")),
+                id: uuid::Uuid::new_v4().to_string(),
+                stability: None,
+                difficulty: None,
+                due: None,
+                last_review: None,
+                created: Some(crate::clock::today()),
+                tags: card::parse_tags(&cell(row, mapping.tags).replace(',', " ")),
+                suspended: false,
+                flagged: false,
+                ease: None,
+                interval: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_columns_become_cards() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.txt");
+        std::fs::write(
+            &path,
+            "\u{feff}Set\tTerm\tDefinition\tLabels\tNotes\nes\thola\thello\tgreeting, basic\tinformal\n\t\tskipped\t\t\n\tadiós\tgoodbye\t\t\n",
+        )
+        .unwrap();
+        let table = read(&path, None, true).unwrap();
+        assert_eq!(table.header.as_deref().unwrap()[0], "Set");
+        assert_eq!(table.width(), 5);
+
+        let mapping = Mapping::parse(
+            "front=term,back=3,deck=1,tags=Labels,extra=notes",
+            table.header.as_deref(),
+        )
+        .unwrap();
+        assert_eq!(mapping.front, 1);
+        assert_eq!(table.lines, vec![2, 3, 4]);
+        let cards = to_cards(&table, &mapping, "vocab", Some("export.txt"));
+        assert_eq!(cards.len(), 2);
+        assert_eq!(
+            (cards[0].deck.as_str(), cards[0].front.as_str()),
+            ("es", "hola")
+        );
+        assert_eq!(cards[0].tags, vec!["greeting", "basic"]);
+        assert_eq!(cards[0].extra, "informal");
+        assert_eq!(cards[1].deck, "vocab");
+        assert_eq!(cards[1].back, "goodbye");
+        assert_eq!(cards[1].origin, "export.txt:4");
+        assert!(cards[1].due.is_none() && cards[0].id != cards[1].id);
+
+        assert!(Mapping::parse("back=2", None).is_err());
+        assert!(Mapping::parse("front=0", None).is_err());
+        assert!(Mapping::parse("front=Term", None).is_err());
+        assert!(Mapping::parse("answer=2,front=1", None).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+// `rote simulate`: Monte-Carlo runs of the FSRS model over the days ahead, to
+// see what a retention target and a daily new-card limit cost in reviews and
+// buy in recall. Every due card is reviewed on its day; whether it is
+// recalled is drawn from its retrievability, and grades from the rating
+// frequencies the FSRS simulator uses.
+
+use chrono::NaiveDate;
+
+use crate::card::Card;
+use crate::fsrs::{self, Grade};
+use crate::review::Rng;
+
+/// Chance of each first grade for a new card: forgot, hard, good, easy.
+const FIRST_GRADES: [f64; 4] = [0.24, 0.094, 0.579, 0.087];
+/// Chance of hard, good and easy given the card was recalled.
+const RECALL_GRADES: [f64; 3] = [0.224, 0.632, 0.144];
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub days: usize,
+    pub new_per_day: usize,
+    pub retention: f64,
+    /// New cards left to learn; unlimited when `None`.
+    pub new_cards: Option<usize>,
+    pub runs: usize,
+    pub seed: u64,
+}
+
+/// One simulated day, averaged over the runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Day {
+    /// Reviews of cards already learned.
+    pub reviews: f64,
+    /// Cards seen for the first time.
+    pub new: f64,
+    /// Reviews that were forgotten.
+    pub lapses: f64,
+}
+
+impl Day {
+    /// Share of reviews recalled, if there were any.
+    pub fn retention(&self) -> Option<f64> {
+        (self.reviews > 0.0).then(|| 1.0 - self.lapses / self.reviews)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub days: Vec<Day>,
+    /// Expected number of cards recalled if all were tested on the last day.
+    pub memorized: f64,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    stability: f64,
+    difficulty: f64,
+    last_review: NaiveDate,
+    due: NaiveDate,
+}
+
+/// Simulate `options.days` days from `today`, starting from the scheduling of
+/// `cards`. Suspended and new cards in `cards` are ignored; new cards come
+/// from `options.new_cards`.
+pub fn run(cards: &[Card], today: NaiveDate, options: &Options) -> Simulation {
+    let start: Vec<State> = cards
+        .iter()
+        .filter(|c| !c.suspended)
+        .filter_map(|c| {
+            Some(State {
+                stability: c.stability?,
+                difficulty: c.difficulty?,
+                last_review: c.last_review?,
+                due: c.due?,
+            })
+        })
+        .collect();
+    let runs = options.runs.max(1);
+    let mut days = vec![Day::default(); options.days];
+    let mut memorized = 0.0;
+    let mut rng = Rng::new(options.seed);
+    for _ in 0..runs {
+        let mut states = start.clone();
+        let mut new_left = options.new_cards;
+        for (offset, day) in days.iter_mut().enumerate() {
+            let date = today + chrono::Days::new(offset as u64);
+            for state in states.iter_mut().filter(|s| s.due <= date) {
+                let elapsed = (date - state.last_review).num_days().max(0) as f64;
+                let recalled = rng.next() < fsrs::retrievability(elapsed, state.stability);
+                let grade = if recalled {
+                    [Grade::Hard, Grade::Good, Grade::Easy][rng.pick(&RECALL_GRADES)]
+                } else {
+                    day.lapses += 1.0;
+                    Grade::Forgot
+                };
+                let outcome = fsrs::review_existing(
+                    state.difficulty,
+                    state.stability,
+                    elapsed,
+                    grade,
+                    date,
+                    options.retention,
+                );
+                *state = State {
+                    stability: outcome.stability,
+                    difficulty: outcome.difficulty,
+                    last_review: date,
+                    due: outcome.due,
+                };
+                day.reviews += 1.0;
+            }
+            let new = new_left.map_or(options.new_per_day, |n| n.min(options.new_per_day));
+            for _ in 0..new {
+                let grade = Grade::from_u8(rng.pick(&FIRST_GRADES) as u8 + 1).unwrap();
+                let outcome = fsrs::review_new(grade, date, options.retention);
+                states.push(State {
+                    stability: outcome.stability,
+                    difficulty: outcome.difficulty,
+                    last_review: date,
+                    due: outcome.due,
+                });
+            }
+            day.new += new as f64;
+            new_left = new_left.map(|n| n - new);
+        }
+        let end = today + chrono::Days::new(options.days as u64);
+        memorized += states
+            .iter()
+            .map(|s| {
+                let elapsed = (end - s.last_review).num_days().max(0) as f64;
+                fsrs::retrievability(elapsed, s.stability)
+            })
+            .sum::<f64>();
+    }
+    let runs = runs as f64;
+    for day in &mut days {
+        day.reviews /= runs;
+        day.new /= runs;
+        day.lapses /= runs;
+    }
+    Simulation {
+        days,
+        memorized: memorized / runs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(retention: f64) -> Options {
+        Options {
+            days: 180,
+            new_per_day: 10,
+            retention,
+            new_cards: Some(500),
+            runs: 4,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn higher_retention_costs_more_reviews() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let total = |sim: &Simulation| sim.days.iter().map(|d| d.reviews).sum::<f64>();
+
+        let normal = run(&[], today, &options(0.9));
+        assert_eq!(normal, run(&[], today, &options(0.9)));
+        let new: f64 = normal.days.iter().map(|d| d.new).sum();
+        assert_eq!(new, 500.0);
+        assert_eq!(normal.days[0].reviews, 0.0);
+
+        let retention = normal.days.iter().map(|d| d.lapses).sum::<f64>() / total(&normal);
+        assert!((retention - 0.1).abs() < 0.05, "lapse rate {retention}");
+
+        let strict = run(&[], today, &options(0.97));
+        assert!(total(&strict) > total(&normal));
+        assert!(strict.memorized > normal.memorized);
+        assert!(normal.memorized < 500.0);
+    }
+}
@@ -0,0 +1,236 @@
+// Scheduling state on its own, keyed by card id, so progress can be saved
+// before re-importing card content or trying another scheduler and restored
+// afterwards (`rote state export` / `rote state import`), and merged between
+// copies of a collection (`rote sync`, `rote merge`).
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
+
+use crate::card::Card;
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CardState {
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+    pub due: Option<NaiveDate>,
+    pub last_review: Option<NaiveDate>,
+    /// SM-2's, for cards it has scheduled (see `crate::scheduler`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ease: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u32>,
+}
+
+impl CardState {
+    pub fn of(card: &Card) -> CardState {
+        CardState {
+            stability: card.stability,
+            difficulty: card.difficulty,
+            due: card.due,
+            last_review: card.last_review,
+            ease: card.ease,
+            interval: card.interval,
+        }
+    }
+
+    pub fn set(&self, card: &mut Card) {
+        card.stability = self.stability;
+        card.difficulty = self.difficulty;
+        card.due = self.due;
+        card.last_review = self.last_review;
+        card.ease = self.ease;
+        card.interval = self.interval;
+    }
+}
+
+/// State of every card that has been reviewed; new cards are left out.
+pub fn export(cards: &[Card]) -> BTreeMap<String, CardState> {
+    cards
+        .iter()
+        .filter(|c| c.due.is_some() || c.last_review.is_some())
+        .map(|c| (c.id.clone(), CardState::of(c)))
+        .collect()
+}
+
+/// Overwrite the scheduling fields of cards whose id appears in `states`.
+/// Returns the indices of cards that changed.
+pub fn import(cards: &mut [Card], states: &BTreeMap<String, CardState>) -> Vec<usize> {
+    let mut changed = Vec::new();
+    for (i, card) in cards.iter_mut().enumerate() {
+        let Some(state) = states.get(&card.id) else {
+            continue;
+        };
+        if CardState::of(card) != *state {
+            state.set(card);
+            changed.push(i);
+        }
+    }
+    changed
+}
+
+/// Whether `incoming` should replace `current` when syncing: it was reviewed
+/// more recently, or on the same day and `take_ties` is set. Exactly one side
+/// of a sync takes ties, so two copies that disagree settle on one state.
+pub fn wins(incoming: &CardState, current: &CardState, take_ties: bool) -> bool {
+    match incoming.last_review.cmp(&current.last_review) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Equal => take_ties && incoming != current,
+        std::cmp::Ordering::Less => false,
+    }
+}
+
+/// `import`, but only of states that `wins` over the card's own.
+pub fn merge(
+    cards: &mut [Card],
+    states: &BTreeMap<String, CardState>,
+    take_ties: bool,
+) -> Vec<usize> {
+    let mut changed = Vec::new();
+    for (i, card) in cards.iter_mut().enumerate() {
+        if let Some(state) = states.get(&card.id)
+            && wins(state, &CardState::of(card), take_ties)
+        {
+            state.set(card);
+            changed.push(i);
+        }
+    }
+    changed
+}
+
+pub struct Merged {
+    pub cards: Vec<Card>,
+    /// Cards whose scheduling came from the second copy.
+    pub updated: usize,
+    /// Cards only in the second copy.
+    pub added: usize,
+    /// Ids of cards whose text, tags or flags differ between the copies; the
+    /// first copy's are kept.
+    pub conflicts: Vec<String>,
+}
+
+/// Merge two copies of a deck file by card id: each card keeps the scheduling
+/// of whichever copy reviewed it last, and cards only in `theirs` are appended
+/// in their order.
+pub fn merge_copies(ours: Vec<Card>, theirs: Vec<Card>) -> Merged {
+    let mut merged = Merged {
+        cards: ours,
+        updated: 0,
+        added: 0,
+        conflicts: Vec::new(),
+    };
+    let index: HashMap<String, usize> = merged
+        .cards
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.id.clone(), i))
+        .collect();
+    for card in theirs {
+        let Some(&i) = index.get(&card.id) else {
+            merged.cards.push(card);
+            merged.added += 1;
+            continue;
+        };
+        let ours = &mut merged.cards[i];
+        let theirs = CardState::of(&card);
+        if wins(&theirs, &CardState::of(ours), false) {
+            theirs.set(ours);
+            merged.updated += 1;
+        }
+        if (&ours.deck, &ours.front, &ours.back, &ours.media, &ours.tags)
+            != (&card.deck, &card.front, &card.back, &card.media, &card.tags)
+            || (ours.suspended, ours.flagged) != (card.suspended, card.flagged)
+        {
+            merged.conflicts.push(card.id);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(id: &str, due: Option<NaiveDate>) -> Card {
+        Card {
+            deck: "d".into(),
+            front: "f".into(),
+            id: id.into(),
+            stability: due.map(|_| 4.0),
+            difficulty: due.map(|_| 5.0),
+            due,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn export_then_import() {
+        let due = NaiveDate::from_ymd_opt(2025, 6, 1);
+        let reviewed = vec![card("a", due), card("b", None)];
+        let states = export(&reviewed);
+        assert_eq!(states.keys().collect::<Vec<_>>(), vec!["a"]);
+
+        let json = serde_json::to_string(&states).unwrap();
+        let states: BTreeMap<String, CardState> = serde_json::from_str(&json).unwrap();
+
+        // Fresh content from upstream: same ids, no progress.
+        let mut fresh = vec![card("b", None), card("a", None), card("c", None)];
+        assert_eq!(import(&mut fresh, &states), vec![1]);
+        assert_eq!(fresh[1].due, due);
+        assert_eq!(fresh[1].stability, Some(4.0));
+        assert!(import(&mut fresh, &states).is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_latest_review() {
+        let day = |d| NaiveDate::from_ymd_opt(2025, 6, d);
+        let reviewed = |id, last, stability| Card {
+            last_review: day(last),
+            stability: Some(stability),
+            ..card(id, day(last + 3))
+        };
+        let mut laptop = vec![
+            reviewed("a", 1, 1.0),
+            reviewed("b", 5, 1.0),
+            reviewed("c", 2, 1.0),
+        ];
+        let desktop = vec![
+            reviewed("a", 4, 2.0),
+            reviewed("b", 3, 2.0),
+            reviewed("c", 2, 2.0),
+        ];
+        let states = export(&desktop);
+
+        assert_eq!(merge(&mut laptop.clone(), &states, false), vec![0]);
+        assert_eq!(merge(&mut laptop, &states, true), vec![0, 2]);
+        assert_eq!(laptop[0].last_review, day(4));
+        assert_eq!(laptop[1].stability, Some(1.0));
+        assert_eq!(laptop[2].stability, Some(2.0));
+    }
+
+    #[test]
+    fn merged_copies_union_cards() {
+        let day = |d| NaiveDate::from_ymd_opt(2025, 6, d);
+        let reviewed = |id, last| Card {
+            last_review: day(last),
+            ..card(id, day(last + 3))
+        };
+        let ours = vec![reviewed("a", 1), reviewed("b", 5), card("c", None)];
+        let theirs = vec![
+            card("d", None),
+            reviewed("b", 2),
+            Card {
+                front: "edited".into(),
+                ..reviewed("a", 4)
+            },
+        ];
+        let merged = merge_copies(ours, theirs);
+        let ids: Vec<&str> = merged.cards.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c", "d"]);
+        assert_eq!((merged.updated, merged.added), (1, 1));
+        assert_eq!(merged.cards[0].last_review, day(4));
+        assert_eq!(merged.cards[0].front, "f");
+        assert_eq!(merged.cards[1].last_review, day(5));
+        assert_eq!(merged.conflicts, vec!["a"]);
+    }
+}
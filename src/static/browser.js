@@ -0,0 +1,49 @@
+// Keyboard-driven card browser on the deck page: move between tiles, open
+// one for editing, or suspend, flag and delete it in place.
+(function () {
+  var browser = document.getElementById("card-browser");
+  if (!browser) return;
+  var tiles = Array.prototype.slice.call(browser.querySelectorAll("[data-card]"));
+  var current = -1;
+
+  function select(i) {
+    if (!tiles.length) return;
+    i = Math.max(0, Math.min(tiles.length - 1, i));
+    if (current >= 0) tiles[current].classList.remove("browser-selected");
+    current = i;
+    var tile = tiles[current];
+    tile.classList.add("browser-selected");
+    tile.scrollIntoView({ block: "nearest" });
+    history.replaceState(null, "", "#" + tile.id);
+  }
+
+  function post(url) {
+    var form = document.createElement("form");
+    form.method = "post";
+    form.action = url;
+    document.body.appendChild(form);
+    form.submit();
+  }
+
+  if (location.hash) {
+    var target = document.getElementById(decodeURIComponent(location.hash.slice(1)));
+    var at = tiles.indexOf(target);
+    if (at >= 0) select(at);
+  }
+
+  document.addEventListener("keydown", function (e) {
+    if (roteTyping(e)) return;
+    var tile = current >= 0 ? tiles[current] : null;
+    if (roteKey(e, "next")) { e.preventDefault(); select(current + 1); }
+    else if (roteKey(e, "prev")) { e.preventDefault(); select(current - 1); }
+    else if (roteKey(e, "review") && browser.dataset.review) { window.location = browser.dataset.review; }
+    else if (roteKey(e, "new_card") && browser.dataset.new) { window.location = browser.dataset.new; }
+    else if (!tile) { return; }
+    else if (roteKey(e, "open")) { e.preventDefault(); window.location = tile.href; }
+    else if (roteKey(e, "suspend") && tile.dataset.suspend) { post(tile.dataset.suspend); }
+    else if (roteKey(e, "flag") && tile.dataset.flag) { post(tile.dataset.flag); }
+    else if (roteKey(e, "delete") && tile.dataset.delete) {
+      if (confirm("Delete this card?")) post(tile.dataset.delete);
+    }
+  });
+})();
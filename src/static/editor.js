@@ -0,0 +1,95 @@
+// Card editor: "Suggest clozes" asks the server to bracket likely cloze
+// deletions in the front field. The text is replaced as one edit, so Ctrl+Z
+// rejects the suggestions.
+(function () {
+  var button = document.querySelector("[data-clozify]");
+  var front = document.getElementById("front");
+  if (!button || !front) return;
+
+  button.addEventListener("click", function () {
+    fetch(button.dataset.clozify, {
+      method: "POST",
+      headers: { "Content-Type": "application/x-www-form-urlencoded" },
+      body: "text=" + encodeURIComponent(front.value),
+    })
+      .then(function (r) {
+        return r.text();
+      })
+      .then(function (text) {
+        front.focus();
+        front.select();
+        if (!document.execCommand("insertText", false, text)) front.value = text;
+      });
+  });
+})();
+
+// Live preview: the server renders the card as review would (clozes blanked,
+// newlines expanded, Markdown), a moment after each pause in typing, with any
+// cloze or line-break mistakes listed underneath.
+(function () {
+  var pane = document.querySelector("[data-preview]");
+  var front = document.getElementById("front");
+  var back = document.getElementById("back");
+  var extra = document.getElementById("extra");
+  if (!pane || !front || !back) return;
+  var shown = {
+    front: pane.querySelector("[data-preview-front]"),
+    media: pane.querySelector("[data-preview-media]"),
+    back: pane.querySelector("[data-preview-back]"),
+    warnings: pane.querySelector("[data-preview-warnings]"),
+  };
+  var flip = pane.querySelector("[data-preview-flip]");
+  var timer;
+
+  function update() {
+    fetch(pane.dataset.preview, {
+      method: "POST",
+      headers: { "Content-Type": "application/x-www-form-urlencoded" },
+      body:
+        "front=" + encodeURIComponent(front.value) +
+        "&back=" + encodeURIComponent(back.value) +
+        "&extra=" + encodeURIComponent(extra ? extra.value : "") +
+        "&id=" + encodeURIComponent(pane.dataset.card),
+    })
+      .then(function (r) {
+        return r.json();
+      })
+      .then(function (card) {
+        shown.front.innerHTML = card.front;
+        shown.media.innerHTML = card.media;
+        shown.back.innerHTML = card.back;
+        shown.warnings.replaceChildren.apply(
+          shown.warnings,
+          card.warnings.map(function (w) {
+            var li = document.createElement("li");
+            li.textContent = w;
+            return li;
+          }),
+        );
+        if (window.renderMathInElement) {
+          renderMathInElement(pane, {
+            delimiters: [
+              { left: "$$", right: "$$", display: true },
+              { left: "$", right: "$", display: false },
+            ],
+            throwOnError: false,
+          });
+        }
+      });
+  }
+
+  function later() {
+    clearTimeout(timer);
+    timer = setTimeout(update, 250);
+  }
+
+  front.addEventListener("input", later);
+  back.addEventListener("input", later);
+  if (extra) extra.addEventListener("input", later);
+  flip.addEventListener("click", function () {
+    var hidden = shown.back.style.display === "none";
+    shown.back.style.display = hidden ? "" : "none";
+    flip.textContent = hidden ? "Hide answer" : "Show answer";
+  });
+  update();
+})();
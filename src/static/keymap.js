@@ -0,0 +1,12 @@
+// Shared keyboard handling. window.ROTE_KEYS maps action names to keys.
+window.roteKey = function (e, action) {
+  var key = (window.ROTE_KEYS || {})[action];
+  if (!key || e.ctrlKey || e.metaKey || e.altKey) return false;
+  if (key === "Space") key = " ";
+  return e.key === key;
+};
+
+window.roteTyping = function (e) {
+  var t = e.target.tagName;
+  return t === "INPUT" || t === "TEXTAREA" || t === "SELECT";
+};
@@ -0,0 +1,31 @@
+// Renders $...$ and $$...$$ with the bundled KaTeX, standing in for KaTeX's
+// auto-render extension when pages are served without CDNs. KaTeX's fonts and
+// stylesheet are not bundled, so formulas are output as MathML, which browsers
+// lay out natively.
+function renderMathInElement(root) {
+  if (!window.katex) return;
+  var skip = /^(SCRIPT|NOSCRIPT|STYLE|TEXTAREA|PRE|CODE|OPTION)$/;
+  var walker = document.createTreeWalker(root, NodeFilter.SHOW_TEXT);
+  var nodes = [];
+  while (walker.nextNode()) {
+    var node = walker.currentNode;
+    if (node.nodeValue.indexOf("$") >= 0 && !skip.test(node.parentNode.nodeName)) nodes.push(node);
+  }
+  nodes.forEach(function (node) {
+    var text = node.nodeValue;
+    var re = /\$\$([\s\S]+?)\$\$|\$([^$\n]+?)\$/g;
+    var frag = document.createDocumentFragment();
+    var last = 0;
+    var m;
+    while ((m = re.exec(text))) {
+      frag.appendChild(document.createTextNode(text.slice(last, m.index)));
+      var span = document.createElement("span");
+      katex.render(m[1] || m[2], span, { displayMode: !!m[1], output: "mathml", throwOnError: false });
+      frag.appendChild(span);
+      last = re.lastIndex;
+    }
+    if (!last) return;
+    frag.appendChild(document.createTextNode(text.slice(last)));
+    node.parentNode.replaceChild(frag, node);
+  });
+}
@@ -0,0 +1,69 @@
+// Mask editor: dragging over the image adds a mask, clicking one removes it.
+// Masks are kept in percent of the image, so they fit it at any size, and
+// written to the form as `x,y,w,h` rectangles when it is saved.
+(function () {
+  var editor = document.querySelector("[data-mask-editor]");
+  var form = document.querySelector("[data-mask-form]");
+  if (!editor || !form) return;
+  var start = null;
+  var drawing = null;
+  var drew = false;
+
+  function point(e) {
+    var box = editor.getBoundingClientRect();
+    var clamp = function (v) { return Math.min(100, Math.max(0, v)); };
+    return {
+      x: clamp(((e.clientX - box.left) / box.width) * 100),
+      y: clamp(((e.clientY - box.top) / box.height) * 100),
+    };
+  }
+
+  function place(mask, a, b) {
+    mask.style.left = Math.min(a.x, b.x) + "%";
+    mask.style.top = Math.min(a.y, b.y) + "%";
+    mask.style.width = Math.abs(a.x - b.x) + "%";
+    mask.style.height = Math.abs(a.y - b.y) + "%";
+  }
+
+  editor.addEventListener("pointerdown", function (e) {
+    if (e.target.hasAttribute("data-mask")) return;
+    e.preventDefault();
+    start = point(e);
+    drawing = document.createElement("div");
+    drawing.setAttribute("data-mask", "");
+    place(drawing, start, start);
+    editor.appendChild(drawing);
+    editor.setPointerCapture(e.pointerId);
+  });
+
+  editor.addEventListener("pointermove", function (e) {
+    if (drawing) place(drawing, start, point(e));
+  });
+
+  editor.addEventListener("pointerup", function (e) {
+    if (!drawing) return;
+    var end = point(e);
+    // A click rather than a drag adds nothing.
+    if (Math.abs(end.x - start.x) < 1 || Math.abs(end.y - start.y) < 1) editor.removeChild(drawing);
+    drawing = null;
+    drew = true;
+  });
+
+  editor.addEventListener("click", function (e) {
+    // The click ending a drag is not a click on the new mask.
+    if (drew) { drew = false; return; }
+    if (e.target.hasAttribute("data-mask")) editor.removeChild(e.target);
+  });
+
+  form.addEventListener("submit", function () {
+    var rects = [];
+    editor.querySelectorAll("[data-mask]").forEach(function (mask) {
+      rects.push(
+        ["left", "top", "width", "height"]
+          .map(function (side) { return parseFloat(mask.style[side]).toFixed(1); })
+          .join(",")
+      );
+    });
+    form.querySelector('input[name="masks"]').value = rects.join(" ");
+  });
+})();
@@ -0,0 +1,61 @@
+// Offline support: registers the service worker and keeps grades made without
+// a connection in localStorage, sending them in order once it is back.
+var roteOffline = (function () {
+  var KEY = "rote-pending";
+  if (window.ROTE_SW && navigator.serviceWorker) {
+    navigator.serviceWorker.register(window.ROTE_SW).catch(function () {});
+  }
+
+  function pending() {
+    try {
+      return JSON.parse(localStorage.getItem(KEY)) || [];
+    } catch (e) {
+      return [];
+    }
+  }
+
+  function store(items) {
+    localStorage.setItem(KEY, JSON.stringify(items));
+  }
+
+  function push(url, body) {
+    var items = pending();
+    items.push({ url: url, body: body });
+    store(items);
+  }
+
+  var flushing = null;
+
+  // Resolves once every queued grade is sent; rejects while still offline.
+  function flush() {
+    if (flushing) return flushing;
+    function next() {
+      var items = pending();
+      if (!items.length) return Promise.resolve();
+      return fetch(items[0].url, {
+        method: "POST",
+        headers: { "Content-Type": "application/x-www-form-urlencoded" },
+        body: items[0].body,
+      }).then(function (r) {
+        // 404: the session is gone, so the grade can never be applied.
+        if (!r.ok && r.status !== 404) throw new Error(r.status);
+        store(pending().slice(1));
+        return next();
+      });
+    }
+    flushing = next().then(
+      function () { flushing = null; },
+      function (e) { flushing = null; throw e; }
+    );
+    return flushing;
+  }
+
+  window.addEventListener("online", function () { flush().catch(function () {}); });
+  if (window.fetch) flush().catch(function () {});
+
+  return {
+    push: push,
+    flush: flush,
+    count: function () { return pending().length; },
+  };
+})();
@@ -3,31 +3,369 @@
   var btn = document.getElementById("reveal-btn");
   var hint = document.getElementById("reveal-hint");
   var form = document.getElementById("grade-form");
+  var skipForm = document.getElementById("skip-form");
   if (!back || !btn) return;
 
   var revealed = false;
+  // Quiz sessions: the answers to pick from, and the one picked.
+  var choices = document.getElementById("card-choices");
+  var continueBtn = document.getElementById("choice-next");
+  var picked = null;
+  function quiz() {
+    return !!(choices && choices.querySelector("[data-choice]"));
+  }
+  var busy = false;
+  // When the current card was shown, to time the answer.
+  var shownAt = Date.now();
+  // Speed training: reveal the answer on its own after this many seconds.
+  var autoReveal = form ? parseInt(form.dataset.autoReveal, 10) : 0;
+  var revealTimer = null;
+
+  function armReveal() {
+    clearTimeout(revealTimer);
+    if (autoReveal > 0) revealTimer = setTimeout(reveal, autoReveal * 1000);
+  }
+  var position = parseInt(document.getElementById("review-position").textContent, 10);
+  // The rest of the session, fetched up front so review can go on offline.
+  var queue = null;
+  if (form && form.dataset.queueUrl && window.fetch) {
+    fetch(form.dataset.queueUrl)
+      .then(function (r) { return r.ok ? r.json() : null; })
+      .then(function (q) { queue = q; })
+      .catch(function () {});
+  }
+
+  // Time in the session, and a suggested break every so many minutes or
+  // cards (`[breaks]` in config.toml), counted from the last one.
+  var sessionTimer = document.getElementById("session-timer");
+  var started = sessionTimer ? +sessionTimer.dataset.started : Date.now();
+  function elapsed(ms) {
+    var s = Math.floor(ms / 1000), h = Math.floor(s / 3600), m = Math.floor(s / 60) % 60;
+    var pad = function (n) { return (n < 10 ? "0" : "") + n; };
+    return (h ? h + ":" + pad(m) : m) + ":" + pad(s % 60);
+  }
+  if (sessionTimer) {
+    setInterval(function () { sessionTimer.textContent = elapsed(Date.now() - started); }, 1000);
+  }
+  var breakMinutes = form ? +form.dataset.breakMinutes || 0 : 0;
+  var breakCards = form ? +form.dataset.breakCards || 0 : 0;
+  var breakPrompt = document.getElementById("break-prompt");
+  var breakKey = "rote-break-" + (form ? form.dataset.session : "");
+  function lastBreak() {
+    try {
+      var last = JSON.parse(sessionStorage.getItem(breakKey));
+      if (last) return last;
+    } catch (e) {}
+    return { at: started, position: 1 };
+  }
+  function resting() {
+    return !!breakPrompt && breakPrompt.style.display !== "none";
+  }
+  function checkBreak() {
+    if (!breakPrompt || !(breakMinutes || breakCards)) return;
+    var last = lastBreak();
+    var ms = Date.now() - last.at, cards = position - last.position;
+    if ((breakMinutes && ms >= breakMinutes * 60000) || (breakCards && cards >= breakCards)) {
+      document.getElementById("break-detail").textContent =
+        cards + " card(s) in " + elapsed(ms) + ". Recall suffers in long sittings.";
+      breakPrompt.style.display = "flex";
+      clearTimeout(revealTimer);
+    }
+  }
+  function endBreak() {
+    breakPrompt.style.display = "none";
+    try {
+      sessionStorage.setItem(breakKey, JSON.stringify({ at: Date.now(), position: position }));
+    } catch (e) {}
+    shownAt = Date.now();
+    armReveal();
+  }
+  if (breakPrompt) document.getElementById("break-continue").addEventListener("click", endBreak);
+
+  // Read the card aloud, one side after the other, for decks with tts.
+  var speaking = null;
+  function speak() {
+    var urls = JSON.parse(back.dataset.speech || "[]");
+    var next = function () {
+      if (!urls.length) return;
+      speaking = new Audio(urls.shift());
+      speaking.onended = next;
+      speaking.play().catch(function () {});
+    };
+    next();
+  }
+
+  // Decks revealing one cloze at a time: the first blank still asked.
+  var front = document.getElementById("card-front");
+  function nextBlank() {
+    return front.querySelector("[data-blank]:not([data-open])");
+  }
+  function openBlank(el) {
+    el.dataset.open = "1";
+    el.firstChild.hidden = false;
+    el.lastChild.hidden = true;
+  }
+  // Open the next blank, or reveal the answer once none is left.
+  function step() {
+    var blank = nextBlank();
+    if (!blank || revealed) { reveal(); return; }
+    openBlank(blank);
+    if (!nextBlank()) reveal();
+  }
 
   function reveal() {
     if (revealed) return;
     revealed = true;
+    front.querySelectorAll("[data-blank]:not([data-open])").forEach(openBlank);
+    clearTimeout(revealTimer);
     back.style.display = "";
+    document.getElementById("card-media").classList.add("revealed");
+    speak();
     btn.style.display = "none";
     if (hint) hint.style.display = "none";
-    if (form) form.style.display = "";
+    if (quiz()) {
+      choices.querySelectorAll("[data-choice]").forEach(function (el) {
+        if (el.dataset.choice === "3") el.dataset.result = "right";
+        else if (el === picked) el.dataset.result = "wrong";
+      });
+      continueBtn.style.display = "";
+    } else if (form) {
+      form.style.display = "";
+    }
+  }
+
+  function hide() {
+    revealed = false;
+    if (speaking) { speaking.onended = null; speaking.pause(); }
+    back.style.display = "none";
+    document.getElementById("card-media").classList.remove("revealed");
+    btn.style.display = quiz() ? "none" : "";
+    if (hint) hint.style.display = quiz() ? "none" : "";
+    if (form) form.style.display = "none";
+    if (continueBtn) continueBtn.style.display = "none";
+    picked = null;
+    shownAt = Date.now();
+    armReveal();
+  }
+
+  // Swap in the next card returned by the grade endpoint.
+  function show(next) {
+    document.getElementById("card-front").innerHTML = next.card.front;
+    document.getElementById("card-media").innerHTML = next.card.media;
+    if (choices) choices.innerHTML = next.card.choices || "";
+    back.innerHTML = next.card.back;
+    back.dataset.speech = JSON.stringify(next.card.speech || []);
+    document.getElementById("card-info").href = next.card.info;
+    document.getElementById("card-edit").href = next.card.edit;
+    var intervals = next.card.intervals || [];
+    document.querySelectorAll("[data-interval]").forEach(function (el) {
+      var i = +el.dataset.interval;
+      el.textContent = intervals[i] ? "· " + intervals[i] : "";
+    });
+    document.getElementById("review-position").textContent = next.position;
+    hide();
+    checkBreak();
+    if (window.renderMathInElement) {
+      renderMathInElement(document.getElementById("card-front").parentNode, {
+        delimiters: [
+          { left: "$$", right: "$$", display: true },
+          { left: "$", right: "$", display: false },
+        ],
+        throwOnError: false,
+      });
+    }
+  }
+
+  // Without a connection: queue the grade and show the next prefetched card.
+  // A skip moves the current card to the back of the queue instead.
+  function gradeOffline(url, body) {
+    roteOffline.push(url, body.toString());
+    roteOffline.flush().catch(function () {});
+    if (body.has("skip")) {
+      var i = position - queue.position;
+      queue.cards.push(queue.cards.splice(i, 1)[0]);
+    } else {
+      position += 1;
+    }
+    var card = queue.cards[position - queue.position];
+    busy = false;
+    if (card) { show({ position: position, card: card }); return; }
+    finishOffline();
+  }
+
+  function finishOffline() {
+    var waiting = roteOffline.count();
+    if (!waiting) { location.href = queue.summary; return; }
+    document.getElementById("card-front").textContent =
+      "Session finished offline. " + waiting + " grade(s) will be sent when you are back online.";
+    document.getElementById("card-media").innerHTML = "";
+    back.style.display = "none";
+    btn.style.display = "none";
+    if (hint) hint.style.display = "none";
+    form.style.display = "none";
+    busy = true;
+    roteOffline.flush().then(function () { location.href = queue.summary; }, function () {
+      window.addEventListener("online", function () {
+        roteOffline.flush().then(function () { location.href = queue.summary; });
+      });
+    });
   }
 
   function grade(n) {
-    if (!revealed || !form) return;
+    if (!revealed || !form || busy) return;
     form.querySelector('input[name="grade"]').value = n;
-    form.submit();
+    var body = new URLSearchParams(new FormData(form));
+    body.set("ms", Date.now() - shownAt);
+    send(form, body);
+  }
+
+  // Put the card at the back of the session without grading it.
+  function skip() {
+    if (!form || !skipForm || busy) return;
+    send(skipForm, new URLSearchParams(new FormData(skipForm)));
+  }
+
+  // Grade (or skip) in the background and show the next card without a page
+  // load, falling back to submitting `fallback` if the server rejects it.
+  // Grades carry their position and time, so ones replayed after going
+  // offline land on the right card and review day.
+  function send(fallback, body) {
+    var url = form.dataset.gradeUrl;
+    if (!url || !window.fetch) { fallback.submit(); return; }
+    body.set("position", position);
+    body.set("at", Date.now());
+    busy = true;
+    // Queued grades go first, so later ones queue behind them.
+    if (queue && roteOffline.count()) { gradeOffline(url, body); return; }
+    fetch(url, { method: "POST", body: body })
+      .then(
+        function (r) {
+          if (!r.ok) { fallback.submit(); return; }
+          return r.json().then(function (next) {
+            if (next.done) { location.href = next.next; return; }
+            position = next.position;
+            show(next);
+            busy = false;
+          });
+        },
+        function () {
+          if (queue) gradeOffline(url, body);
+          else fallback.submit();
+        }
+      );
+  }
+
+  if (form) {
+    form.addEventListener("submit", function (e) {
+      e.preventDefault();
+      grade(form.querySelector('input[name="grade"]').value);
+    });
+  }
+  if (skipForm) {
+    skipForm.addEventListener("submit", function (e) {
+      e.preventDefault();
+      skip();
+    });
+  }
+
+  btn.addEventListener("click", step);
+  front.addEventListener("click", function (e) {
+    var blank = e.target.closest("[data-blank]:not([data-open])");
+    if (!blank || revealed) return;
+    openBlank(blank);
+    if (!nextBlank()) reveal();
+  });
+  hide();
+  checkBreak();
+
+  // Picking an answer shows which was right; continuing grades the card
+  // Good if the pick was, Forgot otherwise.
+  if (choices && continueBtn) {
+    choices.addEventListener("click", function (e) {
+      var choice = e.target.closest("[data-choice]");
+      if (!choice || revealed) return;
+      picked = choice;
+      reveal();
+    });
+    continueBtn.addEventListener("click", function () {
+      grade(picked ? picked.dataset.choice : "1");
+    });
+  }
+
+  // Optional confirmation for tapping Forgot on touch screens: hold the
+  // button, or tap it twice.
+  var forgot = document.getElementById("forgot-btn");
+  var label = document.getElementById("forgot-label");
+  var confirmMode = (window.ROTE_KEYS || {}).forgot_confirm;
+  if (forgot && label && confirmMode && confirmMode !== "off" && matchMedia("(pointer: coarse)").matches) {
+    var text = label.textContent;
+    var timer = null;
+    var armed = false;
+
+    function disarm() {
+      clearTimeout(timer);
+      timer = null;
+      armed = false;
+      label.textContent = text;
+    }
+
+    forgot.addEventListener("click", function (e) {
+      e.preventDefault();
+      if (confirmMode === "double-tap") {
+        if (armed) { grade("1"); return; }
+        armed = true;
+        label.textContent = "Tap again";
+        timer = setTimeout(disarm, 1500);
+      } else if (!timer) {
+        label.textContent = "Hold to confirm";
+      }
+    });
+
+    if (confirmMode === "hold") {
+      forgot.addEventListener("pointerdown", function () {
+        label.textContent = "Keep holding…";
+        timer = setTimeout(function () { grade("1"); }, 600);
+      });
+      ["pointerup", "pointerleave", "pointercancel"].forEach(function (ev) {
+        forgot.addEventListener(ev, function () {
+          clearTimeout(timer);
+          timer = null;
+        });
+      });
+      forgot.addEventListener("contextmenu", function (e) { e.preventDefault(); });
+    }
   }
 
-  btn.addEventListener("click", reveal);
+  var grades = ["forgot", "hard", "good", "easy"];
+  // Pass/fail sessions: Space on a revealed card means Good.
+  var passFail = !!(form && form.dataset.passFail === "1");
 
   document.addEventListener("keydown", function (e) {
-    var t = e.target.tagName;
-    if (t === "INPUT" || t === "TEXTAREA" || t === "SELECT") return;
-    if (e.key === " ") { e.preventDefault(); reveal(); }
-    else if (e.key >= "1" && e.key <= "4") { grade(e.key); }
+    if (roteTyping(e)) return;
+    if (resting()) {
+      if (e.key === "Enter" || e.key === " ") {
+        e.preventDefault();
+        endBreak();
+      }
+      return;
+    }
+    if (roteKey(e, "reveal")) {
+      e.preventDefault();
+      if (revealed && quiz()) continueBtn.click();
+      else if (revealed && passFail) grade("3");
+      else step();
+      return;
+    }
+    if (roteKey(e, "info")) { location.href = document.getElementById("card-info").href; return; }
+    if (roteKey(e, "skip")) { skip(); return; }
+    if (roteKey(e, "edit")) {
+      var edit = document.getElementById("card-edit");
+      if (!edit.hidden) location.href = edit.href;
+      return;
+    }
+    for (var i = 0; i < grades.length; i++) {
+      if (passFail && (i === 1 || i === 3)) continue;
+      if (roteKey(e, grades[i])) { grade(String(i + 1)); return; }
+    }
   });
 })();
@@ -0,0 +1,29 @@
+// Service worker: pages and assets come from the network when it is there and
+// from the cache when it is not, so an open review session survives losing
+// the connection. Grades are queued separately, by offline.js.
+var CACHE = "rote-v1";
+
+self.addEventListener("install", function () {
+  self.skipWaiting();
+});
+
+self.addEventListener("activate", function (e) {
+  e.waitUntil(self.clients.claim());
+});
+
+self.addEventListener("fetch", function (e) {
+  if (e.request.method !== "GET") return;
+  e.respondWith(
+    fetch(e.request)
+      .then(function (r) {
+        if (r.ok || r.type === "opaque") {
+          var copy = r.clone();
+          caches.open(CACHE).then(function (c) { c.put(e.request, copy); });
+        }
+        return r;
+      })
+      .catch(function () {
+        return caches.match(e.request).then(function (r) { return r || Response.error(); });
+      })
+  );
+});
@@ -0,0 +1,497 @@
+// Statistics derived from the review log.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+
+use crate::card::Card;
+use crate::clock;
+use crate::revlog::{Review, SessionNote};
+
+pub struct SessionSummary {
+    pub session: String,
+    pub started: DateTime<Local>,
+    pub decks: Vec<String>,
+    /// Grades given, indexed forgot, hard, good, easy.
+    pub counts: [usize; 4],
+    pub practice: bool,
+    pub note: Option<SessionNote>,
+    recalled: usize,
+    recall_total: usize,
+    answer_ms: u64,
+    timed: u64,
+}
+
+impl SessionSummary {
+    pub fn reviews(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Share of previously seen cards recalled (graded Hard or better).
+    pub fn retention(&self) -> Option<f64> {
+        ratio(self.recalled, self.recall_total)
+    }
+
+    /// Mean seconds from showing a card to grading it, over timed reviews.
+    pub fn answer_secs(&self) -> Option<f64> {
+        (self.timed > 0).then(|| self.answer_ms as f64 / self.timed as f64 / 1000.0)
+    }
+
+    fn excluded(&self) -> bool {
+        self.note.as_ref().is_some_and(|n| n.exclude)
+    }
+}
+
+fn ratio(n: usize, total: usize) -> Option<f64> {
+    (total > 0).then(|| n as f64 / total as f64)
+}
+
+/// Sessions in the log, newest first.
+pub fn sessions(reviews: &[Review], notes: &HashMap<String, SessionNote>) -> Vec<SessionSummary> {
+    let mut by_id: HashMap<&str, SessionSummary> = HashMap::new();
+    for r in reviews {
+        let s = by_id
+            .entry(r.session.as_str())
+            .or_insert_with(|| SessionSummary {
+                session: r.session.clone(),
+                started: r.at,
+                decks: Vec::new(),
+                counts: [0; 4],
+                practice: r.practice,
+                note: notes.get(&r.session).cloned(),
+                recalled: 0,
+                recall_total: 0,
+                answer_ms: 0,
+                timed: 0,
+            });
+        s.started = s.started.min(r.at);
+        if !s.decks.contains(&r.deck) {
+            s.decks.push(r.deck.clone());
+        }
+        if let Some(count) = s.counts.get_mut(usize::from(r.grade.clamp(1, 4)) - 1) {
+            *count += 1;
+        }
+        if r.elapsed_days.is_some() {
+            s.recall_total += 1;
+            s.recalled += usize::from(r.grade > 1);
+        }
+        if let Some(ms) = r.answer_ms {
+            s.answer_ms += ms;
+            s.timed += 1;
+        }
+    }
+    let mut out: Vec<SessionSummary> = by_id.into_values().collect();
+    out.sort_by_key(|s| std::cmp::Reverse(s.started));
+    out
+}
+
+/// Overall retention across sessions, skipping practice sessions and, unless
+/// `include_excluded`, sessions whose note asks to be left out.
+pub fn retention(sessions: &[SessionSummary], include_excluded: bool) -> Option<f64> {
+    let counted = sessions
+        .iter()
+        .filter(|s| !s.practice && (include_excluded || !s.excluded()));
+    let (recalled, total) = counted.fold((0, 0), |(n, t), s| (n + s.recalled, t + s.recall_total));
+    ratio(recalled, total)
+}
+
+/// Scheduled intervals that true retention is broken down by: label and
+/// the largest interval in days.
+pub const INTERVALS: [(&str, i64); 4] = [
+    ("1–7 days", 7),
+    ("8–30 days", 30),
+    ("31–90 days", 90),
+    ("90+ days", i64::MAX),
+];
+
+/// How often cards came due and were recalled (graded Hard or better).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recall {
+    pub label: String,
+    pub recalled: usize,
+    pub total: usize,
+}
+
+impl Recall {
+    pub fn rate(&self) -> Option<f64> {
+        ratio(self.recalled, self.total)
+    }
+}
+
+/// Reviews that measure retention: scheduled ones, outside practice
+/// sessions and, unless `include_excluded`, sessions left out by their note.
+fn scheduled<'a>(
+    reviews: &'a [Review],
+    notes: &'a HashMap<String, SessionNote>,
+    include_excluded: bool,
+) -> impl Iterator<Item = (&'a Review, i64)> {
+    reviews.iter().filter_map(move |r| {
+        let excluded = notes.get(&r.session).is_some_and(|n| n.exclude);
+        let days = r.scheduled_days?;
+        (!r.practice && (include_excluded || !excluded)).then_some((r, days))
+    })
+}
+
+/// True retention per `INTERVALS` bucket of the interval each card was
+/// scheduled for.
+pub fn recall_by_interval(
+    reviews: &[Review],
+    notes: &HashMap<String, SessionNote>,
+    include_excluded: bool,
+) -> Vec<Recall> {
+    let mut out: Vec<Recall> = INTERVALS
+        .iter()
+        .map(|(label, _)| Recall {
+            label: label.to_string(),
+            recalled: 0,
+            total: 0,
+        })
+        .collect();
+    for (r, days) in scheduled(reviews, notes, include_excluded) {
+        let bucket = INTERVALS.iter().position(|&(_, max)| days <= max);
+        if let Some(b) = bucket.and_then(|i| out.get_mut(i)) {
+            b.total += 1;
+            b.recalled += usize::from(r.grade > 1);
+        }
+    }
+    out
+}
+
+/// True retention per deck, by deck name.
+pub fn recall_by_deck(
+    reviews: &[Review],
+    notes: &HashMap<String, SessionNote>,
+    include_excluded: bool,
+) -> Vec<Recall> {
+    let mut by_deck: BTreeMap<&str, Recall> = BTreeMap::new();
+    for (r, _) in scheduled(reviews, notes, include_excluded) {
+        let b = by_deck.entry(r.deck.as_str()).or_insert_with(|| Recall {
+            label: r.deck.clone(),
+            recalled: 0,
+            total: 0,
+        });
+        b.total += 1;
+        b.recalled += usize::from(r.grade > 1);
+    }
+    by_deck.into_values().collect()
+}
+
+/// Reviewed cards per FSRS difficulty, from 1–2 up to 9–10.
+pub fn difficulty_histogram(cards: &[Card]) -> [usize; 9] {
+    let mut counts = [0; 9];
+    for d in cards.iter().filter_map(|c| c.difficulty) {
+        counts[(d.floor() as usize).clamp(1, 9) - 1] += 1;
+    }
+    counts
+}
+
+/// A card worth rewriting: its index in `cards`, and how often it was
+/// forgotten after being learned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hardest {
+    pub index: usize,
+    pub difficulty: f64,
+    pub lapses: usize,
+}
+
+/// The `n` reviewed cards with the highest difficulty, the most lapsed first
+/// among equals.
+pub fn hardest(cards: &[Card], reviews: &[Review], n: usize) -> Vec<Hardest> {
+    let mut lapses: HashMap<&str, usize> = HashMap::new();
+    for r in reviews {
+        if r.grade == 1 && r.elapsed_days.is_some() && !r.practice {
+            *lapses.entry(r.card_id.as_str()).or_default() += 1;
+        }
+    }
+    let mut out: Vec<Hardest> = cards
+        .iter()
+        .enumerate()
+        .filter_map(|(index, c)| {
+            Some(Hardest {
+                index,
+                difficulty: c.difficulty?,
+                lapses: lapses.get(c.id.as_str()).copied().unwrap_or(0),
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        b.difficulty
+            .total_cmp(&a.difficulty)
+            .then(b.lapses.cmp(&a.lapses))
+    });
+    out.truncate(n);
+    out
+}
+
+/// Mean seconds to answer across sessions, over reviews that were timed.
+pub fn answer_secs(sessions: &[SessionSummary]) -> Option<f64> {
+    let (ms, timed) = sessions
+        .iter()
+        .fold((0, 0), |(ms, n), s| (ms + s.answer_ms, n + s.timed));
+    (timed > 0).then(|| ms as f64 / timed as f64 / 1000.0)
+}
+
+/// Reviews per day for the `days` days ending on `end`, oldest first.
+pub fn daily_reviews(reviews: &[Review], end: NaiveDate, days: usize) -> Vec<(NaiveDate, usize)> {
+    let start = end - Duration::days(days as i64 - 1);
+    let mut counts = vec![0; days];
+    for r in reviews {
+        let offset = (clock::day_of(&r.at) - start).num_days();
+        if let Some(count) = usize::try_from(offset).ok().and_then(|i| counts.get_mut(i)) {
+            *count += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, n)| (start + Duration::days(i as i64), n))
+        .collect()
+}
+
+/// Cards added per day for the `days` days ending on `end`, oldest first.
+/// Cards from before the `created` column are not counted.
+pub fn added_per_day(cards: &[Card], end: NaiveDate, days: usize) -> Vec<(NaiveDate, usize)> {
+    let start = end - Duration::days(days as i64 - 1);
+    let mut counts = vec![0; days];
+    for created in cards.iter().filter_map(|c| c.created) {
+        let offset = (created - start).num_days();
+        if let Some(count) = usize::try_from(offset).ok().and_then(|i| counts.get_mut(i)) {
+            *count += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, n)| (start + Duration::days(i as i64), n))
+        .collect()
+}
+
+/// Reviews grouped by the day they were given, most recent day first and in
+/// the order given within a day.
+pub fn by_day(reviews: &[Review]) -> Vec<(NaiveDate, Vec<&Review>)> {
+    let mut days: Vec<(NaiveDate, Vec<&Review>)> = Vec::new();
+    let mut sorted: Vec<&Review> = reviews.iter().collect();
+    sorted.sort_by_key(|r| r.at);
+    for r in sorted {
+        let day = clock::day_of(&r.at);
+        match days.last_mut() {
+            Some((d, list)) if *d == day => list.push(r),
+            _ => days.push((day, vec![r])),
+        }
+    }
+    days.reverse();
+    days
+}
+
+/// Group days into Monday-first weeks for a heatmap; days outside the range
+/// are `None`.
+pub fn weeks(days: &[(NaiveDate, usize)]) -> Vec<[Option<(NaiveDate, usize)>; 7]> {
+    let mut out: Vec<[Option<(NaiveDate, usize)>; 7]> = Vec::new();
+    for &(date, n) in days {
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        if out.is_empty() || weekday == 0 {
+            out.push([None; 7]);
+        }
+        if let Some(week) = out.last_mut() {
+            week[weekday] = Some((date, n));
+        }
+    }
+    out
+}
+
+/// Shade for a day with `n` reviews: 0 for none, 1–4 relative to the busiest
+/// day `max`.
+pub fn level(n: usize, max: usize) -> usize {
+    if n == 0 || max == 0 {
+        0
+    } else {
+        (n * 4).div_ceil(max).clamp(1, 4)
+    }
+}
+
+/// Compact terminal heatmap: one row per weekday, one column per week.
+pub fn heatmap_ascii(days: &[(NaiveDate, usize)]) -> String {
+    const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+    let max = days.iter().map(|&(_, n)| n).max().unwrap_or(0);
+    let weeks = weeks(days);
+    let mut out = String::new();
+    for (row, label) in ["Mon", "", "Wed", "", "Fri", "", "Sun"].iter().enumerate() {
+        out.push_str(&format!("{label:<4}"));
+        for week in &weeks {
+            out.push(week[row].map_or(' ', |(_, n)| SHADES[level(n, max)]));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn review(session: &str, grade: u8, seen: bool) -> Review {
+        Review {
+            at: Local::now(),
+            card_id: "c".into(),
+            deck: "d".into(),
+            grade,
+            session: session.into(),
+            scheduled_days: None,
+            elapsed_days: seen.then_some(3),
+            practice: false,
+            answer_ms: None,
+        }
+    }
+
+    #[test]
+    fn session_summaries() {
+        let reviews = vec![
+            review("s1", 3, true),
+            review("s1", 1, true),
+            review("s1", 4, false),
+        ];
+        let out = sessions(&reviews, &HashMap::new());
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].reviews(), 3);
+        assert_eq!(out[0].counts, [1, 0, 1, 1]);
+        assert_eq!(out[0].retention(), Some(0.5));
+        assert_eq!(out[0].answer_secs(), None);
+
+        let mut timed = reviews.clone();
+        timed[0].answer_ms = Some(4000);
+        timed[1].answer_ms = Some(2000);
+        let out = sessions(&timed, &HashMap::new());
+        assert_eq!(out[0].answer_secs(), Some(3.0));
+        assert_eq!(answer_secs(&out), Some(3.0));
+    }
+
+    #[test]
+    fn heatmap_by_week() {
+        let today = clock::today();
+        let mut reviews = vec![review("s", 3, false), review("s", 3, false)];
+        reviews[1].at -= Duration::days(1);
+        let days = daily_reviews(&reviews, today, 14);
+        assert_eq!(days.len(), 14);
+        assert_eq!(days[13], (today, 1));
+        assert_eq!(days[12].1, 1);
+        assert_eq!(days.iter().map(|d| d.1).sum::<usize>(), 2);
+
+        // 2025-06-05 is a Thursday: the first week starts three days in.
+        let start = NaiveDate::from_ymd_opt(2025, 6, 5).unwrap();
+        let fixed: Vec<(NaiveDate, usize)> =
+            (0..7).map(|i| (start + Duration::days(i), 0)).collect();
+        let grid = weeks(&fixed);
+        assert_eq!(grid.len(), 2);
+        assert!(grid[0][2].is_none() && grid[0][3] == Some((start, 0)));
+        assert!(grid[1][0].is_some() && grid[1][3].is_none());
+
+        let art = heatmap_ascii(&days);
+        assert_eq!(art.lines().count(), 7);
+        assert_eq!(art.matches('█').count(), 2);
+        assert_eq!(level(0, 5), 0);
+        assert_eq!(level(1, 8), 1);
+        assert_eq!(level(8, 8), 4);
+    }
+
+    #[test]
+    fn reviews_by_day() {
+        let mut reviews = vec![
+            review("s", 3, false),
+            review("s", 1, false),
+            review("s", 4, false),
+        ];
+        let noon = Local.with_ymd_and_hms(2025, 6, 5, 12, 0, 0).unwrap();
+        reviews[0].at = noon - Duration::days(2);
+        reviews[1].at = noon - Duration::minutes(1);
+        reviews[1].card_id = "first".into();
+        reviews[2].at = noon;
+        let days = by_day(&reviews);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].0, NaiveDate::from_ymd_opt(2025, 6, 5).unwrap());
+        assert_eq!(days[0].1.len(), 2);
+        assert_eq!(days[0].1[0].card_id, "first");
+        assert_eq!(days[1].0, NaiveDate::from_ymd_opt(2025, 6, 3).unwrap());
+    }
+
+    #[test]
+    fn recall_by_scheduled_interval_and_deck() {
+        let mut reviews = Vec::new();
+        for (days, grade, deck) in [(3, 3, "a"), (7, 1, "a"), (20, 3, "b"), (400, 2, "b")] {
+            let mut r = review("s", grade, true);
+            r.scheduled_days = Some(days);
+            r.deck = deck.into();
+            reviews.push(r);
+        }
+        let mut practice = review("p", 1, true);
+        practice.scheduled_days = Some(3);
+        practice.practice = true;
+        reviews.push(practice);
+        reviews.push(review("s", 1, false));
+
+        let buckets = recall_by_interval(&reviews, &HashMap::new(), false);
+        let counts: Vec<(usize, usize)> = buckets.iter().map(|b| (b.recalled, b.total)).collect();
+        assert_eq!(counts, vec![(1, 2), (1, 1), (0, 0), (1, 1)]);
+        assert_eq!(buckets[0].rate(), Some(0.5));
+        assert_eq!(buckets[2].rate(), None);
+
+        let decks = recall_by_deck(&reviews, &HashMap::new(), false);
+        assert_eq!(decks.len(), 2);
+        assert_eq!((decks[1].label.as_str(), decks[1].recalled), ("b", 2));
+    }
+
+    #[test]
+    fn hardest_cards_and_difficulty_histogram() {
+        let cards: Vec<Card> = [Some(9.5), Some(3.2), None, Some(9.5), Some(1.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, difficulty)| Card {
+                deck: "d".into(),
+                id: format!("c{i}"),
+                difficulty,
+                ..Default::default()
+            })
+            .collect();
+        let mut lapse = review("s", 1, true);
+        lapse.card_id = "c3".into();
+        let hardest = hardest(&cards, &[lapse], 3);
+        let order: Vec<(usize, usize)> = hardest.iter().map(|h| (h.index, h.lapses)).collect();
+        assert_eq!(order, vec![(3, 1), (0, 0), (1, 0)]);
+        assert_eq!(difficulty_histogram(&cards), [1, 0, 1, 0, 0, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn cards_added_per_day() {
+        let today = clock::today();
+        let cards: Vec<Card> = [Some(0), Some(0), Some(2), Some(30), None]
+            .into_iter()
+            .map(|ago| Card {
+                deck: "d".into(),
+                created: ago.map(|n| today - Duration::days(n)),
+                ..Default::default()
+            })
+            .collect();
+        let days = added_per_day(&cards, today, 7);
+        let counts: Vec<usize> = days.iter().map(|d| d.1).collect();
+        assert_eq!(counts, vec![0, 0, 0, 0, 1, 0, 2]);
+        assert_eq!(days[6].0, today);
+    }
+
+    #[test]
+    fn excluded_sessions_leave_retention() {
+        let reviews = vec![review("good", 3, true), review("tired", 1, true)];
+        let mut notes = HashMap::new();
+        notes.insert(
+            "tired".to_string(),
+            SessionNote {
+                at: Local::now(),
+                session: "tired".into(),
+                note: "tired, grades unreliable".into(),
+                exclude: true,
+            },
+        );
+        let out = sessions(&reviews, &notes);
+        assert_eq!(retention(&out, true), Some(0.5));
+        assert_eq!(retention(&out, false), Some(1.0));
+    }
+}
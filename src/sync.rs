@@ -0,0 +1,41 @@
+// `rote sync <url>`: exchange scheduling with a `rote serve` on another
+// machine. The client posts the state of every card it has reviewed; the
+// server takes whichever of those were reviewed more recently than its own
+// copy and answers with its full state, from which the client takes the
+// same. Ties go to the server. Only scheduling travels: card content is
+// expected to be shared already (git, a synced folder), and cards are matched
+// by id.
+
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+use crate::state::CardState;
+use crate::web::Credentials;
+
+/// Path of the server endpoint, below the server's base URL.
+pub const ENDPOINT: &str = "/sync";
+
+/// Send `states` to the server at `url` and return the server's states after
+/// it has merged them.
+pub fn exchange(
+    url: &str,
+    auth: Option<&str>,
+    states: &BTreeMap<String, CardState>,
+) -> Result<BTreeMap<String, CardState>> {
+    let endpoint = format!("{}{ENDPOINT}", url.trim_end_matches('/'));
+    let sync_err = |source| Error::Sync {
+        url: endpoint.clone(),
+        source,
+    };
+    let body = serde_json::to_string(states).unwrap();
+    let mut request = ureq::post(&endpoint).header("Content-Type", "application/json");
+    if let Some(auth) = auth {
+        request = request.header("Authorization", Credentials::parse(auth)?.header());
+    }
+    let mut response = request.send(body).map_err(sync_err)?;
+    let text = response.body_mut().read_to_string().map_err(sync_err)?;
+    serde_json::from_str(&text).map_err(|source| Error::SyncReply {
+        url: endpoint.clone(),
+        source,
+    })
+}
@@ -0,0 +1,137 @@
+// Trash: cards deleted in the web UI, kept one JSON object per line in
+// `trash.jsonl` in the collection's data directory so they can be put back
+// from `/trash`. Unlike the undo journal, restoring one card leaves every
+// later change alone. Entries older than `DAYS` are purged whenever the
+// trash is written.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::card::Card;
+use crate::error::{Error, Result};
+
+const TRASH_FILE: &str = "trash.jsonl";
+
+/// How long a deleted card stays in the trash.
+pub const DAYS: i64 = 30;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    pub at: DateTime<Local>,
+    /// The deck file the card was deleted from.
+    pub file: PathBuf,
+    pub card: Card,
+}
+
+fn trash_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(TRASH_FILE)
+}
+
+/// Every card in the trash, most recently deleted first.
+pub fn list(data_dir: &Path) -> Vec<Entry> {
+    let Ok(content) = std::fs::read_to_string(trash_path(data_dir)) else {
+        return Vec::new();
+    };
+    let now = Local::now();
+    let mut entries: Vec<Entry> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter(|entry| !expired(entry, now))
+        .collect();
+    entries.reverse();
+    entries
+}
+
+fn expired(entry: &Entry, now: DateTime<Local>) -> bool {
+    (now - entry.at).num_days() >= DAYS
+}
+
+fn write(data_dir: &Path, entries: &[Entry]) -> Result<()> {
+    let path = trash_path(data_dir);
+    let mut out = String::new();
+    for entry in entries.iter().rev() {
+        let line = serde_json::to_string(entry).unwrap();
+        out.push_str(&line);
+        out.push('\n');
+    }
+    std::fs::write(&path, out).map_err(Error::io("write", path))
+}
+
+/// Put `card`, deleted from `file`, in the trash, and purge expired entries.
+pub fn add(data_dir: &Path, file: &Path, card: &Card) -> Result<()> {
+    std::fs::create_dir_all(data_dir).map_err(Error::io("create", data_dir))?;
+    let path = trash_path(data_dir);
+    let entry = Entry {
+        at: Local::now(),
+        file: file.to_path_buf(),
+        card: card.clone(),
+    };
+    let line = serde_json::to_string(&entry).unwrap();
+    let mut out = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(Error::io("open", &path))?;
+    writeln!(out, "{line}").map_err(Error::io("write", &path))?;
+    drop(out);
+
+    let entries = list(data_dir);
+    let kept = std::fs::read_to_string(&path).map_or(0, |c| c.lines().count());
+    if entries.len() < kept {
+        write(data_dir, &entries)?;
+    }
+    Ok(())
+}
+
+/// Take the most recently deleted card with `id` out of the trash.
+pub fn take(data_dir: &Path, id: &str) -> Result<Option<Entry>> {
+    let mut entries = list(data_dir);
+    let Some(pos) = entries.iter().position(|e| e.card.id == id) else {
+        return Ok(None);
+    };
+    let entry = entries.remove(pos);
+    write(data_dir, &entries)?;
+    Ok(Some(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(id: &str) -> Card {
+        Card {
+            deck: "es".into(),
+            front: format!("front {id}"),
+            back: "back".into(),
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn deleted_cards_come_back_until_they_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("es.csv");
+        add(dir.path(), &file, &card("c1")).unwrap();
+        add(dir.path(), &file, &card("c2")).unwrap();
+        let ids: Vec<String> = list(dir.path()).into_iter().map(|e| e.card.id).collect();
+        assert_eq!(ids, vec!["c2", "c1"]);
+
+        let taken = take(dir.path(), "c1").unwrap().unwrap();
+        assert_eq!(
+            (taken.file, taken.card.front),
+            (file.clone(), "front c1".into())
+        );
+        assert!(take(dir.path(), "c1").unwrap().is_none());
+
+        let mut old = list(dir.path());
+        old[0].at -= chrono::Duration::days(DAYS);
+        write(dir.path(), &old).unwrap();
+        assert!(list(dir.path()).is_empty());
+        add(dir.path(), &file, &card("c3")).unwrap();
+        let content = std::fs::read_to_string(dir.path().join(TRASH_FILE)).unwrap();
+        assert_eq!(content.lines().count(), 1);
+    }
+}
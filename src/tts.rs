@@ -0,0 +1,249 @@
+// Text-to-speech for language decks: audio of a card's text, made by a local
+// piper voice or fetched from an HTTP service, and cached under `.rote/tts/`
+// so each text is synthesized once. Configured per deck in decks.toml:
+//
+//     [spanish.tts]
+//     piper = "~/voices/es_ES-davefx-medium.onnx"
+//     side = "both"
+//
+// or `url = "http://localhost:5002/api/tts?text={text}"` for a service that
+// answers a GET with audio. The web UI plays it when the answer is revealed.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::card::{self, Card};
+use crate::error::{Error, Result};
+
+const DIR: &str = "tts";
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tts {
+    /// Voice model for the `piper` binary on PATH.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub piper: Option<PathBuf>,
+    /// Service URL, with `{text}` standing for the URL-encoded text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Which side of the card is spoken.
+    #[serde(default)]
+    pub side: Side,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    #[default]
+    Front,
+    Back,
+    Both,
+}
+
+impl Side {
+    pub fn parse(s: &str) -> Option<Side> {
+        match s {
+            "front" => Some(Side::Front),
+            "back" => Some(Side::Back),
+            "both" => Some(Side::Both),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Front => "front",
+            Side::Back => "back",
+            Side::Both => "both",
+        }
+    }
+
+    /// The single sides spoken, in order.
+    pub fn sides(self) -> &'static [Side] {
+        match self {
+            Side::Front => &[Side::Front],
+            Side::Back => &[Side::Back],
+            Side::Both => &[Side::Front, Side::Back],
+        }
+    }
+}
+
+/// What is read aloud for one side of `card`: the front with its clozes
+/// filled in, or the back, without markup.
+pub fn text(card: &Card, side: Side) -> String {
+    let text = match side {
+        Side::Back => card::expand_escapes(&card.back),
+        _ => card::expand_escapes(&card::fill_clozes(&card.front)),
+    };
+    text.replace(['*', '`', '#', '$'], "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Tts {
+    /// Audio of `text`, synthesized into `data_dir/tts` unless it already
+    /// was. Returns the file and its content type.
+    pub fn audio(&self, data_dir: &Path, text: &str) -> Result<(PathBuf, &'static str)> {
+        let dir = data_dir.join(DIR);
+        let key = self.key(text);
+        for (ext, content_type) in FORMATS {
+            let path = dir.join(format!("{key}.{ext}"));
+            if path.exists() {
+                return Ok((path, content_type));
+            }
+        }
+        std::fs::create_dir_all(&dir).map_err(Error::io("create", &dir))?;
+        let tmp = dir.join(format!("{key}.tmp"));
+        let ext = match (&self.piper, &self.url) {
+            (Some(model), _) => {
+                piper(model, text, &tmp)?;
+                "wav"
+            }
+            (None, Some(url)) => {
+                let (bytes, ext) = fetch(url, text)?;
+                std::fs::write(&tmp, bytes).map_err(Error::io("write", &tmp))?;
+                ext
+            }
+            (None, None) => return Err(Error::NoTtsBackend),
+        };
+        let path = dir.join(format!("{key}.{ext}"));
+        std::fs::rename(&tmp, &path).map_err(Error::io("write", &path))?;
+        let content_type = FORMATS.iter().find(|f| f.0 == ext).unwrap().1;
+        Ok((path, content_type))
+    }
+
+    /// Cache key: the same text from another voice or service is another file.
+    fn key(&self, text: &str) -> String {
+        let backend = match (&self.piper, &self.url) {
+            (Some(model), _) => format!("piper:{}", model.display()),
+            (None, url) => format!("url:{}", url.as_deref().unwrap_or("")),
+        };
+        let digest = Sha256::digest(format!("{backend}\n{text}"));
+        digest[..16].iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+/// Extensions cached audio may have, and their content types.
+const FORMATS: [(&str, &str); 3] = [
+    ("wav", "audio/wav"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+];
+
+/// Run piper on `text`, writing WAV to `out`.
+fn piper(model: &Path, text: &str, out: &Path) -> Result<()> {
+    let run_err = |source| Error::Spawn {
+        program: "piper".to_string(),
+        source,
+    };
+    let model = crate::config::expand_home(&model.to_string_lossy());
+    let mut child = Command::new("piper")
+        .arg("--model")
+        .arg(model)
+        .arg("--output_file")
+        .arg(out)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(run_err)?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(text.as_bytes())
+        .map_err(run_err)?;
+    let output = child.wait_with_output().map_err(run_err)?;
+    if !output.status.success() {
+        return Err(Error::Exited {
+            program: "piper".to_string(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn fetch(url: &str, text: &str) -> Result<(Vec<u8>, &'static str)> {
+    let url = url.replace("{text}", &encode(text));
+    let fetch_err = |source| Error::Fetch {
+        url: url.clone(),
+        source,
+    };
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(30)))
+        .build()
+        .into();
+    let mut response = agent.get(&url).call().map_err(fetch_err)?;
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let ext = match content_type {
+        t if t.contains("wav") => "wav",
+        t if t.contains("ogg") => "ogg",
+        _ => "mp3",
+    };
+    let bytes = response.body_mut().read_to_vec().map_err(fetch_err)?;
+    Ok((bytes, ext))
+}
+
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speaks_filled_front_and_caches_by_backend() {
+        let card = Card {
+            deck: "es".into(),
+            front: "El [gato] es **negro**".into(),
+            back: "The cat\\nis black".into(),
+            id: "c1".into(),
+            ..Default::default()
+        };
+        assert_eq!(text(&card, Side::Front), "El gato es negro");
+        assert_eq!(text(&card, Side::Back), "The cat is black");
+
+        let dir = tempfile::tempdir().unwrap();
+        let tts = Tts {
+            url: Some("http://127.0.0.1:9/tts?text={text}".into()),
+            ..Tts::default()
+        };
+        // A cached file is served without asking the service.
+        let cached = dir
+            .path()
+            .join(DIR)
+            .join(format!("{}.mp3", tts.key("hola")));
+        std::fs::create_dir_all(cached.parent().unwrap()).unwrap();
+        std::fs::write(&cached, b"audio").unwrap();
+        assert_eq!(
+            tts.audio(dir.path(), "hola").unwrap(),
+            (cached, "audio/mpeg")
+        );
+        assert!(tts.audio(dir.path(), "adiós").is_err());
+
+        let piper = Tts {
+            piper: Some("es.onnx".into()),
+            ..Tts::default()
+        };
+        assert_ne!(piper.key("hola"), tts.key("hola"));
+        assert_eq!(encode("¿qué?"), "%C2%BFqu%C3%A9%3F");
+    }
+}
@@ -0,0 +1,151 @@
+// Per-user scheduling for shared decks. Card content stays in the CSV files,
+// but a user's stability, difficulty, due date and last review are kept in
+// `.rote/users/<name>/state.json` (the `rote state export` format), next to
+// that user's own revlog, journal and sessions. The CSV's scheduling columns
+// are written back unchanged.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::card::Card;
+use crate::error::{Error, Result};
+use crate::state::{self, CardState};
+
+const STATE_FILE: &str = "state.json";
+
+pub struct Profile {
+    name: String,
+    dir: PathBuf,
+    states: BTreeMap<String, CardState>,
+    /// Scheduling columns as read from the CSV files, by card id.
+    shared: HashMap<String, CardState>,
+}
+
+impl Profile {
+    /// Open `name`'s profile in the collection's data directory. A user with
+    /// no state file yet starts with every card new.
+    pub fn open(data_dir: &Path, name: &str) -> Result<Profile> {
+        let valid = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err(Error::InvalidUser {
+                name: name.to_string(),
+            });
+        }
+        let dir = data_dir.join("users").join(name);
+        let path = dir.join(STATE_FILE);
+        let states = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).map_err(Error::json(&path))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(Error::io("read", path)(e)),
+        };
+        Ok(Profile {
+            name: name.to_string(),
+            dir,
+            states,
+            shared: HashMap::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Where this user's revlog, journal and sessions are kept.
+    pub fn data_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Swap the CSV scheduling of freshly loaded cards for this user's.
+    pub fn apply(&mut self, cards: &mut [Card]) {
+        for card in cards {
+            self.shared.insert(card.id.clone(), CardState::of(card));
+            self.states
+                .get(&card.id)
+                .cloned()
+                .unwrap_or_default()
+                .set(card);
+        }
+    }
+
+    /// Put the CSV's scheduling back into `cards`, undoing `apply`.
+    pub fn unapply(&self, cards: &mut [Card]) {
+        for card in cards {
+            self.shared
+                .get(&card.id)
+                .cloned()
+                .unwrap_or_default()
+                .set(card);
+        }
+    }
+
+    /// Copies of `cards` carrying the CSV's scheduling again, for saving.
+    pub fn shared(&self, cards: &[Card]) -> Vec<Card> {
+        cards
+            .iter()
+            .map(|card| {
+                let mut card = card.clone();
+                self.shared
+                    .get(&card.id)
+                    .cloned()
+                    .unwrap_or_default()
+                    .set(&mut card);
+                card
+            })
+            .collect()
+    }
+
+    /// Record the scheduling of `cards` as this user's and write the state
+    /// file. Cards not in `cards` keep whatever state they had.
+    pub fn save(&mut self, cards: &[Card]) -> Result<()> {
+        for card in cards {
+            self.states.remove(&card.id);
+        }
+        self.states.append(&mut state::export(cards));
+        std::fs::create_dir_all(&self.dir).map_err(Error::io("create", &self.dir))?;
+        let path = self.dir.join(STATE_FILE);
+        let json = serde_json::to_string_pretty(&self.states).unwrap();
+        std::fs::write(&path, json + "\n").map_err(Error::io("write", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn card(id: &str, due: Option<NaiveDate>) -> Card {
+        Card {
+            deck: "d".into(),
+            front: "f".into(),
+            id: id.into(),
+            stability: due.map(|_| 4.0),
+            difficulty: due.map(|_| 5.0),
+            due,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn scheduling_is_kept_per_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv_due = NaiveDate::from_ymd_opt(2025, 6, 1);
+        let user_due = NaiveDate::from_ymd_opt(2025, 7, 1);
+
+        let mut cards = vec![card("a", csv_due), card("b", None)];
+        let mut alex = Profile::open(dir.path(), "alex").unwrap();
+        alex.apply(&mut cards);
+        assert_eq!(cards[0].due, None, "new to alex");
+
+        cards[1] = card("b", user_due);
+        assert_eq!(alex.shared(&cards)[0].due, csv_due);
+        assert_eq!(alex.shared(&cards)[1].due, None);
+        alex.save(&cards).unwrap();
+
+        let mut again = vec![card("a", csv_due), card("b", None)];
+        Profile::open(dir.path(), "alex").unwrap().apply(&mut again);
+        assert_eq!(again[0].due, None);
+        assert_eq!(again[1].due, user_due);
+
+        assert!(Profile::open(dir.path(), "../sam").is_err());
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use axum::Router;
-use axum::extract::{Form, Path, State};
+use axum::extract::{DefaultBodyLimit, Form, Path, State};
 use axum::response::{Html, Redirect};
 use axum::routing::{get, post};
 use chrono::Local;
@@ -11,35 +12,219 @@ use tokio::sync::Mutex;
 
 use crate::card::{self, Card};
 use crate::fsrs::Grade;
-use crate::review;
+use crate::scheduler::Scheduler;
+use crate::state::{self, CardState};
+use crate::{
+    Collection, Error, OpenOptions, backup, bury, clock, clozify, deck, journal, media, occlusion,
+    quiz, quizlet, reschedule, review, revlog, scheduler, stats, trash, tts, webhook,
+};
+
+mod auth;
+mod css;
+#[cfg(feature = "llm")]
+mod generate;
+mod keymap;
+mod logging;
+mod markdown;
+mod public;
+mod render;
+mod sessions;
+mod site;
+mod tls;
+mod urls;
+mod users;
+
+pub(crate) use auth::Credentials;
+pub use keymap::Keymap;
 
 // -- Static assets embedded at compile time --
 
 const BASE_CSS: &str = include_str!("static/style.css");
+const KEYMAP_JS: &str = include_str!("static/keymap.js");
 const REVIEW_JS: &str = include_str!("static/review.js");
+const BROWSER_JS: &str = include_str!("static/browser.js");
+const EDITOR_JS: &str = include_str!("static/editor.js");
+const OCCLUSION_JS: &str = include_str!("static/occlusion.js");
+const OFFLINE_JS: &str = include_str!("static/offline.js");
+const SERVICE_WORKER_JS: &str = include_str!("static/sw.js");
+const ICON_SVG: &str = include_str!("static/icon.svg");
+const KATEX_JS: &str = include_str!("static/katex/katex.min.js");
+const MATH_JS: &str = include_str!("static/math.js");
+/// Browser chrome colour for the installed app.
+const THEME_COLOR: &str = "#1e1e1e";
 
 // -- App state --
 
-struct AppState {
-    cards: Vec<Card>,
-    sources: Vec<PathBuf>,
+// The collection's fallible calls, with failures logged: a page should still
+// render when one file cannot be read or written.
+
+/// Card `i` with its content, or as indexed if that cannot be read.
+fn full_card(app: &Collection, i: usize) -> Cow<'_, Card> {
+    app.full_card(i).unwrap_or_else(|e| {
+        tracing::warn!("{e}");
+        Cow::Borrowed(&app.cards[i])
+    })
+}
+
+fn materialize(app: &mut Collection, i: usize) {
+    if let Err(e) = app.materialize(i) {
+        tracing::warn!("{e}");
+    }
+}
+
+fn save_file(app: &mut Collection, target: &std::path::Path) {
+    let start = std::time::Instant::now();
+    match app.save_file(target) {
+        Ok(()) => tracing::debug!(
+            file = %target.display(),
+            ms = start.elapsed().as_millis() as u64,
+            "saved"
+        ),
+        Err(e) => tracing::error!("saving {}: {e}", target.display()),
+    }
 }
 
+#[derive(Clone)]
 struct ReviewSession {
     order: Vec<usize>,
     position: usize,
     counts: [u32; 4],
+    /// Grades are logged but scheduling is left alone.
+    practice: bool,
+    /// Cards are answered by picking from multiple choices (see `quiz`).
+    quiz: bool,
+    /// Only Forgot and Good are offered, and any pass is graded Good.
+    pass_fail: bool,
+    started: chrono::NaiveDate,
+    /// When the session began, for the timer on the review page.
+    started_at: chrono::DateTime<chrono::Local>,
 }
 
 struct ServerState {
-    app: AppState,
+    app: Collection,
     sessions: HashMap<String, ReviewSession>,
+    retention: f64,
+    new_per_day: Option<usize>,
+    /// Session order where no deck setting gives one.
+    order: review::Order,
+    /// Grade in practice only and keep sessions in memory (see `public`).
+    read_only: bool,
+    auto_reveal: Option<u64>,
+    breaks: review::Breaks,
+    decks: HashMap<String, deck::DeckConfig>,
+    webhooks: Vec<webhook::Webhook>,
+    /// Where the Generate page asks for cards (see `generate`).
+    #[cfg(feature = "llm")]
+    llm: Option<crate::config::Llm>,
+    /// Whose scheduling `app` and `sessions` hold: a profile's name, or
+    /// `None` for the deck files' own (see `users`).
+    user: Option<String>,
+    /// The user of requests that pick none: `--user`, if given.
+    default_user: Option<String>,
+    /// Everyone else picked since the server started.
+    parked: HashMap<Option<String>, users::Parked>,
+    /// Profile locks of the users picked with `?user=`.
+    user_locks: Vec<crate::lock::Lock>,
+}
+
+impl ServerState {
+    /// Open the collection under `paths`, with its deck settings and saved
+    /// sessions, to serve with `options`.
+    fn open(paths: &[String], options: &ServeOptions) -> crate::Result<ServerState> {
+        let app = Collection::open(
+            paths,
+            &OpenOptions {
+                files: options.files.clone(),
+                low_memory: options.low_memory,
+                user: options.user.clone(),
+                read_only: options.read_only,
+                data_dir: options.data_dir.clone(),
+                git_friendly: options.git_friendly,
+            },
+        )?;
+        let decks = deck::load(app.dir()).unwrap_or_else(|e| {
+            tracing::warn!("{e}");
+            HashMap::new()
+        });
+        let sessions = sessions::load(app.data_dir(), &app.cards, clock::today());
+        Ok(ServerState {
+            app,
+            sessions,
+            retention: options.retention,
+            new_per_day: options.new_per_day,
+            order: options.order,
+            read_only: options.read_only,
+            auto_reveal: options.auto_reveal,
+            breaks: options.breaks,
+            decks,
+            // A public copy notifies no one.
+            webhooks: if options.read_only {
+                Vec::new()
+            } else {
+                options.webhooks.clone()
+            },
+            #[cfg(feature = "llm")]
+            llm: options.llm.clone(),
+            user: options.user.clone(),
+            default_user: options.user.clone(),
+            parked: HashMap::new(),
+            user_locks: Vec::new(),
+        })
+    }
+
+    /// Desired retention for cards in `deck`: its setting, or the server's.
+    fn retention_for(&self, deck: &str) -> f64 {
+        deck::setting(&self.decks, deck, |c| c.desired_retention).unwrap_or(self.retention)
+    }
+
+    fn scheduler_for(&self, deck: &str) -> &'static dyn Scheduler {
+        deck::setting(&self.decks, deck, |c| c.scheduler)
+            .unwrap_or_default()
+            .scheduler()
+    }
+}
+
+/// The state every request shares. Locking it also swaps in the scheduling
+/// of the user the request picked (see `users`).
+#[derive(Clone)]
+struct SharedState(Arc<Mutex<ServerState>>);
+
+impl SharedState {
+    fn new(st: ServerState) -> SharedState {
+        SharedState(Arc::new(Mutex::new(st)))
+    }
+
+    async fn lock(&self) -> tokio::sync::MutexGuard<'_, ServerState> {
+        let mut st = self.0.lock().await;
+        if let Ok(user) = users::USER.try_with(Clone::clone)
+            && let Err(e) = st.switch_user(&user)
+        {
+            tracing::error!("{e}");
+        }
+        st
+    }
 }
 
-type SharedState = Arc<Mutex<ServerState>>;
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
 
 // -- HTML helpers --
 
+/// `url` HTML-escaped, or empty on a read-only server, for the data
+/// attributes keyboard shortcuts post to.
+fn writable_url(st: &ServerState, url: String) -> String {
+    if st.read_only {
+        String::new()
+    } else {
+        html_escape(&url)
+    }
+}
+
 fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -47,37 +232,201 @@ fn html_escape(s: &str) -> String {
         .replace('"', "&quot;")
 }
 
+/// Encode a value as a JavaScript literal safe to embed in a `<script>` tag.
+fn js_value<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value)
+        .unwrap_or_else(|_| "null".into())
+        .replace('<', "\\u003c")
+}
+
+fn tag_chips(tags: &[String]) -> String {
+    let mut chips = String::new();
+    for tag in tags {
+        chips.push_str(&format!(
+            r#"<span class="text-[0.65rem] px-1.5 py-0.5 rounded bg-[#383838] text-[#aaa]">#{}</span>"#,
+            html_escape(tag)
+        ));
+    }
+    chips
+}
+
+/// One-line legend of the card browser's shortcuts.
+fn browser_hint() -> String {
+    let site = site::current();
+    let keys = &site.keymap;
+    let kbd = |k: &str| {
+        format!(
+            r#"<span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa]">{}</span>"#,
+            html_escape(k)
+        )
+    };
+    [
+        (format!("{}/{}", keys.next, keys.prev), "move"),
+        (keys.open.clone(), "edit"),
+        (keys.suspend.clone(), "suspend"),
+        (keys.flag.clone(), "flag"),
+        (keys.delete.clone(), "delete"),
+    ]
+    .iter()
+    .map(|(k, action)| format!("{} {action}", kbd(k)))
+    .collect::<Vec<_>>()
+    .join(" · ")
+}
+
+const BODY_CLS: &str = "bg-[#1e1e1e] text-[#d4d4d4] font-sans antialiased h-screen";
+
 fn page(title: &str, body: &str) -> String {
+    let site = site::current();
+    let (styles, math) = if site.cdn {
+        (
+            r#"<script src="https://cdn.tailwindcss.com"></script>
+<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/katex@0.16.21/dist/katex.min.css">"#
+                .to_string(),
+            r#"<script src="https://cdn.jsdelivr.net/npm/katex@0.16.21/dist/katex.min.js"></script>
+<script src="https://cdn.jsdelivr.net/npm/katex@0.16.21/dist/contrib/auto-render.min.js"></script>"#
+                .to_string(),
+        )
+    } else {
+        // Review swaps in cards whose classes may not be on the first one.
+        let css = css::stylesheet(body, &[BODY_CLS, ANSWER_CLS, RULE_CLS]);
+        (
+            format!("<style>{css}</style>"),
+            format!(
+                r#"<script src="{}"></script>
+<script>{MATH_JS}</script>"#,
+                html_escape(&urls::katex_js())
+            ),
+        )
+    };
     format!(
         r#"<!DOCTYPE html>
-<html lang="en">
+<html lang="en" class="{theme}">
 <head>
 <meta charset="utf-8">
 <meta name="viewport" content="width=device-width, initial-scale=1">
 <title>{title} — rote</title>
+<link rel="manifest" href="{manifest}">
+<link rel="icon" href="{icon}">
+<meta name="theme-color" content="{theme_color}">
 <style>body{{background:#1e1e1e;color:#d4d4d4}}</style>
-<script src="https://cdn.tailwindcss.com"></script>
-<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/katex@0.16.21/dist/katex.min.css">
+{styles}
 <style>{base_css}</style>
 </head>
-<body class="bg-[#1e1e1e] text-[#d4d4d4] font-sans antialiased h-screen">
+<body class="{body_cls}">
 {body}
-<script>{js}</script>
-<script src="https://cdn.jsdelivr.net/npm/katex@0.16.21/dist/katex.min.js"></script>
-<script src="https://cdn.jsdelivr.net/npm/katex@0.16.21/dist/contrib/auto-render.min.js"></script>
+<script>window.ROTE_SW={sw};{keys}{offline_js}{keymap_js}{review_js}{browser_js}{editor_js}{occlusion_js}</script>
+{math}
 <script>document.addEventListener("DOMContentLoaded",function(){{renderMathInElement(document.body,{{delimiters:[{{left:"$$",right:"$$",display:true}},{{left:"$",right:"$",display:false}}],throwOnError:false}});}});</script>
 </body>
 </html>"#,
         title = html_escape(title),
+        styles = styles,
+        body_cls = BODY_CLS,
         body = body,
+        math = math,
         base_css = BASE_CSS,
-        js = REVIEW_JS,
+        theme = match site.theme {
+            Theme::Dark => "",
+            Theme::Light => "theme-light",
+        },
+        manifest = html_escape(&urls::manifest()),
+        icon = html_escape(&urls::icon()),
+        theme_color = THEME_COLOR,
+        sw = js_value(&urls::service_worker()),
+        keys = keymap::script(&site.keymap),
+        offline_js = OFFLINE_JS,
+        keymap_js = KEYMAP_JS,
+        review_js = REVIEW_JS,
+        browser_js = BROWSER_JS,
+        editor_js = EDITOR_JS,
+        occlusion_js = OCCLUSION_JS,
+    )
+}
+
+fn sidebar_html(st: &ServerState, summaries: &[review::DeckSummary], active_deck: &str) -> String {
+    let items = deck_tree_html(summaries, &mut 0, 0, active_deck);
+    let undo = match journal::last(st.app.data_dir()).filter(|_| !st.read_only) {
+        Some(entry) => format!(
+            r#"<form method="post" action="{action}" class="px-4 pt-3"><button type="submit" title="{title}" class="w-full text-left text-xs text-[#888] cursor-pointer hover:!text-[#d4d4d4]">↶ Undo {label}</button></form>"#,
+            action = html_escape(&urls::undo()),
+            title = html_escape(&entry.at.format("%Y-%m-%d %H:%M").to_string()),
+            label = html_escape(&entry.describe()),
+        ),
+        None => String::new(),
+    };
+    format!(
+        r#"<div class="w-56 shrink-0 bg-[#252525] border-r border-[#333] py-5 overflow-y-auto flex flex-col">
+<div class="px-4 pb-4 text-[0.95rem] font-semibold text-[#e0e0e0]"><a href="{home}" class="!text-inherit no-underline">rote</a>{user}</div>
+<div class="px-4 py-2 pb-1 text-[0.65rem] uppercase tracking-widest text-[#666]">Decks</div>
+<nav><ul class="list-none m-0 p-0">{items}</ul></nav>
+<div class="flex-1"></div>
+{import}{generate}
+<a href="{stats}" class="px-4 py-1.5 text-sm text-[#999] no-underline hover:!text-[#d4d4d4]">Stats</a>
+<a href="{history}" class="px-4 py-1.5 text-sm text-[#999] no-underline hover:!text-[#d4d4d4]">History</a>
+{trash}{undo}
+</div>"#,
+        home = html_escape(&urls::home()),
+        import = if st.read_only {
+            String::new()
+        } else {
+            format!(
+                r#"<a href="{}" class="px-4 py-1.5 text-sm text-[#999] no-underline hover:!text-[#d4d4d4]">Import</a>"#,
+                html_escape(&urls::import())
+            )
+        },
+        generate = generate_link(st),
+        stats = html_escape(&urls::stats(false)),
+        history = html_escape(&urls::history(None)),
+        trash = if st.read_only {
+            String::new()
+        } else {
+            format!(
+                r#"<a href="{}" class="px-4 py-1.5 text-sm text-[#999] no-underline hover:!text-[#d4d4d4]">Trash</a>"#,
+                html_escape(&urls::trash())
+            )
+        },
+        user = st.app.profile.as_ref().map_or(String::new(), |p| format!(
+            r#" <span class="text-xs font-normal text-[#888]">· {}</span>"#,
+            html_escape(p.name())
+        )),
+    )
+}
+
+/// The sidebar link to the Generate page, when an endpoint is set up.
+#[cfg(feature = "llm")]
+fn generate_link(st: &ServerState) -> String {
+    if st.read_only || st.llm.is_none() {
+        return String::new();
+    }
+    format!(
+        r#"<a href="{}" class="px-4 py-1.5 text-sm text-[#999] no-underline hover:!text-[#d4d4d4]">Generate</a>"#,
+        html_escape(&urls::generate())
     )
 }
 
-fn sidebar_html(summaries: &[review::DeckSummary], active_deck: &str) -> String {
+#[cfg(not(feature = "llm"))]
+fn generate_link(_st: &ServerState) -> String {
+    String::new()
+}
+
+/// Sidebar entries for the decks at `depth` from `summaries[*next]` on, each
+/// parent a collapsible `<details>` holding its children. The active deck and
+/// its parents start open.
+fn deck_tree_html(
+    summaries: &[review::DeckSummary],
+    next: &mut usize,
+    depth: usize,
+    active_deck: &str,
+) -> String {
     let mut items = String::new();
-    for s in summaries {
+    while let Some(s) = summaries.get(*next).filter(|s| s.depth == depth) {
+        *next += 1;
+        let children = match summaries.get(*next) {
+            Some(child) if child.depth > depth => {
+                deck_tree_html(summaries, next, depth + 1, active_deck)
+            }
+            _ => String::new(),
+        };
         let active = if s.name == active_deck {
             " bg-[#333] !text-[#e0e0e0]"
         } else {
@@ -91,21 +440,92 @@ fn sidebar_html(summaries: &[review::DeckSummary], active_deck: &str) -> String
         } else {
             String::new()
         };
-        items.push_str(&format!(
-            r#"<li><a href="/deck/{name}" class="flex items-center justify-between px-4 py-1.5 text-[#999] text-sm no-underline hover:bg-[#2a2a2a] hover:!text-[#d4d4d4]{active}">{name}{badge}</a></li>"#,
-            name = html_escape(&s.name),
-        ));
+        let link = format!(
+            r#"<a href="{href}" title="{title}" class="flex-1 flex items-center justify-between pr-4 py-1.5 text-[#999] text-sm no-underline hover:bg-[#2a2a2a] hover:!text-[#d4d4d4]{active}">{name}{badge}</a>"#,
+            href = html_escape(&urls::deck(&s.name)),
+            title = html_escape(&s.name),
+            name = html_escape(deck::leaf(&s.name)),
+        );
+        let indent = format!("padding-left:{}rem", 0.5 + 0.75 * depth as f64);
+        if children.is_empty() {
+            items.push_str(&format!(
+                r#"<li class="flex" style="{indent}"><span class="w-4 shrink-0"></span>{link}</li>"#
+            ));
+        } else {
+            items.push_str(&format!(
+                r#"<li><details{open}><summary class="flex items-center cursor-pointer" style="{indent}"><span class="deck-caret w-4 shrink-0 text-[0.6rem] text-[#666]"></span>{link}</summary><ul class="list-none m-0 p-0">{children}</ul></details></li>"#,
+                open = if deck::contains(&s.name, active_deck) {
+                    " open"
+                } else {
+                    ""
+                },
+            ));
+        }
+    }
+    items
+}
+
+fn media_html(card: &Card) -> String {
+    let mut out = String::new();
+    for (file, masks) in occlusion::media(card) {
+        let src = html_escape(&urls::card_media(&card.id, file));
+        let tag = match media_type(file) {
+            Some(t) if t.starts_with("image/") && !masks.is_empty() => {
+                format!(
+                    r#"<div class="occluded"><img src="{src}" alt="" class="max-h-72 max-w-full rounded-md">{}</div>"#,
+                    masks_html(&masks)
+                )
+            }
+            Some(t) if t.starts_with("image/") => {
+                format!(r#"<img src="{src}" alt="" class="max-h-72 max-w-full rounded-md">"#)
+            }
+            Some(t) if t.starts_with("audio/") => {
+                format!(r#"<audio src="{src}" controls preload="none"></audio>"#)
+            }
+            _ => continue,
+        };
+        out.push_str(&tag);
+    }
+    if out.is_empty() {
+        return out;
     }
     format!(
-        r#"<div class="w-56 shrink-0 bg-[#252525] border-r border-[#333] py-5 overflow-y-auto flex flex-col">
-<div class="px-4 pb-4 text-[0.95rem] font-semibold text-[#e0e0e0]"><a href="/" class="!text-inherit no-underline">rote</a></div>
-<div class="px-4 py-2 pb-1 text-[0.65rem] uppercase tracking-widest text-[#666]">Decks</div>
-<nav><ul class="list-none m-0 p-0">{items}</ul></nav>
-<div class="flex-1"></div>
-</div>"#,
+        r#"<div class="flex flex-wrap items-center justify-center gap-3 px-8 pb-8">{out}</div>"#
     )
 }
 
+/// Boxes over an image, hidden parts first shown covered (see `occlusion`).
+fn masks_html(masks: &[occlusion::Mask]) -> String {
+    masks
+        .iter()
+        .map(|m| {
+            format!(
+                r#"<div data-mask style="left:{}%;top:{}%;width:{}%;height:{}%"></div>"#,
+                m.x, m.y, m.w, m.h
+            )
+        })
+        .collect()
+}
+
+fn media_type(file: &str) -> Option<&'static str> {
+    let ext = std::path::Path::new(file)
+        .extension()?
+        .to_str()?
+        .to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "ogg" | "oga" => "audio/ogg",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        _ => return None,
+    })
+}
+
 fn breadcrumb(crumbs: &[(&str, &str)]) -> String {
     let mut parts = String::new();
     for (i, (label, href)) in crumbs.iter().enumerate() {
@@ -133,6 +553,35 @@ fn btn_primary(href: &str, label: &str) -> String {
     )
 }
 
+/// Session sizes offered next to a review button, for chipping away at a
+/// backlog in short sittings.
+const SESSION_SIZES: [usize; 3] = [10, 20, 50];
+
+/// A button starting a review of everything `due` in `name`, followed by
+/// ones stopping after each smaller session size; the cards left out stay
+/// due.
+fn review_start_html(name: &str, label: &str, due: usize) -> String {
+    let size_cls = "inline-flex items-center px-2.5 py-2 rounded-md text-sm font-medium bg-[#383838] text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444] hover:!text-[#e0e0e0]";
+    let sizes: String = SESSION_SIZES
+        .iter()
+        .filter(|&&n| n < due)
+        .map(|n| {
+            format!(
+                r#"<button type="submit" name="limit" value="{n}" title="Review {n} now" class="{size_cls}">{n}</button>"#
+            )
+        })
+        .collect();
+    let sizes = if sizes.is_empty() {
+        sizes
+    } else {
+        format!(r#"<span class="text-xs text-[#888] ml-1">or just</span>{sizes}"#)
+    };
+    format!(
+        r#"<form method="get" action="{action}" class="inline-flex items-center gap-1.5"><button type="submit" class="inline-flex items-center gap-1.5 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">{label}</button>{sizes}</form>"#,
+        action = html_escape(&urls::deck_review(name)),
+    )
+}
+
 fn btn_secondary(href: &str, label: &str) -> String {
     format!(
         r#"<a href="{}" class="inline-flex items-center gap-1.5 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">{}</a>"#,
@@ -145,17 +594,32 @@ fn btn_secondary(href: &str, label: &str) -> String {
 
 async fn index(State(state): State<SharedState>) -> Html<String> {
     let st = state.lock().await;
-    let today = Local::now().date_naive();
+    let today = clock::today();
     let summaries = review::deck_summaries(&st.app.cards, today);
 
-    let sidebar = sidebar_html(&summaries, "");
+    let sidebar = sidebar_html(&st, &summaries, "");
+    let unavailable = deck::unavailable(&st.decks, clock::now());
 
-    let total_due: usize = summaries.iter().map(|s| s.due).sum();
-    let review_all = if total_due > 0 {
-        btn_primary("/deck/_all/review", &format!("Review all {total_due} due"))
+    let total_due = st
+        .app
+        .cards
+        .iter()
+        .filter(|c| review::is_due(c, today))
+        .filter(|c| !unavailable.iter().any(|d| deck::contains(d, &c.deck)))
+        .count();
+    let mut review_all = if total_due > 0 {
+        review_start_html(
+            urls::ALL_DECKS,
+            &format!("Review all {total_due} due"),
+            total_due,
+        )
     } else {
         String::new()
     };
+    review_all.push_str(&btn_secondary(
+        &urls::custom_session(urls::ALL_DECKS),
+        "Custom session",
+    ));
 
     let mut rows = String::new();
     if summaries.is_empty() {
@@ -171,9 +635,18 @@ async fn index(State(state): State<SharedState>) -> Html<String> {
             } else {
                 String::new()
             };
+            let off_label = if unavailable.iter().any(|d| deck::contains(d, &s.name)) {
+                r#"<span class="text-[#666]" title="Outside its availability window; left out of Review all">off hours</span>"#
+            } else {
+                ""
+            };
             rows.push_str(&format!(
-                r#"<a href="/deck/{name}" class="flex justify-between items-center py-2.5 px-3 bg-[#2a2a2a] rounded-md !text-[#d4d4d4] text-[0.9rem] no-underline hover:bg-[#333]">{name}<span class="flex items-center gap-3 text-sm text-[#888]">{total} cards{due}</span></a>"#,
-                name = html_escape(&s.name),
+                r#"<a href="{href}" style="margin-left:{indent}rem" class="flex justify-between items-center py-2.5 px-3 bg-[#2a2a2a] rounded-md !text-[#d4d4d4] text-[0.9rem] no-underline hover:bg-[#333]">{name}<span class="flex items-center gap-3 text-sm text-[#888]">{off}<span class="w-24">{bar}</span>{total} cards{due}</span></a>"#,
+                off = off_label,
+                bar = maturity_bar(&s.maturity),
+                href = html_escape(&urls::deck(&s.name)),
+                indent = 1.5 * s.depth as f64,
+                name = html_escape(deck::leaf(&s.name)),
                 total = s.total,
                 due = due_label,
             ));
@@ -181,6 +654,30 @@ async fn index(State(state): State<SharedState>) -> Html<String> {
         rows.push_str("</div>");
     }
 
+    // Tag shortcuts: review everything due under a tag, across decks.
+    let mut tag_due: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+    for c in &st.app.cards {
+        let is_due = review::is_due(c, today);
+        for tag in &c.tags {
+            *tag_due.entry(tag.to_lowercase()).or_default() += usize::from(is_due);
+        }
+    }
+    if !tag_due.is_empty() {
+        rows.push_str(r#"<div class="mt-8 mb-2 text-[0.65rem] uppercase tracking-widest text-[#666]">Tags</div><div class="flex flex-wrap gap-2">"#);
+        for (tag, due) in &tag_due {
+            let label = format!("#{tag} · {due} due");
+            if *due > 0 {
+                rows.push_str(&btn_secondary(&urls::tag_review(tag), &html_escape(&label)));
+            } else {
+                rows.push_str(&format!(
+                    r#"<span class="inline-flex items-center px-3.5 py-2 rounded-md text-sm text-[#666] border border-[#333]">{}</span>"#,
+                    html_escape(&label)
+                ));
+            }
+        }
+        rows.push_str("</div>");
+    }
+
     let body = format!(
         r#"<div class="flex h-screen">
 {sidebar}
@@ -189,89 +686,249 @@ async fn index(State(state): State<SharedState>) -> Html<String> {
 <div class="text-sm text-[#888]">{bc}</div>
 <div class="flex gap-2 items-center">{review_all}</div>
 </div>
-<div class="p-6 max-w-5xl">{rows}</div>
+<div class="p-6 max-w-5xl">{rows}{heatmap}</div>
 </div>
 </div>"#,
         sidebar = sidebar,
         bc = breadcrumb(&[("Decks", "")]),
         review_all = review_all,
         rows = rows,
+        heatmap = heatmap_html(&revlog::load(st.app.data_dir()), today),
     );
     Html(page("Decks", &body))
 }
 
-async fn deck_detail(State(state): State<SharedState>, Path(name): Path<String>) -> Html<String> {
+/// Colours of `review::Maturity::ALL`, least learned first.
+const MATURITY_COLORS: [&str; 4] = ["#555", "#d4a05a", "#6ba3d6", "#6bc06b"];
+
+/// A stacked bar of a deck's cards by maturity.
+fn maturity_bar(counts: &[usize; 4]) -> String {
+    let total: usize = counts.iter().sum();
+    let segments: String = review::Maturity::ALL
+        .iter()
+        .zip(counts)
+        .zip(MATURITY_COLORS)
+        .filter(|((_, n), _)| **n > 0)
+        .map(|((m, n), color)| {
+            format!(
+                r#"<span style="width:{width:.2}%;background:{color}" title="{label}: {n}"></span>"#,
+                width = *n as f64 * 100.0 / total as f64,
+                label = m.label(),
+            )
+        })
+        .collect();
+    format!(r#"<span class="flex h-1.5 rounded-sm overflow-hidden bg-[#333]">{segments}</span>"#)
+}
+
+/// `maturity_bar` with the count of each kind under it.
+fn maturity_html(counts: &[usize; 4]) -> String {
+    let legend: String = review::Maturity::ALL
+        .iter()
+        .zip(counts)
+        .zip(MATURITY_COLORS)
+        .map(|((m, n), color)| {
+            format!(
+                r#"<span class="inline-flex items-center gap-1.5"><span class="w-2 h-2 rounded-sm" style="background:{color}"></span>{label} {n}</span>"#,
+                label = m.label(),
+            )
+        })
+        .collect();
+    format!(
+        r#"<div class="mb-6 max-w-md">{bar}<div class="flex gap-4 mt-2 text-xs text-[#888]">{legend}</div></div>"#,
+        bar = maturity_bar(counts),
+    )
+}
+
+/// Contributions-style grid of reviews per day over the past year.
+fn heatmap_html(reviews: &[revlog::Review], today: chrono::NaiveDate) -> String {
+    const SHADES: [&str; 5] = ["#2a2a2a", "#1f3a52", "#2b5c85", "#4a90d9", "#8cc4f5"];
+    let days = stats::daily_reviews(reviews, today, 365);
+    let max = days.iter().map(|&(_, n)| n).max().unwrap_or(0);
+    let total: usize = days.iter().map(|&(_, n)| n).sum();
+
+    let mut columns = String::new();
+    for week in stats::weeks(&days) {
+        columns.push_str(r#"<div class="flex flex-col gap-[3px]">"#);
+        for day in week {
+            match day {
+                Some((date, n)) => columns.push_str(&format!(
+                    r#"<div class="w-[10px] h-[10px] rounded-sm" style="background:{shade}" title="{date}: {n} review{s}"></div>"#,
+                    shade = SHADES[stats::level(n, max)],
+                    s = if n == 1 { "" } else { "s" },
+                )),
+                None => columns.push_str(r#"<div class="w-[10px] h-[10px]"></div>"#),
+            }
+        }
+        columns.push_str("</div>");
+    }
+
+    format!(
+        r#"<div class="mt-8 mb-2 text-[0.65rem] uppercase tracking-widest text-[#666]">Activity · {total} reviews in the past year</div>
+<div class="flex gap-[3px] overflow-x-auto pb-2">{columns}</div>"#
+    )
+}
+
+/// How far ahead the deck page's "Review ahead" reaches, e.g. before a
+/// week offline.
+const REVIEW_AHEAD_DAYS: u32 = 7;
+
+/// Page sizes the card browser offers.
+const PER_PAGE: [usize; 4] = [50, 100, 250, 500];
+const DEFAULT_PER_PAGE: usize = 100;
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct DeckQuery {
+    /// From 1.
+    page: Option<usize>,
+    per_page: Option<usize>,
+    /// Show the page holding this card.
+    card: Option<String>,
+    /// `newest` for the most recently added cards first.
+    sort: Option<String>,
+}
+
+async fn deck_detail(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<DeckQuery>,
+) -> Html<String> {
     let st = state.lock().await;
-    let today = Local::now().date_naive();
+    let today = clock::today();
     let summaries = review::deck_summaries(&st.app.cards, today);
 
-    let sidebar = sidebar_html(&summaries, &name);
+    let sidebar = sidebar_html(&st, &summaries, &name);
 
-    let deck_cards: Vec<(usize, &Card)> = st
+    let mut deck_cards: Vec<(usize, &Card)> = st
         .app
         .cards
         .iter()
         .enumerate()
-        .filter(|(_, c)| c.deck == name)
+        .filter(|(_, c)| deck::contains(&name, &c.deck))
         .collect();
 
+    let buried = bury::load(st.app.data_dir(), today);
     let due_count = deck_cards
         .iter()
-        .filter(|(_, c)| c.due.is_none() || c.due.unwrap() <= today)
+        .filter(|(_, c)| review::is_due(c, today) && !buried.contains(&c.id))
+        .count();
+    let ahead_count = deck_cards
+        .iter()
+        .filter(|(_, c)| !c.suspended)
+        .filter(|(_, c)| {
+            c.due.is_some_and(|d| {
+                d > today && d <= today + chrono::Days::new(REVIEW_AHEAD_DAYS.into())
+            })
+        })
         .count();
 
     let mut header_actions = String::new();
     if due_count > 0 {
-        header_actions.push_str(&btn_primary(
-            &format!("/deck/{}/review", html_escape(&name)),
+        header_actions.push_str(&review_start_html(
+            &name,
             &format!("Review {due_count} due"),
+            due_count,
+        ));
+        header_actions.push_str(&btn_secondary(&urls::quiz(&name), "Quiz"));
+        header_actions.push_str(&btn_secondary(&urls::deck_queue(&name), "Queue"));
+    } else if ahead_count > 0 {
+        header_actions.push_str(&btn_secondary(
+            &urls::review_ahead(&name, REVIEW_AHEAD_DAYS),
+            &format!("Review {ahead_count} ahead"),
         ));
     }
-    header_actions.push_str(&btn_secondary(
-        &format!("/deck/{}/new", html_escape(&name)),
-        "Add card",
-    ));
+    if !deck_cards.is_empty() {
+        header_actions.push_str(&btn_secondary(&urls::practice(&name), "Practice"));
+    }
+    header_actions.push_str(&btn_secondary(&urls::custom_session(&name), "Custom"));
+    if !st.read_only {
+        header_actions.push_str(&btn_secondary(&urls::card_new(&name), "Add card"));
+        header_actions.push_str(&btn_secondary(&urls::deck_settings(&name), "Settings"));
+    }
+
+    // Cards stay in file order, so a page holds the same cards until some
+    // are added or deleted. Sorting newest first puts cards from before the
+    // `created` column last.
+    let newest = query.sort.as_deref() == Some("newest");
+    if newest {
+        deck_cards.sort_by_key(|(_, c)| std::cmp::Reverse(c.created));
+    }
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(PER_PAGE[0], PER_PAGE[PER_PAGE.len() - 1]);
+    let pages = deck_cards.len().div_ceil(per_page).max(1);
+    let current = match &query.card {
+        Some(id) => deck_cards
+            .iter()
+            .position(|(_, c)| &c.id == id)
+            .map_or(1, |n| n / per_page + 1),
+        None => query.page.unwrap_or(1).clamp(1, pages),
+    };
+    let here = urls::deck_page(&name, current, per_page, newest);
 
     let mut tiles = String::new();
-    for (_, c) in &deck_cards {
-        let front_trunc = if c.front.len() > 80 {
-            format!("{}…", &c.front[..80])
-        } else {
-            c.front.clone()
-        };
-        let back_trunc = if c.back.len() > 60 {
-            format!("{}…", &c.back[..60])
-        } else {
-            c.back.clone()
-        };
-        let status = if c.due.is_none() {
-            r#"<span class="text-[#888]">NEW</span>"#.to_string()
-        } else if c.due.unwrap() <= today {
-            r#"<span class="text-[#6ba3d6]">DUE</span>"#.to_string()
-        } else {
-            format!(
+    for &(i, _) in deck_cards
+        .iter()
+        .skip((current - 1) * per_page)
+        .take(per_page)
+    {
+        let c = full_card(&st.app, i);
+        let front_trunc = truncate(&card::expand_escapes(&c.front), 80);
+        let back_trunc = truncate(&c.back, 60);
+        let mut status = match c.due {
+            _ if c.suspended => r#"<span class="text-[#888]">SUSPENDED</span>"#.to_string(),
+            None => r#"<span class="text-[#888]">NEW</span>"#.to_string(),
+            Some(due) if due <= today => r#"<span class="text-[#6ba3d6]">DUE</span>"#.to_string(),
+            Some(due) => format!(
                 r#"<span class="text-[#666]">{}</span>"#,
-                c.due.unwrap().format("%b %d")
-            )
+                due.format("%b %d")
+            ),
         };
+        if c.flagged {
+            status.push_str(r#"<span class="text-[#d4a05a] ml-auto">⚑ Flagged</span>"#);
+        }
         tiles.push_str(&format!(
-            r#"<a href="/card/{id}/edit" class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-lg p-5 min-h-40 flex flex-col justify-between no-underline hover:border-[#555] transition-colors">
-<div class="text-[0.9rem] font-medium text-[#e0e0e0] text-center flex-1 flex items-center justify-center overflow-hidden break-words">{front}</div>
+            r#"<a href="{href}" id="card-{id}" data-card data-suspend="{suspend}" data-flag="{flag}" data-delete="{delete}" class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-lg p-5 min-h-40 flex flex-col justify-between no-underline hover:border-[#555] transition-colors{dim}">
+<div class="md text-[0.9rem] font-medium text-[#e0e0e0] text-center flex-1 flex flex-col items-center justify-center overflow-hidden break-words">{front}</div>
 <div class="text-xs text-[#888] text-center mt-3 overflow-hidden text-ellipsis whitespace-nowrap">{back}</div>
+<div class="flex flex-wrap justify-center gap-1 mt-2">{tags}</div>
 <div class="flex items-center gap-1 text-[0.65rem] mt-3 uppercase tracking-wider">{status}</div>
 </a>"#,
+            href = html_escape(&match current {
+                _ if st.read_only => urls::card_info(&c.id),
+                1 => urls::card_edit(&c.id),
+                _ => urls::card_edit_from(&c.id, &here),
+            }),
             id = html_escape(&c.id),
-            front = html_escape(&front_trunc),
-            back = html_escape(&back_trunc),
+            suspend = writable_url(&st, urls::card_suspend(&c.id)),
+            flag = writable_url(&st, urls::card_flag(&c.id)),
+            delete = writable_url(&st, urls::card_delete(&c.id)),
+            dim = if c.suspended { " opacity-50" } else { "" },
+            front = markdown::to_html(&front_trunc),
+            back = markdown::inline(&back_trunc),
+            tags = tag_chips(&c.tags),
             status = status,
         ));
     }
 
     // "Add card" tile
-    tiles.push_str(&format!(
-        r#"<a href="/deck/{name}/new" class="bg-transparent border border-dashed border-[#444] rounded-lg p-5 min-h-40 flex items-center justify-center text-[#666] text-sm no-underline cursor-pointer hover:border-[#666] hover:!text-[#999]">+ Add card</a>"#,
-        name = html_escape(&name),
-    ));
+    if !st.read_only {
+        tiles.push_str(&format!(
+        r#"<a href="{href}" class="bg-transparent border border-dashed border-[#444] rounded-lg p-5 min-h-40 flex items-center justify-center text-[#666] text-sm no-underline cursor-pointer hover:border-[#666] hover:!text-[#999]">+ Add card</a>"#,
+        href = html_escape(&urls::card_new(&name)),
+        ));
+    }
+
+    // Each parent of a nested deck links to its own page.
+    let home = urls::home();
+    let parents: Vec<(&str, String)> = name
+        .match_indices(deck::SEPARATOR)
+        .map(|(end, _)| (deck::leaf(&name[..end]), urls::deck(&name[..end])))
+        .collect();
+    let mut crumbs = vec![("Decks", home.as_str())];
+    crumbs.extend(parents.iter().map(|(label, href)| (*label, href.as_str())));
+    crumbs.push((deck::leaf(&name), ""));
 
     let body = format!(
         r#"<div class="flex h-screen">
@@ -281,122 +938,366 @@ async fn deck_detail(State(state): State<SharedState>, Path(name): Path<String>)
 <div class="text-sm text-[#888]">{bc}</div>
 <div class="flex gap-2 items-center">{actions}</div>
 </div>
-<div class="p-6 max-w-5xl">
-<div class="grid grid-cols-[repeat(auto-fill,minmax(220px,1fr))] gap-4">{tiles}</div>
-</div>
+<div class="p-6 max-w-5xl">{maturity}{description}{sort}
+<div id="card-browser" data-review="{review_url}" data-new="{new_url}" class="grid grid-cols-[repeat(auto-fill,minmax(220px,1fr))] gap-4">{tiles}</div>
+{pager}<p class="mt-6 text-xs text-[#666]">{hint}</p>
 </div>
 </div>
-<script>document.addEventListener('keydown',function(e){{if(e.target.tagName==='INPUT'||e.target.tagName==='TEXTAREA')return;if(e.key==='r'){{var a=document.querySelector('[href*="/review"]');if(a)window.location=a.href;}}else if(e.key==='n'){{window.location='/deck/{name_enc}/new';}}}});</script>"#,
+</div>"#,
         sidebar = sidebar,
-        bc = breadcrumb(&[("Decks", "/"), (&name, "")]),
+        bc = breadcrumb(&crumbs),
         actions = header_actions,
         tiles = tiles,
-        name_enc = html_escape(&name),
+        review_url = if due_count > 0 {
+            html_escape(&urls::deck_review(&name))
+        } else {
+            String::new()
+        },
+        new_url = writable_url(&st, urls::card_new(&name)),
+        maturity = summaries
+            .iter()
+            .find(|s| s.name == name)
+            .map_or(String::new(), |s| maturity_html(&s.maturity)),
+        description = st
+            .decks
+            .get(&name)
+            .and_then(|c| c.description.as_deref())
+            .map_or(String::new(), |text| format!(
+                r#"<div class="md bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl px-6 py-4 mb-6 text-[0.9rem] text-[#ccc] leading-relaxed">{}</div>"#,
+                markdown::to_html(text)
+            )),
+        sort = if deck_cards.len() > 1 {
+            sort_html(&name, per_page, newest)
+        } else {
+            String::new()
+        },
+        pager = pager_html(&name, current, per_page, newest, deck_cards.len()),
+        hint = browser_hint(),
     );
     Html(page(&name, &body))
 }
 
-async fn review_page(
-    State(state): State<SharedState>,
-    Path(name): Path<String>,
-    Form(params): Form<HashMap<String, String>>,
-) -> axum::response::Response {
-    let mut st = state.lock().await;
-    let today = Local::now().date_naive();
-
-    let session_id = params.get("session").cloned().unwrap_or_default();
-
-    // If no valid session, create one
-    if session_id.is_empty() || !st.sessions.contains_key(&session_id) {
-        let due_indices: Vec<usize> = st
-            .app
-            .cards
-            .iter()
-            .enumerate()
-            .filter(|(_, c)| {
-                (name == "_all" || c.deck == name) && (c.due.is_none() || c.due.unwrap() <= today)
-            })
-            .map(|(i, _)| i)
-            .collect();
-
-        if due_indices.is_empty() {
-            let back = if name == "_all" {
-                "/".to_string()
+/// The card browser's order: file order, or the most recently added first.
+fn sort_html(name: &str, per_page: usize, newest: bool) -> String {
+    let options: String = [("", "File order"), ("newest", "Newest first")]
+        .iter()
+        .map(|&(value, label)| {
+            let selected = if (value == "newest") == newest {
+                " selected"
             } else {
-                format!("/deck/{}", name)
+                ""
             };
-            return Redirect::to(&back).into_response();
+            format!(r#"<option value="{value}"{selected}>{label}</option>"#)
+        })
+        .collect();
+    format!(
+        r#"<form method="get" action="{action}" class="flex justify-end mb-4"><input type="hidden" name="per_page" value="{per_page}"><select name="sort" onchange="this.form.submit()" class="px-2 py-1.5 border border-[#444] rounded-md text-sm bg-[#383838] text-[#e0e0e0]">{options}</select></form>"#,
+        action = html_escape(&urls::deck(name)),
+    )
+}
+
+/// Previous and next links and the page size picker under the card browser,
+/// for decks with more cards than the smallest page holds.
+fn pager_html(name: &str, page: usize, per_page: usize, newest: bool, total: usize) -> String {
+    if total <= PER_PAGE[0] {
+        return String::new();
+    }
+    let pages = total.div_ceil(per_page);
+    let link = |to: usize, label: &str| {
+        if to == page || to == 0 || to > pages {
+            format!(r#"<span class="px-3.5 py-2 text-[#555]">{label}</span>"#)
+        } else {
+            btn_secondary(&urls::deck_page(name, to, per_page, newest), label)
         }
+    };
+    let options: String = PER_PAGE
+        .iter()
+        .map(|&n| {
+            let selected = if n == per_page { " selected" } else { "" };
+            format!(r#"<option value="{n}"{selected}>{n} per page</option>"#)
+        })
+        .collect();
+    let first = (page - 1) * per_page + 1;
+    let last = (page * per_page).min(total);
+    format!(
+        r#"<div class="flex flex-wrap items-center gap-3 mt-6 text-sm text-[#888]">
+{prev}<span>{first}–{last} of {total}</span>{next}
+<form method="get" action="{action}" class="ml-auto">{sort}<select name="per_page" onchange="this.form.submit()" class="px-2 py-1.5 border border-[#444] rounded-md text-sm bg-[#383838] text-[#e0e0e0]">{options}</select></form>
+</div>
+"#,
+        prev = link(page - 1, "‹ Previous"),
+        next = link(page + 1, "Next ›"),
+        action = html_escape(&urls::deck(name)),
+        sort = if newest {
+            r#"<input type="hidden" name="sort" value="newest">"#
+        } else {
+            ""
+        },
+    )
+}
 
-        let mut order = due_indices;
-        shuffle(&mut order);
+const ANSWER_CLS: &str =
+    "md px-8 py-10 text-center text-lg leading-relaxed text-[#e0e0e0] whitespace-pre-wrap";
 
-        let new_id = uuid::Uuid::new_v4().to_string();
-        st.sessions.insert(
-            new_id.clone(),
-            ReviewSession {
-                order,
-                position: 0,
-                counts: [0; 4],
-            },
-        );
+/// A card's extra notes, under the answer.
+const EXTRA_CLS: &str =
+    "md px-8 pb-8 text-center text-sm leading-relaxed text-[#999] whitespace-pre-wrap";
 
-        return Redirect::to(&format!("/deck/{}/review?session={}", name, new_id)).into_response();
-    }
+/// Divider between the front and the answer.
+const RULE_CLS: &str = "border-0 border-t border-dashed border-[#444] mx-8";
 
-    let summaries = review::deck_summaries(&st.app.cards, today);
-    let sidebar = sidebar_html(&summaries, &name);
-    let session = st.sessions.get(&session_id).unwrap();
+/// The HTML fragments of a card on the review page.
+#[derive(serde::Serialize)]
+struct CardView {
+    front: String,
+    media: String,
+    /// Answer section, hidden until reveal.
+    back: String,
+    /// Time until due again after each grade, Forgot to Easy; empty when the
+    /// session leaves scheduling alone.
+    intervals: Vec<String>,
+    /// The card's info page, for cards under review.
+    info: String,
+    /// Its editor, returning to the review session once saved.
+    edit: String,
+    /// Audio played on reveal, for decks read aloud.
+    speech: Vec<String>,
+    /// Answers to pick from, in quiz sessions.
+    choices: String,
+}
 
-    if session.position >= session.order.len() {
-        return Redirect::to(&format!("/deck/{}/summary?session={}", name, session_id))
-            .into_response();
-    }
+/// A cloze card's front with the answers filled in, each in a
+/// `<span data-cloze>` so it stands out.
+fn revealed_cloze_html(front: &str) -> String {
+    let (open, close) = (markdown::CLOZE_OPEN, markdown::CLOZE_CLOSE);
+    let filled = card::fill_clozes_marked(front, &open.to_string(), &close.to_string());
+    markdown::to_html(&card::expand_escapes(&filled))
+}
 
-    let card_idx = session.order[session.position];
-    let card = &st.app.cards[card_idx];
-    let front_display = review::render_front(&card.front);
-
-    // Build back section HTML (hidden until reveal)
-    let has_cloze = !card::extract_cloze_deletions(&card.front).is_empty();
-    let back_text = card::expand_newlines(&card.back);
-    let answer_cls =
-        "px-8 py-10 text-center text-lg leading-relaxed text-[#e0e0e0] whitespace-pre-wrap";
-    let back_html = match (has_cloze, back_text.trim().is_empty()) {
-        (true, true) => {
-            let filled = card::expand_newlines(&card.front.replace(['[', ']'], ""));
-            format!(
-                r#"<hr class="border-0 border-t border-dashed border-[#444] mx-8"><div class="{cls}">{text}</div>"#,
-                cls = answer_cls,
-                text = html_escape(&filled),
-            )
-        }
-        (true, false) => {
-            let filled = card::expand_newlines(&card.front.replace(['[', ']'], ""));
-            format!(
-                r#"<hr class="border-0 border-t border-dashed border-[#444] mx-8"><div class="{cls}">{top}</div><hr class="border-0 border-t border-dashed border-[#444] mx-8"><div class="{cls}">{bot}</div>"#,
-                cls = answer_cls,
-                top = html_escape(&filled),
-                bot = html_escape(&back_text),
-            )
+/// A cloze card's front for revealing one blank at a time: each blank is a
+/// `<span data-blank>` holding its answer, hidden, and the blank as asked.
+fn blanks_html(front: &str) -> String {
+    use markdown::{BLANK_CLOSE, BLANK_MID, BLANK_OPEN};
+    let asked: String = card::cloze_parts(front)
+        .into_iter()
+        .map(|part| match part {
+            card::ClozePart::Text(text) => text,
+            card::ClozePart::Cloze { answer, hint } => format!(
+                "{BLANK_OPEN}{}{BLANK_MID}{}{BLANK_CLOSE}",
+                card::fill_clozes(&answer),
+                hint.map_or("_____".to_string(), |hint| format!("[{hint}...]"))
+            ),
+        })
+        .collect();
+    markdown::to_html(&card::expand_escapes(&asked))
+}
+
+impl CardView {
+    fn new(card: &Card) -> CardView {
+        let front_display = review::render_front(&card.front);
+        let has_cloze = !card::extract_cloze_deletions(&card.front).is_empty();
+        let back_text = card::expand_escapes(&card.back);
+        let back = match (has_cloze, back_text.trim().is_empty()) {
+            (true, true) => {
+                format!(
+                    r#"<hr class="{rule}"><div class="{cls}">{text}</div>"#,
+                    cls = ANSWER_CLS,
+                    rule = RULE_CLS,
+                    text = revealed_cloze_html(&card.front),
+                )
+            }
+            (true, false) => {
+                format!(
+                    r#"<hr class="{rule}"><div class="{cls}">{top}</div><hr class="{rule}"><div class="{cls}">{bot}</div>"#,
+                    cls = ANSWER_CLS,
+                    rule = RULE_CLS,
+                    top = revealed_cloze_html(&card.front),
+                    bot = markdown::to_html(&back_text),
+                )
+            }
+            (false, _) => {
+                format!(
+                    r#"<hr class="{rule}"><div class="{cls}">{text}</div>"#,
+                    cls = ANSWER_CLS,
+                    rule = RULE_CLS,
+                    text = markdown::to_html(&back_text),
+                )
+            }
+        };
+        let back = match card.extra.trim() {
+            "" => back,
+            extra => format!(
+                r#"{back}<div class="{cls}">{text}</div>"#,
+                cls = EXTRA_CLS,
+                text = markdown::to_html(&card::expand_escapes(extra)),
+            ),
+        };
+        CardView {
+            front: markdown::to_html(&front_display),
+            media: media_html(card),
+            back,
+            intervals: Vec::new(),
+            info: String::new(),
+            edit: String::new(),
+            speech: Vec::new(),
+            choices: String::new(),
         }
-        (false, _) => {
+    }
+
+    /// A card under review in `session_id`, with the intervals its grade
+    /// buttons show.
+    fn reviewed(st: &ServerState, card_idx: usize, name: &str, session_id: &str) -> CardView {
+        let card = full_card(&st.app, card_idx);
+        let mut view = CardView::new(&card);
+        view.info = urls::card_info(&card.id);
+        if !st.read_only {
+            view.edit = urls::card_edit_from(&card.id, &urls::review_session(name, session_id));
+        }
+        if let Some(tts) = deck::setting(&st.decks, &card.deck, |c| c.tts.clone())
+            && !st.read_only
+        {
+            view.speech = tts
+                .side
+                .sides()
+                .iter()
+                .filter(|&&side| !tts::text(&card, side).is_empty())
+                .map(|side| urls::card_speech(&card.id, side.as_str()))
+                .collect();
+        }
+        let progressive = deck::setting(&st.decks, &card.deck, |c| c.progressive_reveal);
+        if progressive == Some(true)
+            && !st.sessions[session_id].quiz
+            && card::extract_cloze_deletions(&card.front).len() > 1
+        {
+            view.front = blanks_html(&card.front);
+        }
+        if st.sessions[session_id].quiz {
+            let others = quiz::candidates(&st.app.cards, card_idx, session_id)
+                .into_iter()
+                .map(|j| full_card(&st.app, j).into_owned());
+            view.choices = choices_html(&quiz::choices(&card, others, session_id));
+        }
+        if !st.sessions[session_id].practice {
+            let today = clock::today();
+            let retention = st.retention_for(&card.deck);
+            let scheduler = st.scheduler_for(&card.deck);
+            view.intervals = review::next_intervals(&card, today, retention, scheduler)
+                .map(review::format_interval)
+                .to_vec();
+        }
+        view
+    }
+}
+
+const CHOICE_CLS: &str = "md w-full px-4 py-3 rounded-lg text-[0.95rem] text-left bg-[#333] text-[#e0e0e0] border border-[#444] cursor-pointer hover:bg-[#3a3a3a]";
+
+/// A quiz card's answers as buttons; each carries the grade picking it gives.
+fn choices_html(choices: &[quiz::Choice]) -> String {
+    if choices.is_empty() {
+        return String::new();
+    }
+    let buttons: String = choices
+        .iter()
+        .map(|c| {
             format!(
-                r#"<hr class="border-0 border-t border-dashed border-[#444] mx-8"><div class="{cls}">{text}</div>"#,
-                cls = answer_cls,
-                text = html_escape(&back_text),
+                r#"<button type="button" data-choice="{grade}" class="{cls}">{text}</button>"#,
+                grade = if c.correct { 3 } else { 1 },
+                cls = CHOICE_CLS,
+                text = markdown::to_html(&c.text),
             )
+        })
+        .collect();
+    format!(r#"<div class="flex flex-col gap-2 px-8 pb-6">{buttons}</div>"#)
+}
+
+async fn review_page(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(params): Form<HashMap<String, String>>,
+) -> axum::response::Response {
+    let mut st = state.lock().await;
+    let today = clock::today();
+
+    let session_id = params.get("session").cloned().unwrap_or_default();
+
+    // If no valid session, create one
+    if session_id.is_empty() || !st.sessions.contains_key(&session_id) {
+        let (filter, order) = session_order(&st, &name, &params, today);
+        if order.is_empty() {
+            return Redirect::to(&urls::deck_or_home(&name)).into_response();
         }
+
+        let pass_fail = params
+            .get("pass_fail")
+            .map(|v| !v.is_empty() && v != "0")
+            .or_else(|| deck::setting(&st.decks, &name, |c| c.pass_fail))
+            .unwrap_or(false);
+        let new_id = uuid::Uuid::new_v4().to_string();
+        st.sessions.insert(
+            new_id.clone(),
+            ReviewSession {
+                order,
+                position: 0,
+                counts: [0; 4],
+                practice: filter.practice,
+                quiz: params
+                    .get("quiz")
+                    .is_some_and(|v| !v.is_empty() && v != "0"),
+                pass_fail,
+                started: today,
+                started_at: chrono::Local::now(),
+            },
+        );
+        save_sessions(&st);
+
+        return Redirect::to(&urls::review_session(&name, &new_id)).into_response();
+    }
+
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(&st, &summaries, &name);
+    let session = st.sessions.get(&session_id).unwrap();
+
+    if session.position >= session.order.len() {
+        return Redirect::to(&urls::summary(&name, &session_id)).into_response();
+    }
+
+    let card_idx = session.order[session.position];
+    let view = CardView::reviewed(&st, card_idx, &name, &session_id);
+    let interval = |i: usize| {
+        view.intervals
+            .get(i)
+            .map_or(String::new(), |t| format!("· {t}"))
     };
 
     let position = session.position + 1;
     let total = session.order.len();
 
-    let deck_display = if name == "_all" { "All decks" } else { &name };
-    let deck_href = if name == "_all" {
-        "/".to_string()
+    let deck_display = if name == urls::ALL_DECKS {
+        "All decks"
+    } else {
+        &name
+    };
+
+    // Pass/fail sessions get two big buttons instead of four.
+    let pass_fail = session.pass_fail;
+    let grade_cls = if pass_fail {
+        "px-10 py-3 text-base"
+    } else {
+        "px-5 py-2 text-sm"
+    };
+    let (hard, easy) = if pass_fail {
+        (String::new(), String::new())
     } else {
-        format!("/deck/{}", name)
+        (
+            format!(
+                r#"<button type="submit" onclick="this.form.grade.value='2'" class="inline-flex items-center gap-1 px-5 py-2 rounded-md text-sm font-medium cursor-pointer bg-[#333] text-[#d4a05a] hover:bg-[#3d3425]"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">2</span> Hard <span data-interval="1" class="text-xs text-[#888]">{}</span></button>"#,
+                interval(1)
+            ),
+            format!(
+                r#"<button type="submit" onclick="this.form.grade.value='4'" class="inline-flex items-center gap-1 px-5 py-2 rounded-md text-sm font-medium cursor-pointer bg-[#333] text-[#6ba3d6] hover:bg-[#2a2f3d]"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">4</span> Easy <span data-interval="3" class="text-xs text-[#888]">{}</span></button>"#,
+                interval(3)
+            ),
+        )
     };
 
     let body = format!(
@@ -405,38 +1306,72 @@ async fn review_page(
 <div class="flex-1 min-w-0 flex flex-col">
 <div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
 <div class="text-sm text-[#888]">{bc}</div>
-<div class="flex items-center gap-1.5 text-sm text-[#888]">Card {pos} of {total}</div>
+<div class="flex items-center gap-1.5 text-sm text-[#888]"><form id="skip-form" method="post" action="{action}" class="inline"><input type="hidden" name="session" value="{session_id}"><input type="hidden" name="skip" value="1"><button type="submit" class="text-[#888] cursor-pointer hover:!text-[#ccc] mr-3">Skip</button></form><a id="card-edit" href="{edit}"{edit_hidden} class="!text-[#888] no-underline hover:!text-[#ccc] mr-3">Edit</a><a id="card-info" href="{info}" class="!text-[#888] no-underline hover:!text-[#ccc] mr-3">Info</a><span id="session-timer" data-started="{started_ms}" title="Time in this session" class="mr-3">{elapsed}</span>Card&nbsp;<span id="review-position">{pos}</span>&nbsp;of {total}</div>
 </div>
 <div class="flex-1 flex items-center justify-center p-8">
 <div class="w-full max-w-[620px]">
 <div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl overflow-hidden">
-<div class="{answer_cls}">{front}</div>
-<div id="back-section" style="display:none">{back_html}</div>
+<div id="card-front" class="{answer_cls}">{front}</div>
+<div id="card-media">{media}</div>
+<div id="card-choices">{choices}</div>
+<div id="back-section" data-speech="{speech}" style="display:none">{back_html}</div>
 <button type="button" id="reveal-btn" class="w-full py-3 text-[#888] text-sm text-center border-t border-[#333] cursor-pointer hover:bg-[#333] hover:!text-[#ccc]">Show Answer</button>
+<button type="button" id="choice-next" class="w-full py-3 text-[#888] text-sm text-center border-t border-[#333] cursor-pointer hover:bg-[#333] hover:!text-[#ccc]" style="display:none">Continue</button>
+</div>
 </div>
 </div>
+<div id="break-prompt" class="fixed inset-0 z-50 items-center justify-center bg-black/60" style="display:none">
+<div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-sm text-center">
+<p class="text-[#e0e0e0] font-semibold mb-1">Time for a break?</p>
+<p id="break-detail" class="text-sm text-[#888] mb-4"></p>
+<button type="button" id="break-continue" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Keep going</button>
+</div>
 </div>
 <div class="text-center py-2 text-sm text-[#666]" id="reveal-hint">Press <span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa]">Space</span> to reveal</div>
-<form id="grade-form" method="post" action="/deck/{name_enc}/review" style="display:none">
+<form id="grade-form" method="post" action="{action}" data-grade-url="{grade_url}" data-queue-url="{queue_url}" data-auto-reveal="{auto_reveal}" data-pass-fail="{pass_fail}" data-session="{session_id}" data-break-minutes="{break_minutes}" data-break-cards="{break_cards}" style="display:none">
 <input type="hidden" name="session" value="{session_id}">
 <input type="hidden" name="grade" value="">
 <div class="border-t border-[#333] bg-[#232323] px-6 py-3 flex items-center justify-center gap-4">
-<button type="submit" onclick="this.form.grade.value='1'" class="inline-flex items-center gap-1 px-5 py-2 rounded-md text-sm font-medium cursor-pointer bg-[#333] text-[#e06c6c] hover:bg-[#3d2a2a]"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">1</span> Forgot</button>
-<button type="submit" onclick="this.form.grade.value='2'" class="inline-flex items-center gap-1 px-5 py-2 rounded-md text-sm font-medium cursor-pointer bg-[#333] text-[#d4a05a] hover:bg-[#3d3425]"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">2</span> Hard</button>
-<button type="submit" onclick="this.form.grade.value='3'" class="inline-flex items-center gap-1 px-5 py-2 rounded-md text-sm font-medium cursor-pointer bg-[#333] text-[#6bc06b] hover:bg-[#2a3d2a]"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">3</span> Good</button>
-<button type="submit" onclick="this.form.grade.value='4'" class="inline-flex items-center gap-1 px-5 py-2 rounded-md text-sm font-medium cursor-pointer bg-[#333] text-[#6ba3d6] hover:bg-[#2a2f3d]"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">4</span> Easy</button>
+<button type="submit" id="forgot-btn" onclick="this.form.grade.value='1'" class="inline-flex items-center gap-1 {grade_cls} rounded-md font-medium cursor-pointer bg-[#333] text-[#e06c6c] hover:bg-[#3d2a2a] select-none"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">1</span> <span id="forgot-label">Forgot</span> <span data-interval="0" class="text-xs text-[#888]">{interval1}</span></button>
+{hard}
+<button type="submit" onclick="this.form.grade.value='3'" class="inline-flex items-center gap-1 {grade_cls} rounded-md font-medium cursor-pointer bg-[#333] text-[#6bc06b] hover:bg-[#2a3d2a]"><span class="inline-block px-1.5 py-0.5 text-xs bg-[#383838] border border-[#555] rounded text-[#aaa] mr-1">3</span> Good <span data-interval="2" class="text-xs text-[#888]">{interval3}</span></button>
+{easy}
 </div>
 </form>
 </div>
 </div>"#,
         sidebar = sidebar,
-        bc = breadcrumb(&[("Decks", "/"), (deck_display, &deck_href), ("Review", "")]),
+        bc = breadcrumb(&[
+            ("Decks", &urls::home()),
+            (deck_display, &urls::deck_or_home(&name)),
+            ("Review", ""),
+        ]),
         pos = position,
         total = total,
-        answer_cls = answer_cls,
-        front = html_escape(&front_display),
-        back_html = back_html,
-        name_enc = html_escape(&name),
+        answer_cls = ANSWER_CLS,
+        front = view.front,
+        media = view.media,
+        choices = view.choices,
+        back_html = view.back,
+        speech = html_escape(&serde_json::to_string(&view.speech).unwrap()),
+        info = html_escape(&view.info),
+        edit = html_escape(&view.edit),
+        edit_hidden = if view.edit.is_empty() { " hidden" } else { "" },
+        interval1 = interval(0),
+        interval3 = interval(2),
+        pass_fail = u8::from(pass_fail),
+        action = html_escape(&urls::deck_review(&name)),
+        grade_url = html_escape(&urls::review_grade(&name)),
+        queue_url = html_escape(&urls::review_queue(&name, &session_id)),
+        auto_reveal = st.auto_reveal.unwrap_or(0),
+        started_ms = session.started_at.timestamp_millis(),
+        elapsed = review::format_elapsed(
+            (chrono::Local::now() - session.started_at)
+                .num_seconds()
+                .max(0) as u64
+        ),
+        break_minutes = st.breaks.minutes.unwrap_or(0),
+        break_cards = st.breaks.cards.unwrap_or(0),
         session_id = html_escape(&session_id),
     );
 
@@ -446,92 +1381,199 @@ async fn review_page(
         .unwrap()
 }
 
-async fn review_submit(
-    State(state): State<SharedState>,
-    Path(name): Path<String>,
-    Form(params): Form<HashMap<String, String>>,
-) -> Redirect {
-    let mut st = state.lock().await;
-    let session_id = params.get("session").cloned().unwrap_or_default();
-    let grade_str = params.get("grade").cloned().unwrap_or_default();
-
-    let grade = grade_str
-        .parse::<u8>()
-        .ok()
-        .and_then(Grade::from_u8)
-        .unwrap_or(Grade::Good);
-
-    let session_info = st.sessions.get(&session_id).and_then(|session| {
-        if session.position < session.order.len() {
-            Some((session.order[session.position], session.position))
-        } else {
-            None
-        }
-    });
+/// `<option>`s for every session order, with `current` selected.
+fn order_options(current: Option<review::Order>) -> String {
+    review::Order::ALL
+        .iter()
+        .map(|&o| {
+            let selected = if current == Some(o) { " selected" } else { "" };
+            format!(
+                r#"<option value="{}"{selected}>{}</option>"#,
+                o.as_str(),
+                o.label()
+            )
+        })
+        .collect()
+}
 
-    if let Some((card_idx, _pos)) = session_info {
-        let today = Local::now().date_naive();
-        review::apply_grade(&mut st.app.cards[card_idx], grade, today);
+fn scheduler_options(current: Option<scheduler::Kind>) -> String {
+    scheduler::Kind::ALL
+        .iter()
+        .map(|&k| {
+            let selected = if current == Some(k) { " selected" } else { "" };
+            format!(
+                r#"<option value="{}"{selected}>{}</option>"#,
+                k.as_str(),
+                k.label()
+            )
+        })
+        .collect()
+}
 
-        let source = st.app.sources[card_idx].clone();
-        save_file(&st.app.cards, &st.app.sources, &source);
-
-        let session = st.sessions.get_mut(&session_id).unwrap();
-        let grade_idx = match grade {
-            Grade::Forgot => 0,
-            Grade::Hard => 1,
-            Grade::Good => 2,
-            Grade::Easy => 3,
-        };
-        session.counts[grade_idx] += 1;
-        session.position += 1;
+/// The filter a new session of `name` would use with `params`, and the cards
+/// it would hold, in order: the deck settings, daily limits and buried cards
+/// applied.
+fn session_order(
+    st: &ServerState,
+    name: &str,
+    params: &HashMap<String, String>,
+    today: chrono::NaiveDate,
+) -> (review::SessionFilter, Vec<usize>) {
+    let mut filter = session_filter(name, params, &st.decks, st.order);
+    if name == urls::ALL_DECKS {
+        filter.skip_decks = deck::unavailable(&st.decks, clock::now());
     }
-
-    if let Some(session) = st.sessions.get(&session_id)
-        && session.position >= session.order.len()
-    {
-        return Redirect::to(&format!("/deck/{}/summary?session={}", name, session_id));
+    filter.skip_cards = bury::load(st.app.data_dir(), today);
+    let reviews = revlog::load(st.app.data_dir());
+    let forgotten = revlog::forgotten_in_last_session(&reviews);
+    filter.practice |= st.read_only;
+    if !filter.practice {
+        filter.new_limit = st
+            .new_per_day
+            .map(|n| n.saturating_sub(revlog::new_cards_on(&reviews, today)));
+        filter.deck_limits = deck::limits(&st.decks, &reviews, today);
     }
-
-    Redirect::to(&format!("/deck/{}/review?session={}", name, session_id))
+    let mut order = filter.select(&st.app.cards, today, &forgotten);
+    review::shuffle(&st.app.cards, &mut order, review::time_seed());
+    let order = filter.arrange(&st.app.cards, order, today);
+    (filter, order)
 }
 
-async fn review_get(
-    state: State<SharedState>,
-    path: Path<String>,
-    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-) -> axum::response::Response {
-    review_page(state, path, Form(params)).await
+/// Build a session filter from review query parameters. Blank fields are
+/// ignored, so the custom session form can submit every input. Without an
+/// `order`, a deck's session uses the order from its settings, then
+/// `default_order`.
+fn session_filter(
+    name: &str,
+    params: &HashMap<String, String>,
+    decks: &HashMap<String, deck::DeckConfig>,
+    default_order: review::Order,
+) -> review::SessionFilter {
+    let number = |key: &str| params.get(key).and_then(|v| v.trim().parse::<usize>().ok());
+    let flag = |key: &str| params.get(key).is_some_and(|v| !v.is_empty() && v != "0");
+    review::SessionFilter {
+        decks: if name == urls::ALL_DECKS {
+            Vec::new()
+        } else {
+            vec![name.to_string()]
+        },
+        skip_decks: Vec::new(),
+        skip_cards: Default::default(),
+        tags: params
+            .get("tag")
+            .map(|t| card::parse_tags(t))
+            .unwrap_or_default(),
+        due_within: number("due_within").map(|n| n as i64),
+        forgotten: flag("forgotten"),
+        hardest: number("hardest"),
+        sample: number("sample"),
+        all: flag("cram"),
+        practice: flag("practice") || flag("cram"),
+        order: params
+            .get("order")
+            .and_then(|o| review::Order::parse(o))
+            .or_else(|| deck::setting(decks, name, |c| c.order))
+            .unwrap_or(default_order),
+        new_limit: None,
+        deck_limits: Vec::new(),
+        limit: number("limit"),
+    }
 }
 
-async fn summary_page(
-    State(state): State<SharedState>,
-    Path(name): Path<String>,
-    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-) -> Html<String> {
+/// Today's review of `name` before it starts: the cards already seen, most
+/// overdue first, each with its interval and last grade and buttons to bury
+/// or suspend it, and how many new cards come with them.
+async fn queue_page(State(state): State<SharedState>, Path(name): Path<String>) -> Html<String> {
     let st = state.lock().await;
-    let today = Local::now().date_naive();
+    let today = clock::today();
     let summaries = review::deck_summaries(&st.app.cards, today);
-    let sidebar = sidebar_html(&summaries, &name);
-    let session_id = params.get("session").cloned().unwrap_or_default();
+    let sidebar = sidebar_html(&st, &summaries, &name);
+    let (_, order) = session_order(&st, &name, &HashMap::new(), today);
+    let last_grades: HashMap<String, u8> = revlog::load(st.app.data_dir())
+        .into_iter()
+        .filter(|r| !r.practice)
+        .map(|r| (r.card_id, r.grade))
+        .collect();
+    let buried = bury::load(st.app.data_dir(), today);
 
-    let (counts, total) = if let Some(session) = st.sessions.get(&session_id) {
-        let total: u32 = session.counts.iter().sum();
-        (session.counts, total)
-    } else {
-        ([0u32; 4], 0)
+    let mut seen: Vec<usize> = order
+        .iter()
+        .copied()
+        .filter(|&i| st.app.cards[i].due.is_some())
+        .collect();
+    seen.sort_by_key(|&i| st.app.cards[i].due);
+    let new = order.len() - seen.len();
+    let buried_here: Vec<usize> = (0..st.app.cards.len())
+        .filter(|&i| buried.contains(&st.app.cards[i].id))
+        .filter(|&i| name == urls::ALL_DECKS || deck::contains(&name, &st.app.cards[i].deck))
+        .collect();
+
+    let action_cls = "text-xs text-[#888] cursor-pointer hover:!text-[#d4d4d4]";
+    let row = |i: usize, actions: &[(&str, &str)]| {
+        let c = full_card(&st.app, i);
+        let interval = match (c.last_review, c.due) {
+            (Some(last), Some(due)) => review::format_interval((due - last).num_days()),
+            _ => "—".to_string(),
+        };
+        let (grade, color) = match last_grades.get(&c.id) {
+            Some(&g) => grade_label(g),
+            None => ("—", "text-[#666]"),
+        };
+        let due = match c.due {
+            Some(due) if due < today => format!("{}d overdue", (today - due).num_days()),
+            Some(due) if due == today => "today".to_string(),
+            Some(due) => due.format("%b %d").to_string(),
+            None => "new".to_string(),
+        };
+        let buttons: String = if st.read_only {
+            String::new()
+        } else {
+            actions
+                .iter()
+                .map(|(action, label)| {
+                    format!(
+                        r#"<form method="post" action="{href}" class="inline"><input type="hidden" name="card" value="{id}"><button type="submit" name="action" value="{action}" class="{action_cls}">{label}</button></form>"#,
+                        href = html_escape(&urls::deck_queue(&name)),
+                        id = html_escape(&c.id),
+                    )
+                })
+                .collect()
+        };
+        format!(
+            r#"<li class="flex items-baseline gap-3 py-2 border-b border-[#333] text-[0.9rem]"><a href="{info}" class="flex-1 min-w-0 overflow-hidden text-ellipsis whitespace-nowrap text-[#e0e0e0] no-underline hover:underline">{front}</a><span class="text-xs text-[#888] w-20 shrink-0">{due}</span><span class="text-xs text-[#888] w-12 shrink-0">{interval}</span><span class="text-xs {color} w-12 shrink-0">{grade}</span><span class="flex gap-3 shrink-0">{buttons}</span></li>"#,
+            info = html_escape(&urls::card_info(&c.id)),
+            front = markdown::inline(&truncate(&card::expand_escapes(&c.front), 80)),
+        )
     };
 
-    let deck_display = if name == "_all" { "All decks" } else { &name };
-    let deck_href = if name == "_all" {
-        "/".to_string()
+    let mut rows: String = seen
+        .iter()
+        .map(|&i| row(i, &[("bury", "Bury"), ("suspend", "Suspend")]))
+        .collect();
+    if rows.is_empty() {
+        rows.push_str(r#"<li class="py-12 text-center text-[#666]">No reviews due.</li>"#);
+    }
+    let buried_list = if buried_here.is_empty() {
+        String::new()
+    } else {
+        let rows: String = buried_here
+            .iter()
+            .map(|&i| row(i, &[("unbury", "Unbury")]))
+            .collect();
+        format!(
+            r#"<h2 class="text-[0.65rem] uppercase tracking-widest text-[#666] mt-8 mb-2">Buried until tomorrow</h2>
+<ul class="list-none m-0 p-0">{rows}</ul>"#
+        )
+    };
+    let start = if order.is_empty() {
+        String::new()
     } else {
-        format!("/deck/{}", name)
+        btn_primary(&urls::deck_review(&name), "Start review")
     };
-    let back_btn = if name == "_all" {
-        btn_primary("/", "All decks")
+    let deck_display = if name == urls::ALL_DECKS {
+        "All decks"
     } else {
-        btn_primary(&format!("/deck/{}", html_escape(&name)), "Back to deck")
+        &name
     };
 
     let body = format!(
@@ -540,47 +1582,73 @@ async fn summary_page(
 <div class="flex-1 overflow-y-auto min-w-0">
 <div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
 <div class="text-sm text-[#888]">{bc}</div>
+<div class="flex gap-2">{start}</div>
 </div>
-<div class="p-6 max-w-lg">
-<h2 class="text-lg font-semibold text-[#e0e0e0] mb-4">Session Complete</h2>
-<ul class="list-none m-0 mb-6 p-0">
-<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#888]">Cards reviewed</span><span class="font-semibold text-[#e0e0e0]">{total}</span></li>
-<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#e06c6c]">Forgot</span><span class="font-semibold text-[#e0e0e0]">{forgot}</span></li>
-<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#d4a05a]">Hard</span><span class="font-semibold text-[#e0e0e0]">{hard}</span></li>
-<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#6bc06b]">Good</span><span class="font-semibold text-[#e0e0e0]">{good}</span></li>
-<li class="flex justify-between py-2 text-[0.9rem]"><span class="text-[#6ba3d6]">Easy</span><span class="font-semibold text-[#e0e0e0]">{easy}</span></li>
-</ul>
-<div class="flex gap-3">{back_btn}{home_btn}</div>
+<div class="p-6 max-w-4xl">
+<p class="text-sm text-[#888] mb-4">{reviews} review(s) and {new} new card(s) in today's session. Buried cards sit out until tomorrow; suspended ones until unsuspended.</p>
+<div class="flex gap-3 pb-2 border-b border-[#333] text-[0.65rem] uppercase tracking-widest text-[#666]"><span class="flex-1">Front</span><span class="w-20">Due</span><span class="w-12">Interval</span><span class="w-12">Last</span><span class="w-24"></span></div>
+<ul class="list-none m-0 p-0">{rows}</ul>
+{buried_list}
 </div>
 </div>
 </div>"#,
-        sidebar = sidebar,
-        bc = breadcrumb(&[("Decks", "/"), (deck_display, &deck_href), ("Summary", ""),]),
-        total = total,
-        forgot = counts[0],
-        hard = counts[1],
-        good = counts[2],
-        easy = counts[3],
-        back_btn = back_btn,
-        home_btn = btn_secondary("/", "Home"),
+        bc = breadcrumb(&[
+            ("Decks", &urls::home()),
+            (deck_display, &urls::deck_or_home(&name)),
+            ("Queue", ""),
+        ]),
+        reviews = seen.len(),
     );
-    Html(page("Summary", &body))
+    Html(page("Queue", &body))
 }
 
-async fn card_edit_form(State(state): State<SharedState>, Path(id): Path<String>) -> Html<String> {
-    let st = state.lock().await;
-    let today = Local::now().date_naive();
-    let summaries = review::deck_summaries(&st.app.cards, today);
-    let card = st.app.cards.iter().find(|c| c.id == id);
+#[derive(serde::Deserialize)]
+struct QueueAction {
+    card: String,
+    /// `bury`, `unbury` or `suspend`.
+    action: String,
+}
 
-    let Some(card) = card else {
-        return Html(page("Not Found", "<p>Card not found.</p>"));
+async fn queue_submit(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(form): Form<QueueAction>,
+) -> Redirect {
+    let mut st = state.lock().await;
+    let today = clock::today();
+    let result = match form.action.as_str() {
+        "bury" | "unbury" => bury::set(st.app.data_dir(), &form.card, form.action == "bury", today),
+        "suspend" => {
+            if let Some(i) = st.app.cards.iter().position(|c| c.id == form.card) {
+                toggle_saved(&mut st, i, "suspend", |c| c.suspended = true);
+            }
+            Ok(())
+        }
+        _ => Ok(()),
     };
+    if let Err(e) = result {
+        tracing::warn!("{e}");
+    }
+    Redirect::to(&urls::deck_queue(&name))
+}
 
-    let deck = card.deck.clone();
-    let sidebar = sidebar_html(&summaries, &deck);
+async fn custom_session_form(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(&st, &summaries, &name);
 
+    let deck_display = if name == urls::ALL_DECKS {
+        "All decks"
+    } else {
+        &name
+    };
     let input_cls = "w-full px-3 py-2.5 border border-[#444] rounded-md text-[0.9rem] bg-[#383838] text-[#e0e0e0] focus:outline-none focus:border-[#6ba3d6] focus:ring-2 focus:ring-[#6ba3d6]/15";
+    let label_cls = "block text-xs font-medium text-[#888] mb-1";
+    let check_cls = "flex items-center gap-2 text-sm text-[#ccc] mb-3";
 
     let body = format!(
         r#"<div class="flex h-screen">
@@ -591,90 +1659,89 @@ async fn card_edit_form(State(state): State<SharedState>, Path(id): Path<String>
 </div>
 <div class="p-6">
 <div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-xl">
-<div class="flex justify-between items-center mb-5">
-<h2 class="text-lg font-semibold text-[#e0e0e0] m-0">Edit Card</h2>
-<form method="post" action="/card/{id}/delete" onsubmit="return confirm('Delete this card?')" class="inline">
-<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] text-[#e06c6c] border border-[#444] cursor-pointer hover:bg-[#3d2a2a]">Delete</button>
-</form>
-</div>
-<form method="post" action="/card/{id}/edit">
+<h2 class="text-lg font-semibold text-[#e0e0e0] mb-1">Custom Session</h2>
+<p class="text-sm text-[#888] mb-5">Leave a field blank to ignore it. Without a due window, forgotten, hardest and sample sessions include cards that are not yet due.</p>
+<form method="get" action="{action}">
 <div class="mb-4">
-<label class="block text-xs font-medium text-[#888] mb-1" for="deck">Deck</label>
-<input type="text" id="deck" name="deck" value="{deck}" class="{input_cls}">
+<label class="{label_cls}" for="tag">Tags</label>
+<input type="text" id="tag" name="tag" placeholder="any of these, space-separated" class="{input_cls}">
 </div>
-<div class="mb-4">
-<label class="block text-xs font-medium text-[#888] mb-1" for="front">Front</label>
-<textarea id="front" name="front" rows="4" class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit">{front}</textarea>
+<div class="grid grid-cols-3 gap-4 mb-4">
+<div><label class="{label_cls}" for="due_within">Due within (days)</label><input type="number" min="0" id="due_within" name="due_within" class="{input_cls}"></div>
+<div><label class="{label_cls}" for="hardest">Hardest</label><input type="number" min="1" id="hardest" name="hardest" placeholder="50" class="{input_cls}"></div>
+<div><label class="{label_cls}" for="sample">Random sample</label><input type="number" min="1" id="sample" name="sample" class="{input_cls}"></div>
 </div>
-<div class="mb-4">
-<label class="block text-xs font-medium text-[#888] mb-1" for="back">Back</label>
-<textarea id="back" name="back" rows="4" class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit">{back}</textarea>
+<div class="grid grid-cols-2 gap-4 mb-4">
+<div><label class="{label_cls}" for="order">Order</label><select id="order" name="order" class="{input_cls}"><option value="">Deck default</option>{orders}</select></div>
+<div><label class="{label_cls}" for="limit">Stop after</label><input type="number" min="1" id="limit" name="limit" placeholder="all" class="{input_cls}"></div>
 </div>
+<label class="{check_cls}"><input type="checkbox" name="forgotten" value="1"> Only cards forgotten in the last session</label>
+<label class="{check_cls}"><input type="checkbox" name="practice" value="1"> Don't update scheduling</label>
+<label class="{check_cls}"><input type="checkbox" name="quiz" value="1"> Multiple choice</label>
+<label class="{check_cls}"><input type="checkbox" name="pass_fail" value="1"> Pass/fail: just Forgot and Good</label>
 <div class="flex gap-3 mt-5">
-<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Save</button>
-<a href="/deck/{deck_enc}" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">Cancel</a>
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Start</button>
+<a href="{back_url}" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">Cancel</a>
 </div>
 </form>
 </div>
 </div>
 </div>
-</div>
-<script>document.addEventListener('keydown',function(e){{if((e.ctrlKey||e.metaKey)&&e.key==='Enter'){{e.preventDefault();document.querySelector('form[action*="edit"]').submit();}}}});</script>"#,
+</div>"#,
         sidebar = sidebar,
         bc = breadcrumb(&[
-            ("Decks", "/"),
-            (&deck, &format!("/deck/{}", deck)),
-            ("Edit", ""),
+            ("Decks", &urls::home()),
+            (deck_display, &urls::deck_or_home(&name)),
+            ("Custom", ""),
         ]),
-        id = html_escape(&id),
-        deck = html_escape(&card.deck),
-        deck_enc = html_escape(&card.deck),
-        front = html_escape(&card.front),
-        back = html_escape(&card.back),
+        action = html_escape(&urls::deck_review(&name)),
+        back_url = html_escape(&urls::deck_or_home(&name)),
         input_cls = input_cls,
+        label_cls = label_cls,
+        check_cls = check_cls,
+        orders = order_options(None),
     );
-    Html(page("Edit Card", &body))
-}
-
-#[derive(serde::Deserialize)]
-struct CardForm {
-    deck: String,
-    front: String,
-    back: String,
+    Html(page("Custom Session", &body))
 }
 
-async fn card_edit_submit(
+async fn deck_settings_form(
     State(state): State<SharedState>,
-    Path(id): Path<String>,
-    Form(form): Form<CardForm>,
-) -> Redirect {
-    let mut st = state.lock().await;
-
-    if let Some((i, card)) = st
-        .app
-        .cards
-        .iter_mut()
-        .enumerate()
-        .find(|(_, c)| c.id == id)
-    {
-        card.deck = form.deck.clone();
-        card.front = form.front;
-        card.back = form.back;
-
-        let source = st.app.sources[i].clone();
-        save_file(&st.app.cards, &st.app.sources, &source);
-    }
-
-    Redirect::to(&format!("/deck/{}", form.deck))
-}
-
-async fn card_new_form(State(state): State<SharedState>, Path(name): Path<String>) -> Html<String> {
+    Path(name): Path<String>,
+) -> Html<String> {
     let st = state.lock().await;
-    let today = Local::now().date_naive();
+    let today = clock::today();
     let summaries = review::deck_summaries(&st.app.cards, today);
-    let sidebar = sidebar_html(&summaries, &name);
+    let sidebar = sidebar_html(&st, &summaries, &name);
+    let own = st.decks.get(&name).cloned().unwrap_or_default();
+    // What a blank field falls back to: a parent's setting, or the global one.
+    let parent = name
+        .rfind(deck::SEPARATOR)
+        .map(|end| &name[..end])
+        .unwrap_or("");
 
     let input_cls = "w-full px-3 py-2.5 border border-[#444] rounded-md text-[0.9rem] bg-[#383838] text-[#e0e0e0] focus:outline-none focus:border-[#6ba3d6] focus:ring-2 focus:ring-[#6ba3d6]/15";
+    let label_cls = "block text-xs font-medium text-[#888] mb-1";
+    let value = |v: Option<String>| html_escape(&v.unwrap_or_default());
+    let selected = |on: bool| if on { " selected" } else { "" };
+    let order_default = deck::setting(&st.decks, parent, |c| c.order).unwrap_or(st.order);
+    let reversed_default = match deck::setting(&st.decks, parent, |c| c.reversed) {
+        Some(true) => "yes",
+        _ => "no",
+    };
+    let pass_fail_default = match deck::setting(&st.decks, parent, |c| c.pass_fail) {
+        Some(true) => "pass/fail",
+        _ => "four grades",
+    };
+    let progressive_default = match deck::setting(&st.decks, parent, |c| c.progressive_reveal) {
+        Some(true) => "one at a time",
+        _ => "all at once",
+    };
+    let fetch_default = match deck::setting(&st.decks, parent, |c| c.fetch_media) {
+        Some(true) => "download",
+        _ => "link",
+    };
+    let limit_default =
+        |limit: Option<usize>| limit.map_or("no limit".to_string(), |n| n.to_string());
 
     let body = format!(
         r#"<div class="flex h-screen">
@@ -685,128 +1752,2530 @@ async fn card_new_form(State(state): State<SharedState>, Path(name): Path<String
 </div>
 <div class="p-6">
 <div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-xl">
-<h2 class="text-lg font-semibold text-[#e0e0e0] mb-5">New Card</h2>
-<form method="post" action="/deck/{name_enc}/new">
-<div class="mb-4">
-<label class="block text-xs font-medium text-[#888] mb-1" for="front">Front</label>
-<textarea id="front" name="front" rows="4" autofocus class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit"></textarea>
+<h2 class="text-lg font-semibold text-[#e0e0e0] mb-1">Deck Settings</h2>
+<p class="text-sm text-[#888] mb-5">These apply to this deck and every deck below it. Leave a field blank to use the default shown.</p>
+<form method="post" action="{action}">
+<div class="mb-4"><label class="{label_cls}" for="description">Description, in Markdown, shown on the deck page</label><textarea id="description" name="description" rows="3" class="{input_cls} resize-y leading-relaxed" style="font-family:inherit">{description}</textarea></div>
+<div class="grid grid-cols-3 gap-4 mb-4">
+<div><label class="{label_cls}" for="new_per_day">New cards per day</label><input type="number" min="0" id="new_per_day" name="new_per_day" value="{new_per_day}" placeholder="{new_default}" class="{input_cls}"></div>
+<div><label class="{label_cls}" for="reviews_per_day">Reviews per day</label><input type="number" min="0" id="reviews_per_day" name="reviews_per_day" value="{reviews_per_day}" placeholder="{reviews_default}" class="{input_cls}"></div>
+<div><label class="{label_cls}" for="desired_retention">Desired retention</label><input type="number" min="0.7" max="0.99" step="0.01" id="desired_retention" name="desired_retention" value="{retention}" placeholder="{retention_default}" class="{input_cls}"></div>
 </div>
-<div class="mb-4">
-<label class="block text-xs font-medium text-[#888] mb-1" for="back">Back</label>
-<textarea id="back" name="back" rows="4" class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit"></textarea>
+<div class="grid grid-cols-2 gap-4 mb-4">
+<div><label class="{label_cls}" for="order">Order</label><select id="order" name="order" class="{input_cls}"><option value="">Default ({order_default})</option>{orders}</select></div>
+<div><label class="{label_cls}" for="reversed">Reversed cards</label><select id="reversed" name="reversed" class="{input_cls}"><option value="">Default ({reversed_default})</option><option value="on"{reversed_on}>Yes, add a back-to-front twin of each card</option><option value="off"{reversed_off}>No</option></select></div>
+<div><label class="{label_cls}" for="scheduler">Scheduler</label><select id="scheduler" name="scheduler" class="{input_cls}"><option value="">Default ({scheduler_default})</option>{schedulers}</select></div>
+<div><label class="{label_cls}" for="pass_fail">Grades</label><select id="pass_fail" name="pass_fail" class="{input_cls}"><option value="">Default ({pass_fail_default})</option><option value="on"{pass_fail_on}>Pass/fail: just Forgot and Good</option><option value="off"{pass_fail_off}>Forgot, Hard, Good and Easy</option></select></div>
+<div><label class="{label_cls}" for="progressive_reveal">Cloze blanks</label><select id="progressive_reveal" name="progressive_reveal" class="{input_cls}"><option value="">Default ({progressive_default})</option><option value="on"{progressive_on}>Reveal one at a time</option><option value="off"{progressive_off}>Reveal all at once</option></select></div>
+<div><label class="{label_cls}" for="overlap">Line cards, lines shown before</label><input type="number" min="0" id="overlap" name="overlap" value="{overlap}" placeholder="{overlap_default}" class="{input_cls}"></div>
+<div><label class="{label_cls}" for="fetch_media">Linked images</label><select id="fetch_media" name="fetch_media" class="{input_cls}"><option value="">Default ({fetch_default})</option><option value="on"{fetch_on}>Download when a card is saved</option><option value="off"{fetch_off}>Link to them</option></select></div>
 </div>
 <div class="flex gap-3 mt-5">
-<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Create</button>
-<a href="/deck/{name_enc}" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">Cancel</a>
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Save</button>
+<a href="{back_url}" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">Cancel</a>
 </div>
 </form>
 </div>
+<div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-xl mt-6">
+<h2 class="text-lg font-semibold text-[#e0e0e0] mb-1">Reschedule</h2>
+<p class="text-sm text-[#888] mb-5">Change the due dates of every card in this deck. Undo reverts it.</p>
+<form method="post" action="{reschedule}" class="flex gap-3 items-end mb-4">
+<input type="hidden" name="action" value="spread">
+<div class="flex-1"><label class="{label_cls}" for="days">Spread overdue cards over days</label><input type="number" min="1" id="days" name="days" value="7" class="{input_cls}"></div>
+<button type="submit" class="{secondary_cls}">Spread</button>
+</form>
+<form method="post" action="{reschedule}" class="flex gap-3 items-center mb-4">
+<input type="hidden" name="action" value="retention">
+<p class="flex-1 text-sm text-[#888]">Refit due dates to the desired retention, after changing it.</p>
+<button type="submit" class="{secondary_cls}">Refit</button>
+</form>
+<form method="post" action="{reschedule}" class="flex gap-3 items-center" onsubmit="return confirm('Reset every card in this deck to new?')">
+<input type="hidden" name="action" value="reset">
+<p class="flex-1 text-sm text-[#888]">Forget all progress, making every card new.</p>
+<button type="submit" class="{secondary_cls}">Reset</button>
+</form>
 </div>
 </div>
 </div>
-<script>document.addEventListener('keydown',function(e){{if((e.ctrlKey||e.metaKey)&&e.key==='Enter'){{e.preventDefault();document.querySelector('form').submit();}}}});</script>"#,
-        sidebar = sidebar,
+</div>"#,
         bc = breadcrumb(&[
-            ("Decks", "/"),
-            (&name, &format!("/deck/{}", name)),
-            ("New", ""),
+            ("Decks", &urls::home()),
+            (&name, &urls::deck(&name)),
+            ("Settings", ""),
         ]),
-        name_enc = html_escape(&name),
-        input_cls = input_cls,
+        action = html_escape(&urls::deck_settings(&name)),
+        reschedule = html_escape(&urls::deck_reschedule(&name)),
+        secondary_cls = "inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444] hover:!text-[#e0e0e0]",
+        back_url = html_escape(&urls::deck(&name)),
+        description = value(own.description.clone()),
+        new_per_day = value(own.new_per_day.map(|n| n.to_string())),
+        new_default = html_escape(&limit_default(
+            deck::setting(&st.decks, parent, |c| c.new_per_day).or(st.new_per_day)
+        )),
+        reviews_per_day = value(own.reviews_per_day.map(|n| n.to_string())),
+        reviews_default = html_escape(&limit_default(
+            deck::setting(&st.decks, parent, |c| c.reviews_per_day)
+        )),
+        retention = value(own.desired_retention.map(|r| r.to_string())),
+        retention_default =
+            deck::setting(&st.decks, parent, |c| c.desired_retention).unwrap_or(st.retention),
+        order_default = order_default.label().to_lowercase(),
+        orders = order_options(own.order),
+        reversed_on = selected(own.reversed == Some(true)),
+        reversed_off = selected(own.reversed == Some(false)),
+        scheduler_default = deck::setting(&st.decks, parent, |c| c.scheduler)
+            .unwrap_or_default()
+            .label(),
+        schedulers = scheduler_options(own.scheduler),
+        pass_fail_on = selected(own.pass_fail == Some(true)),
+        pass_fail_off = selected(own.pass_fail == Some(false)),
+        progressive_on = selected(own.progressive_reveal == Some(true)),
+        progressive_off = selected(own.progressive_reveal == Some(false)),
+        fetch_on = selected(own.fetch_media == Some(true)),
+        fetch_off = selected(own.fetch_media == Some(false)),
+        overlap = value(own.overlap.map(|n| n.to_string())),
+        overlap_default = match deck::setting(&st.decks, parent, |c| c.overlap) {
+            Some(0) => "all".to_string(),
+            Some(n) => n.to_string(),
+            None => "off".to_string(),
+        },
     );
-    Html(page("New Card", &body))
+    Html(page("Deck Settings", &body))
 }
 
-#[derive(serde::Deserialize)]
-struct NewCardForm {
+async fn deck_settings_submit(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(params): Form<HashMap<String, String>>,
+) -> Redirect {
+    let mut st = state.lock().await;
+    let field = |key: &str| params.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let config = st.decks.entry(name.clone()).or_default();
+    config.description = field("description").map(|v| v.replace("\r\n", "\n"));
+    config.new_per_day = field("new_per_day").and_then(|v| v.parse().ok());
+    config.reviews_per_day = field("reviews_per_day").and_then(|v| v.parse().ok());
+    config.desired_retention = field("desired_retention")
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|r| *r > 0.0 && *r < 1.0);
+    config.order = field("order").and_then(review::Order::parse);
+    config.reversed = field("reversed").map(|v| v == "on");
+    config.scheduler = field("scheduler").and_then(scheduler::Kind::parse);
+    config.pass_fail = field("pass_fail").map(|v| v == "on");
+    config.progressive_reveal = field("progressive_reveal").map(|v| v == "on");
+    config.fetch_media = field("fetch_media").map(|v| v == "on");
+    config.overlap = field("overlap").and_then(|v| v.parse().ok());
+    // Deck settings are shared like the deck files, so they take their lock.
+    let saved = st
+        .app
+        .lock_files()
+        .and_then(|()| deck::save(st.app.dir(), &st.decks));
+    if let Err(e) = saved {
+        tracing::error!("{e}");
+    }
+    add_siblings(&mut st);
+    Redirect::to(&urls::deck(&name))
+}
+
+/// Spread the deck's backlog, refit its due dates or reset it, as one
+/// undoable change.
+async fn deck_reschedule(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(params): Form<HashMap<String, String>>,
+) -> Redirect {
+    let mut st = state.lock().await;
+    let selected: Vec<usize> = (0..st.app.cards.len())
+        .filter(|&i| deck::contains(&name, &st.app.cards[i].deck))
+        .collect();
+    for &i in &selected {
+        materialize(&mut st.app, i);
+    }
+    let today = clock::today();
+    let before = st.app.cards.clone();
+    let action = params.get("action").map_or("", String::as_str);
+    let changed = match action {
+        "spread" => {
+            let days = params.get("days").and_then(|d| d.parse().ok()).unwrap_or(7);
+            reschedule::spread(&mut st.app.cards, &selected, today, days)
+        }
+        "retention" => {
+            let retention: HashMap<String, f64> = selected
+                .iter()
+                .map(|&i| st.app.cards[i].deck.clone())
+                .map(|deck| (deck.clone(), st.retention_for(&deck)))
+                .collect();
+            reschedule::retention(&mut st.app.cards, &selected, |deck| retention[deck])
+        }
+        "reset" => reschedule::reset(&mut st.app.cards, &selected),
+        _ => Vec::new(),
+    };
+    if !changed.is_empty() {
+        snapshot_before("reschedule", &st.app, &st.app.files);
+        let mut entry = journal::Entry::new(&format!("reschedule {action}"));
+        for &i in &changed {
+            entry.push(
+                &st.app.sources,
+                i,
+                &st.app.sources[i],
+                Some(before[i].clone()),
+                Some(st.app.cards[i].clone()),
+            );
+            st.app.touch(i);
+        }
+        if let Err(e) = st.app.save() {
+            tracing::error!("{e}");
+        }
+        record_journal(st.app.data_dir(), &entry);
+    }
+    Redirect::to(&urls::deck(&name))
+}
+
+/// Give each card in a deck set to `reversed` its back-to-front twin, and
+/// each in a deck set to `overlap` its line cards, saved in the same file.
+fn add_siblings(st: &mut ServerState) {
+    for i in 0..st.app.cards.len() {
+        let deck = &st.app.cards[i].deck;
+        if deck::setting(&st.decks, deck, |c| c.reversed) == Some(true)
+            || deck::setting(&st.decks, deck, |c| c.overlap).is_some()
+        {
+            materialize(&mut st.app, i);
+        }
+    }
+    let mut missing: Vec<(usize, Card)> = deck::needs_reversed(&st.decks, &st.app.cards)
+        .into_iter()
+        .filter_map(|i| Some((i, deck::reversed_card(&st.app.cards[i])?)))
+        .collect();
+    missing.extend(deck::missing_lines(&st.decks, &st.app.cards));
+    if missing.is_empty() {
+        return;
+    }
+    let mut entry = journal::Entry::new("add siblings");
+    let mut touched: Vec<PathBuf> = Vec::new();
+    for (i, twin) in missing {
+        let source = st.app.sources[i].clone();
+        entry.push(
+            &st.app.sources,
+            st.app.cards.len(),
+            &source,
+            None,
+            Some(twin.clone()),
+        );
+        st.app.sources.push(source.clone());
+        st.app.cards.push(twin);
+        if !touched.contains(&source) {
+            touched.push(source);
+        }
+    }
+    for source in &touched {
+        save_file(&mut st.app, source);
+    }
+    record_journal(st.app.data_dir(), &entry);
+}
+
+fn parse_grade(params: &HashMap<String, String>) -> Grade {
+    params
+        .get("grade")
+        .and_then(|g| g.parse::<u8>().ok())
+        .and_then(Grade::from_u8)
+        .unwrap_or(Grade::Good)
+}
+
+/// How long the card was on screen before the grade, as timed by the browser.
+fn parse_answer_ms(params: &HashMap<String, String>) -> Option<u64> {
+    params.get("ms").and_then(|ms| ms.parse().ok())
+}
+
+/// Grade the session's current card as of `today` and move to the next one.
+/// Returns false when the session does not exist or is already finished.
+fn grade_current(
+    st: &mut ServerState,
+    session_id: &str,
+    grade: Grade,
+    answer_ms: Option<u64>,
+    today: chrono::NaiveDate,
+) -> bool {
+    let Some(card_idx) = st
+        .sessions
+        .get(session_id)
+        .and_then(|s| s.order.get(s.position).copied())
+    else {
+        return false;
+    };
+
+    let practice = st.sessions[session_id].practice;
+    let grade = if st.sessions[session_id].pass_fail {
+        grade.pass_fail()
+    } else {
+        grade
+    };
+    let mut logged = revlog::Review::new(&st.app.cards[card_idx], grade, session_id, today);
+    logged.practice = practice;
+    logged.answer_ms = answer_ms;
+    if st.read_only {
+        // Nothing is written, not even the review log.
+    } else if let Err(e) = revlog::append(st.app.data_dir(), &[logged]) {
+        tracing::warn!("{e}");
+    }
+
+    if !practice {
+        let deck = &st.app.cards[card_idx].deck;
+        let (retention, scheduler) = (st.retention_for(deck), st.scheduler_for(deck));
+        let before = match st
+            .app
+            .grade_with(card_idx, grade, today, retention, scheduler)
+        {
+            Ok(before) => before,
+            Err(e) => {
+                tracing::warn!("{e}");
+                return false;
+            }
+        };
+        if let Err(e) = st.app.save() {
+            tracing::error!("{e}");
+        }
+        let source = st.app.sources[card_idx].clone();
+
+        let after = st.app.cards[card_idx].clone();
+        let mut entry = journal::Entry::new("grade");
+        entry.push(
+            &st.app.sources,
+            card_idx,
+            &source,
+            Some(before),
+            Some(after),
+        );
+        record_journal(st.app.data_dir(), &entry);
+    }
+
+    let session = st.sessions.get_mut(session_id).unwrap();
+    let grade_idx = match grade {
+        Grade::Forgot => 0,
+        Grade::Hard => 1,
+        Grade::Good => 2,
+        Grade::Easy => 3,
+    };
+    session.counts[grade_idx] += 1;
+    session.position += 1;
+    if session.position == session.order.len() {
+        let mut decks: Vec<String> = session
+            .order
+            .iter()
+            .map(|&i| st.app.cards[i].deck.clone())
+            .collect();
+        decks.sort();
+        decks.dedup();
+        notify(
+            &st.webhooks,
+            webhook::Event::SessionComplete {
+                counts: session.counts,
+                decks,
+                practice: session.practice,
+            },
+        );
+    }
+    save_sessions(st);
+    true
+}
+
+/// Send `event` to the webhooks off the async runtime, logging failures.
+fn notify(hooks: &[webhook::Webhook], event: webhook::Event) {
+    if hooks.is_empty() {
+        return;
+    }
+    let hooks = hooks.to_vec();
+    tokio::task::spawn_blocking(move || {
+        for e in webhook::send(&hooks, &event) {
+            tracing::warn!("{e}");
+        }
+    });
+}
+
+/// Check the due count against the webhooks' thresholds every few minutes.
+async fn watch_due(state: SharedState) {
+    let mut ticks = tokio::time::interval(std::time::Duration::from_secs(300));
+    loop {
+        ticks.tick().await;
+        let (hooks, due, data_dir) = {
+            let st = state.lock().await;
+            if st.webhooks.iter().all(|h| h.due_threshold.is_none()) {
+                return;
+            }
+            let today = clock::today();
+            let unavailable = deck::unavailable(&st.decks, clock::now());
+            let due = st
+                .app
+                .cards
+                .iter()
+                .filter(|c| review::is_due(c, today))
+                .filter(|c| !unavailable.iter().any(|d| deck::contains(d, &c.deck)))
+                .count();
+            (st.webhooks.clone(), due, st.app.data_dir().to_path_buf())
+        };
+        let today = clock::today();
+        let errors =
+            tokio::task::spawn_blocking(move || webhook::notify_due(&hooks, due, today, &data_dir))
+                .await
+                .unwrap_or_default();
+        for e in errors {
+            tracing::warn!("{e}");
+        }
+    }
+}
+
+/// Move the session's current card to the end of its queue, ungraded.
+/// Returns false when the session does not exist or is already finished.
+fn skip_current(st: &mut ServerState, session_id: &str) -> bool {
+    let Some(session) = st.sessions.get_mut(session_id) else {
+        return false;
+    };
+    if session.position >= session.order.len() {
+        return false;
+    }
+    let card_idx = session.order.remove(session.position);
+    session.order.push(card_idx);
+    save_sessions(st);
+    true
+}
+
+async fn review_submit(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(params): Form<HashMap<String, String>>,
+) -> Redirect {
+    let mut st = state.lock().await;
+    let session_id = params.get("session").cloned().unwrap_or_default();
+    let today = clock::today();
+    if params.contains_key("skip") {
+        skip_current(&mut st, &session_id);
+    } else {
+        grade_current(
+            &mut st,
+            &session_id,
+            parse_grade(&params),
+            parse_answer_ms(&params),
+            today,
+        );
+    }
+
+    if let Some(session) = st.sessions.get(&session_id)
+        && session.position >= session.order.len()
+    {
+        return Redirect::to(&urls::summary(&name, &session_id));
+    }
+
+    Redirect::to(&urls::review_session(&name, &session_id))
+}
+
+/// Grade (or, with `skip`, put off) without a page load: returns the next
+/// card's HTML fragments, or where to go once the session is over.
+///
+/// Grades queued while offline are replayed later with the 1-based `position`
+/// and the time (`at`, epoch milliseconds) they were given, which counts
+/// toward that review day. A grade whose position has already been graded is
+/// skipped, so a replay is never applied twice.
+async fn review_grade(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(params): Form<HashMap<String, String>>,
+) -> axum::response::Response {
+    let mut st = state.lock().await;
+    let session_id = params.get("session").cloned().unwrap_or_default();
+    let Some((current, started)) = st
+        .sessions
+        .get(&session_id)
+        .map(|s| (s.position + 1, s.started))
+    else {
+        return axum::http::StatusCode::NOT_FOUND.into_response();
+    };
+    let today = clock::today();
+    let day = params
+        .get("at")
+        .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms.parse().ok()?))
+        .map(|at| clock::day_of(&at))
+        .filter(|d| (started..=today).contains(d))
+        .unwrap_or(today);
+    let position = params.get("position").and_then(|p| p.parse::<usize>().ok());
+    if position.is_none_or(|p| p == current) {
+        let applied = if params.contains_key("skip") {
+            skip_current(&mut st, &session_id)
+        } else {
+            grade_current(
+                &mut st,
+                &session_id,
+                parse_grade(&params),
+                parse_answer_ms(&params),
+                day,
+            )
+        };
+        if !applied {
+            return axum::http::StatusCode::NOT_FOUND.into_response();
+        }
+    }
+
+    let session = &st.sessions[&session_id];
+    let Some(&card_idx) = session.order.get(session.position) else {
+        return axum::Json(serde_json::json!({
+            "done": true,
+            "next": urls::summary(&name, &session_id),
+        }))
+        .into_response();
+    };
+    axum::Json(serde_json::json!({
+        "done": false,
+        "position": session.position + 1,
+        "card": CardView::reviewed(&st, card_idx, &name, &session_id),
+    }))
+    .into_response()
+}
+
+/// The session's remaining cards, starting with the current one.
+async fn review_queue(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> axum::response::Response {
+    let st = state.lock().await;
+    let session_id = params.get("session").cloned().unwrap_or_default();
+    let Some(session) = st.sessions.get(&session_id) else {
+        return axum::http::StatusCode::NOT_FOUND.into_response();
+    };
+    let cards: Vec<CardView> = session.order[session.position.min(session.order.len())..]
+        .iter()
+        .map(|&i| CardView::reviewed(&st, i, &name, &session_id))
+        .collect();
+    axum::Json(serde_json::json!({
+        "position": session.position + 1,
+        "cards": cards,
+        "summary": urls::summary(&name, &session_id),
+    }))
+    .into_response()
+}
+
+async fn review_get(
+    state: State<SharedState>,
+    path: Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> axum::response::Response {
+    review_page(state, path, Form(params)).await
+}
+
+async fn summary_page(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(&st, &summaries, &name);
+    let session_id = params.get("session").cloned().unwrap_or_default();
+
+    let (counts, total, practice) = if let Some(session) = st.sessions.get(&session_id) {
+        let total: u32 = session.counts.iter().sum();
+        (session.counts, total, session.practice)
+    } else {
+        ([0u32; 4], 0, false)
+    };
+    let practice_note = if practice {
+        r#"<p class="text-sm text-[#888] mb-4">Practice session: grades were logged, scheduling was not changed.</p>"#
+    } else {
+        ""
+    };
+
+    let deck_display = if name == urls::ALL_DECKS {
+        "All decks"
+    } else {
+        &name
+    };
+    let back_btn = if name == urls::ALL_DECKS {
+        btn_primary(&urls::home(), "All decks")
+    } else {
+        btn_primary(&urls::deck(&name), "Back to deck")
+    };
+
+    let note = revlog::load_notes(st.app.data_dir()).remove(&session_id);
+    let note_form = if total > 0 && !st.read_only {
+        format!(
+            r#"<form method="post" action="{action}" class="mb-6">
+<input type="hidden" name="session" value="{session}">
+<label class="block text-xs font-medium text-[#888] mb-1" for="note">Session note</label>
+<input type="text" id="note" name="note" value="{text}" placeholder="e.g. tired, grades unreliable" class="w-full px-3 py-2 border border-[#444] rounded-md text-[0.9rem] bg-[#383838] text-[#e0e0e0] focus:outline-none focus:border-[#6ba3d6]">
+<label class="flex items-center gap-2 text-sm text-[#ccc] mt-2"><input type="checkbox" name="exclude" value="1"{checked}> Exclude from retention stats</label>
+<button type="submit" class="mt-3 inline-flex items-center px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444]">{label}</button>
+</form>"#,
+            action = html_escape(&urls::summary(&name, &session_id)),
+            session = html_escape(&session_id),
+            text = html_escape(note.as_ref().map_or("", |n| n.note.as_str())),
+            checked = if note.as_ref().is_some_and(|n| n.exclude) {
+                " checked"
+            } else {
+                ""
+            },
+            label = if note.is_some() {
+                "Update note"
+            } else {
+                "Save note"
+            },
+        )
+    } else {
+        String::new()
+    };
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6 max-w-lg">
+<h2 class="text-lg font-semibold text-[#e0e0e0] mb-4">Session Complete</h2>
+{practice_note}
+<ul class="list-none m-0 mb-6 p-0">
+<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#888]">Cards reviewed</span><span class="font-semibold text-[#e0e0e0]">{total}</span></li>
+<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#e06c6c]">Forgot</span><span class="font-semibold text-[#e0e0e0]">{forgot}</span></li>
+<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#d4a05a]">Hard</span><span class="font-semibold text-[#e0e0e0]">{hard}</span></li>
+<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#6bc06b]">Good</span><span class="font-semibold text-[#e0e0e0]">{good}</span></li>
+<li class="flex justify-between py-2 text-[0.9rem]"><span class="text-[#6ba3d6]">Easy</span><span class="font-semibold text-[#e0e0e0]">{easy}</span></li>
+</ul>
+{note_form}
+<div class="flex gap-3">{back_btn}{home_btn}</div>
+</div>
+</div>
+</div>"#,
+        sidebar = sidebar,
+        bc = breadcrumb(&[
+            ("Decks", &urls::home()),
+            (deck_display, &urls::deck_or_home(&name)),
+            ("Summary", ""),
+        ]),
+        total = total,
+        forgot = counts[0],
+        hard = counts[1],
+        good = counts[2],
+        easy = counts[3],
+        back_btn = back_btn,
+        home_btn = btn_secondary(&urls::home(), "Home"),
+        practice_note = practice_note,
+        note_form = note_form,
+    );
+    Html(page("Summary", &body))
+}
+
+#[derive(serde::Deserialize)]
+struct NoteForm {
+    session: String,
+    note: String,
+    #[serde(default)]
+    exclude: Option<String>,
+}
+
+async fn summary_note(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(form): Form<NoteForm>,
+) -> Redirect {
+    let st = state.lock().await;
+    let note = revlog::SessionNote {
+        at: Local::now(),
+        session: form.session.clone(),
+        note: form.note.trim().to_string(),
+        exclude: form.exclude.is_some(),
+    };
+    if let Err(e) = revlog::add_note(st.app.data_dir(), &note) {
+        tracing::warn!("{e}");
+    }
+    Redirect::to(&urls::summary(&name, &form.session))
+}
+
+async fn stats_page(
+    State(state): State<SharedState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(&st, &summaries, "");
+    let include_excluded = params.contains_key("all");
+
+    let reviews = revlog::load(st.app.data_dir());
+    let notes = revlog::load_notes(st.app.data_dir());
+    let sessions = stats::sessions(&reviews, &notes);
+    let percent = |r: Option<f64>| r.map_or("–".to_string(), |r| format!("{:.0}%", r * 100.0));
+    let answer_time =
+        |secs: Option<f64>| secs.map_or(String::new(), |s| format!(" · {s:.1}s per card"));
+    let toggle = if include_excluded {
+        format!(
+            r#"<a href="{}">Leave out excluded sessions</a>"#,
+            html_escape(&urls::stats(false))
+        )
+    } else {
+        format!(
+            r#"<a href="{}">Include excluded sessions</a>"#,
+            html_escape(&urls::stats(true))
+        )
+    };
+
+    let mut rows = String::new();
+    for s in &sessions {
+        let mut badges = String::new();
+        if s.practice {
+            badges.push_str(r#"<span class="text-[0.65rem] uppercase tracking-wider text-[#888] ml-2">practice</span>"#);
+        }
+        let note = match &s.note {
+            Some(n) if !n.note.is_empty() || n.exclude => {
+                if n.exclude {
+                    badges.push_str(r#"<span class="text-[0.65rem] uppercase tracking-wider text-[#d4a05a] ml-2">excluded</span>"#);
+                }
+                format!(
+                    r#"<div class="text-sm text-[#aaa] italic mt-1">{}</div>"#,
+                    html_escape(&n.note)
+                )
+            }
+            _ => String::new(),
+        };
+        rows.push_str(&format!(
+            r#"<li class="py-3 border-b border-[#333]">
+<div class="flex justify-between text-[0.9rem]"><span class="text-[#e0e0e0]">{when}{badges}</span><span class="text-[#888]">{reviews} cards · {retention} recalled{timing}</span></div>
+<div class="text-xs text-[#666] mt-0.5">{decks}</div>{note}
+</li>"#,
+            when = s.started.format("%Y-%m-%d %H:%M"),
+            reviews = s.reviews(),
+            retention = percent(s.retention()),
+            timing = answer_time(s.answer_secs()),
+            decks = html_escape(&s.decks.join(", ")),
+        ));
+    }
+    if rows.is_empty() {
+        rows.push_str(r#"<li class="py-12 text-center text-[#666]">No reviews logged yet.</li>"#);
+    }
+
+    // True retention, against what scheduling aimed for.
+    let recall_rows = |rows: &[stats::Recall], target: &dyn Fn(&str) -> f64| -> String {
+        rows.iter()
+            .map(|b| {
+                let target = target(&b.label);
+                let color = match b.rate() {
+                    Some(rate) if rate + 0.05 < target => "text-[#e06c6c]",
+                    Some(_) => "text-[#e0e0e0]",
+                    None => "text-[#666]",
+                };
+                format!(
+                    r#"<tr><td class="py-1.5 border-b border-[#333] text-[#999]">{label}</td><td class="py-1.5 border-b border-[#333] text-right text-[#888]">{total}</td><td class="py-1.5 border-b border-[#333] text-right font-semibold {color}">{rate}</td><td class="py-1.5 border-b border-[#333] text-right text-[#888]">{target:.0}%</td></tr>"#,
+                    label = html_escape(&b.label),
+                    total = b.total,
+                    rate = percent(b.rate()),
+                    target = target * 100.0,
+                )
+            })
+            .collect()
+    };
+    let recall_table = |title: &str, rows: String| {
+        format!(
+            r#"<table class="w-full text-[0.9rem] mb-6"><thead><tr class="text-[0.65rem] uppercase tracking-widest text-[#666]"><th class="text-left font-normal pb-1">{title}</th><th class="text-right font-normal pb-1">Reviews</th><th class="text-right font-normal pb-1">Recalled</th><th class="text-right font-normal pb-1">Target</th></tr></thead><tbody>{rows}</tbody></table>"#
+        )
+    };
+    let by_interval = stats::recall_by_interval(&reviews, &notes, include_excluded);
+    let by_deck = stats::recall_by_deck(&reviews, &notes, include_excluded);
+    let recall = if by_deck.is_empty() {
+        String::new()
+    } else {
+        recall_table(
+            "Scheduled interval",
+            recall_rows(&by_interval, &|_| st.retention),
+        ) + &recall_table(
+            "Deck",
+            recall_rows(&by_deck, &|deck| st.retention_for(deck)),
+        )
+    };
+
+    let difficulty = difficulty_html(&st, &reviews);
+    let added = added_html(&st.app.cards, clock::today());
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6 max-w-2xl">
+<div class="flex items-baseline justify-between mb-6">
+<div><span class="text-3xl font-semibold text-[#e0e0e0]">{retention}</span> <span class="text-sm text-[#888]">retention of reviewed cards{timing}</span></div>
+<div class="text-xs">{toggle}</div>
+</div>
+{recall}{difficulty}{added}
+<h2 class="text-[0.65rem] uppercase tracking-widest text-[#666] mb-1">Sessions</h2>
+<ul class="list-none m-0 p-0">{rows}</ul>
+</div>
+</div>
+</div>"#,
+        sidebar = sidebar,
+        bc = breadcrumb(&[("Decks", &urls::home()), ("Stats", "")]),
+        retention = percent(stats::retention(&sessions, include_excluded)),
+        timing = answer_time(stats::answer_secs(&sessions)),
+        toggle = toggle,
+        rows = rows,
+    );
+    Html(page("Stats", &body))
+}
+
+/// How many cards have each FSRS difficulty, and the hardest ones, linked to
+/// their editor so they can be rewritten.
+fn difficulty_html(st: &ServerState, reviews: &[revlog::Review]) -> String {
+    let app = &st.app;
+    let histogram = stats::difficulty_histogram(&app.cards);
+    let max = histogram.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return String::new();
+    }
+    let bars: String = histogram
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| {
+            format!(
+                r#"<div class="flex-1 flex flex-col items-center justify-end h-full" title="Difficulty {from}–{to}: {n} cards"><div class="w-full rounded-sm bg-[#2b5c85]" style="height:{height}%"></div><div class="text-[0.65rem] text-[#666] mt-1">{from}</div></div>"#,
+                from = i + 1,
+                to = i + 2,
+                height = n * 100 / max,
+            )
+        })
+        .collect();
+    let rows: String = stats::hardest(&app.cards, reviews, 20)
+        .iter()
+        .map(|h| {
+            let card = &app.cards[h.index];
+            format!(
+                r#"<li class="flex items-baseline gap-3 py-2 border-b border-[#333] text-[0.9rem]"><a href="{href}" class="flex-1 min-w-0 overflow-hidden text-ellipsis whitespace-nowrap text-[#e0e0e0] no-underline hover:underline">{front}</a><span class="text-xs text-[#888]">{deck} · {lapses} lapses</span><span class="w-10 text-right font-semibold text-[#e06c6c]">{difficulty:.1}</span></li>"#,
+                href = html_escape(&if st.read_only {
+                    urls::card_info(&card.id)
+                } else {
+                    urls::card_edit(&card.id)
+                }),
+                front = markdown::inline(&truncate(&full_card(app, h.index).front, 60)),
+                deck = html_escape(&card.deck),
+                lapses = h.lapses,
+                difficulty = h.difficulty,
+            )
+        })
+        .collect();
+    format!(
+        r#"<h2 class="text-[0.65rem] uppercase tracking-widest text-[#666] mb-2">Difficulty</h2>
+<div class="flex items-end gap-1 h-24 mb-6">{bars}</div>
+<h2 class="text-[0.65rem] uppercase tracking-widest text-[#666] mb-1">Hardest cards</h2>
+<ul class="list-none m-0 mb-6 p-0">{rows}</ul>"#
+    )
+}
+
+/// Cards added on each of the last 30 days; empty until any were.
+fn added_html(cards: &[Card], today: chrono::NaiveDate) -> String {
+    let days = stats::added_per_day(cards, today, 30);
+    let max = days.iter().map(|d| d.1).max().unwrap_or(0);
+    if max == 0 {
+        return String::new();
+    }
+    let bars: String = days
+        .iter()
+        .map(|&(day, n)| {
+            format!(
+                r#"<div class="flex-1 h-full flex items-end" title="{day}: {n} added"><div class="w-full rounded-sm bg-[#2b5c85]" style="height:{height}%"></div></div>"#,
+                day = day.format("%b %d"),
+                height = n * 100 / max,
+            )
+        })
+        .collect();
+    format!(
+        r#"<h2 class="text-[0.65rem] uppercase tracking-widest text-[#666] mb-2">Cards added, last 30 days</h2>
+<div class="flex items-end gap-0.5 h-16 mb-6">{bars}</div>"#
+    )
+}
+
+/// A logged grade's name and colour.
+fn grade_label(grade: u8) -> (&'static str, &'static str) {
+    match grade {
+        1 => ("Forgot", "text-[#e06c6c]"),
+        2 => ("Hard", "text-[#d4a05a]"),
+        3 => ("Good", "text-[#6bc06b]"),
+        _ => ("Easy", "text-[#6ba3d6]"),
+    }
+}
+
+/// Every review given on one day (`?day=`, else the latest day with any),
+/// beside a list of the days with reviews.
+async fn history_page(
+    State(state): State<SharedState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(&st, &summaries, "");
+    let reviews = revlog::load(st.app.data_dir());
+    let days = stats::by_day(&reviews);
+    let selected = params
+        .get("day")
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .or_else(|| days.first().map(|(day, _)| *day))
+        .unwrap_or(today);
+    let by_id: HashMap<&str, usize> = st
+        .app
+        .cards
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.id.as_str(), i))
+        .collect();
+
+    let day_links: String = days
+        .iter()
+        .map(|(day, list)| {
+            let cls = if *day == selected {
+                "text-[#e0e0e0] font-semibold"
+            } else {
+                "text-[#999]"
+            };
+            format!(
+                r#"<li><a href="{href}" class="flex justify-between py-1 text-sm no-underline {cls} hover:!text-[#d4d4d4]"><span>{day}</span><span class="text-[#666]">{n}</span></a></li>"#,
+                href = html_escape(&urls::history(Some(*day))),
+                day = day.format("%a %Y-%m-%d"),
+                n = list.len(),
+            )
+        })
+        .collect();
+
+    let mut rows = String::new();
+    let mut answer_ms = 0;
+    let day_reviews = days
+        .iter()
+        .find(|(day, _)| *day == selected)
+        .map_or(&[][..], |(_, list)| list.as_slice());
+    for r in day_reviews {
+        let (grade, color) = grade_label(r.grade);
+        let card = match by_id.get(r.card_id.as_str()) {
+            Some(&i) => format!(
+                r#"<a href="{}" class="text-[#e0e0e0] no-underline hover:underline">{}</a>"#,
+                html_escape(&urls::card_info(&r.card_id)),
+                markdown::inline(&truncate(&full_card(&st.app, i).front, 60)),
+            ),
+            None => r#"<span class="text-[#666] italic">deleted card</span>"#.to_string(),
+        };
+        let mut detail = vec![html_escape(&r.deck)];
+        if let Some(ms) = r.answer_ms {
+            answer_ms += ms;
+            detail.push(format!("{:.1}s", ms as f64 / 1000.0));
+        }
+        if r.practice {
+            detail.push("practice".to_string());
+        }
+        rows.push_str(&format!(
+            r#"<li class="flex items-baseline gap-3 py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#666] text-xs">{when}</span><span class="{color} w-14 shrink-0">{grade}</span><span class="flex-1 min-w-0 overflow-hidden text-ellipsis whitespace-nowrap">{card}</span><span class="text-xs text-[#888]">{detail}</span></li>"#,
+            when = r.at.format("%H:%M"),
+            detail = detail.join(" · "),
+        ));
+    }
+    if rows.is_empty() {
+        rows.push_str(r#"<li class="py-12 text-center text-[#666]">No reviews on this day.</li>"#);
+    }
+    let summary = if day_reviews.is_empty() {
+        String::new()
+    } else {
+        let secs = answer_ms / 1000;
+        let answering = if secs < 60 {
+            format!("{secs}s")
+        } else {
+            format!("{} min", (secs as f64 / 60.0).round())
+        };
+        format!("{} reviews · {answering} answering", day_reviews.len())
+    };
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6 flex gap-8">
+<ul class="list-none m-0 p-0 w-44 shrink-0">{day_links}</ul>
+<div class="flex-1 max-w-2xl min-w-0">
+<div class="flex items-baseline justify-between mb-3">
+<h2 class="text-lg font-semibold text-[#e0e0e0]">{day}</h2>
+<span class="text-sm text-[#888]">{summary}</span>
+</div>
+<ul class="list-none m-0 p-0">{rows}</ul>
+</div>
+</div>
+</div>
+</div>"#,
+        bc = breadcrumb(&[("Decks", &urls::home()), ("History", "")]),
+        day = selected.format("%A %Y-%m-%d"),
+    );
+    Html(page("History", &body))
+}
+
+/// A card's scheduling state and every logged review of it.
+async fn card_info(State(state): State<SharedState>, Path(id): Path<String>) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+        return Html(page("Not Found", "<p>Card not found.</p>"));
+    };
+    let card = full_card(&st.app, i);
+    let sidebar = sidebar_html(&st, &summaries, &card.deck);
+    let reviews = revlog::load(st.app.data_dir());
+    let history = revlog::history(&reviews, &id);
+
+    let or_dash = |v: Option<String>| v.unwrap_or_else(|| "—".to_string());
+    let mut status = Vec::new();
+    if card.suspended {
+        status.push("suspended");
+    }
+    if card.flagged {
+        status.push("flagged");
+    }
+    let facts = [
+        (
+            "Stability",
+            or_dash(card.stability.map(|s| format!("{s:.1} days"))),
+        ),
+        (
+            "Difficulty",
+            or_dash(card.difficulty.map(|d| format!("{d:.1}"))),
+        ),
+        ("Due", card.due.map_or("new".to_string(), |d| d.to_string())),
+        (
+            "Last review",
+            or_dash(card.last_review.map(|d| d.to_string())),
+        ),
+        ("Added", or_dash(card.created.map(|d| d.to_string()))),
+        (
+            "Retrievability today",
+            or_dash(
+                review::predicted_retrievability(&card, today)
+                    .map(|r| format!("{:.0}%", r * 100.0)),
+            ),
+        ),
+        ("Reviews", history.len().to_string()),
+        ("Lapses", revlog::lapses(&history).to_string()),
+        (
+            "Status",
+            if status.is_empty() {
+                "active".to_string()
+            } else {
+                status.join(", ")
+            },
+        ),
+    ];
+    let facts: String = facts
+        .iter()
+        .map(|(label, value)| {
+            format!(
+                r#"<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#888]">{label}</span><span class="font-semibold text-[#e0e0e0]">{}</span></li>"#,
+                html_escape(value)
+            )
+        })
+        .collect();
+
+    let mut rows = String::new();
+    for r in history.iter().rev() {
+        let (grade, color) = grade_label(r.grade);
+        let mut detail = Vec::new();
+        if let Some(days) = r.elapsed_days {
+            detail.push(format!("{days}d since last review"));
+        }
+        if let Some(days) = r.scheduled_days {
+            detail.push(format!("{days}d scheduled"));
+        }
+        if let Some(ms) = r.answer_ms {
+            detail.push(format!("answered in {:.1}s", ms as f64 / 1000.0));
+        }
+        let practice = if r.practice {
+            r#"<span class="text-[0.65rem] uppercase tracking-wider text-[#888] ml-2">practice</span>"#
+        } else {
+            ""
+        };
+        rows.push_str(&format!(
+            r#"<li class="flex justify-between py-2 border-b border-[#333] text-[0.9rem]"><span class="text-[#e0e0e0]">{when}<span class="{color} ml-2">{grade}</span>{practice}</span><span class="text-[#888]">{detail}</span></li>"#,
+            when = r.at.format("%Y-%m-%d %H:%M"),
+            detail = detail.join(" · "),
+        ));
+    }
+    if rows.is_empty() {
+        rows.push_str(r#"<li class="py-12 text-center text-[#666]">Not reviewed yet.</li>"#);
+    }
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6 max-w-2xl">
+<div class="flex justify-between items-start gap-4 mb-4">
+<div class="text-[#e0e0e0] whitespace-pre-wrap">{front}</div>
+{edit}
+</div>
+<ul class="list-none m-0 mb-6 p-0">{facts}</ul>
+<h2 class="text-[0.65rem] uppercase tracking-widest text-[#666] mb-1">History</h2>
+<ul class="list-none m-0 p-0">{rows}</ul>
+</div>
+</div>
+</div>"#,
+        bc = breadcrumb(&[
+            ("Decks", &urls::home()),
+            (&card.deck, &urls::deck_card(&card.deck, &id)),
+            ("Info", ""),
+        ]),
+        front = html_escape(&card::expand_escapes(&card.front)),
+        edit = if st.read_only {
+            String::new()
+        } else {
+            btn_secondary(&urls::card_edit(&id), "Edit")
+        },
+    );
+    Html(page("Card Info", &body))
+}
+
+/// Query of the edit form: where to go once the card is saved or the edit
+/// cancelled, e.g. back to a review session. The deck page if unset.
+#[derive(serde::Deserialize, Default)]
+struct ReturnTo {
+    #[serde(default)]
+    return_to: String,
+}
+
+impl ReturnTo {
+    fn or_deck(&self, deck: &str) -> String {
+        if urls::is_local(&self.return_to) {
+            self.return_to.clone()
+        } else {
+            urls::deck(deck)
+        }
+    }
+}
+
+/// Links opening card `card`'s row in `source` and, for an imported card,
+/// the file it came from, through `editor_url`.
+fn source_links(card: &Card, source: &std::path::Path) -> String {
+    let row = card::load_csv_lines(source)
+        .ok()
+        .and_then(|rows| rows.into_iter().find(|(_, c)| c.id == card.id))
+        .map(|(line, _)| card::Origin {
+            path: source.to_path_buf(),
+            line: Some(line),
+            anchor: None,
+        });
+    let origin = card::Origin::parse(&card.origin, source);
+    let site = site::current();
+    let link = |label: &str, at: &card::Origin| {
+        let path = at.path.canonicalize().unwrap_or_else(|_| at.path.clone());
+        let template = site.editor_url.as_deref().unwrap_or("file://{path}");
+        let url = template
+            .replace("{path}", &path.display().to_string().replace(' ', "%20"))
+            .replace("{line}", &at.line.unwrap_or(1).to_string());
+        format!(
+            r#"<span>{label} <a href="{}" class="!text-[#888] hover:!text-[#ccc]">{}</a></span>"#,
+            html_escape(&url),
+            html_escape(&at.to_string())
+        )
+    };
+    let links: Vec<String> = [("Row", row), ("Open source", origin)]
+        .iter()
+        .filter_map(|(label, at)| at.as_ref().map(|at| link(label, at)))
+        .collect();
+    if links.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"<div class="flex flex-wrap gap-3 text-xs text-[#888] mb-4">{}</div>"#,
+        links.join("")
+    )
+}
+
+async fn card_edit_form(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    axum::extract::Query(back_to): axum::extract::Query<ReturnTo>,
+) -> Html<String> {
+    let st = state.lock().await;
+    let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+        return Html(page("Not Found", "<p>Card not found.</p>"));
+    };
+    let card = full_card(&st.app, i).into_owned();
+    Html(edit_page(&st, i, &card, &back_to, None))
+}
+
+/// The edit form of card `i`, filled in with `card`. With `conflict`, the
+/// card as saved by someone else since `card` was edited: it is shown above
+/// the form, whose revision is then the saved one, so saving again replaces it.
+fn edit_page(
+    st: &ServerState,
+    i: usize,
+    card: &Card,
+    back_to: &ReturnTo,
+    conflict: Option<&Card>,
+) -> String {
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let saved = full_card(&st.app, i);
+    let id = saved.id.clone();
+
+    let deck = card.deck.clone();
+    let sidebar = sidebar_html(st, &summaries, &deck);
+
+    let input_cls = "w-full px-3 py-2.5 border border-[#444] rounded-md text-[0.9rem] bg-[#383838] text-[#e0e0e0] focus:outline-none focus:border-[#6ba3d6] focus:ring-2 focus:ring-[#6ba3d6]/15";
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6">
+<div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-xl">
+<div class="flex justify-between items-center mb-5">
+<h2 class="text-lg font-semibold text-[#e0e0e0] m-0">Edit Card</h2>
+<div class="flex items-center gap-3">
+{masks}<form method="post" action="{delete_url}" onsubmit="return confirm('Delete this card?')" class="inline">
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] text-[#e06c6c] border border-[#444] cursor-pointer hover:bg-[#3d2a2a]">Delete</button>
+</form>
+</div>
+</div>
+{conflict}{source}<form method="post" action="{edit_url}">
+<input type="hidden" name="return_to" value="{return_to}">
+<input type="hidden" name="revision" value="{revision}">
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="deck">Deck</label>
+<input type="text" id="deck" name="deck" value="{deck}" class="{input_cls}">
+</div>
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="front">Front</label>
+<textarea id="front" name="front" rows="4" class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit">{front}</textarea>
+<button type="button" data-clozify="{clozify_url}" class="mt-2 inline-flex items-center gap-1 px-2.5 py-1 rounded-md text-xs font-medium bg-[#383838] text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444]">Suggest clozes</button>
+</div>
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="back">Back</label>
+<textarea id="back" name="back" rows="4" class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit">{back}</textarea>
+</div>
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="extra">Extra</label>
+<textarea id="extra" name="extra" rows="2" placeholder="notes, source or mnemonic, shown under the answer" class="{input_cls} resize-y leading-relaxed" style="font-family:inherit">{extra}</textarea>
+</div>
+{preview}
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="tags">Tags</label>
+<input type="text" id="tags" name="tags" value="{tags}" placeholder="space-separated" class="{input_cls}">
+</div>
+<div class="flex gap-3 mt-5">
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Save</button>
+<a href="{deck_url}" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">Cancel</a>
+</div>
+</form>
+</div>
+</div>
+</div>
+</div>
+<script>document.addEventListener('keydown',function(e){{if((e.ctrlKey||e.metaKey)&&e.key==='Enter'){{e.preventDefault();document.querySelector('form[action*="edit"]').submit();}}}});</script>"#,
+        sidebar = sidebar,
+        bc = breadcrumb(&[
+            ("Decks", &urls::home()),
+            (&deck, &urls::deck(&deck)),
+            ("Edit", ""),
+        ]),
+        masks = match occlusion_image(&saved) {
+            Some(_) => format!(
+                r#"<a href="{}" class="text-sm !text-[#888] no-underline hover:!text-[#ccc]">Image masks</a>"#,
+                html_escape(&urls::card_masks(&id))
+            ),
+            None => String::new(),
+        },
+        conflict = conflict.map_or(String::new(), conflict_html),
+        source = source_links(&saved, &st.app.sources[i]),
+        revision = card::revision(&saved),
+        delete_url = html_escape(&urls::card_delete(&id)),
+        edit_url = html_escape(&urls::card_edit(&id)),
+        clozify_url = html_escape(&urls::clozify(&card.deck)),
+        deck = html_escape(&card.deck),
+        deck_url = html_escape(&back_to.or_deck(&card.deck)),
+        return_to = html_escape(&back_to.return_to),
+        front = html_escape(&card.front),
+        back = html_escape(&card.back),
+        extra = html_escape(&card.extra),
+        tags = html_escape(&card.tags.join(" ")),
+        preview = preview_pane(&id),
+        input_cls = input_cls,
+    );
+    page("Edit Card", &body)
+}
+
+/// The merge prompt shown when a save finds the card changed since its form
+/// was opened: what is saved now, next to the form holding the user's edits.
+fn conflict_html(saved: &Card) -> String {
+    let fields: String = [
+        ("Deck", saved.deck.clone()),
+        ("Front", saved.front.clone()),
+        ("Back", saved.back.clone()),
+        ("Extra", saved.extra.clone()),
+        ("Tags", saved.tags.join(" ")),
+    ]
+    .iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(label, value)| {
+        format!(
+            r#"<div class="text-xs font-medium text-[#888] mt-2">{label}</div><div class="text-[#e0e0e0] whitespace-pre-wrap">{}</div>"#,
+            html_escape(value)
+        )
+    })
+    .collect();
+    format!(
+        r#"<div class="mb-4 p-3 rounded-md border border-[#d4a05a] text-sm">
+<p class="m-0 font-semibold text-[#d4a05a]">This card was changed elsewhere since you opened it.</p>
+<p class="m-0 mt-1 text-[#888]">Your edits are in the form below. The saved version is:</p>
+{fields}
+<p class="m-0 mt-2 text-[#888]">Merge in anything you want to keep, then save again to replace it.</p>
+</div>
+"#
+    )
+}
+
+#[derive(serde::Deserialize)]
+struct CardForm {
+    deck: String,
+    front: String,
+    back: String,
+    #[serde(default)]
+    extra: String,
+    #[serde(default)]
+    tags: String,
+    /// `card::revision` of the card the form was filled from.
+    #[serde(default)]
+    revision: String,
+    #[serde(flatten)]
+    back_to: ReturnTo,
+}
+
+async fn card_edit_submit(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Form(form): Form<CardForm>,
+) -> axum::response::Response {
+    let mut st = state.lock().await;
+
+    let found = st.app.cards.iter().position(|c| c.id == id);
+    if let Some(mut i) = found {
+        materialize(&mut st.app, i);
+        let before = st.app.cards[i].clone();
+        let mut after = before.clone();
+        after.deck = form.deck.clone();
+        after.front = form.front;
+        after.back = form.back;
+        after.extra = form.extra;
+        after.tags = card::parse_tags(&form.tags);
+        // Saved from another tab or client since this form was opened.
+        if !form.revision.is_empty() && form.revision != card::revision(&before) {
+            let html = edit_page(&st, i, &after, &form.back_to, Some(&before));
+            return (axum::http::StatusCode::CONFLICT, Html(html)).into_response();
+        }
+        if wants_fetch(&st, &after) {
+            let media_dir = card::media_dir(&st.app.sources[i]);
+            drop(st);
+            after = localize_media(after, media_dir).await;
+            st = state.lock().await;
+            let Some(j) = st.app.cards.iter().position(|c| c.id == id) else {
+                return Redirect::to(&form.back_to.or_deck(&form.deck)).into_response();
+            };
+            // Saved elsewhere while the images downloaded.
+            if card::revision(&st.app.cards[j]) != card::revision(&before) {
+                let saved = st.app.cards[j].clone();
+                let html = edit_page(&st, j, &after, &form.back_to, Some(&saved));
+                return (axum::http::StatusCode::CONFLICT, Html(html)).into_response();
+            }
+            i = j;
+        }
+        st.app.cards[i] = after.clone();
+
+        let source = st.app.sources[i].clone();
+        save_file(&mut st.app, &source);
+
+        let mut entry = journal::Entry::new("edit");
+        entry.push(&st.app.sources, i, &source, Some(before), Some(after));
+        record_journal(st.app.data_dir(), &entry);
+    }
+
+    Redirect::to(&form.back_to.or_deck(&form.deck)).into_response()
+}
+
+/// The card's first image, for the mask editor.
+fn occlusion_image(card: &Card) -> Option<(&str, Vec<occlusion::Mask>)> {
+    occlusion::media(card)
+        .into_iter()
+        .find(|(file, _)| media_type(file).is_some_and(|t| t.starts_with("image/")))
+}
+
+/// Mask editor: drag over the card's image to hide a part of it, click a
+/// mask to remove it.
+async fn card_masks_form(State(state): State<SharedState>, Path(id): Path<String>) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+        return Html(page("Not Found", "<p>Card not found.</p>"));
+    };
+    let card = full_card(&st.app, i);
+    let Some((file, masks)) = occlusion_image(&card) else {
+        return Html(page("Not Found", "<p>This card has no image.</p>"));
+    };
+    let sidebar = sidebar_html(&st, &summaries, &card.deck);
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6">
+<div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-3xl">
+<h2 class="text-lg font-semibold text-[#e0e0e0] m-0 mb-2">Image Masks</h2>
+<p class="text-sm text-[#888] mb-4">Drag over the image to hide a part of it until the answer is shown. Click a mask to remove it.</p>
+<div class="occluded select-none" data-mask-editor><img src="{src}" alt="" draggable="false" class="max-w-full rounded-md">{masks_html}</div>
+<form method="post" action="{action}" data-mask-form>
+<input type="hidden" name="file" value="{file}">
+<input type="hidden" name="masks" value="{masks}">
+<div class="flex gap-3 mt-5">
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Save</button>
+<a href="{edit_url}" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">Cancel</a>
+</div>
+</form>
+</div>
+</div>
+</div>
+</div>"#,
+        bc = breadcrumb(&[
+            ("Decks", &urls::home()),
+            (&card.deck, &urls::deck(&card.deck)),
+            ("Edit", &urls::card_edit(&id)),
+            ("Masks", ""),
+        ]),
+        src = html_escape(&urls::card_media(&id, file)),
+        masks_html = masks_html(&masks),
+        action = html_escape(&urls::card_masks(&id)),
+        file = html_escape(file),
+        masks = html_escape(&occlusion::format(&masks)),
+        edit_url = html_escape(&urls::card_edit(&id)),
+    );
+    Html(page("Image Masks", &body))
+}
+
+#[derive(serde::Deserialize)]
+struct MasksForm {
+    file: String,
+    #[serde(default)]
+    masks: String,
+}
+
+async fn card_masks_submit(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Form(form): Form<MasksForm>,
+) -> Redirect {
+    let mut st = state.lock().await;
+    let masks = match occlusion::parse(&form.masks) {
+        Ok(masks) => masks,
+        Err(e) => {
+            tracing::error!("{e}");
+            return Redirect::to(&urls::card_masks(&id));
+        }
+    };
+    if let Some(i) = st.app.cards.iter().position(|c| c.id == id) {
+        materialize(&mut st.app, i);
+        let before = st.app.cards[i].clone();
+        if occlusion::set(&mut st.app.cards[i], &form.file, &masks)
+            && st.app.cards[i].media != before.media
+        {
+            let after = st.app.cards[i].clone();
+            let source = st.app.sources[i].clone();
+            save_file(&mut st.app, &source);
+
+            let mut entry = journal::Entry::new("edit masks");
+            entry.push(&st.app.sources, i, &source, Some(before), Some(after));
+            record_journal(st.app.data_dir(), &entry);
+        }
+    }
+    Redirect::to(&urls::card_edit(&id))
+}
+
+#[derive(serde::Deserialize)]
+struct ClozifyForm {
+    text: String,
+}
+
+/// Front text with suggested cloze deletions bracketed, using the deck's tags
+/// as terms.
+async fn card_clozify(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(form): Form<ClozifyForm>,
+) -> String {
+    let st = state.lock().await;
+    let mut terms: Vec<String> = Vec::new();
+    for tag in st
+        .app
+        .cards
+        .iter()
+        .filter(|c| deck::contains(&name, &c.deck))
+        .flat_map(|c| &c.tags)
+    {
+        if !terms.contains(tag) {
+            terms.push(tag.clone());
+        }
+    }
+    clozify::apply(&form.text, &clozify::suggest(&form.text, &terms))
+}
+
+#[derive(serde::Deserialize)]
+struct PreviewForm {
+    front: String,
+    back: String,
+    #[serde(default)]
+    extra: String,
+    /// The card being edited, whose media the preview shows.
+    #[serde(default)]
+    id: String,
+}
+
+#[derive(serde::Serialize)]
+struct Preview {
+    #[serde(flatten)]
+    card: CardView,
+    /// `lint` problems with the text, shown under the preview.
+    warnings: Vec<String>,
+}
+
+/// The card being written as the review page would show it.
+async fn card_preview(
+    State(state): State<SharedState>,
+    Form(form): Form<PreviewForm>,
+) -> axum::Json<Preview> {
+    let st = state.lock().await;
+    let media = st
+        .app
+        .cards
+        .iter()
+        .find(|c| !form.id.is_empty() && c.id == form.id)
+        .map(|c| c.media.clone())
+        .unwrap_or_default();
+    let warnings = crate::lint::check(&form.front, &form.back);
+    let card = CardView::new(&Card {
+        deck: String::new(),
+        front: form.front,
+        back: form.back,
+        extra: form.extra,
+        media,
+        origin: String::new(),
+        id: form.id,
+        stability: None,
+        difficulty: None,
+        due: None,
+        last_review: None,
+        created: None,
+        tags: Vec::new(),
+        suspended: false,
+        flagged: false,
+        ease: None,
+        interval: None,
+    });
+    axum::Json(Preview { card, warnings })
+}
+
+/// The editor's live preview, filled in by `editor.js`.
+fn preview_pane(card_id: &str) -> String {
+    format!(
+        r#"<div class="mb-4" data-preview="{url}" data-card="{card_id}">
+<div class="flex justify-between items-center mb-1">
+<span class="block text-xs font-medium text-[#888]">Preview</span>
+<button type="button" data-preview-flip class="inline-flex items-center gap-1 px-2.5 py-1 rounded-md text-xs font-medium bg-[#383838] text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444]">Show answer</button>
+</div>
+<div class="border border-[#3a3a3a] rounded-xl overflow-hidden">
+<div data-preview-front class="{answer_cls}"></div>
+<div data-preview-media></div>
+<div data-preview-back style="display:none"></div>
+</div>
+<ul data-preview-warnings class="mt-1.5 text-xs text-[#e06c6c]"></ul>
+</div>"#,
+        url = html_escape(&urls::preview()),
+        card_id = html_escape(card_id),
+        answer_cls = ANSWER_CLS,
+    )
+}
+
+async fn card_new_form(State(state): State<SharedState>, Path(name): Path<String>) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(&st, &summaries, &name);
+
+    let input_cls = "w-full px-3 py-2.5 border border-[#444] rounded-md text-[0.9rem] bg-[#383838] text-[#e0e0e0] focus:outline-none focus:border-[#6ba3d6] focus:ring-2 focus:ring-[#6ba3d6]/15";
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6">
+<div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-xl">
+<h2 class="text-lg font-semibold text-[#e0e0e0] mb-5">New Card</h2>
+<form method="post" action="{new_url}">
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="front">Front</label>
+<textarea id="front" name="front" rows="4" autofocus class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit"></textarea>
+<button type="button" data-clozify="{clozify_url}" class="mt-2 inline-flex items-center gap-1 px-2.5 py-1 rounded-md text-xs font-medium bg-[#383838] text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444]">Suggest clozes</button>
+</div>
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="back">Back</label>
+<textarea id="back" name="back" rows="4" class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit"></textarea>
+</div>
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="extra">Extra</label>
+<textarea id="extra" name="extra" rows="2" placeholder="notes, source or mnemonic, shown under the answer" class="{input_cls} resize-y leading-relaxed" style="font-family:inherit"></textarea>
+</div>
+{preview}
+<div class="mb-4">
+<label class="block text-xs font-medium text-[#888] mb-1" for="tags">Tags</label>
+<input type="text" id="tags" name="tags" placeholder="space-separated" class="{input_cls}">
+</div>
+<div class="flex gap-3 mt-5">
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Create</button>
+<a href="{deck_url}" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] !text-[#ccc] border border-[#444] no-underline hover:bg-[#444] hover:!text-[#e0e0e0]">Cancel</a>
+</div>
+</form>
+</div>
+</div>
+</div>
+</div>
+<script>document.addEventListener('keydown',function(e){{if((e.ctrlKey||e.metaKey)&&e.key==='Enter'){{e.preventDefault();document.querySelector('form').submit();}}}});</script>"#,
+        sidebar = sidebar,
+        bc = breadcrumb(&[
+            ("Decks", &urls::home()),
+            (&name, &urls::deck(&name)),
+            ("New", ""),
+        ]),
+        new_url = html_escape(&urls::card_new(&name)),
+        clozify_url = html_escape(&urls::clozify(&name)),
+        deck_url = html_escape(&urls::deck(&name)),
+        preview = preview_pane(""),
+        input_cls = input_cls,
+    );
+    Html(page("New Card", &body))
+}
+
+#[derive(serde::Deserialize)]
+struct NewCardForm {
+    front: String,
+    back: String,
+    #[serde(default)]
+    extra: String,
+    #[serde(default)]
+    tags: String,
+}
+
+async fn card_new_submit(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Form(form): Form<NewCardForm>,
+) -> Redirect {
+    let mut st = state.lock().await;
+
+    let Some(source) = deck_file(&st, &name) else {
+        return Redirect::to(&urls::home());
+    };
+
+    let mut new_card = Card {
+        deck: name.clone(),
+        front: form.front,
+        back: form.back,
+        extra: form.extra,
+        media: String::new(),
+        origin: String::new(),
+        id: uuid::Uuid::new_v4().to_string(),
+        stability: None,
+        difficulty: None,
+        due: None,
+        last_review: None,
+        created: Some(clock::today()),
+        tags: card::parse_tags(&form.tags),
+        suspended: false,
+        flagged: false,
+        ease: None,
+        interval: None,
+    };
+    if wants_fetch(&st, &new_card) {
+        drop(st);
+        new_card = localize_media(new_card, card::media_dir(&source)).await;
+        st = state.lock().await;
+    }
+
+    let mut entry = journal::Entry::new("add");
+    entry.push(
+        &st.app.sources,
+        st.app.cards.len(),
+        &source,
+        None,
+        Some(new_card.clone()),
+    );
+
+    st.app.sources.push(source.clone());
+    st.app.cards.push(new_card);
+
+    save_file(&mut st.app, &source);
+    record_journal(st.app.data_dir(), &entry);
+    add_siblings(&mut st);
+
+    Redirect::to(&urls::deck(&name))
+}
+
+/// Whether `card`'s deck has its linked images downloaded on save and it
+/// links any (see `media::localize`).
+fn wants_fetch(st: &ServerState, card: &Card) -> bool {
+    deck::setting(&st.decks, &card.deck, |c| c.fetch_media).unwrap_or(false)
+        && media::has_remote(card)
+}
+
+/// `card` with its linked images downloaded into `media_dir`. Downloads can
+/// take a while, so callers unlock the state meanwhile; failures are logged
+/// and leave the link.
+async fn localize_media(card: Card, media_dir: PathBuf) -> Card {
+    let fallback = card.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut card = card;
+        for e in media::localize(&mut card, |url| media::fetch(url, &media_dir)) {
+            tracing::warn!("{e}");
+        }
+        card
+    })
+    .await
+    .unwrap_or(fallback)
+}
+
+/// The file new cards for `deck` go in: one already holding cards of the deck
+/// (or of a deck below it), else the first file loaded.
+fn deck_file(st: &ServerState, deck: &str) -> Option<PathBuf> {
+    st.app
+        .cards
+        .iter()
+        .position(|c| deck::contains(deck, &c.deck))
+        .map(|i| st.app.sources[i].clone())
+        .or_else(|| st.app.sources.first().cloned())
+}
+
+#[derive(serde::Deserialize, Default)]
+struct ImportForm {
+    #[serde(default)]
+    deck: String,
+    #[serde(default)]
+    text: String,
+    /// `tab`, `semicolon`, or empty to guess.
+    #[serde(default)]
+    separator: String,
+    /// Set by the button under the preview.
+    #[serde(default)]
+    confirm: Option<String>,
+}
+
+async fn import_form(
+    State(state): State<SharedState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Html<String> {
+    let st = state.lock().await;
+    let form = ImportForm {
+        deck: params.get("deck").cloned().unwrap_or_default(),
+        ..ImportForm::default()
+    };
+    import_page(&st, &form, None, 0)
+}
+
+/// Preview pasted cards, or with `confirm` add them to the deck.
+async fn import_submit(
+    State(state): State<SharedState>,
+    Form(form): Form<ImportForm>,
+) -> axum::response::Response {
+    let mut st = state.lock().await;
+    let deck = form.deck.trim();
+    let term_sep = match form.separator.as_str() {
+        "tab" => "\t",
+        "semicolon" => ";",
+        _ => quizlet::guess_term_sep(&form.text),
+    };
+    let mut parsed = if deck.is_empty() {
+        Err("Choose a deck to import into.".to_string())
+    } else {
+        quizlet::parse(&form.text, term_sep, "\n", deck).map_err(|e| e.to_string())
+    };
+    let known = match &mut parsed {
+        Ok(imported) => card::drop_known_fronts(&st.app.cards, &mut imported.cards),
+        Err(_) => 0,
+    };
+    let imported = match parsed {
+        Ok(imported) if form.confirm.is_some() && !imported.cards.is_empty() => imported,
+        parsed => return import_page(&st, &form, Some(parsed), known).into_response(),
+    };
+    let Some(source) = deck_file(&st, deck) else {
+        return Redirect::to(&urls::home()).into_response();
+    };
+    snapshot_before("import", &st.app, std::slice::from_ref(&source));
+
+    let mut entry = journal::Entry::new("import");
+    for card in imported.cards {
+        entry.push(
+            &st.app.sources,
+            st.app.cards.len(),
+            &source,
+            None,
+            Some(card.clone()),
+        );
+        st.app.sources.push(source.clone());
+        st.app.cards.push(card);
+    }
+    save_file(&mut st.app, &source);
+    record_journal(st.app.data_dir(), &entry);
+    add_siblings(&mut st);
+    Redirect::to(&urls::deck(deck)).into_response()
+}
+
+fn import_page(
+    st: &ServerState,
+    form: &ImportForm,
+    parsed: Option<Result<quizlet::QuizletImport, String>>,
+    known: usize,
+) -> Html<String> {
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(st, &summaries, "");
+    let input_cls = "w-full px-3 py-2.5 border border-[#444] rounded-md text-[0.9rem] bg-[#383838] text-[#e0e0e0] focus:outline-none focus:border-[#6ba3d6] focus:ring-2 focus:ring-[#6ba3d6]/15";
+    let label_cls = "block text-xs font-medium text-[#888] mb-1";
+    let cell_cls = "text-left px-3 py-2 border-b border-[#333]";
+
+    let deck_options: String = summaries
+        .iter()
+        .map(|s| format!(r#"<option value="{}">"#, html_escape(&s.name)))
+        .collect();
+    let separator = |value: &str| {
+        if form.separator == value {
+            " selected"
+        } else {
+            ""
+        }
+    };
+
+    let preview = match &parsed {
+        None => String::new(),
+        Some(Err(e)) => format!(
+            r#"<p class="mt-4 text-sm text-[#e06c6c]">{}</p>"#,
+            html_escape(e)
+        ),
+        Some(Ok(imported)) if imported.cards.is_empty() && known > 0 => format!(
+            r#"<p class="mt-4 text-sm text-[#888]">All {known} card(s) are already in the deck.</p>"#
+        ),
+        Some(Ok(imported)) if imported.cards.is_empty() => {
+            r#"<p class="mt-4 text-sm text-[#888]">Nothing to import yet.</p>"#.to_string()
+        }
+        Some(Ok(imported)) => {
+            let rows: String = imported
+                .cards
+                .iter()
+                .map(|c| {
+                    format!(
+                        r#"<tr><td class="{cell_cls} text-[#e0e0e0]">{}</td><td class="{cell_cls} text-[#aaa]">{}</td></tr>"#,
+                        html_escape(&c.front),
+                        html_escape(&c.back),
+                    )
+                })
+                .collect();
+            let skipped = match imported.skipped {
+                0 => String::new(),
+                n => format!(" {n} line(s) without a separator will be skipped."),
+            };
+            let skipped = match known {
+                0 => skipped,
+                n => format!("{skipped} {n} card(s) already in the deck will be skipped."),
+            };
+            format!(
+                r#"<div class="mt-6">
+<p class="text-sm text-[#888] mb-2">{n} card(s) for <span class="text-[#e0e0e0]">{deck}</span>.{skipped}</p>
+<table class="w-full text-sm"><thead><tr><th class="{cell_cls} text-[#888] font-medium">Front</th><th class="{cell_cls} text-[#888] font-medium">Back</th></tr></thead><tbody>{rows}</tbody></table>
+<button type="submit" name="confirm" value="1" class="mt-4 inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Import {n} card(s)</button>
+</div>"#,
+                n = imported.cards.len(),
+                deck = html_escape(form.deck.trim()),
+            )
+        }
+    };
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6">
+<div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-3xl">
+<h2 class="text-lg font-semibold text-[#e0e0e0] mb-1">Import</h2>
+<p class="text-sm text-[#888] mb-5">Paste one card per line as <code>front&lt;Tab&gt;back</code> or <code>front;back</code>, as copied from a spreadsheet.</p>
+<form method="post" action="{action}">
+<div class="grid grid-cols-2 gap-4 mb-4">
+<div><label class="{label_cls}" for="deck">Deck</label><input type="text" id="deck" name="deck" list="deck-names" value="{deck}" required class="{input_cls}"><datalist id="deck-names">{deck_options}</datalist></div>
+<div><label class="{label_cls}" for="separator">Separator</label><select id="separator" name="separator" class="{input_cls}"><option value="">Guess</option><option value="tab"{tab}>Tab</option><option value="semicolon"{semicolon}>Semicolon</option></select></div>
+</div>
+<label class="{label_cls}" for="text">Cards</label>
+<textarea id="text" name="text" rows="12" autofocus class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit">{text}</textarea>
+<div class="flex gap-3 mt-4">
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444] hover:!text-[#e0e0e0]">Preview</button>
+</div>
+{preview}
+</form>
+</div>
+</div>
+</div>
+</div>"#,
+        bc = breadcrumb(&[("Decks", &urls::home()), ("Import", "")]),
+        action = html_escape(&urls::import()),
+        deck = html_escape(&form.deck),
+        tab = separator("tab"),
+        semicolon = separator("semicolon"),
+        text = html_escape(&form.text),
+    );
+    Html(page("Import", &body))
+}
+
+async fn card_delete(State(state): State<SharedState>, Path(id): Path<String>) -> Redirect {
+    let mut st = state.lock().await;
+
+    let pos = st.app.cards.iter().position(|c| c.id == id);
+    if let Some(i) = pos {
+        materialize(&mut st.app, i);
+        let deck = st.app.cards[i].deck.clone();
+        let source = st.app.sources[i].clone();
+        let mut entry = journal::Entry::new("delete");
+        entry.push(
+            &st.app.sources,
+            i,
+            &source,
+            Some(st.app.cards[i].clone()),
+            None,
+        );
+        let trashed = st
+            .app
+            .lock_files()
+            .and_then(|()| trash::add(st.app.dir(), &source, &st.app.cards[i]));
+        if let Err(e) = trashed {
+            tracing::warn!("{e}");
+        }
+        st.app.cards.remove(i);
+        st.app.sources.remove(i);
+        save_file(&mut st.app, &source);
+        record_journal(st.app.data_dir(), &entry);
+        // Keep the browser's selection on the card that took this one's place.
+        let next = st.app.cards[i..]
+            .iter()
+            .chain(st.app.cards[..i].iter().rev())
+            .find(|c| c.deck == deck);
+        return match next {
+            Some(c) => Redirect::to(&urls::deck_card(&deck, &c.id)),
+            None => Redirect::to(&urls::deck(&deck)),
+        };
+    }
+
+    Redirect::to(&urls::home())
+}
+
+/// Cards deleted in the last `trash::DAYS` days, each with a restore button.
+async fn trash_page(State(state): State<SharedState>) -> Html<String> {
+    let st = state.lock().await;
+    let today = clock::today();
+    let summaries = review::deck_summaries(&st.app.cards, today);
+    let sidebar = sidebar_html(&st, &summaries, "");
+    // Undo may already have put a card back.
+    let present: HashSet<&str> = st.app.cards.iter().map(|c| c.id.as_str()).collect();
+    let mut rows = String::new();
+    for entry in trash::list(st.app.dir()) {
+        let c = &entry.card;
+        if present.contains(c.id.as_str()) {
+            continue;
+        }
+        rows.push_str(&format!(
+            r#"<li class="py-3 border-b border-[#333] flex items-center gap-4">
+<div class="flex-1 min-w-0"><div class="text-[0.9rem] text-[#e0e0e0] overflow-hidden text-ellipsis whitespace-nowrap">{front}</div>
+<div class="text-xs text-[#666] mt-0.5">{deck} · deleted {at}</div></div>
+<form method="post" action="{restore}"><button type="submit" class="px-3 py-1.5 rounded-md text-sm bg-[#383838] text-[#d4d4d4] border border-[#444] cursor-pointer hover:bg-[#404040]">Restore</button></form>
+</li>"#,
+            front = markdown::inline(&truncate(&c.front, 80)),
+            deck = html_escape(&c.deck),
+            at = entry.at.format("%Y-%m-%d %H:%M"),
+            restore = html_escape(&urls::trash_restore(&c.id)),
+        ));
+    }
+    if rows.is_empty() {
+        rows.push_str(r#"<li class="py-12 text-center text-[#666]">The trash is empty.</li>"#);
+    }
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6 max-w-2xl">
+<p class="text-sm text-[#888] mb-5">Deleted cards are kept for {days} days.</p>
+<ul class="list-none m-0 p-0">{rows}</ul>
+</div>
+</div>
+</div>"#,
+        bc = breadcrumb(&[("Decks", &urls::home()), ("Trash", "")]),
+        days = trash::DAYS,
+    );
+    Html(page("Trash", &body))
+}
+
+/// Put a deleted card back at the end of the file it was deleted from.
+async fn trash_restore(State(state): State<SharedState>, Path(id): Path<String>) -> Redirect {
+    let mut st = state.lock().await;
+    let entry = match st
+        .app
+        .lock_files()
+        .and_then(|()| trash::take(st.app.dir(), &id))
+    {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return Redirect::to(&urls::trash()),
+        Err(e) => {
+            tracing::warn!("{e}");
+            return Redirect::to(&urls::trash());
+        }
+    };
+    let card = entry.card;
+    if st.app.cards.iter().any(|c| c.id == card.id) {
+        return Redirect::to(&urls::trash());
+    }
+    // The file may have been renamed or left out since.
+    let Some(source) = Some(entry.file)
+        .filter(|f| st.app.files.contains(f))
+        .or_else(|| deck_file(&st, &card.deck))
+    else {
+        return Redirect::to(&urls::trash());
+    };
+    let mut entry = journal::Entry::new("restore");
+    entry.push(
+        &st.app.sources,
+        st.app.cards.len(),
+        &source,
+        None,
+        Some(card.clone()),
+    );
+    let deck = card.deck.clone();
+    st.app.sources.push(source.clone());
+    st.app.cards.push(card);
+    save_file(&mut st.app, &source);
+    record_journal(st.app.data_dir(), &entry);
+    Redirect::to(&urls::deck_card(&deck, &id))
+}
+
+async fn card_suspend(State(state): State<SharedState>, Path(id): Path<String>) -> Redirect {
+    toggle_card(&state, &id, "suspend", |c| c.suspended = !c.suspended).await
+}
+
+async fn card_flag(State(state): State<SharedState>, Path(id): Path<String>) -> Redirect {
+    toggle_card(&state, &id, "flag", |c| c.flagged = !c.flagged).await
+}
+
+async fn toggle_card(
+    state: &SharedState,
+    id: &str,
+    action: &str,
+    toggle: fn(&mut Card),
+) -> Redirect {
+    let mut st = state.lock().await;
+    let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+        return Redirect::to(&urls::home());
+    };
+    toggle_saved(&mut st, i, action, toggle);
+    Redirect::to(&urls::deck_card(&st.app.cards[i].deck, id))
+}
+
+/// Apply `toggle` to card `i`, save it and journal it as `action`.
+fn toggle_saved(st: &mut ServerState, i: usize, action: &str, toggle: fn(&mut Card)) {
+    materialize(&mut st.app, i);
+    let before = st.app.cards[i].clone();
+    toggle(&mut st.app.cards[i]);
+    let source = st.app.sources[i].clone();
+    save_file(&mut st.app, &source);
+
+    let mut entry = journal::Entry::new(action);
+    entry.push(
+        &st.app.sources,
+        i,
+        &source,
+        Some(before),
+        Some(st.app.cards[i].clone()),
+    );
+    record_journal(st.app.data_dir(), &entry);
+}
+
+async fn card_media(
+    State(state): State<SharedState>,
+    Path((id, file)): Path<(String, String)>,
+) -> axum::response::Response {
+    let st = state.lock().await;
+    let not_found = || axum::http::StatusCode::NOT_FOUND.into_response();
+
+    let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+        return not_found();
+    };
+    // Only serve files the card actually references, never arbitrary paths.
+    if !card::media_files(&full_card(&st.app, i)).contains(&file.as_str())
+        || file.contains(['/', '\\'])
+        || file.starts_with('.')
+    {
+        return not_found();
+    }
+    let path = card::media_dir(&st.app.sources[i]).join(&file);
+    let Ok(bytes) = std::fs::read(&path) else {
+        return not_found();
+    };
+    let content_type = media_type(&file).unwrap_or("application/octet-stream");
+    ([("content-type", content_type)], bytes).into_response()
+}
+
+/// One side of a card read aloud, synthesized on first request.
+async fn card_speech(
+    State(state): State<SharedState>,
+    Path((id, side)): Path<(String, String)>,
+) -> axum::response::Response {
+    let not_found = || axum::http::StatusCode::NOT_FOUND.into_response();
+    let (tts, text, dir) = {
+        let st = state.lock().await;
+        let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+            return not_found();
+        };
+        let card = full_card(&st.app, i);
+        let Some(tts) = deck::setting(&st.decks, &card.deck, |c| c.tts.clone()) else {
+            return not_found();
+        };
+        let Some(side) = tts::Side::parse(&side).filter(|s| *s != tts::Side::Both) else {
+            return not_found();
+        };
+        (tts, tts::text(&card, side), st.app.dir().to_path_buf())
+    };
+    // Synthesis can take a while; the state stays unlocked meanwhile.
+    let audio = tokio::task::spawn_blocking(move || {
+        let (path, content_type) = tts.audio(&dir, &text)?;
+        let bytes = std::fs::read(&path).map_err(Error::io("read", &path))?;
+        Ok::<_, Error>((content_type, bytes))
+    })
+    .await;
+    match audio {
+        Ok(Ok((content_type, bytes))) => ([("content-type", content_type)], bytes).into_response(),
+        Ok(Err(e)) => {
+            tracing::warn!("{e}");
+            axum::http::StatusCode::BAD_GATEWAY.into_response()
+        }
+        Err(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn undo(State(state): State<SharedState>) -> Redirect {
+    let mut st = state.lock().await;
+    let entry = match journal::pop(st.app.data_dir()) {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return Redirect::to(&urls::home()),
+        Err(e) => {
+            tracing::error!("{e}");
+            return Redirect::to(&urls::home());
+        }
+    };
+
+    let st = &mut *st;
+    let count_before = st.app.cards.len();
+    let touched = journal::revert(&entry, &mut st.app.cards, &mut st.app.sources);
+    // Restored cards come from the journal with their content.
+    for change in &entry.changes {
+        for c in change.before.iter().chain(&change.after) {
+            st.app.index.set_loaded(&change.file, &c.id);
+        }
+    }
+    for path in &touched {
+        save_file(&mut st.app, path);
+    }
+    // Sessions hold card indices, which shift when cards are added or removed.
+    if st.app.cards.len() != count_before {
+        st.sessions.clear();
+        save_sessions(st);
+    }
+    Redirect::to(&urls::home())
+}
+
+// -- Installable app --
+
+async fn manifest() -> axum::response::Response {
+    let manifest = serde_json::json!({
+        "name": "rote",
+        "short_name": "rote",
+        "start_url": urls::home(),
+        "scope": urls::home(),
+        "display": "standalone",
+        "background_color": THEME_COLOR,
+        "theme_color": THEME_COLOR,
+        "icons": [{ "src": urls::icon(), "sizes": "any", "type": "image/svg+xml" }],
+    });
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "application/manifest+json",
+        )],
+        manifest.to_string(),
+    )
+        .into_response()
+}
+
+async fn service_worker() -> axum::response::Response {
+    (
+        [
+            (axum::http::header::CONTENT_TYPE, "text/javascript"),
+            (axum::http::header::CACHE_CONTROL, "no-cache"),
+        ],
+        SERVICE_WORKER_JS,
+    )
+        .into_response()
+}
+
+async fn icon() -> axum::response::Response {
+    (
+        [(axum::http::header::CONTENT_TYPE, "image/svg+xml")],
+        ICON_SVG,
+    )
+        .into_response()
+}
+
+async fn katex_js() -> axum::response::Response {
+    (
+        [
+            (axum::http::header::CONTENT_TYPE, "text/javascript"),
+            (axum::http::header::CACHE_CONTROL, "public, max-age=604800"),
+        ],
+        KATEX_JS,
+    )
+        .into_response()
+}
+
+// -- API --
+
+/// A card pushed through the API. `media` names files already in the deck
+/// file's media directory.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiCard {
+    deck: String,
     front: String,
+    #[serde(default)]
     back: String,
+    #[serde(default)]
+    extra: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    media: Vec<String>,
+    /// Add the card even when its deck already has one with the same front
+    /// (compared by `card::normalized_front`).
+    #[serde(default)]
+    allow_duplicate: bool,
 }
 
-async fn card_new_submit(
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum ApiCards {
+    One(ApiCard),
+    Many(Vec<ApiCard>),
+}
+
+/// `POST /api/v1/cards`: add one card (answering `{"id": ...}`) or an array
+/// of them (`{"ids": [...]}`), for scripts and browser extensions. Nothing is
+/// added unless every card is valid.
+async fn api_create_cards(
     State(state): State<SharedState>,
-    Path(name): Path<String>,
-    Form(form): Form<NewCardForm>,
-) -> Redirect {
+    axum::Json(body): axum::Json<ApiCards>,
+) -> axum::response::Response {
+    use axum::http::StatusCode;
+    let error = |status: StatusCode, message: String| {
+        (status, axum::Json(serde_json::json!({ "error": message }))).into_response()
+    };
+    let (cards, one) = match body {
+        ApiCards::One(card) => (vec![card], true),
+        ApiCards::Many(cards) => (cards, false),
+    };
+
     let mut st = state.lock().await;
+    let mut new_cards: Vec<(PathBuf, Card)> = Vec::new();
+    for (n, c) in cards.into_iter().enumerate() {
+        let deck = c.deck.trim();
+        if deck.is_empty() || c.front.trim().is_empty() {
+            return error(
+                StatusCode::BAD_REQUEST,
+                format!("card {n}: deck and front are required"),
+            );
+        }
+        let front = card::normalized_front(&c.front);
+        let same =
+            |other: &Card| other.deck == deck && card::normalized_front(&other.front) == front;
+        // Fronts of cards indexed with `--low-memory` are still on disk.
+        let duplicate = (0..st.app.cards.len())
+            .filter(|&i| st.app.cards[i].deck == deck)
+            .any(|i| st.app.full_card(i).is_ok_and(|other| same(&other)))
+            || new_cards.iter().any(|(_, other)| same(other));
+        if duplicate && !c.allow_duplicate {
+            return error(
+                StatusCode::CONFLICT,
+                format!("card {n}: {deck} already has a card with this front"),
+            );
+        }
+        let Some(source) = deck_file(&st, deck) else {
+            return error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "no deck files loaded".to_string(),
+            );
+        };
+        new_cards.push((
+            source,
+            Card {
+                deck: deck.to_string(),
+                front: c.front,
+                back: c.back,
+                extra: c.extra,
+                media: c.media.join(";"),
+                origin: String::new(),
+                id: uuid::Uuid::new_v4().to_string(),
+                stability: None,
+                difficulty: None,
+                due: None,
+                last_review: None,
+                created: Some(clock::today()),
+                tags: card::parse_tags(&c.tags.join(" ")),
+                suspended: false,
+                flagged: false,
+                ease: None,
+                interval: None,
+            },
+        ));
+    }
 
-    let source = st
-        .app
-        .cards
-        .iter()
-        .enumerate()
-        .find(|(_, c)| c.deck == name)
-        .map(|(i, _)| st.app.sources[i].clone())
-        .or_else(|| st.app.sources.first().cloned());
+    let fetch: Vec<bool> = new_cards.iter().map(|(_, c)| wants_fetch(&st, c)).collect();
+    if fetch.contains(&true) {
+        drop(st);
+        for ((source, card), fetch) in new_cards.iter_mut().zip(fetch) {
+            if fetch {
+                *card = localize_media(card.clone(), card::media_dir(source)).await;
+            }
+        }
+        st = state.lock().await;
+    }
 
-    let Some(source) = source else {
-        return Redirect::to("/");
-    };
+    let mut entry = journal::Entry::new("add");
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut ids = Vec::new();
+    for (source, card) in new_cards {
+        entry.push(
+            &st.app.sources,
+            st.app.cards.len(),
+            &source,
+            None,
+            Some(card.clone()),
+        );
+        ids.push(card.id.clone());
+        st.app.sources.push(source.clone());
+        st.app.cards.push(card);
+        if !files.contains(&source) {
+            files.push(source);
+        }
+    }
+    for file in &files {
+        save_file(&mut st.app, file);
+    }
+    record_journal(st.app.data_dir(), &entry);
+    add_siblings(&mut st);
 
-    let new_card = Card {
-        deck: name.clone(),
-        front: form.front,
-        back: form.back,
-        media: String::new(),
-        id: uuid::Uuid::new_v4().to_string(),
-        stability: None,
-        difficulty: None,
-        due: None,
-        last_review: None,
+    let body = if one {
+        serde_json::json!({ "id": ids[0] })
+    } else {
+        serde_json::json!({ "ids": ids })
     };
+    (StatusCode::CREATED, axum::Json(body)).into_response()
+}
 
-    st.app.sources.push(source.clone());
-    st.app.cards.push(new_card);
+/// The card as JSON, with its revision as the `ETag` a later `PUT` sends
+/// back in `If-Match`.
+fn api_card(status: axum::http::StatusCode, card: &Card) -> axum::response::Response {
+    let etag = format!("\"{}\"", card::revision(card));
+    (status, [(axum::http::header::ETAG, etag)], axum::Json(card)).into_response()
+}
 
-    save_file(&st.app.cards, &st.app.sources, &source);
+/// `GET /api/v1/cards/{id}`.
+async fn api_get_card(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> axum::response::Response {
+    use axum::http::StatusCode;
+    let st = state.lock().await;
+    let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+        let body = serde_json::json!({ "error": format!("no card {id}") });
+        return (StatusCode::NOT_FOUND, axum::Json(body)).into_response();
+    };
+    api_card(StatusCode::OK, &full_card(&st.app, i))
+}
 
-    Redirect::to(&format!("/deck/{}", name))
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiEdit {
+    deck: Option<String>,
+    front: Option<String>,
+    back: Option<String>,
+    extra: Option<String>,
+    tags: Option<Vec<String>>,
 }
 
-async fn card_delete(State(state): State<SharedState>, Path(id): Path<String>) -> Redirect {
+/// `PUT /api/v1/cards/{id}`: change the fields given. With `If-Match`, a card
+/// saved since that revision is refused with 412 and sent back as it is now,
+/// for the client to merge and retry.
+async fn api_update_card(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+    axum::Json(edit): axum::Json<ApiEdit>,
+) -> axum::response::Response {
+    use axum::http::StatusCode;
     let mut st = state.lock().await;
+    let Some(i) = st.app.cards.iter().position(|c| c.id == id) else {
+        let body = serde_json::json!({ "error": format!("no card {id}") });
+        return (StatusCode::NOT_FOUND, axum::Json(body)).into_response();
+    };
+    materialize(&mut st.app, i);
+    let before = st.app.cards[i].clone();
+    let if_match = headers
+        .get(axum::http::header::IF_MATCH)
+        .and_then(|v| v.to_str().ok());
+    if let Some(expected) = if_match {
+        let current = card::revision(&before);
+        let matches = expected.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/").trim_matches('"') == current
+        });
+        if !matches {
+            return api_card(StatusCode::PRECONDITION_FAILED, &before);
+        }
+    }
+    if edit.deck.as_deref().is_some_and(|d| d.trim().is_empty())
+        || edit.front.as_deref().is_some_and(|f| f.trim().is_empty())
+    {
+        let body = serde_json::json!({ "error": "deck and front cannot be empty" });
+        return (StatusCode::BAD_REQUEST, axum::Json(body)).into_response();
+    }
 
-    let pos = st.app.cards.iter().position(|c| c.id == id);
-    if let Some(i) = pos {
-        let deck = st.app.cards[i].deck.clone();
-        let source = st.app.sources[i].clone();
-        st.app.cards.remove(i);
-        st.app.sources.remove(i);
-        save_file(&st.app.cards, &st.app.sources, &source);
-        return Redirect::to(&format!("/deck/{}", deck));
+    let mut after = before.clone();
+    after.deck = edit.deck.unwrap_or(after.deck);
+    after.front = edit.front.unwrap_or(after.front);
+    after.back = edit.back.unwrap_or(after.back);
+    after.extra = edit.extra.unwrap_or(after.extra);
+    if let Some(tags) = edit.tags {
+        after.tags = card::parse_tags(&tags.join(" "));
     }
+    st.app.cards[i] = after.clone();
+    let source = st.app.sources[i].clone();
+    save_file(&mut st.app, &source);
+    let mut entry = journal::Entry::new("edit");
+    entry.push(
+        &st.app.sources,
+        i,
+        &source,
+        Some(before),
+        Some(after.clone()),
+    );
+    record_journal(st.app.data_dir(), &entry);
+    api_card(StatusCode::OK, &after)
+}
 
-    Redirect::to("/")
+// -- Sync --
+
+/// `rote sync` from another machine: keep whichever side reviewed each card
+/// last (ties stay here) and answer with the merged states.
+async fn sync_states(
+    State(state): State<SharedState>,
+    axum::Json(incoming): axum::Json<BTreeMap<String, CardState>>,
+) -> axum::Json<BTreeMap<String, CardState>> {
+    let mut st = state.lock().await;
+    let newer: Vec<usize> = (0..st.app.cards.len())
+        .filter(|&i| {
+            let card = &st.app.cards[i];
+            incoming
+                .get(&card.id)
+                .is_some_and(|s| state::wins(s, &CardState::of(card), false))
+        })
+        .collect();
+    let mut entry = journal::Entry::new("sync");
+    let mut files = Vec::new();
+    for &i in &newer {
+        materialize(&mut st.app, i);
+        let before = st.app.cards[i].clone();
+        incoming[&before.id].set(&mut st.app.cards[i]);
+        let source = st.app.sources[i].clone();
+        entry.push(
+            &st.app.sources,
+            i,
+            &source,
+            Some(before),
+            Some(st.app.cards[i].clone()),
+        );
+        if !files.contains(&source) {
+            files.push(source);
+        }
+    }
+    for file in &files {
+        save_file(&mut st.app, file);
+    }
+    record_journal(st.app.data_dir(), &entry);
+    axum::Json(state::export(&st.app.cards))
 }
 
 // -- Helpers --
 
-fn save_file(cards: &[Card], sources: &[PathBuf], target: &PathBuf) {
-    let file_cards: Vec<Card> = cards
-        .iter()
-        .enumerate()
-        .filter(|(i, _)| sources[*i] == *target)
-        .map(|(_, c)| c.clone())
-        .collect();
-    if let Err(e) = card::save_csv(target, &file_cards) {
-        eprintln!("Error saving {}: {e}", target.display());
+fn save_sessions(st: &ServerState) {
+    if st.read_only {
+        return;
+    }
+    let today = clock::today();
+    if let Err(e) = sessions::save(st.app.data_dir(), &st.sessions, &st.app.cards, today) {
+        tracing::warn!("{e}");
     }
 }
 
-fn shuffle<T>(items: &mut [T]) {
-    let mut state: u64 = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64;
-    if state == 0 {
-        state = 1;
+/// Snapshot `files` and the review log before `action` rewrites them.
+fn snapshot_before(action: &str, app: &Collection, files: &[PathBuf]) {
+    let files = backup::with_revlog(files, app.data_dir());
+    if let Err(e) = backup::before(action, &files, app.dir()) {
+        tracing::warn!("no snapshot before {action}: {e}");
+    }
+}
+
+fn record_journal(data_dir: &std::path::Path, entry: &journal::Entry) {
+    if let Err(e) = journal::record(data_dir, entry) {
+        tracing::warn!("{e}");
     }
-    for i in (1..items.len()).rev() {
-        state ^= state << 13;
-        state ^= state >> 7;
-        state ^= state << 17;
-        let j = (state as usize) % (i + 1);
-        items.swap(i, j);
+}
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &s[..i]),
+        None => s.to_string(),
     }
 }
 
@@ -814,60 +4283,645 @@ use axum::response::IntoResponse;
 
 // -- Public entry point --
 
-pub async fn serve(paths: Vec<String>, port: u16) {
-    let files = card::discover_files(&paths);
-    if files.is_empty() {
-        eprintln!("No CSV files found.");
-        std::process::exit(1);
-    }
-
-    let mut all_cards: Vec<Card> = Vec::new();
-    let mut card_sources: Vec<PathBuf> = Vec::new();
+pub struct ServeOptions {
+    pub port: u16,
+    /// Path prefix when mounted behind a reverse proxy, e.g. `/rote`.
+    pub base_path: String,
+    pub files: card::PathFilter,
+    pub retention: f64,
+    /// Maximum number of new cards introduced per day.
+    pub new_per_day: Option<usize>,
+    /// Session order for decks whose settings choose none.
+    pub order: review::Order,
+    /// Seconds before the answer is revealed without a key press.
+    pub auto_reveal: Option<u64>,
+    /// Suggest a break this often during a review.
+    pub breaks: review::Breaks,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    /// Index cards at startup and read their content from disk when shown.
+    pub low_memory: bool,
+    /// Review with this user's scheduling (see `crate::user`).
+    pub user: Option<String>,
+    /// `user:password` required on every request, via HTTP Basic auth.
+    pub auth: Option<String>,
+    /// PEM certificate chain and private key; serves HTTPS when both are set.
+    pub tls: Option<(PathBuf, PathBuf)>,
+    /// Load Tailwind and KaTeX from CDNs rather than the bundled assets.
+    pub cdn: bool,
+    /// Link opening a card's source file, with `{path}` and `{line}`.
+    pub editor_url: Option<String>,
+    pub webhooks: Vec<webhook::Webhook>,
+    /// Endpoint for card suggestions; needs the `llm` feature.
+    pub llm: Option<crate::config::Llm>,
+    /// Most verbose events logged (see `logging`).
+    pub log_level: tracing::level_filters::LevelFilter,
+    /// Log JSON lines instead of text.
+    pub log_json: bool,
+    /// Browse and practise without saving anything (see `public`).
+    pub read_only: bool,
+    /// See `OpenOptions::data_dir`.
+    pub data_dir: Option<PathBuf>,
+    pub git_friendly: bool,
+}
 
-    for file in &files {
-        match card::load_csv(file) {
-            Ok(cards) => {
-                for c in cards {
-                    card_sources.push(file.clone());
-                    all_cards.push(c);
-                }
-            }
-            Err(e) => {
-                eprintln!("Warning: {e}");
-            }
+impl Default for ServeOptions {
+    fn default() -> Self {
+        ServeOptions {
+            port: 3000,
+            base_path: String::new(),
+            files: card::PathFilter::default(),
+            retention: crate::fsrs::DEFAULT_RETENTION,
+            new_per_day: None,
+            order: review::Order::default(),
+            auto_reveal: None,
+            breaks: review::Breaks::default(),
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            low_memory: false,
+            user: None,
+            auth: None,
+            tls: None,
+            cdn: false,
+            editor_url: None,
+            webhooks: Vec::new(),
+            llm: None,
+            log_level: tracing::level_filters::LevelFilter::WARN,
+            log_json: false,
+            read_only: false,
+            data_dir: None,
+            git_friendly: false,
         }
     }
+}
 
-    println!(
-        "Loaded {} cards from {} files.",
-        all_cards.len(),
-        files.len()
-    );
-
-    let state = Arc::new(Mutex::new(ServerState {
-        app: AppState {
-            cards: all_cards,
-            sources: card_sources,
-        },
-        sessions: HashMap::new(),
-    }));
-
+/// The pages and API under the base path, before `--auth`.
+fn router(state: SharedState, read_only: bool) -> Router {
     let app = Router::new()
         .route("/", get(index))
         .route("/deck/{name}", get(deck_detail))
         .route("/deck/{name}/review", get(review_get).post(review_submit))
-        .route("/deck/{name}/summary", get(summary_page))
+        .route("/deck/{name}/review/grade", post(review_grade))
+        .route("/deck/{name}/review/queue", get(review_queue))
+        .route("/deck/{name}/queue", get(queue_page).post(queue_submit))
+        .route("/deck/{name}/custom", get(custom_session_form))
+        .route(
+            "/deck/{name}/settings",
+            get(deck_settings_form).post(deck_settings_submit),
+        )
+        .route("/deck/{name}/reschedule", post(deck_reschedule))
+        .route("/deck/{name}/summary", get(summary_page).post(summary_note))
+        .route("/stats", get(stats_page))
+        .route("/history", get(history_page))
+        .route("/import", get(import_form).post(import_submit))
         .route("/deck/{name}/new", get(card_new_form).post(card_new_submit))
+        .route("/deck/{name}/clozify", post(card_clozify))
+        .route("/preview", post(card_preview))
         .route(
             "/card/{id}/edit",
             get(card_edit_form).post(card_edit_submit),
         )
+        .route("/card/{id}", get(card_info))
         .route("/card/{id}/delete", post(card_delete))
+        .route("/card/{id}/suspend", post(card_suspend))
+        .route("/card/{id}/flag", post(card_flag))
+        .route("/card/{id}/media/{file}", get(card_media))
+        .route(
+            "/card/{id}/masks",
+            get(card_masks_form).post(card_masks_submit),
+        )
+        .route("/card/{id}/speech/{side}", get(card_speech))
+        .route("/api/v1/cards", post(api_create_cards))
+        .route("/api/v1/cards/{id}", get(api_get_card).put(api_update_card))
+        .route("/trash", get(trash_page))
+        .route("/trash/{id}/restore", post(trash_restore))
+        .route("/undo", post(undo))
+        .route("/manifest.webmanifest", get(manifest))
+        .route("/sw.js", get(service_worker))
+        .route("/icon.svg", get(icon))
+        .route("/assets/katex.min.js", get(katex_js))
+        .route(
+            crate::sync::ENDPOINT,
+            // A whole collection's states can exceed axum's 2 MB default.
+            post(sync_states).layer(DefaultBodyLimit::max(64 << 20)),
+        );
+    #[cfg(feature = "llm")]
+    let app = app
+        .route("/generate", get(generate::form).post(generate::suggest))
+        .route("/generate/accept", post(generate::accept));
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            users::select,
+        ))
         .with_state(state);
+    if read_only {
+        app.layer(axum::middleware::from_fn(public::guard))
+    } else {
+        app
+    }
+}
+
+/// Load the cards under `paths` and serve them until the process ends.
+pub async fn serve(paths: Vec<String>, options: ServeOptions) -> crate::Result<()> {
+    logging::init(options.log_level, options.log_json);
+    let st = ServerState::open(&paths, &options)?;
+    for e in &st.app.skipped {
+        tracing::warn!("{e}");
+    }
+    println!(
+        "Loaded {} cards from {} files.",
+        st.app.cards.len(),
+        st.app.files.len()
+    );
+    if let Some(name) = &options.user {
+        println!("Reviewing as {name}.");
+    }
+    #[cfg(not(feature = "llm"))]
+    if options.llm.is_some() {
+        tracing::warn!("[llm] is set but rote was built without the llm feature");
+    }
+    let state = SharedState::new(st);
+    tokio::spawn(watch_due(state.clone()));
+
+    let app = router(state.clone(), options.read_only);
+
+    let port = options.port;
+    let site = Arc::new(site::Site::new(&options));
+    let app = if site.base_path.is_empty() {
+        app
+    } else {
+        Router::new().nest(&site.base_path, app)
+    };
+    let app = match options.auth.as_deref().map(auth::Credentials::parse) {
+        Some(Ok(credentials)) => app.layer(axum::middleware::from_fn_with_state(
+            credentials,
+            auth::require,
+        )),
+        Some(Err(e)) => return Err(e),
+        None if options.read_only => app,
+        None => {
+            println!("Warning: no --auth set; anyone who can reach port {port} can edit cards.");
+            app
+        }
+    };
+    // Probes sit at the root, outside the base path and any --auth.
+    let app = app
+        .merge(
+            Router::new()
+                .route("/healthz", get(healthz))
+                .route("/readyz", get(readyz))
+                .with_state(state.clone()),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            site.clone(),
+            site::scope,
+        ))
+        .layer(axum::middleware::from_fn(logging::access));
+    let home = urls::join(&site.base_path, "/");
 
     let addr = format!("0.0.0.0:{port}");
-    println!("Serving at http://localhost:{port}");
+    let listen_err = |source| Error::Listen {
+        addr: addr.clone(),
+        source,
+    };
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(listen_err)?;
+    match &options.tls {
+        Some((cert, key)) => {
+            let acceptor = tls::acceptor(cert, key)?;
+            let listener = tls::TlsListener::new(listener, acceptor).map_err(listen_err)?;
+            println!("Serving at https://localhost:{port}{home}");
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+                .map_err(listen_err)?;
+        }
+        None => {
+            println!("Serving at http://localhost:{port}{home}");
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+                .map_err(listen_err)?;
+        }
+    }
+    // In-flight requests have finished; write whatever they left behind.
+    let mut st = state.lock().await;
+    if let Err(e) = st.app.save() {
+        tracing::error!("{e}");
+    }
+    save_sessions(&st);
+    println!("Stopped.");
+    Ok(())
+}
+
+/// Resolves on Ctrl-C or SIGTERM, as sent by `docker stop`.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Liveness probe: the process is up and answering.
+async fn healthz() -> &'static str {
+    "ok\n"
+}
+
+/// Readiness probe: the collection is loaded and not stuck behind a lock.
+async fn readyz(State(state): State<SharedState>) -> axum::response::Response {
+    match tokio::time::timeout(std::time::Duration::from_secs(1), state.lock()).await {
+        Ok(_) => "ready\n".into_response(),
+        Err(_) => (axum::http::StatusCode::SERVICE_UNAVAILABLE, "busy\n").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A server on one deck file, `es.csv`, holding `rows`.
+    fn server(dir: &std::path::Path, rows: &str, options: ServeOptions) -> SharedState {
+        std::fs::write(dir.join("es.csv"), format!("deck,front,back\n{rows}")).unwrap();
+        let paths = [dir.to_string_lossy().into_owned()];
+        SharedState::new(ServerState::open(&paths, &options).unwrap())
+    }
+
+    /// Run `request` as a request picking `name` would.
+    async fn as_user<F: Future>(state: &SharedState, name: &str, request: F) -> F::Output {
+        let user = Some(name.to_string());
+        state.lock().await.load_user(&user).unwrap();
+        users::USER.scope(user, request).await
+    }
+
+    /// Send `request` through the whole router, as a browser's would go.
+    async fn respond(
+        state: &SharedState,
+        request: axum::http::Request<axum::body::Body>,
+    ) -> axum::response::Response {
+        use tower::ServiceExt;
+        router(state.clone(), false).oneshot(request).await.unwrap()
+    }
+
+    /// `respond`, as the status and the body.
+    async fn send(
+        state: &SharedState,
+        request: axum::http::Request<axum::body::Body>,
+    ) -> (axum::http::StatusCode, String) {
+        let response = respond(state, request).await;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    async fn get(state: &SharedState, uri: &str) -> (axum::http::StatusCode, String) {
+        let request = axum::http::Request::get(uri).body(axum::body::Body::empty());
+        send(state, request.unwrap()).await
+    }
+
+    /// Submit `form`, URL-encoded, as a browser's form would.
+    async fn post(state: &SharedState, uri: &str, form: &str) -> (axum::http::StatusCode, String) {
+        let request = axum::http::Request::post(uri)
+            .header(
+                axum::http::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(axum::body::Body::from(form.to_string()));
+        send(state, request.unwrap()).await
+    }
+
+    /// A server on the deck files already in `dir`.
+    fn open(dir: &std::path::Path) -> SharedState {
+        let paths = [dir.to_string_lossy().into_owned()];
+        SharedState::new(ServerState::open(&paths, &ServeOptions::default()).unwrap())
+    }
+
+    async fn json(response: axum::response::Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn users_grade_the_same_card_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = server(dir.path(), "es,hola,hello\n", ServeOptions::default());
+        let csv = std::fs::read_to_string(dir.path().join("es.csv")).unwrap();
+        let start = || async {
+            let started = review_get(
+                State(state.clone()),
+                Path("es".into()),
+                axum::extract::Query(HashMap::new()),
+            )
+            .await;
+            let location = started.headers()[axum::http::header::LOCATION].to_str();
+            let (_, session) = location.unwrap().split_once("session=").unwrap();
+            session.to_string()
+        };
+        let grade = |session: &str, grade: &str| {
+            let params = HashMap::from([
+                ("session".to_string(), session.to_string()),
+                ("grade".to_string(), grade.to_string()),
+            ]);
+            review_grade(State(state.clone()), Path("es".into()), Form(params))
+        };
+        let due = || async { state.lock().await.app.cards[0].due };
+
+        let alex = as_user(&state, "alex", start()).await;
+        let sam = as_user(&state, "sam", start()).await;
+        as_user(&state, "alex", grade(&alex, "3")).await;
+        // Sessions are the user's own.
+        let foreign = as_user(&state, "sam", grade(&alex, "1")).await;
+        assert_eq!(foreign.status(), axum::http::StatusCode::NOT_FOUND);
+        as_user(&state, "sam", grade(&sam, "1")).await;
+
+        let today = clock::today();
+        let alex_due = as_user(&state, "alex", due()).await.unwrap();
+        let sam_due = as_user(&state, "sam", due()).await.unwrap();
+        assert!(alex_due > today);
+        assert!(sam_due < alex_due);
+        for name in ["alex", "sam"] {
+            let state_file = dir.path().join(".rote/users").join(name).join("state.json");
+            assert!(
+                std::fs::read_to_string(state_file)
+                    .unwrap()
+                    .contains("\"due\"")
+            );
+        }
+        // The deck file's own scheduling is left alone.
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("es.csv")).unwrap(),
+            csv
+        );
+        state.lock().await.load_user(&None).unwrap();
+        assert_eq!(due().await, None);
+    }
+
+    #[tokio::test]
+    async fn api_creates_cards_and_refuses_duplicates() {
+        use axum::http::StatusCode;
+        let dir = tempfile::tempdir().unwrap();
+        let options = ServeOptions {
+            low_memory: true,
+            ..ServeOptions::default()
+        };
+        let state = server(dir.path(), "es,**Hola**,hello\n", options);
+        let post = |body: serde_json::Value| {
+            let body = serde_json::from_value(body).unwrap();
+            api_create_cards(State(state.clone()), axum::Json(body))
+        };
+
+        let created =
+            post(serde_json::json!({"deck": "es", "front": "adiós", "back": "bye"})).await;
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let id = json(created).await["id"].as_str().unwrap().to_string();
+        let saved = std::fs::read_to_string(dir.path().join("es.csv")).unwrap();
+        assert!(saved.contains("adiós") && saved.contains(&id));
+
+        // The indexed card's front is read from disk to compare.
+        let duplicate = post(serde_json::json!({"deck": "es", "front": "hola!"})).await;
+        assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+        let allowed =
+            post(serde_json::json!({"deck": "es", "front": "hola!", "allow_duplicate": true}));
+        assert_eq!(allowed.await.status(), StatusCode::CREATED);
+
+        let invalid =
+            post(serde_json::json!([{"deck": "es", "front": "ok"}, {"deck": "es", "front": " "}]))
+                .await;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            json(invalid).await["error"],
+            "card 1: deck and front are required"
+        );
+    }
+
+    #[tokio::test]
+    async fn paths_decode_to_the_names_they_encode() {
+        use axum::http::StatusCode;
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("mixed.csv"),
+            "deck,front,back,id\n日本語/文法,は,topic,a#1\nmy deck,uno,one,b2\n",
+        )
+        .unwrap();
+        let state = open(dir.path());
+
+        let (status, body) = get(
+            &state,
+            "/deck/%E6%97%A5%E6%9C%AC%E8%AA%9E%2F%E6%96%87%E6%B3%95",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("topic") && !body.contains("uno"), "{body}");
+        let (status, body) = get(&state, &urls::deck("my deck")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("uno") && !body.contains("topic"), "{body}");
+        let (status, body) = get(&state, &urls::card_info("a#1")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            body.contains("は") && !body.contains("Card not found"),
+            "{body}"
+        );
+    }
+
+    #[tokio::test]
+    async fn reviews_grade_and_prefetch_as_json() {
+        use axum::http::StatusCode;
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("es.csv"),
+            "deck,front,back,id\nes,uno,one,a\nes,dos,two,b\n",
+        )
+        .unwrap();
+        let state = open(dir.path());
+        let request = axum::http::Request::get(urls::deck_review("es"));
+        let started = respond(&state, request.body(axum::body::Body::empty()).unwrap()).await;
+        assert_eq!(started.status(), StatusCode::SEE_OTHER);
+        let location = started.headers()[axum::http::header::LOCATION].to_str();
+        let (_, session) = location.unwrap().split_once("session=").unwrap();
+        let grade_url = urls::review_grade("es");
+        let grade = |form: String| {
+            let (state, grade_url) = (&state, &grade_url);
+            async move { post(state, grade_url, &form).await }
+        };
+
+        let (status, body) = get(&state, &urls::review_queue("es", session)).await;
+        assert_eq!(status, StatusCode::OK);
+        let queue: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(queue["position"], 1);
+        assert_eq!(queue["cards"].as_array().unwrap().len(), 2);
+
+        let (status, body) = grade(format!("session={session}&grade=3&position=1")).await;
+        assert_eq!(status, StatusCode::OK);
+        let next: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            (&next["done"], &next["position"]),
+            (&false.into(), &2.into())
+        );
+        // A replayed grade for a position already graded is not applied again.
+        let (_, body) = grade(format!("session={session}&grade=1&position=1")).await;
+        let replayed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(replayed["position"], 2);
+        let (status, body) = grade(format!("session={session}&grade=3")).await;
+        assert_eq!(status, StatusCode::OK);
+        let done: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(done["done"], true);
+        assert_eq!(done["next"], urls::summary("es", session));
+        let st = state.lock().await;
+        assert!(st.app.cards.iter().all(|c| c.due > Some(clock::today())));
+        drop(st);
+
+        let (status, _) = grade("session=gone&grade=3".into()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&state, &urls::review_queue("es", "gone")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn stale_edits_are_refused() {
+        use axum::http::{StatusCode, header};
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("es.csv"),
+            "deck,front,back,id\nes,uno,one,a\n",
+        )
+        .unwrap();
+        let state = open(dir.path());
+        let revision = card::revision(&state.lock().await.app.cards[0]);
+        let put = |id: &str, if_match: &str, body: &str| {
+            axum::http::Request::put(format!("/api/v1/cards/{id}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::IF_MATCH, format!("\"{if_match}\""))
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let request = axum::http::Request::get("/api/v1/cards/a");
+        let fetched = respond(&state, request.body(axum::body::Body::empty()).unwrap()).await;
+        assert_eq!(fetched.status(), StatusCode::OK);
+        assert_eq!(fetched.headers()[header::ETAG], format!("\"{revision}\""));
+        let (status, _) = send(&state, put("a", &revision, r#"{"back": "one!"}"#)).await;
+        assert_eq!(status, StatusCode::OK);
+        // The second writer still holds the old revision and gets the card back.
+        let (status, body) = send(&state, put("a", &revision, r#"{"back": "1"}"#)).await;
+        assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+        assert_eq!(serde_json::from_str::<Card>(&body).unwrap().back, "one!");
+        let (status, _) = send(&state, put("zz", &revision, r#"{"back": "1"}"#)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // So does a form opened before the save.
+        let edit = |revision: &str| format!("deck=es&front=uno&back=1&revision={revision}");
+        let (status, body) = post(&state, &urls::card_edit("a"), &edit(&revision)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(body.contains("one!"), "{body}");
+        let current = card::revision(&state.lock().await.app.cards[0]);
+        let (status, _) = post(&state, &urls::card_edit("a"), &edit(&current)).await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        let saved = std::fs::read_to_string(dir.path().join("es.csv")).unwrap();
+        assert!(saved.contains("es,uno,1,a"), "{saved}");
+    }
+
+    #[tokio::test]
+    async fn deck_pages_stay_in_bounds() {
+        use axum::http::StatusCode;
+        let dir = tempfile::tempdir().unwrap();
+        let rows: String = (1..=120)
+            .map(|n| format!("es,card {n},back,c{n}\n"))
+            .collect();
+        std::fs::write(
+            dir.path().join("es.csv"),
+            format!("deck,front,back,id\n{rows}"),
+        )
+        .unwrap();
+        let state = open(dir.path());
+        let page = |query: &'static str| {
+            let state = &state;
+            async move {
+                let (status, body) = get(state, &format!("/deck/es?{query}")).await;
+                assert_eq!(status, StatusCode::OK);
+                body
+            }
+        };
+
+        let last = page("page=99&per_page=50").await;
+        assert!(last.contains("101–120 of 120"), "{last}");
+        assert!(last.contains(r#"id="card-c120""#) && !last.contains(r#"id="card-c100""#));
+        assert!(page("page=0&per_page=50").await.contains("1–50 of 120"));
+        assert!(page("per_page=7").await.contains("1–50 of 120"));
+        assert!(page("per_page=100000").await.contains("1–120 of 120"));
+        assert!(page("card=c75&per_page=50").await.contains("51–100 of 120"));
+        let (status, _) = get(&state, "/deck/es?page=two").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn queue_page_buries_and_suspends() {
+        use axum::http::StatusCode;
+        let dir = tempfile::tempdir().unwrap();
+        let due = clock::today() - chrono::Days::new(1);
+        std::fs::write(
+            dir.path().join("es.csv"),
+            format!(
+                "deck,front,back,id,due\nes,uno,one,a,{due}\nes,dos,two,b,{due}\nes,tres,three,c,\n"
+            ),
+        )
+        .unwrap();
+        let state = open(dir.path());
+        let queue = urls::deck_queue("es");
+
+        let (status, body) = get(&state, &queue).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("2 review(s) and 1 new card(s)"), "{body}");
+        assert!(body.contains("uno") && body.contains("dos"));
+        let (status, _) = post(&state, &queue, "card=a&action=bury").await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        let (_, body) = get(&state, &queue).await;
+        assert!(body.contains("1 review(s)") && body.contains("Buried until tomorrow"));
+        let (status, _) = post(&state, &queue, "card=b&action=suspend").await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+        assert!(state.lock().await.app.cards[1].suspended);
+        let (_, body) = get(&state, &queue).await;
+        assert!(body.contains("No reviews due."), "{body}");
+
+        let (status, _) = post(&state, &queue, "card=a").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn revealed_clozes_stay_well_nested() {
+        assert_eq!(
+            revealed_cloze_html("[one\\n\\ntwo] three"),
+            "<p><span data-cloze>one</span></p><p>two three</p>"
+        );
+        assert_eq!(
+            revealed_cloze_html("\\[map\\](https://example.com/[Paris])"),
+            r#"<a href="https://example.com/Paris" rel="noopener noreferrer">map</a>"#
+        );
+        assert_eq!(
+            revealed_cloze_html("\\[capital: [Paris]\\](https://example.com)"),
+            r#"<a href="https://example.com" rel="noopener noreferrer">capital: <span data-cloze>Paris</span></a>"#
+        );
+        assert_eq!(
+            blanks_html("- [a]\\n- b"),
+            "<ul><li><span data-blank><span data-cloze hidden>a</span><span>_____</span></span></li><li>b</li></ul>"
+        );
+    }
 }
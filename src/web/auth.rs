@@ -0,0 +1,102 @@
+// Optional HTTP Basic authentication for every route, configured as
+// `user:password` (`--auth` or `auth` in the config file).
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// The `Authorization` header value a client must send.
+#[derive(Clone)]
+pub(crate) struct Credentials(Arc<String>);
+
+impl Credentials {
+    pub(crate) fn parse(spec: &str) -> crate::Result<Credentials> {
+        match spec.split_once(':') {
+            Some((user, password)) if !user.is_empty() && !password.is_empty() => Ok(Credentials(
+                Arc::new(format!("Basic {}", base64(spec.as_bytes()))),
+            )),
+            _ => Err(crate::Error::Auth {
+                spec: spec.to_string(),
+            }),
+        }
+    }
+
+    /// The `Authorization` header value, for clients.
+    pub(crate) fn header(&self) -> &str {
+        &self.0
+    }
+
+    fn accepts(&self, header: &[u8]) -> bool {
+        let expected = self.0.as_bytes();
+        // Compare every byte so timing says nothing about how much matched.
+        header.len() == expected.len()
+            && header
+                .iter()
+                .zip(expected)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+pub(super) async fn require(
+    State(credentials): State<Credentials>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .is_some_and(|h| credentials.accepts(h.as_bytes()));
+    if authorized {
+        return next.run(request).await;
+    }
+    let mut response = StatusCode::UNAUTHORIZED.into_response();
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::from_static(r#"Basic realm="rote", charset="UTF-8""#),
+    );
+    response
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_credentials() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+
+        let creds = Credentials::parse("Aladdin:open sesame").unwrap();
+        assert!(creds.accepts(b"Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+        assert!(!creds.accepts(b"Basic QWxhZGRpbjpvcGVuIHNlc2FtZR=="));
+        assert!(!creds.accepts(b""));
+        assert!(Credentials::parse("nopassword").is_err());
+        assert!(Credentials::parse("user:").is_err());
+    }
+}
@@ -0,0 +1,424 @@
+// Stylesheet for the utility classes a page uses, so the UI works without the
+// Tailwind CDN. Covers the part of Tailwind the templates are written in:
+// spacing and sizing scales, arbitrary `[...]` values, colours (with `/NN`
+// opacity), `hover:`/`focus:` variants and the `!` important prefix. Classes
+// it does not know produce no rule.
+
+use std::collections::BTreeSet;
+
+const PREFLIGHT: &str = include_str!("../static/preflight.css");
+
+/// Preflight plus a rule for every known class in the `class` attributes of
+/// `html` and in the class lists `extra`.
+pub(super) fn stylesheet(html: &str, extra: &[&str]) -> String {
+    let classes: BTreeSet<&str> = class_attributes(html)
+        .chain(extra.iter().copied())
+        .flat_map(str::split_whitespace)
+        .collect();
+    let mut rules: Vec<(u8, u8, String)> = classes
+        .into_iter()
+        .filter_map(|class| {
+            let (variant, rank, css) = rule(class)?;
+            Some((variant, rank, css))
+        })
+        .collect();
+    // Later rules win ties: sides over axes over all sides, and variants over
+    // plain utilities, as in Tailwind's own ordering.
+    rules.sort_by_key(|(variant, rank, _)| (*variant, *rank));
+    let mut out = PREFLIGHT.to_string();
+    for (_, _, css) in rules {
+        out.push_str(&css);
+        out.push('\n');
+    }
+    out
+}
+
+fn class_attributes(html: &str) -> impl Iterator<Item = &str> {
+    html.split("class=\"").skip(1).filter_map(|rest| {
+        let end = rest.find('"')?;
+        Some(&rest[..end])
+    })
+}
+
+/// `(variant order, rank, rule)` for one class.
+fn rule(class: &str) -> Option<(u8, u8, String)> {
+    let (variant, pseudo, utility) = if let Some(u) = class.strip_prefix("hover:") {
+        (1, ":hover", u)
+    } else if let Some(u) = class.strip_prefix("focus:") {
+        (2, ":focus", u)
+    } else {
+        (0, "", class)
+    };
+    let (important, utility) = match utility.strip_prefix('!') {
+        Some(u) => (true, u),
+        None => (false, utility),
+    };
+    let (rank, declarations) = declarations(utility)?;
+    let body = declarations
+        .split(';')
+        .filter(|d| !d.trim().is_empty())
+        .map(|d| {
+            if important {
+                format!("{} !important;", d.trim())
+            } else {
+                format!("{};", d.trim())
+            }
+        })
+        .collect::<String>();
+    Some((
+        variant,
+        rank,
+        format!(".{}{pseudo}{{{body}}}", escape(class)),
+    ))
+}
+
+/// Escape a class name for use in a selector.
+fn escape(class: &str) -> String {
+    let mut out = String::new();
+    for c in class.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+            out.push(c);
+        } else {
+            out.push('\\');
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The value inside `[...]`, with `_` standing for a space.
+fn arbitrary(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+    Some(inner.replace('_', " "))
+}
+
+/// A colour: `[#rgb]`, `[#rrggbb]` or a name, optionally `/NN` opacity.
+fn color(value: &str) -> Option<String> {
+    let (value, alpha) = match value.rsplit_once('/') {
+        Some((v, a)) if !value.ends_with(']') => (v, Some(a.parse::<u8>().ok()?)),
+        _ => (value, None),
+    };
+    let hex = match value {
+        "white" => "#fff".to_string(),
+        "black" => "#000".to_string(),
+        "transparent" => return alpha.is_none().then(|| "transparent".into()),
+        "inherit" => return alpha.is_none().then(|| "inherit".into()),
+        "current" => return alpha.is_none().then(|| "currentColor".into()),
+        _ => arbitrary(value).filter(|v| v.starts_with('#'))?,
+    };
+    match alpha {
+        None => Some(hex),
+        Some(a) => {
+            let digits: Vec<u8> = hex[1..]
+                .chars()
+                .map(|c| c.to_digit(16).map(|d| d as u8))
+                .collect::<Option<_>>()?;
+            let [r, g, b] = match digits[..] {
+                [r, g, b] => [r * 17, g * 17, b * 17],
+                [r1, r2, g1, g2, b1, b2] => [r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2],
+                _ => return None,
+            };
+            Some(format!("rgb({r} {g} {b} / {})", f64::from(a) / 100.0))
+        }
+    }
+}
+
+/// Spacing scale: `4` is 1rem, plus `px`, `auto` and `[...]`.
+fn spacing(value: &str) -> Option<String> {
+    match value {
+        "0" => Some("0px".into()),
+        "px" => Some("1px".into()),
+        "auto" => Some("auto".into()),
+        _ if value.starts_with('[') => arbitrary(value),
+        _ => {
+            let n: f64 = value.parse().ok()?;
+            Some(format!("{}rem", n / 4.0))
+        }
+    }
+}
+
+fn size(value: &str, screen: &str) -> Option<String> {
+    match value {
+        "full" => Some("100%".into()),
+        "screen" => Some(screen.into()),
+        "min" | "max" | "fit" => Some(format!("{value}-content")),
+        _ => spacing(value),
+    }
+}
+
+fn max_width(value: &str) -> Option<String> {
+    let rem = match value {
+        "xs" => "20rem",
+        "sm" => "24rem",
+        "md" => "28rem",
+        "lg" => "32rem",
+        "xl" => "36rem",
+        "2xl" => "42rem",
+        "3xl" => "48rem",
+        "4xl" => "56rem",
+        "5xl" => "64rem",
+        "6xl" => "72rem",
+        "7xl" => "80rem",
+        "none" => "none",
+        _ => return size(value, "100vw"),
+    };
+    Some(rem.into())
+}
+
+/// Properties for padding or margin shorthands such as `px`.
+fn sides(prefix: &str, property: &str) -> Option<(u8, Vec<String>)> {
+    let side = |s: &str| format!("{property}-{s}");
+    Some(match prefix {
+        "" => (0, vec![property.to_string()]),
+        "x" => (1, vec![side("left"), side("right")]),
+        "y" => (1, vec![side("top"), side("bottom")]),
+        "t" => (2, vec![side("top")]),
+        "r" => (2, vec![side("right")]),
+        "b" => (2, vec![side("bottom")]),
+        "l" => (2, vec![side("left")]),
+        _ => return None,
+    })
+}
+
+fn each(properties: &[String], value: &str) -> String {
+    properties.iter().map(|p| format!("{p}:{value};")).collect()
+}
+
+/// `(rank, declarations)` for a utility without variant or `!`.
+fn declarations(utility: &str) -> Option<(u8, String)> {
+    let fixed = match utility {
+        "block" => "display:block",
+        "inline" => "display:inline",
+        "inline-block" => "display:inline-block",
+        "flex" => "display:flex",
+        "inline-flex" => "display:inline-flex",
+        "grid" => "display:grid",
+        "hidden" => "display:none",
+        "fixed" => "position:fixed",
+        "inset-0" => "inset:0px",
+        "z-50" => "z-index:50",
+        "flex-col" => "flex-direction:column",
+        "flex-row" => "flex-direction:row",
+        "flex-wrap" => "flex-wrap:wrap",
+        "flex-1" => "flex:1 1 0%",
+        "shrink-0" => "flex-shrink:0",
+        "grow" => "flex-grow:1",
+        "items-center" => "align-items:center",
+        "items-baseline" => "align-items:baseline",
+        "items-start" => "align-items:flex-start",
+        "items-end" => "align-items:flex-end",
+        "justify-between" => "justify-content:space-between",
+        "justify-center" => "justify-content:center",
+        "justify-end" => "justify-content:flex-end",
+        "overflow-hidden" => "overflow:hidden",
+        "overflow-auto" => "overflow:auto",
+        "overflow-x-auto" => "overflow-x:auto",
+        "overflow-y-auto" => "overflow-y:auto",
+        "text-left" => "text-align:left",
+        "text-center" => "text-align:center",
+        "text-right" => "text-align:right",
+        "text-ellipsis" => "text-overflow:ellipsis",
+        "whitespace-nowrap" => "white-space:nowrap",
+        "whitespace-pre-wrap" => "white-space:pre-wrap",
+        "break-words" => "overflow-wrap:break-word",
+        "uppercase" => "text-transform:uppercase",
+        "italic" => "font-style:italic",
+        "no-underline" => "text-decoration-line:none",
+        "underline" => "text-decoration-line:underline",
+        "antialiased" => "-webkit-font-smoothing:antialiased;-moz-osx-font-smoothing:grayscale",
+        "font-sans" => {
+            "font-family:ui-sans-serif,system-ui,sans-serif,\"Apple Color Emoji\",\"Segoe UI Emoji\""
+        }
+        "font-mono" => "font-family:ui-monospace,SFMono-Regular,Menlo,Consolas,monospace",
+        "font-normal" => "font-weight:400",
+        "font-medium" => "font-weight:500",
+        "font-semibold" => "font-weight:600",
+        "font-bold" => "font-weight:700",
+        "tracking-wide" => "letter-spacing:0.025em",
+        "tracking-wider" => "letter-spacing:0.05em",
+        "tracking-widest" => "letter-spacing:0.1em",
+        "leading-none" => "line-height:1",
+        "leading-tight" => "line-height:1.25",
+        "leading-normal" => "line-height:1.5",
+        "leading-relaxed" => "line-height:1.625",
+        "list-none" => "list-style-type:none",
+        "cursor-pointer" => "cursor:pointer",
+        "resize-y" => "resize:vertical",
+        "resize-none" => "resize:none",
+        "select-none" => "user-select:none;-webkit-user-select:none",
+        "outline-none" => "outline:2px solid transparent;outline-offset:2px",
+        "transition-colors" => {
+            "transition-property:color,background-color,border-color,text-decoration-color,fill,stroke;transition-timing-function:cubic-bezier(0.4,0,0.2,1);transition-duration:150ms"
+        }
+        "border-solid" => "border-style:solid",
+        "border-dashed" => "border-style:dashed",
+        "border" => "border-width:1px",
+        "border-0" => "border-width:0px",
+        "border-2" => "border-width:2px",
+        "rounded-none" => "border-radius:0px",
+        "rounded-sm" => "border-radius:0.125rem",
+        "rounded" => "border-radius:0.25rem",
+        "rounded-md" => "border-radius:0.375rem",
+        "rounded-lg" => "border-radius:0.5rem",
+        "rounded-xl" => "border-radius:0.75rem",
+        "rounded-full" => "border-radius:9999px",
+        "text-xs" => "font-size:0.75rem;line-height:1rem",
+        "text-sm" => "font-size:0.875rem;line-height:1.25rem",
+        "text-base" => "font-size:1rem;line-height:1.5rem",
+        "text-lg" => "font-size:1.125rem;line-height:1.75rem",
+        "text-xl" => "font-size:1.25rem;line-height:1.75rem",
+        "text-2xl" => "font-size:1.5rem;line-height:2rem",
+        "text-3xl" => "font-size:1.875rem;line-height:2.25rem",
+        _ => "",
+    };
+    if !fixed.is_empty() {
+        return Some((0, fixed.to_string()));
+    }
+
+    let (name, value) = utility.split_at(utility.find('-')?);
+    let value = &value[1..];
+    match name {
+        "p" | "px" | "py" | "pt" | "pr" | "pb" | "pl" => {
+            let (rank, props) = sides(&name[1..], "padding")?;
+            Some((rank, each(&props, &spacing(value)?)))
+        }
+        "m" | "mx" | "my" | "mt" | "mr" | "mb" | "ml" => {
+            let (rank, props) = sides(&name[1..], "margin")?;
+            Some((rank, each(&props, &spacing(value)?)))
+        }
+        "gap" => Some((0, format!("gap:{}", spacing(value)?))),
+        "w" => Some((0, format!("width:{}", size(value, "100vw")?))),
+        "h" => Some((0, format!("height:{}", size(value, "100vh")?))),
+        "min" | "max" => {
+            let (axis, value) = value.split_once('-')?;
+            let (property, screen) = match axis {
+                "w" => ("width", "100vw"),
+                "h" => ("height", "100vh"),
+                _ => return None,
+            };
+            let value = if name == "max" && axis == "w" {
+                max_width(value)?
+            } else {
+                size(value, screen)?
+            };
+            Some((0, format!("{name}-{property}:{value}")))
+        }
+        "grid" => {
+            let columns = value.strip_prefix("cols-")?;
+            let template = match columns.parse::<u8>() {
+                Ok(n) => format!("repeat({n},minmax(0,1fr))"),
+                Err(_) => arbitrary(columns)?,
+            };
+            Some((0, format!("grid-template-columns:{template}")))
+        }
+        "text" => {
+            // `text-[0.9rem]` is a size, `text-[#888]` a colour.
+            if let Some(v) = arbitrary(value).filter(|v| !v.starts_with('#')) {
+                return Some((0, format!("font-size:{v}")));
+            }
+            Some((0, format!("color:{}", color(value)?)))
+        }
+        "bg" => Some((0, format!("background-color:{}", color(value)?))),
+        "border" => {
+            let props = |side: &str, what: &str| format!("border-{side}-{what}");
+            match value {
+                "t" | "r" | "b" | "l" => {
+                    let side = match value {
+                        "t" => "top",
+                        "r" => "right",
+                        "b" => "bottom",
+                        _ => "left",
+                    };
+                    Some((1, format!("{}:1px", props(side, "width"))))
+                }
+                _ => Some((0, format!("border-color:{}", color(value)?))),
+            }
+        }
+        "ring" => match value.parse::<u8>() {
+            Ok(n) => Some((
+                0,
+                format!("box-shadow:0 0 0 {n}px var(--tw-ring-color,rgb(59 130 246 / 0.5))"),
+            )),
+            Err(_) => Some((1, format!("--tw-ring-color:{}", color(value)?))),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utilities() {
+        assert_eq!(
+            rule("px-3.5").unwrap().2,
+            ".px-3\\.5{padding-left:0.875rem;padding-right:0.875rem;}"
+        );
+        assert_eq!(
+            rule("hover:!text-[#d4d4d4]").unwrap().2,
+            ".hover\\:\\!text-\\[\\#d4d4d4\\]:hover{color:#d4d4d4 !important;}"
+        );
+        assert_eq!(
+            rule("text-[0.9rem]").unwrap().2,
+            ".text-\\[0\\.9rem\\]{font-size:0.9rem;}"
+        );
+        assert_eq!(
+            rule("focus:ring-[#6ba3d6]/15").unwrap().2,
+            ".focus\\:ring-\\[\\#6ba3d6\\]\\/15:focus{--tw-ring-color:rgb(107 163 214 / 0.15);}"
+        );
+        assert_eq!(
+            rule("grid-cols-[repeat(auto-fill,minmax(220px,1fr))]")
+                .unwrap()
+                .2,
+            ".grid-cols-\\[repeat\\(auto-fill\\,minmax\\(220px\\,1fr\\)\\)\\]{grid-template-columns:repeat(auto-fill,minmax(220px,1fr));}"
+        );
+        assert!(rule("md").is_none());
+    }
+
+    #[test]
+    fn sides_come_after_axes() {
+        let css = stylesheet(
+            r#"<div class="pb-1 py-2 p-0 hover:bg-[#333]">"#,
+            &["bg-[#222]"],
+        );
+        let at = |s: &str| css.find(s).unwrap();
+        assert!(at(".p-0{") < at(".py-2{"));
+        assert!(at(".py-2{") < at(".pb-1{"));
+        assert!(at(".bg-\\[\\#222\\]{") < at(":hover{"));
+    }
+
+    /// Every class the templates use, other than those styled in style.css,
+    /// must produce a rule.
+    #[test]
+    fn templates_are_covered() {
+        const CUSTOM: &[&str] = &[
+            "md",
+            "browser-selected",
+            "theme-light",
+            "deck-caret",
+            "occluded",
+        ];
+        let sources = [
+            include_str!("../web.rs"),
+            include_str!("markdown.rs"),
+            include_str!("generate.rs"),
+        ];
+        for source in sources {
+            // Class attributes, and class lists kept in `*_cls` strings.
+            let locals = source.split("_cls = \"").skip(1);
+            let constants = source
+                .split("_CLS: &str =")
+                .skip(1)
+                .filter_map(|rest| rest.split('"').nth(1));
+            let constants = constants.chain(locals.filter_map(|rest| rest.split('"').next()));
+            let lists = class_attributes(source).chain(constants);
+            for class in lists.flat_map(str::split_whitespace) {
+                if class.contains(['{', '}']) || CUSTOM.contains(&class) {
+                    continue;
+                }
+                assert!(rule(class).is_some(), "no rule for {class}");
+            }
+        }
+    }
+}
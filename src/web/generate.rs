@@ -0,0 +1,213 @@
+// The Generate page (built with `--features llm`): paste study notes, get
+// question and answer and cloze cards proposed by the `[llm]` endpoint (see
+// `crate::llm`), edit them, and add the ones kept to a deck:
+//
+//     GET  /generate          notes form
+//     POST /generate          notes -> editable suggestions
+//     POST /generate/accept   checked suggestions -> cards in the deck
+
+use std::collections::HashMap;
+
+use axum::Form;
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+
+use super::{
+    ServerState, SharedState, add_siblings, breadcrumb, deck_file, html_escape, page,
+    record_journal, save_file, sidebar_html, snapshot_before, urls,
+};
+use crate::card::Card;
+use crate::llm::{self, Suggestion};
+use crate::{clock, journal, review};
+
+#[derive(serde::Deserialize, Default)]
+pub(super) struct NotesForm {
+    #[serde(default)]
+    deck: String,
+    #[serde(default)]
+    notes: String,
+}
+
+pub(super) async fn form(
+    State(state): State<SharedState>,
+    axum::extract::Query(form): axum::extract::Query<NotesForm>,
+) -> Html<String> {
+    let st = state.lock().await;
+    generate_page(&st, &form, None)
+}
+
+/// Ask the endpoint for cards from the notes. That can take a while, so the
+/// state is unlocked meanwhile.
+pub(super) async fn suggest(
+    State(state): State<SharedState>,
+    Form(form): Form<NotesForm>,
+) -> Html<String> {
+    let st = state.lock().await;
+    let Some(config) = st.llm.clone() else {
+        let missing = Err("No [llm] endpoint is set in config.toml.".to_string());
+        return generate_page(&st, &form, Some(missing));
+    };
+    if form.notes.trim().is_empty() {
+        return generate_page(&st, &form, Some(Err("Paste some notes first.".to_string())));
+    }
+    drop(st);
+    let notes = form.notes.clone();
+    let suggested = tokio::task::spawn_blocking(move || llm::suggest(&config, &notes))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    if let Err(e) = &suggested {
+        tracing::warn!("{e}");
+    }
+    let st = state.lock().await;
+    generate_page(&st, &form, Some(suggested))
+}
+
+/// Add the checked suggestions, as edited, to the deck. Fields are numbered
+/// `front_0`, `back_0`, `accept_0` and so on, up to `count`.
+pub(super) async fn accept(
+    State(state): State<SharedState>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    let mut st = state.lock().await;
+    let field = |key: &str| form.get(key).map(|v| v.trim()).unwrap_or("");
+    let deck = field("deck").to_string();
+    let count: usize = field("count").parse().unwrap_or(0);
+    let today = clock::today();
+    let cards: Vec<Card> = (0..count)
+        .filter(|n| form.contains_key(&format!("accept_{n}")))
+        .filter(|n| !field(&format!("front_{n}")).is_empty())
+        .map(|n| Card {
+            deck: deck.clone(),
+            front: field(&format!("front_{n}")).to_string(),
+            back: field(&format!("back_{n}")).to_string(),
+            extra: String::new(),
+            origin: String::new(),
+            media: String::new(),
+            id: uuid::Uuid::new_v4().to_string(),
+            stability: None,
+            difficulty: None,
+            due: None,
+            last_review: None,
+            created: Some(today),
+            tags: Vec::new(),
+            suspended: false,
+            flagged: false,
+            ease: None,
+            interval: None,
+        })
+        .collect();
+    if deck.is_empty() || cards.is_empty() {
+        return Redirect::to(&urls::generate()).into_response();
+    }
+    let Some(source) = deck_file(&st, &deck) else {
+        return Redirect::to(&urls::home()).into_response();
+    };
+    snapshot_before("generate", &st.app, std::slice::from_ref(&source));
+
+    let mut entry = journal::Entry::new("generate");
+    for card in cards {
+        entry.push(
+            &st.app.sources,
+            st.app.cards.len(),
+            &source,
+            None,
+            Some(card.clone()),
+        );
+        st.app.sources.push(source.clone());
+        st.app.cards.push(card);
+    }
+    save_file(&mut st.app, &source);
+    record_journal(st.app.data_dir(), &entry);
+    add_siblings(&mut st);
+    Redirect::to(&urls::deck(&deck)).into_response()
+}
+
+fn generate_page(
+    st: &ServerState,
+    form: &NotesForm,
+    suggested: Option<Result<Vec<Suggestion>, String>>,
+) -> Html<String> {
+    let summaries = review::deck_summaries(&st.app.cards, clock::today());
+    let sidebar = sidebar_html(st, &summaries, "");
+    let input_cls = "w-full px-3 py-2.5 border border-[#444] rounded-md text-[0.9rem] bg-[#383838] text-[#e0e0e0] focus:outline-none focus:border-[#6ba3d6] focus:ring-2 focus:ring-[#6ba3d6]/15";
+    let label_cls = "block text-xs font-medium text-[#888] mb-1";
+
+    let deck_options: String = summaries
+        .iter()
+        .map(|s| format!(r#"<option value="{}">"#, html_escape(&s.name)))
+        .collect();
+
+    let results = match &suggested {
+        None => String::new(),
+        Some(Err(e)) => format!(
+            r#"<p class="mt-4 text-sm text-[#e06c6c]">{}</p>"#,
+            html_escape(e)
+        ),
+        Some(Ok(cards)) if cards.is_empty() => {
+            r#"<p class="mt-4 text-sm text-[#888]">No cards were suggested.</p>"#.to_string()
+        }
+        Some(Ok(cards)) => {
+            let rows: String = cards
+                .iter()
+                .enumerate()
+                .map(|(n, c)| {
+                    format!(
+                        r#"<div class="flex gap-3 items-start py-3 border-b border-[#333]">
+<input type="checkbox" name="accept_{n}" checked class="mt-2.5" aria-label="Keep card {number}">
+<div class="flex-1 grid grid-cols-2 gap-3">
+<textarea name="front_{n}" rows="2" aria-label="Front" class="{input_cls} resize-y leading-relaxed" style="font-family:inherit">{front}</textarea>
+<textarea name="back_{n}" rows="2" aria-label="Back" placeholder="(cloze)" class="{input_cls} resize-y leading-relaxed" style="font-family:inherit">{back}</textarea>
+</div>
+</div>"#,
+                        number = n + 1,
+                        front = html_escape(&c.front),
+                        back = html_escape(&c.back),
+                    )
+                })
+                .collect();
+            format!(
+                r#"<form method="post" action="{action}" class="mt-6">
+<input type="hidden" name="deck" value="{deck}">
+<input type="hidden" name="count" value="{n}">
+<p class="text-sm text-[#888] mb-2">{n} suggestion(s) for <span class="text-[#e0e0e0]">{deck}</span>. Edit them, uncheck the ones to leave out; a cloze card keeps its answers in <code>[brackets]</code> and an empty back.</p>
+{rows}
+<button type="submit" class="mt-4 inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#4a90d9] text-white cursor-pointer hover:bg-[#5a9de6]">Add checked cards</button>
+</form>"#,
+                action = html_escape(&urls::generate_accept()),
+                deck = html_escape(form.deck.trim()),
+                n = cards.len(),
+            )
+        }
+    };
+
+    let body = format!(
+        r#"<div class="flex h-screen">
+{sidebar}
+<div class="flex-1 overflow-y-auto min-w-0">
+<div class="flex items-center justify-between px-6 py-3 border-b border-[#333] bg-[#232323]">
+<div class="text-sm text-[#888]">{bc}</div>
+</div>
+<div class="p-6">
+<div class="bg-[#2d2d2d] border border-[#3a3a3a] rounded-xl p-6 max-w-3xl">
+<h2 class="text-lg font-semibold text-[#e0e0e0] mb-1">Generate</h2>
+<p class="text-sm text-[#888] mb-5">Paste a paragraph of study notes to have question and answer and cloze cards suggested from it.</p>
+<form method="post" action="{action}">
+<div class="mb-4"><label class="{label_cls}" for="deck">Deck</label><input type="text" id="deck" name="deck" list="deck-names" value="{deck}" required class="{input_cls}"><datalist id="deck-names">{deck_options}</datalist></div>
+<label class="{label_cls}" for="notes">Notes</label>
+<textarea id="notes" name="notes" rows="10" autofocus class="{input_cls} min-h-[100px] resize-y leading-relaxed" style="font-family:inherit">{notes}</textarea>
+<div class="flex gap-3 mt-4">
+<button type="submit" class="inline-flex items-center gap-1 px-3.5 py-2 rounded-md text-sm font-medium bg-[#383838] text-[#ccc] border border-[#444] cursor-pointer hover:bg-[#444] hover:!text-[#e0e0e0]">Suggest cards</button>
+</div>
+</form>
+{results}
+</div>
+</div>
+</div>
+</div>"#,
+        bc = breadcrumb(&[("Decks", &urls::home()), ("Generate", "")]),
+        action = html_escape(&urls::generate()),
+        deck = html_escape(&form.deck),
+        notes = html_escape(&form.notes),
+    );
+    Html(page("Generate", &body))
+}
@@ -0,0 +1,87 @@
+// Keyboard shortcuts shared by the review page and the card browser. The map
+// is serialized into every page as `window.ROTE_KEYS`; `static/keymap.js`
+// matches key events against it by action name.
+
+/// How a tap on the Forgot button is confirmed on touch screens, where a stray
+/// tap is easy and resets a mature card. Keyboard grading is unaffected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForgotConfirm {
+    #[default]
+    Off,
+    Hold,
+    DoubleTap,
+}
+
+/// One key per action, named as in `KeyboardEvent.key` (`Space` for " ").
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    pub reveal: String,
+    pub forgot: String,
+    pub hard: String,
+    pub good: String,
+    pub easy: String,
+    /// Open the current card's info page from review.
+    pub info: String,
+    /// Edit the current card from review, then carry on with the session.
+    pub edit: String,
+    /// Put the current card at the end of the session, ungraded.
+    pub skip: String,
+    pub next: String,
+    pub prev: String,
+    pub open: String,
+    pub suspend: String,
+    pub flag: String,
+    pub delete: String,
+    pub review: String,
+    pub new_card: String,
+    pub forgot_confirm: ForgotConfirm,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let key = |k: &str| k.to_string();
+        Keymap {
+            reveal: key("Space"),
+            forgot: key("1"),
+            hard: key("2"),
+            good: key("3"),
+            easy: key("4"),
+            info: key("i"),
+            edit: key("e"),
+            skip: key("s"),
+            next: key("j"),
+            prev: key("k"),
+            open: key("Enter"),
+            suspend: key("s"),
+            flag: key("f"),
+            delete: key("d"),
+            review: key("r"),
+            new_card: key("n"),
+            forgot_confirm: ForgotConfirm::Off,
+        }
+    }
+}
+
+/// `<script>` body defining `window.ROTE_KEYS`.
+pub fn script(keys: &Keymap) -> String {
+    format!("window.ROTE_KEYS={};", super::js_value(keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_keymap_keeps_defaults() {
+        let keymap: Keymap = serde_json::from_str(r#"{"suspend":"z"}"#).unwrap();
+        assert_eq!(keymap.suspend, "z");
+        assert_eq!(keymap.next, "j");
+        assert_eq!(keymap.reveal, "Space");
+        assert_eq!(keymap.forgot_confirm, ForgotConfirm::Off);
+
+        let keymap: Keymap = serde_json::from_str(r#"{"forgot_confirm":"double-tap"}"#).unwrap();
+        assert_eq!(keymap.forgot_confirm, ForgotConfirm::DoubleTap);
+    }
+}
@@ -0,0 +1,54 @@
+// Logging for `rote serve`, on stderr. Warnings only by default; with
+// `--log-level info` every request gets a line with its status and latency,
+// and `debug` adds how long each deck file took to save:
+//
+//     INFO rote::web::logging: request method=POST path=/deck/es/review/grade status=200 ms=3.2
+//
+// `--log-json` (or `log_json = true`) writes the same events as JSON lines.
+
+use std::io::IsTerminal;
+use std::time::Instant;
+
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Install the subscriber. Other crates' events are shown from warnings up
+/// however verbose `level` is. Only the first call has an effect.
+pub fn init(level: LevelFilter, json: bool) {
+    let filter = Targets::new()
+        .with_target("rote", level)
+        .with_default(level.min(LevelFilter::WARN));
+    let fmt = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let _ = if json {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt.json())
+            .try_init()
+    } else {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt.with_ansi(std::io::stderr().is_terminal()))
+            .try_init()
+    };
+}
+
+/// Log each request's method, path, status and time taken.
+pub async fn access(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+    tracing::info!(
+        %method,
+        path = %path,
+        status = response.status().as_u16(),
+        ms = (start.elapsed().as_secs_f64() * 1e4).round() / 10.0,
+        "request"
+    );
+    response
+}
@@ -0,0 +1,347 @@
+// Minimal Markdown renderer for card content.
+//
+// Inline HTML is left as written and the result goes through
+// `render::sanitize`, so only allowlisted tags reach the page; code spans and
+// blocks show it literally. Supported: **bold**, *italic*, `code`, fenced code blocks,
+// bullet/numbered lists, [links](https://...) and pipe tables. Underscore
+// emphasis is deliberately unsupported because cloze blanks render as `_____`.
+// Math spans (`$...$`, `$$...$$`) pass through untouched for KaTeX.
+//
+// Cloze answers come marked with private-use characters (`CLOZE_OPEN` and so
+// on), which become spans here, where they are text; in URLs, tags and math
+// the marks are dropped, so a cloze never lands inside an attribute.
+
+use super::html_escape;
+use super::render::{safe_url, sanitize, tag_len};
+
+/// Marks a revealed cloze answer: `<span data-cloze>`.
+pub const CLOZE_OPEN: char = '\u{E000}';
+pub const CLOZE_CLOSE: char = '\u{E001}';
+/// Mark a blank revealed on its own, as `BLANK_OPEN` answer `BLANK_MID`
+/// blank as asked `BLANK_CLOSE`; the answer starts out hidden.
+pub const BLANK_OPEN: char = '\u{E003}';
+pub const BLANK_MID: char = '\u{E002}';
+pub const BLANK_CLOSE: char = '\u{E004}';
+
+fn cloze_tag(mark: char) -> Option<&'static str> {
+    Some(match mark {
+        CLOZE_OPEN => "<span data-cloze>",
+        CLOZE_CLOSE => "</span>",
+        BLANK_OPEN => "<span data-blank><span data-cloze hidden>",
+        BLANK_MID => "</span><span>",
+        BLANK_CLOSE => "</span></span>",
+        _ => return None,
+    })
+}
+
+/// `text` with its cloze marks as spans.
+fn marked(text: &str) -> String {
+    text.chars()
+        .map(|c| cloze_tag(c).map_or_else(|| c.to_string(), str::to_string))
+        .collect()
+}
+
+/// `text` without its cloze marks.
+fn unmarked(text: &str) -> String {
+    text.chars().filter(|&c| cloze_tag(c).is_none()).collect()
+}
+
+pub fn to_html(text: &str) -> String {
+    sanitize(&blocks(text))
+}
+
+fn blocks(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                code.push(lines[i]);
+                i += 1;
+            }
+            i += 1; // closing fence (or end of input)
+            blocks.push(format!(
+                "<pre><code>{}</code></pre>",
+                marked(&html_escape(&code.join("\n")))
+            ));
+            continue;
+        }
+
+        if let Some(ordered) = list_kind(line) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let tag = if ordered { "ol" } else { "ul" };
+            let mut items = String::new();
+            while i < lines.len() && list_kind(lines[i]) == Some(ordered) {
+                items.push_str(&format!("<li>{}</li>", spans(list_item_text(lines[i]))));
+                i += 1;
+            }
+            blocks.push(format!("<{tag}>{items}</{tag}>"));
+            continue;
+        }
+
+        if trimmed.starts_with('|') && i + 1 < lines.len() && is_table_separator(lines[i + 1]) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            let mut table = String::from("<table><thead><tr>");
+            for cell in table_cells(line) {
+                table.push_str(&format!("<th>{}</th>", spans(cell)));
+            }
+            table.push_str("</tr></thead><tbody>");
+            i += 2;
+            while i < lines.len() && lines[i].trim_start().starts_with('|') {
+                table.push_str("<tr>");
+                for cell in table_cells(lines[i]) {
+                    table.push_str(&format!("<td>{}</td>", spans(cell)));
+                }
+                table.push_str("</tr>");
+                i += 1;
+            }
+            table.push_str("</tbody></table>");
+            blocks.push(table);
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks);
+        } else {
+            paragraph.push(line);
+        }
+        i += 1;
+    }
+    flush_paragraph(&mut paragraph, &mut blocks);
+
+    // A lone paragraph renders bare so simple cards keep their inline layout.
+    if blocks.len() == 1
+        && let Some(inner) = blocks[0]
+            .strip_prefix("<p>")
+            .and_then(|s| s.strip_suffix("</p>"))
+    {
+        return inner.to_string();
+    }
+    blocks.concat()
+}
+
+fn flush_paragraph(paragraph: &mut Vec<&str>, blocks: &mut Vec<String>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    let lines: Vec<String> = paragraph.iter().map(|l| spans(l)).collect();
+    blocks.push(format!("<p>{}</p>", lines.join("<br>")));
+    paragraph.clear();
+}
+
+/// `Some(false)` for a bullet item, `Some(true)` for a numbered item.
+fn list_kind(line: &str) -> Option<bool> {
+    let t = line.trim_start();
+    if t.starts_with("- ") || t.starts_with("* ") || t.starts_with("+ ") {
+        return Some(false);
+    }
+    let digits = t.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        let rest = &t[digits..];
+        if rest.starts_with(". ") || rest.starts_with(") ") {
+            return Some(true);
+        }
+    }
+    None
+}
+
+fn list_item_text(line: &str) -> &str {
+    let t = line.trim_start();
+    match t.find(' ') {
+        Some(pos) => t[pos + 1..].trim_start(),
+        None => "",
+    }
+}
+
+fn is_table_separator(line: &str) -> bool {
+    let t = line.trim();
+    t.starts_with('|') && t.contains('-') && t.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+fn table_cells(line: &str) -> Vec<&str> {
+    let t = line.trim();
+    let t = t.strip_prefix('|').unwrap_or(t);
+    let t = t.strip_suffix('|').unwrap_or(t);
+    t.split('|').map(str::trim).collect()
+}
+
+/// Render inline spans: code, math, bold, italic and links.
+pub fn inline(text: &str) -> String {
+    sanitize(&spans(text))
+}
+
+fn spans(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some(ch) = rest.chars().next() {
+        // Math spans are kept verbatim so KaTeX sees the original source.
+        if ch == '$' {
+            let delim = if rest.starts_with("$$") { "$$" } else { "$" };
+            if let Some(end) = rest[delim.len()..].find(delim) {
+                let span_len = delim.len() * 2 + end;
+                out.push_str(&html_escape(&unmarked(&rest[..span_len])));
+                rest = &rest[span_len..];
+                continue;
+            }
+        }
+
+        if ch == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            out.push_str(&format!(
+                "<code>{}</code>",
+                marked(&html_escape(&rest[1..1 + end]))
+            ));
+            rest = &rest[end + 2..];
+            continue;
+        }
+
+        if rest.starts_with("**")
+            && let Some(end) = rest[2..].find("**")
+            && end > 0
+        {
+            out.push_str(&format!("<strong>{}</strong>", spans(&rest[2..2 + end])));
+            rest = &rest[end + 4..];
+            continue;
+        }
+
+        if ch == '*'
+            && !rest[1..].starts_with([' ', '*'])
+            && let Some(end) = rest[1..].find('*')
+            && end > 0
+        {
+            out.push_str(&format!("<em>{}</em>", spans(&rest[1..1 + end])));
+            rest = &rest[end + 2..];
+            continue;
+        }
+
+        if ch == '['
+            && let Some(close) = rest.find("](")
+            && let Some(end) = rest[close + 2..].find(')')
+        {
+            let label = &rest[1..close];
+            let url = &rest[close + 2..close + 2 + end];
+            if !label.contains('[') && safe_url(url) {
+                out.push_str(&format!(
+                    r#"<a href="{}" rel="noopener noreferrer">{}</a>"#,
+                    html_escape(&unmarked(url)),
+                    spans(label)
+                ));
+                rest = &rest[close + 3 + end..];
+                continue;
+            }
+        }
+
+        if let Some(tag) = cloze_tag(ch) {
+            out.push_str(tag);
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+
+        // A tag is copied whole, so emphasis markers in its attributes stay.
+        let len = match ch {
+            '<' => tag_len(rest).unwrap_or(1),
+            _ => ch.len_utf8(),
+        };
+        out.push_str(&unmarked(&rest[..len]));
+        rest = &rest[len..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_is_escaped() {
+        assert_eq!(to_html("a < b & c"), "a &lt; b &amp; c");
+    }
+
+    #[test]
+    fn raw_html_is_not_passed_through() {
+        assert_eq!(
+            to_html("<script>alert(1)</script>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+    }
+
+    #[test]
+    fn bold_italic_code() {
+        assert_eq!(
+            to_html("**bold** and *it* with `x < y`"),
+            "<strong>bold</strong> and <em>it</em> with <code>x &lt; y</code>"
+        );
+    }
+
+    #[test]
+    fn cloze_blank_untouched() {
+        assert_eq!(to_html("The _____ is"), "The _____ is");
+    }
+
+    #[test]
+    fn math_spans_untouched() {
+        assert_eq!(to_html("$a * b * c$"), "$a * b * c$");
+        assert_eq!(to_html("$$x_1 * y$$"), "$$x_1 * y$$");
+    }
+
+    #[test]
+    fn multiline_paragraph_uses_breaks() {
+        assert_eq!(to_html("one\ntwo"), "one<br>two");
+    }
+
+    #[test]
+    fn paragraphs_and_lists() {
+        assert_eq!(
+            to_html("Intro\n\n- a\n- *b*\n\n1. x\n2. y"),
+            "<p>Intro</p><ul><li>a</li><li><em>b</em></li></ul><ol><li>x</li><li>y</li></ol>"
+        );
+    }
+
+    #[test]
+    fn fenced_code_block() {
+        assert_eq!(
+            to_html("```\nfn main() {\n  a < b\n}\n```"),
+            "<pre><code>fn main() {\n  a &lt; b\n}</code></pre>"
+        );
+    }
+
+    #[test]
+    fn links_only_allow_safe_schemes() {
+        assert_eq!(
+            to_html("[docs](https://example.com)"),
+            r#"<a href="https://example.com" rel="noopener noreferrer">docs</a>"#
+        );
+        assert_eq!(
+            to_html("[x](javascript:alert(1))"),
+            "[x](javascript:alert(1))"
+        );
+    }
+
+    #[test]
+    fn inline_html_is_sanitized() {
+        assert_eq!(
+            to_html("**H<sub>2</sub>O** <img src=\"a*b*.png\" onerror=\"x()\">\n`<b>`"),
+            r#"<strong>H<sub>2</sub>O</strong> <img src="a*b*.png"><br><code>&lt;b&gt;</code>"#
+        );
+        assert_eq!(inline("<i>open"), "<i>open</i>");
+    }
+
+    #[test]
+    fn pipe_table() {
+        assert_eq!(
+            to_html("| a | b |\n|---|---|\n| 1 | 2 |"),
+            "<table><thead><tr><th>a</th><th>b</th></tr></thead><tbody><tr><td>1</td><td>2</td></tr></tbody></table>"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+// Read-only public mode (`rote serve --read-only`), for sharing a collection:
+// decks can be browsed and practised, but nothing is saved. Pages leave out
+// their edit controls, sessions are practice sessions kept in memory, and
+// this guard refuses every route that would change a file:
+//
+//     POST /card/{id}/delete          -> 403
+//     GET  /card/{id}/edit            -> 403
+//     GET  /card/{id}/speech/{side}   -> 403, it fills the audio cache
+//     POST /deck/{name}/review        -> graded, not saved
+
+use axum::extract::Request;
+use axum::http::{Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+pub(super) async fn guard(request: Request, next: Next) -> Response {
+    if allowed(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+    (StatusCode::FORBIDDEN, "This collection is read-only.\n").into_response()
+}
+
+/// Whether a read-only server answers `method` on `path`: reading anything
+/// but the editors and spoken audio, and grading practice sessions.
+fn allowed(method: &Method, path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match *method {
+        Method::GET | Method::HEAD => !matches!(
+            segments[..],
+            ["import" | "trash" | "generate"]
+                | ["deck", _, "new" | "settings"]
+                | ["card", _, "edit" | "masks"]
+                | ["card", _, "speech", _]
+        ),
+        Method::POST => matches!(
+            segments[..],
+            ["deck", _, "review"] | ["deck", _, "review", "grade"]
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_reading_and_practice_get_through() {
+        assert!(allowed(&Method::GET, "/"));
+        assert!(allowed(&Method::GET, "/deck/es"));
+        assert!(allowed(&Method::GET, "/card/c1/media/a.png"));
+        assert!(allowed(&Method::POST, "/deck/es/review"));
+        assert!(allowed(&Method::POST, "/deck/es/review/grade"));
+        assert!(!allowed(&Method::GET, "/card/c1/edit"));
+        assert!(!allowed(&Method::GET, "/deck/es/new"));
+        assert!(!allowed(&Method::GET, "/import"));
+        assert!(!allowed(&Method::GET, "/trash"));
+        assert!(!allowed(&Method::GET, "/generate"));
+        assert!(!allowed(&Method::GET, "/card/c1/speech/front"));
+        assert!(!allowed(&Method::POST, "/card/c1/delete"));
+        assert!(!allowed(&Method::POST, "/deck/es/summary"));
+        assert!(!allowed(&Method::POST, "/undo"));
+        assert!(!allowed(&Method::PUT, "/api/v1/cards/c1"));
+    }
+}
@@ -0,0 +1,336 @@
+// HTML sanitizing for card content. Cards may mix Markdown with inline HTML;
+// whatever `markdown` makes of them is passed through `sanitize`, which keeps
+// allowlisted tags and attributes and escapes everything else as text:
+//
+//     H<sub>2</sub>O is <span title="dihydrogen monoxide">water</span>
+//     <img src="media/flask.png" alt="flask" width="120">
+//
+// Links and images must point at http(s), mailto or a relative URL, and links
+// always get `rel="noopener noreferrer"`. Unclosed tags are closed at the end,
+// so a card cannot restyle the page around it.
+
+use super::html_escape;
+
+/// Tags kept, with the attributes each may carry.
+const TAGS: &[(&str, &[&str])] = &[
+    ("a", &["href", "title"]),
+    ("abbr", &["title"]),
+    ("b", &[]),
+    ("blockquote", &[]),
+    ("br", &[]),
+    ("caption", &[]),
+    ("code", &[]),
+    ("dd", &[]),
+    ("del", &[]),
+    ("details", &[]),
+    ("div", &[]),
+    ("dl", &[]),
+    ("dt", &[]),
+    ("em", &[]),
+    ("h1", &[]),
+    ("h2", &[]),
+    ("h3", &[]),
+    ("h4", &[]),
+    ("h5", &[]),
+    ("h6", &[]),
+    ("hr", &[]),
+    ("i", &[]),
+    ("img", &["src", "alt", "title", "width", "height"]),
+    ("ins", &[]),
+    ("kbd", &[]),
+    ("li", &[]),
+    ("mark", &[]),
+    ("ol", &["start"]),
+    ("p", &[]),
+    ("pre", &[]),
+    ("rp", &[]),
+    ("rt", &[]),
+    ("ruby", &[]),
+    ("s", &[]),
+    ("small", &[]),
+    ("span", &["title", "data-cloze", "data-blank", "hidden"]),
+    ("strong", &[]),
+    ("sub", &[]),
+    ("summary", &[]),
+    ("sup", &[]),
+    ("table", &[]),
+    ("tbody", &[]),
+    ("td", &["colspan", "rowspan"]),
+    ("tfoot", &[]),
+    ("th", &["colspan", "rowspan"]),
+    ("thead", &[]),
+    ("tr", &[]),
+    ("u", &[]),
+    ("ul", &[]),
+];
+
+/// Tags without content or a closing tag.
+const VOID: &[&str] = &["br", "hr", "img"];
+
+/// Attributes holding a URL.
+const URLS: &[&str] = &["href", "src"];
+
+/// `html` with only allowlisted tags and attributes left as markup.
+pub fn sanitize(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut open: Vec<&'static str> = Vec::new();
+    let mut rest = html;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '<'
+            && let Some((tag, len)) = parse_tag(rest)
+        {
+            match allowed(&tag.name) {
+                Some((name, _)) if tag.closing => {
+                    if let Some(pos) = open.iter().rposition(|&t| t == name) {
+                        for t in open.drain(pos..).rev() {
+                            out.push_str(&format!("</{t}>"));
+                        }
+                    }
+                }
+                Some((name, attrs)) => {
+                    out.push('<');
+                    out.push_str(name);
+                    for (key, value) in &tag.attrs {
+                        let value = decode(value);
+                        if attrs.contains(&key.as_str())
+                            && (!URLS.contains(&key.as_str()) || safe_url(&value))
+                        {
+                            if value.is_empty() {
+                                out.push_str(&format!(" {key}"));
+                            } else {
+                                out.push_str(&format!(r#" {key}="{}""#, html_escape(&value)));
+                            }
+                        }
+                    }
+                    if name == "a" {
+                        out.push_str(r#" rel="noopener noreferrer""#);
+                    }
+                    out.push('>');
+                    if !VOID.contains(&name) {
+                        open.push(name);
+                    }
+                }
+                None => out.push_str(&html_escape(&rest[..len])),
+            }
+            rest = &rest[len..];
+            continue;
+        }
+        if ch == '&'
+            && let Some(len) = entity_len(rest)
+        {
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        out.push_str(&html_escape(&rest[..ch.len_utf8()]));
+        rest = &rest[ch.len_utf8()..];
+    }
+    for t in open.into_iter().rev() {
+        out.push_str(&format!("</{t}>"));
+    }
+    out
+}
+
+/// Whether a link or image may point at `url`: http(s), mailto, or a
+/// relative URL, which has no scheme.
+pub fn safe_url(url: &str) -> bool {
+    let lower: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let scheme_end = lower.find(['/', '?', '#']).unwrap_or(lower.len());
+    lower.starts_with("http://")
+        || lower.starts_with("https://")
+        || lower.starts_with("mailto:")
+        || !lower[..scheme_end].contains(':')
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    attrs: Vec<(String, String)>,
+}
+
+fn allowed(name: &str) -> Option<(&'static str, &'static [&'static str])> {
+    TAGS.iter()
+        .find(|(t, _)| t.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+/// Length of the tag `s` starts with, if it is one: `<name attr="v">`,
+/// `<name/>` or `</name>`.
+pub fn tag_len(s: &str) -> Option<usize> {
+    parse_tag(s).map(|(_, len)| len)
+}
+
+fn parse_tag(s: &str) -> Option<(Tag, usize)> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    let closing = bytes.get(i) == Some(&b'/');
+    if closing {
+        i += 1;
+    }
+    let name_start = i;
+    while i < bytes.len() && bytes[i].is_ascii_alphanumeric() {
+        i += 1;
+    }
+    if i == name_start || !bytes[name_start].is_ascii_alphabetic() {
+        return None;
+    }
+    let name = s[name_start..i].to_ascii_lowercase();
+    let mut attrs = Vec::new();
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let end = match bytes.get(i)? {
+            b'>' => Some(i + 1),
+            b'/' if bytes.get(i + 1) == Some(&b'>') => Some(i + 2),
+            _ if closing => return None,
+            _ => None,
+        };
+        if let Some(end) = end {
+            let tag = Tag {
+                name,
+                closing,
+                attrs,
+            };
+            return Some((tag, end));
+        }
+        let key_start = i;
+        while i < bytes.len()
+            && !bytes[i].is_ascii_whitespace()
+            && !matches!(bytes[i], b'=' | b'>' | b'/' | b'"' | b'\'' | b'<')
+        {
+            i += 1;
+        }
+        if i == key_start {
+            return None;
+        }
+        let key = s[key_start..i].to_ascii_lowercase();
+        let mut value = String::new();
+        if bytes.get(i) == Some(&b'=') {
+            i += 1;
+            match bytes.get(i)? {
+                &quote @ (b'"' | b'\'') => {
+                    let end = s[i + 1..].find(quote as char)? + i + 1;
+                    value = s[i + 1..end].to_string();
+                    i = end + 1;
+                }
+                _ => {
+                    let start = i;
+                    while i < bytes.len()
+                        && !bytes[i].is_ascii_whitespace()
+                        && !matches!(bytes[i], b'>' | b'<' | b'"' | b'\'')
+                    {
+                        i += 1;
+                    }
+                    value = s[start..i].to_string();
+                }
+            }
+        }
+        attrs.push((key, value));
+    }
+}
+
+/// Length of the character reference `s` starts with: `&amp;`, `&#38;` or
+/// `&#x26;`.
+fn entity_len(s: &str) -> Option<usize> {
+    let end = s[..s.len().min(34)].find(';')?;
+    let body = &s[1..end];
+    let valid = match body.strip_prefix('#') {
+        Some(hex) if hex.starts_with(['x', 'X']) => {
+            hex.len() > 1 && hex[1..].chars().all(|c| c.is_ascii_hexdigit())
+        }
+        Some(dec) => !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit()),
+        None => {
+            body.starts_with(|c: char| c.is_ascii_alphabetic())
+                && body.chars().all(|c| c.is_ascii_alphanumeric())
+        }
+    };
+    valid.then_some(end + 1)
+}
+
+/// An attribute value with its character references resolved, so a URL's
+/// scheme is checked as the browser will read it. Named references other
+/// than the basic five are left out, which no safe URL needs.
+fn decode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '&'
+            && let Some(len) = entity_len(rest)
+        {
+            let body = &rest[1..len - 1];
+            let code = match body.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok(),
+                Some(dec) => dec.parse().ok(),
+                None => None,
+            };
+            let named = match body {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => None,
+            };
+            if let Some(c) = code.and_then(char::from_u32).or(named) {
+                out.push(c);
+            }
+            rest = &rest[len..];
+            continue;
+        }
+        out.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_allowlisted_markup_only() {
+        assert_eq!(
+            sanitize(r#"H<sub>2</sub>O <SPAN title="x" onclick="evil()">y</SPAN>"#),
+            r#"H<sub>2</sub>O <span title="x">y</span>"#
+        );
+        assert_eq!(
+            sanitize("<script>alert(1)</script><b>bold"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;<b>bold</b>"
+        );
+        assert_eq!(
+            sanitize("</div>a < b &amp; c & d"),
+            "a &lt; b &amp; c &amp; d"
+        );
+        assert_eq!(
+            sanitize(r#"<img src="media/a.png" alt='a "b"'/><br>"#),
+            r#"<img src="media/a.png" alt="a &quot;b&quot;"><br>"#
+        );
+        assert_eq!(
+            sanitize(r#"<a href="https://example.com" target="_blank">x</a>"#),
+            r#"<a href="https://example.com" rel="noopener noreferrer">x</a>"#
+        );
+    }
+
+    #[test]
+    fn drops_scripted_urls() {
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "java\tscript:alert(1)",
+            "javascript&#58;alert(1)",
+            "javascript&#x3A;alert(1)",
+            "data:text/html,x",
+        ] {
+            let html = sanitize(&format!(r#"<a href="{url}">x</a><img src="{url}">"#));
+            assert_eq!(html, r#"<a rel="noopener noreferrer">x</a><img>"#, "{url}");
+        }
+        assert!(safe_url("/media/a.png"));
+        assert!(safe_url("a.png?v=1:2"));
+        assert!(safe_url("mailto:me@example.com"));
+    }
+}
@@ -0,0 +1,173 @@
+// Review sessions saved to `.rote/sessions.json`, so a restarted server picks
+// up where it left off. Cards are stored by id rather than index, since files
+// may change between runs; cards that have since disappeared are dropped.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use super::ReviewSession;
+use crate::card::Card;
+use crate::error::{Error, Result};
+
+const FILE: &str = "sessions.json";
+/// Sessions started longer ago than this are forgotten.
+const MAX_AGE_DAYS: i64 = 7;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Saved {
+    cards: Vec<String>,
+    position: usize,
+    counts: [u32; 4],
+    practice: bool,
+    #[serde(default)]
+    quiz: bool,
+    #[serde(default)]
+    pass_fail: bool,
+    started: NaiveDate,
+    #[serde(default)]
+    started_at: Option<DateTime<Local>>,
+}
+
+fn path(data_dir: &Path) -> PathBuf {
+    data_dir.join(FILE)
+}
+
+fn is_recent(started: NaiveDate, today: NaiveDate) -> bool {
+    (today - started).num_days() <= MAX_AGE_DAYS
+}
+
+pub(super) fn save(
+    data_dir: &Path,
+    sessions: &HashMap<String, ReviewSession>,
+    cards: &[Card],
+    today: NaiveDate,
+) -> Result<()> {
+    let saved: HashMap<&String, Saved> = sessions
+        .iter()
+        .filter(|(_, s)| is_recent(s.started, today))
+        .map(|(id, s)| {
+            let saved = Saved {
+                cards: s
+                    .order
+                    .iter()
+                    .filter_map(|&i| cards.get(i).map(|c| c.id.clone()))
+                    .collect(),
+                position: s.position,
+                counts: s.counts,
+                practice: s.practice,
+                quiz: s.quiz,
+                pass_fail: s.pass_fail,
+                started: s.started,
+                started_at: Some(s.started_at),
+            };
+            (id, saved)
+        })
+        .collect();
+    std::fs::create_dir_all(data_dir).map_err(Error::io("create", data_dir))?;
+    let json = serde_json::to_string(&saved).unwrap();
+    let path = path(data_dir);
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(Error::io("write", &tmp))?;
+    std::fs::rename(&tmp, &path).map_err(Error::io("write", path))
+}
+
+/// Saved sessions mapped back onto `cards`. A missing or unreadable file
+/// means no sessions.
+pub(super) fn load(
+    data_dir: &Path,
+    cards: &[Card],
+    today: NaiveDate,
+) -> HashMap<String, ReviewSession> {
+    let Ok(content) = std::fs::read_to_string(path(data_dir)) else {
+        return HashMap::new();
+    };
+    let Ok(saved) = serde_json::from_str::<HashMap<String, Saved>>(&content) else {
+        tracing::warn!("ignoring unreadable {}", path(data_dir).display());
+        return HashMap::new();
+    };
+    let index: HashMap<&str, usize> = cards
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.id.as_str(), i))
+        .collect();
+    saved
+        .into_iter()
+        .filter(|(_, s)| is_recent(s.started, today))
+        .map(|(id, s)| (id, restore(s, &index)))
+        .collect()
+}
+
+fn restore(saved: Saved, index: &HashMap<&str, usize>) -> ReviewSession {
+    let mut order = Vec::new();
+    let mut position = saved.position;
+    for (n, id) in saved.cards.iter().enumerate() {
+        match index.get(id.as_str()) {
+            Some(&i) => order.push(i),
+            // Keep pointing at the same next card.
+            None if n < saved.position => position -= 1,
+            None => {}
+        }
+    }
+    ReviewSession {
+        order,
+        position,
+        counts: saved.counts,
+        practice: saved.practice,
+        quiz: saved.quiz,
+        pass_fail: saved.pass_fail,
+        started: saved.started,
+        // Saved before the timer existed: time it from the restart.
+        started_at: saved.started_at.unwrap_or_else(Local::now),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(id: &str) -> Card {
+        Card {
+            deck: "d".into(),
+            front: "f".into(),
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sessions_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let cards = vec![card("a"), card("b"), card("c"), card("d")];
+        let session = ReviewSession {
+            order: vec![3, 1, 0, 2],
+            position: 2,
+            counts: [0, 0, 2, 0],
+            practice: false,
+            quiz: true,
+            pass_fail: true,
+            started: today,
+            started_at: Local::now(),
+        };
+        let old = ReviewSession {
+            started: today - chrono::Days::new(30),
+            ..session.clone()
+        };
+        let sessions = HashMap::from([("s".to_string(), session), ("old".to_string(), old)]);
+        save(dir.path(), &sessions, &cards, today).unwrap();
+
+        // "b" was deleted and the rest reordered while the server was down.
+        let cards = vec![card("c"), card("a"), card("d")];
+        let loaded = load(dir.path(), &cards, today);
+        assert_eq!(loaded.len(), 1);
+        let s = &loaded["s"];
+        assert_eq!(s.order, vec![2, 1, 0]);
+        assert_eq!(s.position, 1);
+        assert_eq!(cards[s.order[s.position]].id, "a");
+        assert_eq!(s.counts, [0, 0, 2, 0]);
+        assert!(s.quiz && s.pass_fail);
+        assert_eq!(s.started_at, sessions["s"].started_at);
+    }
+}
@@ -0,0 +1,73 @@
+// How one server's pages look and where it is mounted: the theme, assets,
+// keys, editor links and base path from its `ServeOptions`. Pages and URLs
+// are built deep inside handlers, so rather than pass these down, `scope`
+// runs each request with its server's `Site`, as `users::select` does for
+// the user. Outside a request (tests, background tasks) the defaults apply.
+
+use std::sync::{Arc, LazyLock};
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use super::{Keymap, ServeOptions, Theme, urls};
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct Site {
+    pub(super) theme: Theme,
+    /// Load Tailwind and KaTeX from their CDNs instead of the bundled assets.
+    pub(super) cdn: bool,
+    /// Link template for opening a card's source file (see `source_links`).
+    pub(super) editor_url: Option<String>,
+    pub(super) keymap: Keymap,
+    /// The path prefix the server is mounted under, `/rote` or empty.
+    pub(super) base_path: String,
+}
+
+impl Site {
+    pub(super) fn new(options: &ServeOptions) -> Site {
+        Site {
+            theme: options.theme,
+            cdn: options.cdn,
+            editor_url: options.editor_url.clone(),
+            keymap: options.keymap.clone(),
+            base_path: urls::normalize_base(&options.base_path),
+        }
+    }
+}
+
+tokio::task_local! {
+    static SITE: Arc<Site>;
+}
+
+static DEFAULT: LazyLock<Arc<Site>> = LazyLock::new(Arc::default);
+
+/// The site of the request being handled.
+pub(super) fn current() -> Arc<Site> {
+    SITE.try_with(Arc::clone)
+        .unwrap_or_else(|_| DEFAULT.clone())
+}
+
+pub(super) async fn scope(State(site): State<Arc<Site>>, request: Request, next: Next) -> Response {
+    SITE.scope(site, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn each_server_links_under_its_own_base_path() {
+        let mounted = |base: &str| {
+            Arc::new(Site::new(&ServeOptions {
+                base_path: base.into(),
+                ..ServeOptions::default()
+            }))
+        };
+        let rote = SITE.scope(mounted("/rote/"), async { urls::deck("es") });
+        let cards = SITE.scope(mounted("cards"), async { urls::deck("es") });
+        assert_eq!(rote.await, "/rote/deck/es");
+        assert_eq!(cards.await, "/cards/deck/es");
+        assert_eq!(urls::deck("es"), "/deck/es");
+    }
+}
@@ -0,0 +1,391 @@
+// Every link, form action and redirect the web UI emits is built here, so
+// routes stay consistent and can be mounted under a reverse-proxy prefix
+// (`rote serve --base-path /rote`). Returned URLs are root-relative, with
+// deck names, card ids and file names percent-encoded (`日本語::文法` becomes
+// `/deck/%E6%97%A5%E6%9C%AC%E8%AA%9E::%E6%96%87%E6%B3%95`; axum decodes them
+// again); callers HTML-escape them when interpolating into attributes.
+
+use super::site;
+
+/// Deck name used for sessions spanning every deck.
+pub const ALL_DECKS: &str = "_all";
+
+/// `base` as a path prefix: `/rote`, or empty for the root.
+pub(super) fn normalize_base(base: &str) -> String {
+    let trimmed = base.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{trimmed}")
+    }
+}
+
+pub(super) fn join(base: &str, path: &str) -> String {
+    if base.is_empty() {
+        path.to_string()
+    } else if path == "/" {
+        // Nested routers match the bare prefix, not a trailing slash.
+        base.to_string()
+    } else {
+        format!("{base}{path}")
+    }
+}
+
+/// Percent-encode everything outside the RFC 3986 unreserved set.
+pub fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// Percent-encode a path segment. Like `encode`, but `:` and `@` stay, so
+/// nested deck names remain readable.
+pub fn segment(s: &str) -> String {
+    encode(s).replace("%3A", ":").replace("%40", "@")
+}
+
+/// `path` under the base path of the server handling the request.
+fn url(path: &str) -> String {
+    join(&site::current().base_path, path)
+}
+
+pub fn home() -> String {
+    url("/")
+}
+
+pub fn deck(name: &str) -> String {
+    url(&format!("/deck/{}", segment(name)))
+}
+
+/// The page of the deck's card browser holding `id`, with it selected.
+pub fn deck_card(name: &str, id: &str) -> String {
+    format!("{}?card={}#card-{}", deck(name), encode(id), encode(id))
+}
+
+/// Page `page` (from 1) of the deck's cards, `per_page` at a time, the most
+/// recently added first if `newest`.
+pub fn deck_page(name: &str, page: usize, per_page: usize, newest: bool) -> String {
+    let sort = if newest { "&sort=newest" } else { "" };
+    format!("{}?page={page}&per_page={per_page}{sort}", deck(name))
+}
+
+/// Where a review session scoped to `name` links back to.
+pub fn deck_or_home(name: &str) -> String {
+    if name == ALL_DECKS {
+        home()
+    } else {
+        deck(name)
+    }
+}
+
+pub fn deck_review(name: &str) -> String {
+    url(&format!("/deck/{}/review", segment(name)))
+}
+
+/// Start a cross-deck review of everything due under `tag`.
+pub fn tag_review(tag: &str) -> String {
+    format!("{}?tag={}", deck_review(ALL_DECKS), encode(tag))
+}
+
+/// Cram every card in `name` without touching scheduling.
+pub fn practice(name: &str) -> String {
+    format!("{}?cram=1", deck_review(name))
+}
+
+/// Review what is due by picking answers from multiple choices.
+pub fn quiz(name: &str) -> String {
+    format!("{}?quiz=1", deck_review(name))
+}
+
+/// What a review of `name` would hold today, to bury or suspend some first.
+pub fn deck_queue(name: &str) -> String {
+    url(&format!("/deck/{}/queue", segment(name)))
+}
+
+/// Form for building a filtered review session scoped to `name`.
+pub fn custom_session(name: &str) -> String {
+    url(&format!("/deck/{}/custom", segment(name)))
+}
+
+/// Per-deck limits, retention, order and reversed cards.
+pub fn deck_settings(name: &str) -> String {
+    url(&format!("/deck/{}/settings", segment(name)))
+}
+
+pub fn deck_reschedule(name: &str) -> String {
+    url(&format!("/deck/{}/reschedule", segment(name)))
+}
+
+/// JSON endpoint the review page grades through without reloading.
+pub fn review_grade(name: &str) -> String {
+    url(&format!("/deck/{}/review/grade", segment(name)))
+}
+
+/// The rest of a session's cards as JSON, prefetched for offline review.
+pub fn review_queue(name: &str, session: &str) -> String {
+    url(&format!(
+        "/deck/{}/review/queue?session={}",
+        segment(name),
+        encode(session)
+    ))
+}
+
+/// A session of the cards falling due within `days`, likeliest forgotten
+/// first.
+pub fn review_ahead(name: &str, days: u32) -> String {
+    format!("{}?due_within={days}&order=risk", deck_review(name))
+}
+
+pub fn review_session(name: &str, session: &str) -> String {
+    format!("{}?session={}", deck_review(name), encode(session))
+}
+
+pub fn summary(name: &str, session: &str) -> String {
+    url(&format!(
+        "/deck/{}/summary?session={}",
+        segment(name),
+        encode(session)
+    ))
+}
+
+pub fn card_new(deck: &str) -> String {
+    url(&format!("/deck/{}/new", segment(deck)))
+}
+
+pub fn clozify(deck: &str) -> String {
+    url(&format!("/deck/{}/clozify", segment(deck)))
+}
+
+pub fn preview() -> String {
+    url("/preview")
+}
+
+/// A card's scheduling state and review history.
+pub fn card_info(id: &str) -> String {
+    url(&format!("/card/{}", segment(id)))
+}
+
+pub fn card_edit(id: &str) -> String {
+    url(&format!("/card/{}/edit", segment(id)))
+}
+
+/// The editor for `id`, sending the user back to `return_to` after saving.
+pub fn card_edit_from(id: &str, return_to: &str) -> String {
+    format!("{}?return_to={}", card_edit(id), encode(return_to))
+}
+
+/// Whether `url` stays on this site, so it is safe to redirect to.
+pub fn is_local(url: &str) -> bool {
+    url.starts_with('/') && !url.starts_with("//") && !url.contains('\\')
+}
+
+pub fn card_delete(id: &str) -> String {
+    url(&format!("/card/{}/delete", segment(id)))
+}
+
+pub fn card_suspend(id: &str) -> String {
+    url(&format!("/card/{}/suspend", segment(id)))
+}
+
+pub fn card_flag(id: &str) -> String {
+    url(&format!("/card/{}/flag", segment(id)))
+}
+
+/// The stats page; `all` also counts sessions excluded by their note.
+pub fn stats(all: bool) -> String {
+    if all {
+        url("/stats?all=1")
+    } else {
+        url("/stats")
+    }
+}
+
+/// The reviews given on `day`, or on the latest day with any.
+pub fn history(day: Option<chrono::NaiveDate>) -> String {
+    match day {
+        Some(day) => url(&format!("/history?day={}", day.format("%Y-%m-%d"))),
+        None => url("/history"),
+    }
+}
+
+/// Paste many `front<TAB>back` lines into a deck at once.
+pub fn import() -> String {
+    url("/import")
+}
+
+/// Cards suggested from pasted study notes (see `crate::llm`).
+#[cfg(feature = "llm")]
+pub fn generate() -> String {
+    url("/generate")
+}
+
+#[cfg(feature = "llm")]
+pub fn generate_accept() -> String {
+    url("/generate/accept")
+}
+
+/// Cards deleted in the last month (see `crate::trash`).
+pub fn trash() -> String {
+    url("/trash")
+}
+
+pub fn trash_restore(id: &str) -> String {
+    url(&format!("/trash/{}/restore", segment(id)))
+}
+
+pub fn undo() -> String {
+    url("/undo")
+}
+
+pub fn card_media(id: &str, file: &str) -> String {
+    url(&format!("/card/{}/media/{}", segment(id), segment(file)))
+}
+
+/// The image occlusion editor for `id` (see `crate::occlusion`).
+pub fn card_masks(id: &str) -> String {
+    url(&format!("/card/{}/masks", segment(id)))
+}
+
+/// Spoken audio of one side of a card (see `crate::tts`).
+pub fn card_speech(id: &str, side: &str) -> String {
+    url(&format!("/card/{}/speech/{}", segment(id), segment(side)))
+}
+
+pub fn manifest() -> String {
+    url("/manifest.webmanifest")
+}
+
+/// Served from the root so its scope covers every page.
+pub fn service_worker() -> String {
+    url("/sw.js")
+}
+
+pub fn icon() -> String {
+    url("/icon.svg")
+}
+
+pub fn katex_js() -> String {
+    url("/assets/katex.min.js")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_base_variants() {
+        assert_eq!(normalize_base(""), "");
+        assert_eq!(normalize_base("/"), "");
+        assert_eq!(normalize_base("rote"), "/rote");
+        assert_eq!(normalize_base("/rote/"), "/rote");
+        assert_eq!(normalize_base("/apps/rote"), "/apps/rote");
+    }
+
+    #[test]
+    fn join_with_and_without_base() {
+        assert_eq!(join("", "/"), "/");
+        assert_eq!(join("", "/deck/math"), "/deck/math");
+        assert_eq!(join("/rote", "/"), "/rote");
+        assert_eq!(join("/rote", "/deck/math"), "/rote/deck/math");
+    }
+
+    #[test]
+    fn routes_are_root_relative() {
+        assert_eq!(home(), "/");
+        assert_eq!(deck("math"), "/deck/math");
+        assert_eq!(deck_review("math"), "/deck/math/review");
+        assert_eq!(practice("math"), "/deck/math/review?cram=1");
+        assert_eq!(quiz("math"), "/deck/math/review?quiz=1");
+        assert_eq!(custom_session("math"), "/deck/math/custom");
+        assert_eq!(deck_settings("math"), "/deck/math/settings");
+        assert_eq!(deck_reschedule("math"), "/deck/math/reschedule");
+        assert_eq!(clozify("math"), "/deck/math/clozify");
+        assert_eq!(preview(), "/preview");
+        assert_eq!(review_session("math", "s1"), "/deck/math/review?session=s1");
+        assert_eq!(
+            review_ahead("math", 7),
+            "/deck/math/review?due_within=7&order=risk"
+        );
+        assert_eq!(review_grade("math"), "/deck/math/review/grade");
+        assert_eq!(
+            review_queue("math", "s1"),
+            "/deck/math/review/queue?session=s1"
+        );
+        assert_eq!(summary("math", "s1"), "/deck/math/summary?session=s1");
+        assert_eq!(card_new("math"), "/deck/math/new");
+        assert_eq!(card_info("abc"), "/card/abc");
+        assert_eq!(card_edit("abc"), "/card/abc/edit");
+        assert_eq!(
+            card_edit_from("abc", "/deck/d/review?session=s1"),
+            "/card/abc/edit?return_to=%2Fdeck%2Fd%2Freview%3Fsession%3Ds1"
+        );
+        assert_eq!(card_delete("abc"), "/card/abc/delete");
+        assert_eq!(card_suspend("abc"), "/card/abc/suspend");
+        assert_eq!(card_flag("abc"), "/card/abc/flag");
+        assert_eq!(deck_card("math", "abc"), "/deck/math?card=abc#card-abc");
+        assert_eq!(
+            deck_page("math", 3, 100, false),
+            "/deck/math?page=3&per_page=100"
+        );
+        assert_eq!(
+            deck_page("math", 1, 50, true),
+            "/deck/math?page=1&per_page=50&sort=newest"
+        );
+        assert_eq!(card_media("abc", "x.png"), "/card/abc/media/x.png");
+        assert_eq!(card_speech("abc", "front"), "/card/abc/speech/front");
+        assert_eq!(card_masks("abc"), "/card/abc/masks");
+        assert_eq!(undo(), "/undo");
+        assert_eq!(import(), "/import");
+        assert_eq!(stats(false), "/stats");
+        assert_eq!(stats(true), "/stats?all=1");
+        assert_eq!(manifest(), "/manifest.webmanifest");
+        assert_eq!(service_worker(), "/sw.js");
+    }
+
+    #[test]
+    fn encode_reserved_characters() {
+        assert_eq!(encode("anatomy"), "anatomy");
+        assert_eq!(encode("a b&c"), "a%20b%26c");
+        assert_eq!(encode("é"), "%C3%A9");
+        assert_eq!(tag_review("a&b"), "/deck/_all/review?tag=a%26b");
+    }
+
+    #[test]
+    fn deck_names_and_ids_are_encoded_in_paths() {
+        assert_eq!(
+            deck("日本語/文法"),
+            "/deck/%E6%97%A5%E6%9C%AC%E8%AA%9E%2F%E6%96%87%E6%B3%95"
+        );
+        assert_eq!(
+            deck_review("Japanese::Vocab"),
+            "/deck/Japanese::Vocab/review"
+        );
+        assert_eq!(custom_session("a b?"), "/deck/a%20b%3F/custom");
+        assert_eq!(card_edit("a#1"), "/card/a%231/edit");
+        assert_eq!(card_media("a#1", "x y.png"), "/card/a%231/media/x%20y.png");
+        assert_eq!(
+            review_session("a/b", "s 1"),
+            "/deck/a%2Fb/review?session=s%201"
+        );
+    }
+
+    #[test]
+    fn only_local_urls_are_redirect_targets() {
+        assert!(is_local("/deck/math/review?session=s1"));
+        assert!(!is_local("//evil.example/"));
+        assert!(!is_local("https://evil.example/"));
+        assert!(!is_local("/\\evil.example/"));
+    }
+
+    #[test]
+    fn all_decks_links_home() {
+        assert_eq!(deck_or_home(ALL_DECKS), "/");
+        assert_eq!(deck_or_home("math"), "/deck/math");
+    }
+}
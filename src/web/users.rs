@@ -0,0 +1,154 @@
+// Picking whose scheduling a request sees, so one server can serve everyone
+// sharing the decks. `?user=NAME` on any page picks that profile (see
+// `crate::user`) and a cookie remembers it; `?user=` goes back to the server's
+// own, `--user` or else the deck files' scheduling. A user's profile and
+// sessions are loaded the first time they are picked and kept in
+// `ServerState`; locking the state swaps the request's user into `app`, so
+// handlers need not know about users.
+
+use std::collections::HashMap;
+
+use axum::extract::{Query, Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use super::{ReviewSession, ServerState, SharedState, sessions, urls};
+use crate::{Error, clock, lock, user};
+
+const COOKIE: &str = "rote_user";
+
+tokio::task_local! {
+    /// The user the request being handled picked.
+    pub(super) static USER: Option<String>;
+}
+
+/// A user's scheduling and sessions while someone else's are in `app`.
+pub(super) struct Parked {
+    profile: Option<user::Profile>,
+    sessions: HashMap<String, ReviewSession>,
+}
+
+pub(super) async fn select(
+    State(state): State<SharedState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let query = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(mut params)| params.remove("user"));
+    let picked = match &query {
+        Some(name) => Some(name.clone()).filter(|n| !n.is_empty()),
+        None => from_cookie(request.headers()),
+    };
+    let user = {
+        let mut st = state.lock().await;
+        let user = picked.or_else(|| st.default_user.clone());
+        if let Err(e) = st.load_user(&user) {
+            let status = match e {
+                Error::InvalidUser { .. } => StatusCode::BAD_REQUEST,
+                Error::UserLocked { .. } => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            return (status, format!("{e}\n")).into_response();
+        }
+        user
+    };
+    let mut response = USER.scope(user, next.run(request)).await;
+    if let Some(name) = query {
+        let cookie = match name.as_str() {
+            "" => format!("{COOKIE}=; Path=/; Max-Age=0"),
+            name => format!("{COOKIE}={}; Path=/; SameSite=Lax", urls::encode(name)),
+        };
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    response
+}
+
+/// The user named by the request's cookie, if any.
+fn from_cookie(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(COOKIE)?.strip_prefix('='))
+        .and_then(decode)
+        .filter(|name| !name.is_empty())
+}
+
+/// Undo `urls::encode`.
+fn decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+impl ServerState {
+    /// Make `user` the one whose scheduling `app` holds, opening their
+    /// profile and sessions if nobody picked them before.
+    pub(super) fn load_user(&mut self, user: &Option<String>) -> crate::Result<()> {
+        if *user != self.user && !self.parked.contains_key(user) {
+            let profile = user
+                .as_deref()
+                .map(|name| user::Profile::open(self.app.dir(), name))
+                .transpose()?;
+            if let Some(name) = user
+                && !self.read_only
+            {
+                self.user_locks
+                    .push(lock::acquire_user(self.app.dir(), name)?);
+            }
+            let data_dir = profile.as_ref().map_or(self.app.dir(), |p| p.data_dir());
+            let sessions = sessions::load(data_dir, &self.app.cards, clock::today());
+            self.parked
+                .insert(user.clone(), Parked { profile, sessions });
+        }
+        self.switch_user(user)
+    }
+
+    /// Swap `user`'s scheduling and sessions, loaded before, into `app`.
+    pub(super) fn switch_user(&mut self, user: &Option<String>) -> crate::Result<()> {
+        if *user == self.user {
+            return Ok(());
+        }
+        let Some(mut parked) = self.parked.remove(user) else {
+            return Ok(());
+        };
+        if let Err(e) = self.app.swap_profile(&mut parked.profile) {
+            self.parked.insert(user.clone(), parked);
+            return Err(e);
+        }
+        std::mem::swap(&mut self.sessions, &mut parked.sessions);
+        let previous = std::mem::replace(&mut self.user, user.clone());
+        self.parked.insert(previous, parked);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cookie_names_the_user() {
+        let mut headers = axum::http::HeaderMap::new();
+        assert_eq!(from_cookie(&headers), None);
+        let cookie = format!("theme=dark; {COOKIE}={}", urls::encode("José"));
+        headers.insert(header::COOKIE, HeaderValue::from_str(&cookie).unwrap());
+        assert_eq!(from_cookie(&headers).as_deref(), Some("José"));
+        assert_eq!(decode("%E"), None);
+    }
+}
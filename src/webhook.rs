@@ -0,0 +1,267 @@
+// Webhooks: HTTP POSTs sent when a review session finishes and when the number
+// of due cards first reaches a threshold on a given day, e.g. to an ntfy topic
+// or a Discord channel. Configured in config.toml:
+//
+//     [[webhooks]]
+//     url = "https://ntfy.sh/my-rote"
+//     format = "text"
+//     due_threshold = 50
+//
+// `events` limits a hook to some of "session_complete" and "due_threshold";
+// without it a hook gets both (the latter only when it sets a threshold).
+// Which hooks have already been nudged today is kept in `.rote/webhooks.json`,
+// so the threshold fires once a day however often it is checked.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+
+use crate::error::{Error, Result};
+
+const STATE_FILE: &str = "webhooks.json";
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// Due count at which `due_threshold` fires.
+    pub due_threshold: Option<usize>,
+    #[serde(default)]
+    pub format: Format,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    SessionComplete,
+    DueThreshold,
+}
+
+/// Body of the POST: the event as JSON, its message as plain text (ntfy), or
+/// the message wrapped for a Discord webhook.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    Text,
+    Discord,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    SessionComplete {
+        /// Forgot, hard, good and easy.
+        counts: [u32; 4],
+        decks: Vec<String>,
+        practice: bool,
+    },
+    DueThreshold {
+        due: usize,
+        threshold: usize,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::SessionComplete { .. } => EventKind::SessionComplete,
+            Event::DueThreshold { .. } => EventKind::DueThreshold,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Event::SessionComplete {
+                counts,
+                decks,
+                practice,
+            } => {
+                let total: u32 = counts.iter().sum();
+                let verb = if *practice { "Practised" } else { "Reviewed" };
+                let plural = if total == 1 { "" } else { "s" };
+                let decks = if decks.is_empty() {
+                    String::new()
+                } else {
+                    format!(" in {}", decks.join(", "))
+                };
+                format!(
+                    "{verb} {total} card{plural}{decks} (forgot {}, hard {}, good {}, easy {}).",
+                    counts[0], counts[1], counts[2], counts[3]
+                )
+            }
+            Event::DueThreshold { due, threshold } => {
+                format!("{due} cards are due (threshold {threshold}).")
+            }
+        }
+    }
+
+    fn json(&self) -> serde_json::Value {
+        match self {
+            Event::SessionComplete {
+                counts,
+                decks,
+                practice,
+            } => serde_json::json!({
+                "event": "session_complete",
+                "message": self.message(),
+                "reviews": counts.iter().sum::<u32>(),
+                "counts": {
+                    "forgot": counts[0],
+                    "hard": counts[1],
+                    "good": counts[2],
+                    "easy": counts[3],
+                },
+                "decks": decks,
+                "practice": practice,
+            }),
+            Event::DueThreshold { due, threshold } => serde_json::json!({
+                "event": "due_threshold",
+                "message": self.message(),
+                "due": due,
+                "threshold": threshold,
+            }),
+        }
+    }
+}
+
+impl Webhook {
+    fn wants(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    /// Content type and body to POST for `event`.
+    pub fn body(&self, event: &Event) -> (&'static str, String) {
+        match self.format {
+            Format::Json => ("application/json", event.json().to_string()),
+            Format::Text => ("text/plain; charset=utf-8", event.message()),
+            Format::Discord => (
+                "application/json",
+                serde_json::json!({ "content": event.message() }).to_string(),
+            ),
+        }
+    }
+
+    fn post(&self, event: &Event) -> Result<()> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build()
+            .into();
+        let (content_type, body) = self.body(event);
+        agent
+            .post(&self.url)
+            .header("Content-Type", content_type)
+            .send(body)
+            .map(|_| ())
+            .map_err(|source| Error::Webhook {
+                url: self.url.clone(),
+                source,
+            })
+    }
+}
+
+/// Send `event` to every hook that wants it, returning the failures.
+pub fn send(hooks: &[Webhook], event: &Event) -> Vec<Error> {
+    hooks
+        .iter()
+        .filter(|h| h.wants(event.kind()))
+        .filter_map(|h| h.post(event).err())
+        .collect()
+}
+
+/// Hooks whose threshold `due` reaches and that have not fired on `today`,
+/// given the dates each URL last fired.
+fn owed<'a>(
+    hooks: &'a [Webhook],
+    due: usize,
+    today: NaiveDate,
+    fired: &BTreeMap<String, NaiveDate>,
+) -> Vec<(&'a Webhook, usize)> {
+    hooks
+        .iter()
+        .filter(|h| h.wants(EventKind::DueThreshold))
+        .filter_map(|h| Some((h, h.due_threshold?)))
+        .filter(|&(h, threshold)| due >= threshold && fired.get(&h.url) != Some(&today))
+        .collect()
+}
+
+/// Fire `due_threshold` on every hook it is owed to today and remember that
+/// it did. Returns the failures; a hook that failed is retried next time.
+pub fn notify_due(hooks: &[Webhook], due: usize, today: NaiveDate, data_dir: &Path) -> Vec<Error> {
+    let path = data_dir.join(STATE_FILE);
+    let mut fired: BTreeMap<String, NaiveDate> = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let owed = owed(hooks, due, today, &fired);
+    if owed.is_empty() {
+        return Vec::new();
+    }
+    let mut errors = Vec::new();
+    for (hook, threshold) in owed {
+        match hook.post(&Event::DueThreshold { due, threshold }) {
+            Ok(()) => {
+                fired.insert(hook.url.clone(), today);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    let written = std::fs::create_dir_all(data_dir)
+        .and_then(|()| std::fs::write(&path, serde_json::to_string_pretty(&fired).unwrap()));
+    if let Err(e) = written {
+        errors.push(Error::io("write", path)(e));
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn due_threshold_fires_once_a_day() {
+        let hooks: Vec<Webhook> = toml::from_str::<BTreeMap<String, Vec<Webhook>>>(
+            r#"
+[[webhooks]]
+url = "https://ntfy.sh/a"
+format = "text"
+due_threshold = 50
+
+[[webhooks]]
+url = "https://example.com/b"
+events = ["session_complete"]
+due_threshold = 10
+"#,
+        )
+        .unwrap()
+        .remove("webhooks")
+        .unwrap();
+        let today = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+
+        let mut fired = BTreeMap::new();
+        assert!(owed(&hooks, 49, today, &fired).is_empty());
+        let due = owed(&hooks, 50, today, &fired);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0.url, "https://ntfy.sh/a");
+
+        fired.insert("https://ntfy.sh/a".to_string(), today);
+        assert!(owed(&hooks, 80, today, &fired).is_empty());
+        assert_eq!(owed(&hooks, 80, today.succ_opt().unwrap(), &fired).len(), 1);
+
+        let event = Event::DueThreshold {
+            due: 52,
+            threshold: 50,
+        };
+        assert_eq!(
+            hooks[0].body(&event),
+            (
+                "text/plain; charset=utf-8",
+                "52 cards are due (threshold 50).".to_string()
+            )
+        );
+    }
+}